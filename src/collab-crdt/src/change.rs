@@ -0,0 +1,66 @@
+//! Mirror of the contract's `StateChange`, as produced by replica edits
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Type of change, mirroring the contract's `ChangeType`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChangeType {
+    ParameterUpdate,
+    ToolSwitch,
+    CanvasAction,
+    TimelineSeek,
+}
+
+/// One change to submit with `submit_patch`
+///
+/// Values are JSON text, as the contract stores them. `old_value` is the replica's
+/// value before the edit. The contract applies a patch
+/// only while its state still matches, so batch changes in the order they were made.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateChange {
+    pub parameter_path: String,
+    pub old_value: String,
+    pub new_value: String,
+    pub change_type: ChangeType,
+}
+
+impl StateChange {
+    pub fn parameter(key: &str, old_value: Value, new_value: Value) -> Self {
+        Self {
+            parameter_path: json_pointer(key),
+            old_value: old_value.to_string(),
+            new_value: new_value.to_string(),
+            change_type: ChangeType::ParameterUpdate,
+        }
+    }
+
+    pub fn tool_switch(old_value: Value, new_value: Value) -> Self {
+        Self {
+            parameter_path: String::new(),
+            old_value: old_value.to_string(),
+            new_value: new_value.to_string(),
+            change_type: ChangeType::ToolSwitch,
+        }
+    }
+}
+
+/// RFC 6901 pointer to a top-level parameter
+pub fn json_pointer(key: &str) -> String {
+    format!("/{}", key.replace('~', "~0").replace('/', "~1"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_serializes_like_the_contract() {
+        let change = StateChange::parameter("a/b~c", json!(1), json!(2));
+        assert_eq!(
+            serde_json::to_value(&change).unwrap(),
+            json!({"parameter_path": "/a~1b~0c", "old_value": "1", "new_value": "2", "change_type": "ParameterUpdate"})
+        );
+    }
+}
@@ -0,0 +1,32 @@
+//! Lamport stamps that totally order operations across replicas
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Replica identifier, usually the editor's account ID
+pub type ReplicaId = String;
+
+/// Lamport time plus the replica that issued it
+///
+/// Unique per operation. Ordering compares the counter first and breaks ties by
+/// replica ID, so every replica resolves concurrent writes the same way.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Stamp {
+    pub counter: u64,
+    pub replica: ReplicaId,
+}
+
+impl Stamp {
+    pub fn new(counter: u64, replica: impl Into<ReplicaId>) -> Self {
+        Self {
+            counter,
+            replica: replica.into(),
+        }
+    }
+}
+
+impl fmt::Display for Stamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}@{}", self.counter, self.replica)
+    }
+}
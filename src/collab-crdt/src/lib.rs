@@ -0,0 +1,45 @@
+//! # NFT Collaboration CRDT
+//!
+//! Real-time co-editing for `CollaborationContract` sessions. Peers edit a local
+//! replica and exchange operations directly; replicas converge whatever order the
+//! operations arrive in, so concurrent editors no longer clobber each other.
+//!
+//! - Parameters are a map of last-writer-wins registers keyed by top-level name.
+//! - The tool type is a single last-writer-wins register.
+//! - Timeline events are a replicated growable array (RGA) stored under
+//!   `parameters.timeline`.
+//!
+//! Every visible effect of an operation is reported as a contract `StateChange`, so
+//! edits can be batched into patches. Peers periodically checkpoint the hash of the
+//! merged state on-chain with `checkpoint_state`.
+
+mod change;
+mod clock;
+mod lww;
+mod replica;
+mod rga;
+
+pub use change::*;
+pub use clock::*;
+pub use lww::*;
+pub use replica::*;
+pub use rga::*;
+
+use thiserror::Error;
+
+/// Errors applying an edit to a replica
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum CrdtError {
+    /// The operation refers to a timeline event this replica has not seen yet
+    #[error("missing timeline event {0}")]
+    MissingEvent(Stamp),
+
+    #[error("timeline index {index} is out of range for {len} events")]
+    IndexOutOfRange { index: usize, len: usize },
+
+    #[error("parameter `{0}` is reserved")]
+    ReservedKey(String),
+
+    #[error("invalid snapshot: {0}")]
+    InvalidSnapshot(&'static str),
+}
@@ -0,0 +1,114 @@
+//! Last-writer-wins registers
+//!
+//! A write replaces the current value only if its stamp is greater, so replicas
+//! that see the same writes in any order keep the same value.
+
+use crate::clock::Stamp;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// Single last-writer-wins value
+#[derive(Debug, Clone, PartialEq)]
+pub struct LwwRegister<T> {
+    entry: Option<(Stamp, T)>,
+}
+
+impl<T> Default for LwwRegister<T> {
+    fn default() -> Self {
+        Self { entry: None }
+    }
+}
+
+impl<T> LwwRegister<T> {
+    /// Write `value`; returns false if a write with a greater or equal stamp is already held
+    pub fn set(&mut self, stamp: Stamp, value: T) -> bool {
+        if self.entry.as_ref().is_some_and(|(current, _)| *current >= stamp) {
+            return false;
+        }
+        self.entry = Some((stamp, value));
+        true
+    }
+
+    pub fn get(&self) -> Option<&T> {
+        self.entry.as_ref().map(|(_, value)| value)
+    }
+
+    pub fn stamp(&self) -> Option<&Stamp> {
+        self.entry.as_ref().map(|(stamp, _)| stamp)
+    }
+}
+
+/// Map of last-writer-wins registers
+///
+/// Removal writes `null`, which stays as a tombstone so that an older concurrent
+/// write cannot bring the entry back.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LwwMap {
+    entries: BTreeMap<String, LwwRegister<Value>>,
+}
+
+impl LwwMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write `value` at `key`; `null` removes it. Returns false if the write lost.
+    pub fn set(&mut self, key: &str, stamp: Stamp, value: Value) -> bool {
+        self.entries.entry(key.to_string()).or_default().set(stamp, value)
+    }
+
+    /// Live value at `key`; removed and unknown keys both give `None`
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.entries.get(key).and_then(LwwRegister::get).filter(|value| !value.is_null())
+    }
+
+    /// Live entries, sorted by key
+    pub fn to_json(&self) -> Map<String, Value> {
+        self.entries
+            .iter()
+            .filter_map(|(key, register)| {
+                register
+                    .get()
+                    .filter(|value| !value.is_null())
+                    .map(|value| (key.clone(), value.clone()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_greatest_stamp_wins_in_any_order() {
+        let writes = [
+            (Stamp::new(2, "alice"), json!(1.5)),
+            (Stamp::new(2, "bob"), json!(3.0)),
+            (Stamp::new(1, "carol"), json!(0.5)),
+        ];
+
+        let mut forward = LwwMap::new();
+        let mut backward = LwwMap::new();
+        for (stamp, value) in &writes {
+            forward.set("zoom", stamp.clone(), value.clone());
+        }
+        for (stamp, value) in writes.iter().rev() {
+            backward.set("zoom", stamp.clone(), value.clone());
+        }
+
+        assert_eq!(forward, backward);
+        assert_eq!(forward.get("zoom"), Some(&json!(3.0)));
+    }
+
+    #[test]
+    fn test_removal_is_not_undone_by_an_older_write() {
+        let mut map = LwwMap::new();
+        map.set("color", Stamp::new(3, "alice"), Value::Null);
+        assert!(!map.set("color", Stamp::new(2, "bob"), json!("blue")));
+
+        assert_eq!(map.get("color"), None);
+        assert!(map.to_json().is_empty());
+    }
+}
@@ -0,0 +1,422 @@
+//! Session replicas: local edits, remote operations and checkpoints
+
+use crate::change::StateChange;
+use crate::clock::{ReplicaId, Stamp};
+use crate::lww::{LwwMap, LwwRegister};
+use crate::rga::Rga;
+use crate::CrdtError;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+
+/// Parameter holding the timeline events; it cannot be set as a plain parameter
+pub const TIMELINE_KEY: &str = "timeline";
+
+/// Replica ID of the operations that load the starting snapshot
+pub const GENESIS_REPLICA: &str = "genesis";
+
+/// Operation exchanged between replicas
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Op {
+    /// Write a top-level parameter; `null` removes it
+    SetParameter { stamp: Stamp, key: String, value: Value },
+    SetTool { stamp: Stamp, tool_type: String },
+    InsertEvent { stamp: Stamp, after: Option<Stamp>, event: Value },
+    RemoveEvent { stamp: Stamp, target: Stamp },
+}
+
+impl Op {
+    pub fn stamp(&self) -> &Stamp {
+        match self {
+            Op::SetParameter { stamp, .. }
+            | Op::SetTool { stamp, .. }
+            | Op::InsertEvent { stamp, .. }
+            | Op::RemoveEvent { stamp, .. } => stamp,
+        }
+    }
+}
+
+/// Shared session state, in the shape of the contract's `tool_type` and `parameters`
+///
+/// An empty timeline is stored as an absent `timeline` parameter.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub tool_type: String,
+    pub parameters: Value,
+}
+
+impl Snapshot {
+    /// sha256 of the canonical JSON encoding (object keys sorted), as checkpointed on-chain
+    pub fn hash(&self) -> [u8; 32] {
+        let canonical = serde_json::to_vec(self).expect("snapshot serializes");
+        Sha256::digest(canonical).into()
+    }
+}
+
+/// Arguments for the contract's `checkpoint_state`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub session_id: String,
+    /// Base64 `Snapshot::hash`
+    pub state_hash: String,
+    /// Operations merged into the state, genesis excluded
+    pub op_count: u64,
+}
+
+/// One peer's copy of a collaboration session
+#[derive(Debug, Clone)]
+pub struct SessionReplica {
+    replica_id: ReplicaId,
+    clock: u64,
+    parameters: LwwMap,
+    tool: LwwRegister<String>,
+    timeline: Rga,
+    log: Vec<Op>,        // Applied operations, genesis excluded
+    seen: HashSet<Stamp>,
+    pending: Vec<Op>,    // Remote operations waiting for a timeline event
+}
+
+impl SessionReplica {
+    /// Start from the session's on-chain state; all peers must start from the same snapshot
+    pub fn new(replica_id: impl Into<ReplicaId>, base: &Snapshot) -> Result<Self, CrdtError> {
+        let mut replica = Self {
+            replica_id: replica_id.into(),
+            clock: 0,
+            parameters: LwwMap::new(),
+            tool: LwwRegister::default(),
+            timeline: Rga::new(),
+            log: Vec::new(),
+            seen: HashSet::new(),
+            pending: Vec::new(),
+        };
+        for op in genesis_ops(base)? {
+            replica.integrate(&op)?;
+        }
+        replica.log.clear();
+        Ok(replica)
+    }
+
+    pub fn replica_id(&self) -> &str {
+        &self.replica_id
+    }
+
+    /// Set a parameter; `null` removes it
+    pub fn set_parameter(&mut self, key: &str, value: Value) -> Result<(Op, Vec<StateChange>), CrdtError> {
+        if key == TIMELINE_KEY {
+            return Err(CrdtError::ReservedKey(key.to_string()));
+        }
+        let op = Op::SetParameter {
+            stamp: self.tick(),
+            key: key.to_string(),
+            value,
+        };
+        self.local(op)
+    }
+
+    pub fn remove_parameter(&mut self, key: &str) -> Result<(Op, Vec<StateChange>), CrdtError> {
+        self.set_parameter(key, Value::Null)
+    }
+
+    pub fn set_tool(&mut self, tool_type: &str) -> Result<(Op, Vec<StateChange>), CrdtError> {
+        let op = Op::SetTool {
+            stamp: self.tick(),
+            tool_type: tool_type.to_string(),
+        };
+        self.local(op)
+    }
+
+    /// Insert a timeline event so that it ends up at `index`
+    pub fn insert_event(&mut self, index: usize, event: Value) -> Result<(Op, Vec<StateChange>), CrdtError> {
+        let len = self.timeline.len();
+        if index > len {
+            return Err(CrdtError::IndexOutOfRange { index, len });
+        }
+        let after = index.checked_sub(1).and_then(|previous| self.timeline.id_at(previous)).cloned();
+        let op = Op::InsertEvent {
+            stamp: self.tick(),
+            after,
+            event,
+        };
+        self.local(op)
+    }
+
+    pub fn remove_event(&mut self, index: usize) -> Result<(Op, Vec<StateChange>), CrdtError> {
+        let target = self.timeline.id_at(index).cloned().ok_or(CrdtError::IndexOutOfRange {
+            index,
+            len: self.timeline.len(),
+        })?;
+        let op = Op::RemoveEvent { stamp: self.tick(), target };
+        self.local(op)
+    }
+
+    /// Apply an operation from another peer; duplicates are ignored
+    ///
+    /// Operations anchored to a timeline event this replica has not seen yet are
+    /// held back and applied once it arrives. Returns the changes to this replica's
+    /// visible state, in order.
+    pub fn apply(&mut self, op: Op) -> Vec<StateChange> {
+        let mut changes = Vec::new();
+        match self.integrate(&op) {
+            Ok(change) => changes.extend(change),
+            Err(_) => {
+                if !self.pending.contains(&op) {
+                    self.pending.push(op);
+                }
+                return changes;
+            }
+        }
+
+        // The new operation may unblock held-back ones, which may unblock more
+        let mut progress = true;
+        while progress {
+            progress = false;
+            for op in std::mem::take(&mut self.pending) {
+                match self.integrate(&op) {
+                    Ok(change) => {
+                        changes.extend(change);
+                        progress = true;
+                    }
+                    Err(_) => self.pending.push(op),
+                }
+            }
+        }
+        changes
+    }
+
+    /// Applied operations in application order, for sending to peers
+    pub fn ops(&self) -> &[Op] {
+        &self.log
+    }
+
+    /// Operations waiting for a missing timeline event
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn timeline(&self) -> Vec<Value> {
+        self.timeline.values()
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        let mut parameters = self.parameters.to_json();
+        if !self.timeline.is_empty() {
+            parameters.insert(TIMELINE_KEY.to_string(), self.timeline_value());
+        }
+        Snapshot {
+            tool_type: self.tool.get().cloned().unwrap_or_default(),
+            parameters: Value::Object(parameters),
+        }
+    }
+
+    pub fn checkpoint(&self, session_id: &str) -> Checkpoint {
+        Checkpoint {
+            session_id: session_id.to_string(),
+            state_hash: BASE64.encode(self.snapshot().hash()),
+            op_count: self.log.len() as u64,
+        }
+    }
+
+    fn tick(&mut self) -> Stamp {
+        self.clock += 1;
+        Stamp::new(self.clock, self.replica_id.clone())
+    }
+
+    fn local(&mut self, op: Op) -> Result<(Op, Vec<StateChange>), CrdtError> {
+        let change = self.integrate(&op)?;
+        Ok((op, change.into_iter().collect()))
+    }
+
+    /// Apply one operation whose dependencies are present
+    fn integrate(&mut self, op: &Op) -> Result<Option<StateChange>, CrdtError> {
+        if self.seen.contains(op.stamp()) {
+            return Ok(None);
+        }
+
+        let change = match op {
+            Op::SetParameter { stamp, key, value } => {
+                let old_value = self.parameters.get(key).cloned().unwrap_or(Value::Null);
+                self.parameters.set(key, stamp.clone(), value.clone());
+                let new_value = self.parameters.get(key).cloned().unwrap_or(Value::Null);
+                (old_value != new_value).then(|| StateChange::parameter(key, old_value, new_value))
+            }
+            Op::SetTool { stamp, tool_type } => {
+                let old_value = self.tool.get().cloned();
+                self.tool.set(stamp.clone(), tool_type.clone());
+                let new_value = self.tool.get().cloned();
+                (old_value != new_value).then(|| StateChange::tool_switch(old_value.into(), new_value.into()))
+            }
+            Op::InsertEvent { stamp, after, event } => {
+                let old_value = self.timeline_value();
+                self.timeline.insert(stamp.clone(), after.as_ref(), event.clone())?;
+                Some(StateChange::parameter(TIMELINE_KEY, old_value, self.timeline_value()))
+            }
+            Op::RemoveEvent { target, .. } => {
+                let old_value = self.timeline_value();
+                let removed = self.timeline.remove(target)?;
+                removed.then(|| StateChange::parameter(TIMELINE_KEY, old_value, self.timeline_value()))
+            }
+        };
+
+        self.clock = self.clock.max(op.stamp().counter);
+        self.seen.insert(op.stamp().clone());
+        self.log.push(op.clone());
+        Ok(change)
+    }
+
+    fn timeline_value(&self) -> Value {
+        if self.timeline.is_empty() {
+            Value::Null
+        } else {
+            Value::Array(self.timeline.values())
+        }
+    }
+}
+
+/// Operations that load `base`; every replica derives the same ones
+fn genesis_ops(base: &Snapshot) -> Result<Vec<Op>, CrdtError> {
+    let empty = Map::new();
+    let parameters = match &base.parameters {
+        Value::Object(parameters) => parameters,
+        Value::Null => &empty,
+        _ => return Err(CrdtError::InvalidSnapshot("parameters must be an object")),
+    };
+
+    let mut counter = 0;
+    let mut stamp = || {
+        counter += 1;
+        Stamp::new(counter, GENESIS_REPLICA)
+    };
+
+    let mut ops = vec![Op::SetTool {
+        stamp: stamp(),
+        tool_type: base.tool_type.clone(),
+    }];
+    for (key, value) in parameters {
+        if key == TIMELINE_KEY {
+            continue;
+        }
+        ops.push(Op::SetParameter {
+            stamp: stamp(),
+            key: key.clone(),
+            value: value.clone(),
+        });
+    }
+
+    let events = match parameters.get(TIMELINE_KEY) {
+        Some(Value::Array(events)) => events.as_slice(),
+        None | Some(Value::Null) => &[],
+        Some(_) => return Err(CrdtError::InvalidSnapshot("timeline must be an array")),
+    };
+    let mut after = None;
+    for event in events {
+        let id = stamp();
+        ops.push(Op::InsertEvent {
+            stamp: id.clone(),
+            after: after.replace(id),
+            event: event.clone(),
+        });
+    }
+    Ok(ops)
+}
+
+/// Decides when a peer should checkpoint: after every `interval` merged operations
+///
+/// Run it on one peer per session, such as the creator's; the contract rejects a
+/// checkpoint that is not newer than the last one.
+#[derive(Debug, Clone)]
+pub struct Checkpointer {
+    session_id: String,
+    interval: u64,
+    last_op_count: u64,
+}
+
+impl Checkpointer {
+    pub fn new(session_id: impl Into<String>, interval: u64) -> Self {
+        Self {
+            session_id: session_id.into(),
+            interval: interval.max(1),
+            last_op_count: 0,
+        }
+    }
+
+    /// The checkpoint to submit, if one is due
+    pub fn poll(&mut self, replica: &SessionReplica) -> Option<Checkpoint> {
+        let checkpoint = replica.checkpoint(&self.session_id);
+        if checkpoint.op_count < self.last_op_count + self.interval {
+            return None;
+        }
+        self.last_op_count = checkpoint.op_count;
+        Some(checkpoint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn base() -> Snapshot {
+        Snapshot {
+            tool_type: "fractal_shader".to_string(),
+            parameters: json!({"iterations": 50, "zoom": 1.0, "timeline": [{"at": 0, "cue": "intro"}]}),
+        }
+    }
+
+    #[test]
+    fn test_edits_map_to_state_changes() {
+        let mut alice = SessionReplica::new("alice.testnet", &base()).unwrap();
+        assert_eq!(alice.snapshot(), base());
+
+        let (_, changes) = alice.set_parameter("zoom", json!(2.5)).unwrap();
+        assert_eq!(changes, vec![StateChange::parameter("zoom", json!(1.0), json!(2.5))]);
+
+        let (_, changes) = alice.insert_event(1, json!({"at": 4, "cue": "drop"})).unwrap();
+        assert_eq!(
+            changes,
+            vec![StateChange::parameter(
+                "timeline",
+                json!([{"at": 0, "cue": "intro"}]),
+                json!([{"at": 0, "cue": "intro"}, {"at": 4, "cue": "drop"}])
+            )]
+        );
+
+        let (_, changes) = alice.set_tool("wgsl_shader").unwrap();
+        assert_eq!(changes, vec![StateChange::tool_switch(json!("fractal_shader"), json!("wgsl_shader"))]);
+        assert_eq!(alice.set_parameter("timeline", json!([])).unwrap_err(), CrdtError::ReservedKey("timeline".into()));
+    }
+
+    #[test]
+    fn test_out_of_order_operations_wait_for_their_event() {
+        let mut alice = SessionReplica::new("alice.testnet", &base()).unwrap();
+        let (insert, _) = alice.insert_event(0, json!({"at": 2, "cue": "build"})).unwrap();
+        let (remove, _) = alice.remove_event(0).unwrap();
+
+        let mut bob = SessionReplica::new("bob.testnet", &base()).unwrap();
+        assert!(bob.apply(remove).is_empty());
+        assert_eq!(bob.pending(), 1);
+
+        let changes = bob.apply(insert);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(bob.pending(), 0);
+        assert_eq!(bob.snapshot(), alice.snapshot());
+    }
+
+    #[test]
+    fn test_checkpoints_follow_the_interval() {
+        let mut alice = SessionReplica::new("alice.testnet", &base()).unwrap();
+        let mut checkpointer = Checkpointer::new("jam", 2);
+
+        alice.set_parameter("zoom", json!(2.0)).unwrap();
+        assert_eq!(checkpointer.poll(&alice), None);
+        alice.set_parameter("zoom", json!(3.0)).unwrap();
+
+        let checkpoint = checkpointer.poll(&alice).unwrap();
+        assert_eq!(checkpoint.op_count, 2);
+        assert_eq!(checkpoint.state_hash, BASE64.encode(alice.snapshot().hash()));
+        assert_eq!(checkpointer.poll(&alice), None);
+    }
+}
@@ -0,0 +1,127 @@
+//! Replicated growable array for timeline events
+//!
+//! Each event is identified by the stamp of its insert and placed right after the
+//! event it was inserted behind. Concurrent inserts at the same position are
+//! ordered by descending stamp, and removed events stay as tombstones so later
+//! inserts can still anchor to them.
+
+use crate::clock::Stamp;
+use crate::CrdtError;
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Node {
+    id: Stamp,
+    value: Value,
+    removed: bool,
+}
+
+/// Ordered sequence that converges under concurrent inserts and removals
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Rga {
+    nodes: Vec<Node>, // Document order, tombstones included
+}
+
+impl Rga {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert `value` as event `id` after `after` (`None` for the start)
+    ///
+    /// Returns false if the event is already present.
+    pub fn insert(&mut self, id: Stamp, after: Option<&Stamp>, value: Value) -> Result<bool, CrdtError> {
+        if self.position(&id).is_some() {
+            return Ok(false);
+        }
+        let mut index = match after {
+            Some(after) => self.position(after).ok_or_else(|| CrdtError::MissingEvent(after.clone()))? + 1,
+            None => 0,
+        };
+        // Newer concurrent inserts at this position, and everything anchored behind
+        // them, carry greater stamps and stay in front
+        while index < self.nodes.len() && self.nodes[index].id > id {
+            index += 1;
+        }
+        self.nodes.insert(index, Node { id, value, removed: false });
+        Ok(true)
+    }
+
+    /// Remove event `id`; returns false if it was already removed
+    pub fn remove(&mut self, id: &Stamp) -> Result<bool, CrdtError> {
+        let index = self.position(id).ok_or_else(|| CrdtError::MissingEvent(id.clone()))?;
+        let node = &mut self.nodes[index];
+        let was_live = !node.removed;
+        node.removed = true;
+        Ok(was_live)
+    }
+
+    /// ID of the event at a visible index
+    pub fn id_at(&self, index: usize) -> Option<&Stamp> {
+        self.live().nth(index).map(|node| &node.id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.live().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Visible events in order
+    pub fn values(&self) -> Vec<Value> {
+        self.live().map(|node| node.value.clone()).collect()
+    }
+
+    fn live(&self) -> impl Iterator<Item = &Node> {
+        self.nodes.iter().filter(|node| !node.removed)
+    }
+
+    fn position(&self, id: &Stamp) -> Option<usize> {
+        self.nodes.iter().position(|node| node.id == *id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_concurrent_inserts_converge() {
+        let a = Stamp::new(1, "alice");
+        let b = Stamp::new(2, "alice");
+        let c = Stamp::new(2, "bob");
+
+        // Bob inserts "c" at the start while Alice inserts "b" behind her "a"
+        let mut alice = Rga::new();
+        alice.insert(a.clone(), None, json!("a")).unwrap();
+        alice.insert(b.clone(), Some(&a), json!("b")).unwrap();
+        alice.insert(c.clone(), None, json!("c")).unwrap();
+
+        let mut bob = Rga::new();
+        bob.insert(c.clone(), None, json!("c")).unwrap();
+        bob.insert(a.clone(), None, json!("a")).unwrap();
+        bob.insert(b.clone(), Some(&a), json!("b")).unwrap();
+
+        assert_eq!(alice, bob);
+        assert_eq!(alice.values(), vec![json!("c"), json!("a"), json!("b")]);
+    }
+
+    #[test]
+    fn test_removed_events_still_anchor_inserts() {
+        let a = Stamp::new(1, "alice");
+        let mut rga = Rga::new();
+        rga.insert(a.clone(), None, json!("a")).unwrap();
+        assert!(rga.remove(&a).unwrap());
+        assert!(!rga.remove(&a).unwrap());
+
+        rga.insert(Stamp::new(2, "bob"), Some(&a), json!("b")).unwrap();
+        assert_eq!(rga.values(), vec![json!("b")]);
+        assert_eq!(
+            rga.insert(Stamp::new(3, "bob"), Some(&Stamp::new(9, "carol")), json!("x")),
+            Err(CrdtError::MissingEvent(Stamp::new(9, "carol")))
+        );
+    }
+}
@@ -0,0 +1,508 @@
+//! CAR archives - pack session bundles into CARv1/CARv2 files and read them back
+//!
+//! A session archive is a UnixFS directory whose entries are the session manifest
+//! (`session.json`) and the files it references (frames, shader outputs, audio), so
+//! the root CID is browsable on any gateway once the CAR is uploaded.
+
+use crate::storage_backend::{StorageBackend, StorageError};
+use crate::unixfs::{
+    decode_pb_node, decode_unixfs, decode_varint, directory_block, import, is_directory_node,
+    is_file_node, take_bytes, Block, DecodeError, ImportOptions, DAG_PB, RAW,
+};
+use cid::{Cid, Version};
+use multihash::{Code, MultihashDigest};
+use serde::de::DeserializeOwned;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use thiserror::Error;
+
+/// Name of the manifest entry in a session archive
+pub const MANIFEST_NAME: &str = "session.json";
+
+/// Fixed prefix identifying a CARv2 file
+const CARV2_PRAGMA: [u8; 11] = [0x0a, 0xa1, 0x67, 0x76, 0x65, 0x72, 0x73, 0x69, 0x6f, 0x6e, 0x02];
+const CARV2_HEADER_LEN: usize = 40;
+
+/// Errors packing or unpacking CAR archives
+#[derive(Debug, Error)]
+pub enum CarError {
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+
+    #[error("malformed CAR: {0}")]
+    Malformed(String),
+
+    #[error("invalid CID: {0}")]
+    InvalidCid(String),
+
+    #[error("content of {expected} hashes to {actual}")]
+    CidMismatch { expected: String, actual: String },
+
+    #[error("block {0} is missing from the archive")]
+    MissingBlock(String),
+
+    #[error("archive has no entry named {0}")]
+    MissingEntry(String),
+
+    #[error("serialization failed: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+/// CAR format version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CarVersion {
+    V1,
+    V2,
+}
+
+/// Decoded CAR archive with verified blocks
+#[derive(Debug, Clone)]
+pub struct CarArchive {
+    pub version: CarVersion,
+    pub roots: Vec<Cid>,
+    pub blocks: Vec<Block>,
+    index: HashMap<Cid, usize>,
+}
+
+impl CarArchive {
+    /// Raw bytes of a block
+    pub fn get(&self, cid: &Cid) -> Option<&[u8]> {
+        self.index.get(cid).map(|&i| self.blocks[i].data.as_slice())
+    }
+
+    fn block(&self, cid: &Cid) -> Result<&[u8], CarError> {
+        self.get(cid).ok_or_else(|| CarError::MissingBlock(cid.to_string()))
+    }
+
+    /// First root of the archive
+    pub fn root(&self) -> Result<Cid, CarError> {
+        self.roots
+            .first()
+            .copied()
+            .ok_or_else(|| CarError::Malformed("no roots".to_string()))
+    }
+
+    /// Reassemble the content of a UnixFS file
+    pub fn read_file(&self, cid: &Cid) -> Result<Vec<u8>, CarError> {
+        let mut content = Vec::new();
+        self.append_file(cid, &mut content)?;
+        Ok(content)
+    }
+
+    fn append_file(&self, cid: &Cid, content: &mut Vec<u8>) -> Result<(), CarError> {
+        let block = self.block(cid)?;
+        match cid.codec() {
+            RAW => content.extend_from_slice(block),
+            DAG_PB => {
+                let node = decode_pb_node(block)?;
+                let unixfs = decode_unixfs(&node.data)?;
+                if !is_file_node(&unixfs) {
+                    return Err(CarError::Malformed(format!("{} is not a file", cid)));
+                }
+                content.extend_from_slice(&unixfs.data);
+                for link in &node.links {
+                    self.append_file(&link.cid, content)?;
+                }
+            }
+            codec => return Err(CarError::Malformed(format!("unsupported codec 0x{:x}", codec))),
+        }
+        Ok(())
+    }
+
+    /// Entries of a UnixFS directory (name -> CID); duplicate names are rejected
+    pub fn read_directory(&self, cid: &Cid) -> Result<BTreeMap<String, Cid>, CarError> {
+        let node = decode_pb_node(self.block(cid)?)?;
+        if !is_directory_node(&decode_unixfs(&node.data)?) {
+            return Err(CarError::Malformed(format!("{} is not a directory", cid)));
+        }
+        let mut entries = BTreeMap::new();
+        for link in node.links {
+            if entries.insert(link.name.clone(), link.cid).is_some() {
+                return Err(CarError::Malformed(format!("duplicate entry {:?} in directory {}", link.name, cid)));
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Deserialize the session manifest of a session archive
+    pub fn read_manifest<T: DeserializeOwned>(&self) -> Result<T, CarError> {
+        let entries = self.read_directory(&self.root()?)?;
+        let cid = entries
+            .get(MANIFEST_NAME)
+            .ok_or_else(|| CarError::MissingEntry(MANIFEST_NAME.to_string()))?;
+        Ok(serde_json::from_slice(&self.read_file(cid)?)?)
+    }
+}
+
+/// Collects the files of a session archive
+pub struct CarPacker {
+    options: ImportOptions,
+    entries: BTreeMap<String, (Cid, u64)>,
+    blocks: Vec<Block>,
+    seen: HashSet<Cid>,
+}
+
+impl Default for CarPacker {
+    fn default() -> Self {
+        Self::new(ImportOptions::cid_v1())
+    }
+}
+
+impl CarPacker {
+    /// Packer importing new files with the given settings
+    pub fn new(options: ImportOptions) -> Self {
+        Self {
+            options,
+            entries: BTreeMap::new(),
+            blocks: Vec::new(),
+            seen: HashSet::new(),
+        }
+    }
+
+    /// Add a file from memory, returning its CID
+    pub fn add_file(&mut self, name: &str, data: &[u8]) -> Cid {
+        let options = self.options.clone();
+        self.add_imported(name, data, &options)
+    }
+
+    /// Fetch referenced content from a backend and add it under its existing CID
+    ///
+    /// The content is re-imported with the settings implied by the CID and must
+    /// hash back to it, so the archive never carries content under a wrong address.
+    pub async fn add_referenced<B: StorageBackend>(
+        &mut self,
+        name: &str,
+        cid: &str,
+        backend: &B,
+    ) -> Result<(), CarError> {
+        let expected = Cid::try_from(cid).map_err(|_| CarError::InvalidCid(cid.to_string()))?;
+        let data = backend.get(cid).await?;
+        let actual = self.add_imported(name, &data, &ImportOptions::for_cid(&expected));
+        if actual != expected {
+            return Err(CarError::CidMismatch {
+                expected: expected.to_string(),
+                actual: actual.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn add_imported(&mut self, name: &str, data: &[u8], options: &ImportOptions) -> Cid {
+        let file = import(data, options);
+        for block in file.blocks {
+            if self.seen.insert(block.cid) {
+                self.blocks.push(block);
+            }
+        }
+        // UnixFS names are path segments
+        self.entries
+            .insert(name.replace('/', "_"), (file.root, file.cumulative_size));
+        file.root
+    }
+
+    /// Close the root directory and serialize the archive
+    pub fn finish(mut self, version: CarVersion) -> (Cid, Vec<u8>) {
+        let (root, _) = directory_block(&self.entries, self.options.cid_version);
+        let root_cid = root.cid;
+        if self.seen.insert(root.cid) {
+            self.blocks.push(root);
+        }
+        (root_cid, write_car(version, &[root_cid], &self.blocks))
+    }
+}
+
+/// Serialize blocks into a CAR file
+pub fn write_car(version: CarVersion, roots: &[Cid], blocks: &[Block]) -> Vec<u8> {
+    let mut payload = Vec::new();
+    let header = encode_header(roots);
+    encode_varint(&mut payload, header.len() as u64);
+    payload.extend_from_slice(&header);
+    for block in blocks {
+        let cid = block.cid.to_bytes();
+        encode_varint(&mut payload, (cid.len() + block.data.len()) as u64);
+        payload.extend_from_slice(&cid);
+        payload.extend_from_slice(&block.data);
+    }
+
+    match version {
+        CarVersion::V1 => payload,
+        CarVersion::V2 => {
+            let data_offset = (CARV2_PRAGMA.len() + CARV2_HEADER_LEN) as u64;
+            let mut car = Vec::with_capacity(data_offset as usize + payload.len());
+            car.extend_from_slice(&CARV2_PRAGMA);
+            car.extend_from_slice(&[0u8; 16]); // characteristics
+            car.extend_from_slice(&data_offset.to_le_bytes());
+            car.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+            car.extend_from_slice(&0u64.to_le_bytes()); // no index
+            car.extend_from_slice(&payload);
+            car
+        }
+    }
+}
+
+/// Parse a CARv1 or CARv2 file, verifying every block against its CID
+pub fn read_car(bytes: &[u8]) -> Result<CarArchive, CarError> {
+    if !bytes.starts_with(&CARV2_PRAGMA) {
+        return read_car_v1(bytes, CarVersion::V1);
+    }
+
+    let header = bytes
+        .get(CARV2_PRAGMA.len()..CARV2_PRAGMA.len() + CARV2_HEADER_LEN)
+        .ok_or_else(|| malformed("truncated CARv2 header"))?;
+    let read_u64 = |at: usize| u64::from_le_bytes(header[at..at + 8].try_into().unwrap());
+    let mut data_offset = usize::try_from(read_u64(16)).map_err(|_| malformed("CARv2 data offset"))?;
+    let payload = take_bytes(bytes, &mut data_offset, read_u64(24))
+        .ok_or_else(|| malformed("CARv2 data payload out of bounds"))?;
+    read_car_v1(payload, CarVersion::V2)
+}
+
+fn read_car_v1(bytes: &[u8], version: CarVersion) -> Result<CarArchive, CarError> {
+    let mut pos = 0;
+    let header_len = decode_varint(bytes, &mut pos).ok_or_else(|| malformed("header length"))?;
+    let header = take_bytes(bytes, &mut pos, header_len).ok_or_else(|| malformed("truncated header"))?;
+    let roots = decode_header(header)?;
+
+    let mut blocks = Vec::new();
+    let mut index = HashMap::new();
+    while pos < bytes.len() {
+        let len = decode_varint(bytes, &mut pos).ok_or_else(|| malformed("section length"))?;
+        let section = take_bytes(bytes, &mut pos, len).ok_or_else(|| malformed("truncated section"))?;
+
+        let mut reader = std::io::Cursor::new(section);
+        let cid = Cid::read_bytes(&mut reader).map_err(|_| malformed("section CID"))?;
+        let data = section[reader.position() as usize..].to_vec();
+        verify_block(&cid, &data)?;
+
+        index.entry(cid).or_insert(blocks.len());
+        blocks.push(Block { cid, data });
+    }
+
+    Ok(CarArchive {
+        version,
+        roots,
+        blocks,
+        index,
+    })
+}
+
+fn verify_block(cid: &Cid, data: &[u8]) -> Result<(), CarError> {
+    let code = Code::try_from(cid.hash().code())
+        .map_err(|_| malformed(&format!("unsupported hash in {}", cid)))?;
+    let digest = code.digest(data);
+    if &digest != cid.hash() {
+        let actual = match cid.version() {
+            Version::V0 => Cid::new_v0(digest).map(|c| c.to_string()),
+            Version::V1 => Ok(Cid::new_v1(cid.codec(), digest).to_string()),
+        };
+        return Err(CarError::CidMismatch {
+            expected: cid.to_string(),
+            actual: actual.unwrap_or_default(),
+        });
+    }
+    Ok(())
+}
+
+fn malformed(what: &str) -> CarError {
+    CarError::Malformed(what.to_string())
+}
+
+fn encode_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+/// DAG-CBOR head of a major type with an argument
+fn encode_cbor_head(buf: &mut Vec<u8>, major: u8, value: u64) {
+    let major = major << 5;
+    match value {
+        0..=23 => buf.push(major | value as u8),
+        24..=0xff => buf.extend_from_slice(&[major | 24, value as u8]),
+        0x100..=0xffff => {
+            buf.push(major | 25);
+            buf.extend_from_slice(&(value as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            buf.push(major | 26);
+            buf.extend_from_slice(&(value as u32).to_be_bytes());
+        }
+        _ => {
+            buf.push(major | 27);
+            buf.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+}
+
+/// `{"roots": [CID...], "version": 1}` in canonical DAG-CBOR
+fn encode_header(roots: &[Cid]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    encode_cbor_head(&mut buf, 5, 2);
+    encode_cbor_head(&mut buf, 3, 5);
+    buf.extend_from_slice(b"roots");
+    encode_cbor_head(&mut buf, 4, roots.len() as u64);
+    for root in roots {
+        let cid = root.to_bytes();
+        encode_cbor_head(&mut buf, 6, 42);
+        encode_cbor_head(&mut buf, 2, cid.len() as u64 + 1);
+        buf.push(0); // multibase identity prefix
+        buf.extend_from_slice(&cid);
+    }
+    encode_cbor_head(&mut buf, 3, 7);
+    buf.extend_from_slice(b"version");
+    encode_cbor_head(&mut buf, 0, 1);
+    buf
+}
+
+fn decode_cbor_head(buf: &[u8], pos: &mut usize) -> Result<(u8, u64), CarError> {
+    let initial = *buf.get(*pos).ok_or_else(|| malformed("truncated header"))?;
+    *pos += 1;
+    let (major, info) = (initial >> 5, initial & 0x1f);
+    let width = match info {
+        0..=23 => return Ok((major, info as u64)),
+        24 => 1,
+        25 => 2,
+        26 => 4,
+        27 => 8,
+        _ => return Err(malformed("indefinite-length header item")),
+    };
+    let bytes = take_bytes(buf, pos, width as u64).ok_or_else(|| malformed("truncated header"))?;
+    Ok((major, bytes.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64)))
+}
+
+fn decode_cbor_bytes<'a>(buf: &'a [u8], pos: &mut usize, major: u8) -> Result<&'a [u8], CarError> {
+    let (found, len) = decode_cbor_head(buf, pos)?;
+    if found != major {
+        return Err(malformed("unexpected header item"));
+    }
+    take_bytes(buf, pos, len).ok_or_else(|| malformed("truncated header"))
+}
+
+/// Read the roots of a CARv1 header, requiring version 1
+fn decode_header(buf: &[u8]) -> Result<Vec<Cid>, CarError> {
+    let mut pos = 0;
+    let (major, entries) = decode_cbor_head(buf, &mut pos)?;
+    if major != 5 {
+        return Err(malformed("header is not a map"));
+    }
+
+    let (mut roots, mut version) = (Vec::new(), None);
+    for _ in 0..entries {
+        match decode_cbor_bytes(buf, &mut pos, 3)? {
+            b"roots" => {
+                let (major, count) = decode_cbor_head(buf, &mut pos)?;
+                if major != 4 {
+                    return Err(malformed("roots is not a list"));
+                }
+                for _ in 0..count {
+                    if decode_cbor_head(buf, &mut pos)? != (6, 42) {
+                        return Err(malformed("root is not a CID"));
+                    }
+                    let bytes = decode_cbor_bytes(buf, &mut pos, 2)?;
+                    let cid = bytes
+                        .split_first()
+                        .filter(|(prefix, _)| **prefix == 0)
+                        .and_then(|(_, cid)| Cid::try_from(cid).ok())
+                        .ok_or_else(|| malformed("root CID"))?;
+                    roots.push(cid);
+                }
+            }
+            b"version" => match decode_cbor_head(buf, &mut pos)? {
+                (0, v) => version = Some(v),
+                _ => return Err(malformed("version is not an integer")),
+            },
+            _ => return Err(malformed("unknown header key")),
+        }
+    }
+
+    match version {
+        Some(1) => Ok(roots),
+        _ => Err(malformed("unsupported CARv1 header version")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::unixfs::file_cid;
+
+    #[test]
+    fn test_car_v1_and_v2_roundtrip() {
+        let mut roots = Vec::new();
+        for version in [CarVersion::V1, CarVersion::V2] {
+            let mut packer = CarPacker::default();
+            packer.add_file("shader-glow.wgsl", b"@fragment fn main() {}");
+            packer.add_file(MANIFEST_NAME, br#"{"session_id":"s1"}"#);
+            let (root, car) = packer.finish(version);
+
+            let archive = read_car(&car).unwrap();
+            assert_eq!(archive.version, version);
+            assert_eq!(archive.roots, vec![root]);
+
+            let entries = archive.read_directory(&root).unwrap();
+            let shader = archive.read_file(&entries["shader-glow.wgsl"]).unwrap();
+            assert_eq!(shader, b"@fragment fn main() {}");
+            let manifest: serde_json::Value = archive.read_manifest().unwrap();
+            assert_eq!(manifest["session_id"], "s1");
+            roots.push(root);
+        }
+        // The container version does not change the content address
+        assert_eq!(roots[0], roots[1]);
+    }
+
+    #[test]
+    fn test_multi_block_file_and_corruption() {
+        let frame = vec![3u8; 600_000];
+        let mut packer = CarPacker::new(ImportOptions::default());
+        let cid = packer.add_file("frame.png", &frame);
+        assert_eq!(cid, file_cid(&frame, &ImportOptions::default()));
+        let (_, mut car) = packer.finish(CarVersion::V1);
+
+        assert_eq!(read_car(&car).unwrap().read_file(&cid).unwrap(), frame);
+
+        let last = car.len() - 1;
+        car[last] ^= 0xff;
+        assert!(matches!(read_car(&car), Err(CarError::CidMismatch { .. })));
+    }
+
+    #[test]
+    fn test_oversized_lengths_are_rejected() {
+        let (_, car) = CarPacker::default().finish(CarVersion::V1);
+
+        // A section length near u64::MAX must not wrap the read offset
+        let mut huge = car.clone();
+        huge.extend_from_slice(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]);
+        assert!(matches!(read_car(&huge), Err(CarError::Malformed(_))));
+
+        let mut huge = vec![0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f];
+        huge.extend_from_slice(&car);
+        assert!(matches!(read_car(&huge), Err(CarError::Malformed(_))));
+
+        // Same for a dag-pb field length
+        let block = [0x0a, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01, 0x00];
+        assert!(decode_pb_node(&block).is_err());
+    }
+
+    #[test]
+    fn test_duplicate_directory_entries_are_rejected() {
+        let mut packer = CarPacker::default();
+        packer.add_file("a", b"first");
+        packer.add_file("b", b"second");
+        let (root, car) = packer.finish(CarVersion::V1);
+        let archive = read_car(&car).unwrap();
+
+        // Rename entry "b" to "a" and re-address the directory block
+        let mut block = archive.get(&root).unwrap().to_vec();
+        let at = block.windows(3).position(|w| w == [0x12, 0x01, b'b']).unwrap();
+        block[at + 2] = b'a';
+        let cid = Cid::new_v0(Code::Sha2_256.digest(&block)).unwrap();
+        let mut blocks = archive.blocks.clone();
+        blocks.push(Block { cid, data: block });
+
+        let archive = read_car(&write_car(CarVersion::V1, &[cid], &blocks)).unwrap();
+        assert!(matches!(archive.read_directory(&cid), Err(CarError::Malformed(_))));
+    }
+}
@@ -0,0 +1,319 @@
+//! Chunkers - split file content into UnixFS leaves the way `ipfs add` does
+//!
+//! `size-<bytes>` cuts fixed-size blocks. `rabin-<min>-<avg>-<max>` is a port of the
+//! content-defined chunker Kubo uses (restic's rabin fingerprint over a 16-byte window).
+
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Default chunk size of `ipfs add` (`size-262144`)
+pub const DEFAULT_CHUNK_SIZE: usize = 262_144;
+
+/// Irreducible polynomial Kubo's rabin chunker is seeded with
+const RABIN_POLY: u64 = 17_437_180_132_763_653;
+const RABIN_WINDOW: usize = 16;
+
+/// Error parsing a Kubo chunker string
+#[derive(Debug, Error)]
+#[error("invalid chunker: {0}")]
+pub struct InvalidChunker(pub String);
+
+/// How content is split into leaves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chunker {
+    FixedSize(usize),
+    Rabin { min: usize, avg: usize, max: usize },
+}
+
+impl Default for Chunker {
+    fn default() -> Self {
+        Chunker::FixedSize(DEFAULT_CHUNK_SIZE)
+    }
+}
+
+impl Chunker {
+    /// Rabin chunker with Kubo's default bounds around `avg`
+    pub fn rabin(avg: usize) -> Self {
+        Chunker::Rabin {
+            min: avg / 3,
+            avg,
+            max: avg + avg / 2,
+        }
+    }
+
+    /// Split a complete buffer into chunks
+    pub fn split(&self, data: &[u8]) -> Vec<Vec<u8>> {
+        let mut splitter = self.splitter();
+        let mut chunks = Vec::new();
+        splitter.push(data, &mut chunks);
+        chunks.extend(splitter.finish());
+        chunks
+    }
+
+    pub(crate) fn splitter(&self) -> Splitter {
+        match *self {
+            Chunker::FixedSize(size) => Splitter::Fixed {
+                size: size.max(1),
+                buf: Vec::new(),
+            },
+            Chunker::Rabin { min, avg, max } => Splitter::Rabin(Box::new(Rabin::new(min, avg, max))),
+        }
+    }
+}
+
+impl FromStr for Chunker {
+    type Err = InvalidChunker;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidChunker(s.to_string());
+        let parse = |n: &str| n.parse::<usize>().map_err(|_| invalid());
+
+        if let Some(size) = s.strip_prefix("size-") {
+            let size = parse(size)?;
+            return if size == 0 { Err(invalid()) } else { Ok(Chunker::FixedSize(size)) };
+        }
+
+        let parts: Vec<&str> = s.split('-').collect();
+        match parts.as_slice() {
+            ["rabin"] => Ok(Chunker::rabin(DEFAULT_CHUNK_SIZE)),
+            ["rabin", avg] => Ok(Chunker::rabin(parse(avg)?)),
+            ["rabin", min, avg, max] => {
+                let (min, avg, max) = (parse(min)?, parse(avg)?, parse(max)?);
+                if min < RABIN_WINDOW || min > avg || avg > max {
+                    return Err(invalid());
+                }
+                Ok(Chunker::Rabin { min, avg, max })
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Chunker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Chunker::FixedSize(size) => write!(f, "size-{}", size),
+            Chunker::Rabin { min, avg, max } => write!(f, "rabin-{}-{}-{}", min, avg, max),
+        }
+    }
+}
+
+/// Incremental splitter fed with arbitrary slices
+pub(crate) enum Splitter {
+    Fixed { size: usize, buf: Vec<u8> },
+    Rabin(Box<Rabin>),
+}
+
+impl Splitter {
+    /// Feed data, appending every completed chunk to `chunks`
+    pub(crate) fn push(&mut self, data: &[u8], chunks: &mut Vec<Vec<u8>>) {
+        match self {
+            Splitter::Fixed { size, buf } => {
+                buf.extend_from_slice(data);
+                if buf.len() >= *size {
+                    let mut rest = buf.as_slice();
+                    while rest.len() >= *size {
+                        chunks.push(rest[..*size].to_vec());
+                        rest = &rest[*size..];
+                    }
+                    *buf = rest.to_vec();
+                }
+            }
+            Splitter::Rabin(rabin) => rabin.push(data, chunks),
+        }
+    }
+
+    /// Trailing partial chunk, if any
+    pub(crate) fn finish(&mut self) -> Option<Vec<u8>> {
+        let buf = match self {
+            Splitter::Fixed { buf, .. } => std::mem::take(buf),
+            Splitter::Rabin(rabin) => std::mem::take(&mut rabin.current),
+        };
+        (!buf.is_empty()).then_some(buf)
+    }
+}
+
+/// Rabin fingerprint chunker state
+pub(crate) struct Rabin {
+    out_table: [u64; 256],
+    mod_table: [u64; 256],
+    pol_shift: u32,
+    mask: u64,
+    min: usize,
+    max: usize,
+    window: [u8; RABIN_WINDOW],
+    wpos: usize,
+    digest: u64,
+    pre: usize,
+    current: Vec<u8>,
+}
+
+impl Rabin {
+    fn new(min: usize, avg: usize, max: usize) -> Self {
+        let degree = deg(RABIN_POLY) as u32;
+
+        // out_table[b] = H(b || 0 ... 0) slides byte b out of the window
+        let mut out_table = [0u64; 256];
+        for (b, out) in out_table.iter_mut().enumerate() {
+            let mut h = append_byte(0, b as u8);
+            for _ in 0..RABIN_WINDOW - 1 {
+                h = append_byte(h, 0);
+            }
+            *out = h;
+        }
+
+        // mod_table[b] reduces the 8 bits shifted above the polynomial's degree
+        let mut mod_table = [0u64; 256];
+        for (b, m) in mod_table.iter_mut().enumerate() {
+            let high = (b as u64) << degree;
+            *m = pol_mod(high, RABIN_POLY) | high;
+        }
+
+        let avg_bits = usize::BITS - 1 - avg.max(1).leading_zeros();
+        let mut rabin = Self {
+            out_table,
+            mod_table,
+            pol_shift: degree - 8,
+            mask: (1u64 << avg_bits) - 1,
+            min,
+            max: max.max(1),
+            window: [0; RABIN_WINDOW],
+            wpos: 0,
+            digest: 0,
+            pre: 0,
+            current: Vec::new(),
+        };
+        rabin.reset();
+        rabin
+    }
+
+    fn reset(&mut self) {
+        self.window = [0; RABIN_WINDOW];
+        self.wpos = 0;
+        self.digest = 0;
+        self.slide(1);
+        // Bytes before min - window can never end a chunk, so they are not hashed
+        self.pre = self.min.saturating_sub(RABIN_WINDOW);
+    }
+
+    fn slide(&mut self, b: u8) {
+        let out = self.window[self.wpos];
+        self.window[self.wpos] = b;
+        self.digest ^= self.out_table[out as usize];
+        self.wpos = (self.wpos + 1) % RABIN_WINDOW;
+
+        let index = (self.digest >> self.pol_shift) as usize;
+        self.digest = (self.digest << 8) | b as u64;
+        self.digest ^= self.mod_table[index];
+    }
+
+    fn push(&mut self, data: &[u8], chunks: &mut Vec<Vec<u8>>) {
+        for &b in data {
+            self.current.push(b);
+            if self.pre > 0 {
+                self.pre -= 1;
+                continue;
+            }
+
+            self.slide(b);
+            let len = self.current.len();
+            if len < self.min {
+                continue;
+            }
+            if self.digest & self.mask == 0 || len >= self.max {
+                chunks.push(std::mem::take(&mut self.current));
+                self.reset();
+            }
+        }
+    }
+}
+
+/// Degree of a GF(2) polynomial
+fn deg(x: u64) -> i32 {
+    63 - x.leading_zeros() as i32
+}
+
+/// GF(2) polynomial remainder
+fn pol_mod(mut x: u64, d: u64) -> u64 {
+    while x != 0 && deg(x) >= deg(d) {
+        x ^= d << (deg(x) - deg(d));
+    }
+    x
+}
+
+fn append_byte(hash: u64, b: u8) -> u64 {
+    pol_mod((hash << 8) | b as u64, RABIN_POLY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pseudo_random(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                (state >> 33) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_kubo_chunker_strings() {
+        assert_eq!("size-1024".parse::<Chunker>().unwrap(), Chunker::FixedSize(1024));
+        assert_eq!("rabin".parse::<Chunker>().unwrap(), Chunker::rabin(DEFAULT_CHUNK_SIZE));
+        assert_eq!(
+            "rabin-128-256-512".parse::<Chunker>().unwrap(),
+            Chunker::Rabin { min: 128, avg: 256, max: 512 }
+        );
+        assert!("size-0".parse::<Chunker>().is_err());
+        assert!("rabin-8-256-512".parse::<Chunker>().is_err());
+        assert!("buzhash".parse::<Chunker>().is_err());
+    }
+
+    #[test]
+    fn test_fixed_size_chunks() {
+        let chunks = Chunker::FixedSize(4).split(b"0123456789");
+        assert_eq!(chunks, vec![b"0123".to_vec(), b"4567".to_vec(), b"89".to_vec()]);
+        assert!(Chunker::FixedSize(4).split(b"").is_empty());
+    }
+
+    #[test]
+    fn test_rabin_tables_match_go_ipfs() {
+        // Entries of the lookup tables go-ipfs ships precomputed for its rabin chunker
+        let rabin = Rabin::new(87_381, 262_144, 393_216);
+        for (b, out, m) in [
+            (1, 0x17fa63217c2ad7, 0x3df305dfb2a805),
+            (2, 0x1207c39d4afdab, 0x46150e60d7f80f),
+            (128, 0x176c16210a4a24, 0x10113dbd770e13e2),
+            (255, 0x171e3aded86a75, 0x1fe1d6d65a0be2bc),
+        ] {
+            assert_eq!(rabin.out_table[b], out, "out_table[{}]", b);
+            assert_eq!(rabin.mod_table[b], m, "mod_table[{}]", b);
+        }
+        assert_eq!(rabin.pol_shift, 45);
+        assert_eq!(rabin.mask, (1 << 18) - 1);
+    }
+
+    #[test]
+    fn test_rabin_bounds_and_resync() {
+        let chunker = Chunker::Rabin { min: 256, avg: 1024, max: 4096 };
+        let data = pseudo_random(64 * 1024, 7);
+        let chunks = chunker.split(&data);
+
+        assert_eq!(chunks.concat(), data);
+        assert!(chunks.len() > 4);
+        for chunk in &chunks[..chunks.len() - 1] {
+            assert!(chunk.len() >= 256 && chunk.len() <= 4096);
+        }
+
+        // Content-defined cuts: a prefix insert only disturbs the chunks around it
+        let mut shifted = b"inserted prefix".to_vec();
+        shifted.extend_from_slice(&data);
+        let shifted_chunks = chunker.split(&shifted);
+        let shared = chunks.iter().filter(|c| shifted_chunks.contains(c)).count();
+        assert!(shared >= chunks.len() - 2);
+    }
+}
@@ -0,0 +1,199 @@
+//! In-process fake of the Kubo HTTP RPC API so tests run without a daemon
+//!
+//! Implements `add`, `cat`, `files/stat`, `pin/add`, `pin/rm` and `pin/ls` with Kubo's response and error shapes.
+
+use crate::ipfs_client::IpfsClient;
+use crate::unixfs::{file_cid, ImportOptions};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+#[derive(Default)]
+struct State {
+    blocks: HashMap<String, Vec<u8>>,
+    pins: HashSet<String>,
+}
+
+/// Fake Kubo daemon listening on an ephemeral localhost port
+pub struct FakeKubo {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl FakeKubo {
+    /// Start the fake daemon on the current tokio runtime
+    pub async fn start() -> Self {
+        let state = Arc::new(Mutex::new(State::default()));
+        let service_state = state.clone();
+        let make_service = make_service_fn(move |_| {
+            let state = service_state.clone();
+            async move { Ok::<_, Infallible>(service_fn(move |req| handle(state.clone(), req))) }
+        });
+
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service);
+        let addr = server.local_addr();
+        let (shutdown, stopped) = oneshot::channel::<()>();
+        tokio::spawn(server.with_graceful_shutdown(async {
+            stopped.await.ok();
+        }));
+
+        Self {
+            addr,
+            state,
+            shutdown: Some(shutdown),
+        }
+    }
+
+    /// Client pointed at this daemon
+    pub fn client(&self) -> IpfsClient {
+        IpfsClient::new(self.addr.ip().to_string(), self.addr.port())
+    }
+
+    /// Drop every pin, keeping the content
+    pub fn unpin_all(&self) {
+        self.state.lock().unwrap().pins.clear();
+    }
+
+    /// Whether the daemon holds a pin for the CID
+    pub fn is_pinned(&self, cid: &str) -> bool {
+        self.state.lock().unwrap().pins.contains(cid)
+    }
+}
+
+impl Drop for FakeKubo {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+        }
+    }
+}
+
+async fn handle(state: Arc<Mutex<State>>, req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    if req.method() != Method::POST {
+        return Ok(reply(StatusCode::METHOD_NOT_ALLOWED, "405 - Method Not Allowed".into()));
+    }
+
+    let path = req.uri().path().to_string();
+    let arg = query_arg(req.uri().query().unwrap_or_default());
+    let content_type = req
+        .headers()
+        .get(hyper::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let body = hyper::body::to_bytes(req.into_body()).await.unwrap_or_default();
+
+    let mut state = state.lock().unwrap();
+    let response = match (path.as_str(), arg) {
+        ("/api/v0/add", _) => match multipart_file(&body, &content_type) {
+            Some(data) => {
+                let cid = file_cid(data, &ImportOptions::default()).to_string();
+                let size = data.len();
+                state.blocks.insert(cid.clone(), data.to_vec());
+                state.pins.insert(cid.clone());
+                json(format!(r#"{{"Name":"data","Hash":"{}","Size":"{}"}}"#, cid, size))
+            }
+            None => error("file argument 'path' is required"),
+        },
+        ("/api/v0/cat", Some(cid)) => match state.blocks.get(&cid) {
+            Some(data) => reply(StatusCode::OK, data.clone().into()),
+            None => error(&format!("block was not found locally (offline): {}", cid)),
+        },
+        ("/api/v0/pin/add", Some(cid)) => {
+            if state.blocks.contains_key(&cid) {
+                state.pins.insert(cid.clone());
+                json(format!(r#"{{"Pins":["{}"]}}"#, cid))
+            } else {
+                error(&format!("pin: block was not found locally (offline): {}", cid))
+            }
+        }
+        ("/api/v0/files/stat", Some(path)) => {
+            let cid = path.trim_start_matches("/ipfs/");
+            match state.blocks.get(cid) {
+                Some(data) => json(format!(
+                    r#"{{"Hash":"{}","Size":{},"CumulativeSize":{},"Blocks":0,"Type":"file"}}"#,
+                    cid,
+                    data.len(),
+                    data.len()
+                )),
+                None => error(&format!("block was not found locally (offline): {}", cid)),
+            }
+        }
+        ("/api/v0/pin/rm", Some(cid)) => {
+            if state.pins.remove(&cid) {
+                json(format!(r#"{{"Pins":["{}"]}}"#, cid))
+            } else {
+                error("not pinned or pinned indirectly")
+            }
+        }
+        ("/api/v0/pin/ls", Some(cid)) => {
+            if state.pins.contains(&cid) {
+                json(format!(r#"{{"Keys":{{"{}":{{"Type":"recursive"}}}}}}"#, cid))
+            } else {
+                error(&format!("path '{}' is not pinned", cid))
+            }
+        }
+        ("/api/v0/pin/ls", None) => {
+            let keys: Vec<String> = state
+                .pins
+                .iter()
+                .map(|cid| format!(r#""{}":{{"Type":"recursive"}}"#, cid))
+                .collect();
+            json(format!(r#"{{"Keys":{{{}}}}}"#, keys.join(",")))
+        }
+        ("/api/v0/cat" | "/api/v0/files/stat" | "/api/v0/pin/add" | "/api/v0/pin/rm", None) => error("argument \"ipfs-path\" is required"),
+        _ => reply(StatusCode::NOT_FOUND, "404 page not found".into()),
+    };
+    Ok(response)
+}
+
+fn reply(status: StatusCode, body: Body) -> Response<Body> {
+    Response::builder().status(status).body(body).unwrap()
+}
+
+fn json(body: String) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .body(body.into())
+        .unwrap()
+}
+
+/// Kubo reports command errors as HTTP 500 with a JSON body
+fn error(message: &str) -> Response<Body> {
+    let body = serde_json::json!({ "Message": message, "Code": 0, "Type": "error" });
+    Response::builder()
+        .status(StatusCode::INTERNAL_SERVER_ERROR)
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .body(body.to_string().into())
+        .unwrap()
+}
+
+fn query_arg(query: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == "arg")
+        .map(|(_, value)| value.replace("%2F", "/"))
+}
+
+/// Extract the first file part of a multipart/form-data body
+fn multipart_file<'a>(body: &'a [u8], content_type: &str) -> Option<&'a [u8]> {
+    let boundary = content_type.split("boundary=").nth(1)?.trim_matches('"');
+    let delimiter = format!("\r\n--{}", boundary);
+    let headers_end = find(body, b"\r\n\r\n", 0)? + 4;
+    let content_end = find(body, delimiter.as_bytes(), headers_end)?;
+    Some(&body[headers_end..content_end])
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack[from..]
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|pos| pos + from)
+}
@@ -1,43 +1,249 @@
 //! IPFS Client wrapper for all storage operations
+//!
+//! Talks to a Kubo daemon over its HTTP RPC API (`/api/v0/*`).
 
-use std::error::Error;
+use reqwest::multipart::{Form, Part};
+use serde::Deserialize;
+use std::collections::HashMap;
+use thiserror::Error;
 
-/// Simplified IPFS client for adding JSON data
+/// Errors returned by the IPFS client
+#[derive(Debug, Error)]
+pub enum IpfsError {
+    #[error("HTTP request to IPFS daemon failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("IPFS daemon returned {status}: {message}")]
+    Api { status: u16, message: String },
+
+    #[error("unexpected response from IPFS daemon: {0}")]
+    InvalidResponse(String),
+
+    #[error("serialization failed: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+/// Error body returned by Kubo on non-2xx responses
+#[derive(Deserialize)]
+struct KuboError {
+    #[serde(rename = "Message")]
+    message: String,
+}
+
+/// Response of `/api/v0/add`
+#[derive(Deserialize)]
+struct AddResponse {
+    #[serde(rename = "Hash")]
+    hash: String,
+}
+
+/// Response of `/api/v0/pin/ls`
+#[derive(Deserialize)]
+struct PinLsResponse {
+    #[serde(rename = "Keys", default)]
+    keys: HashMap<String, PinLsEntry>,
+}
+
+#[derive(Deserialize)]
+struct PinLsEntry {
+    #[serde(rename = "Type")]
+    pin_type: String,
+}
+
+/// Response of `/api/v0/files/stat`
+#[derive(Debug, Clone, Deserialize)]
+pub struct FileStat {
+    #[serde(rename = "Hash")]
+    pub cid: String,
+    #[serde(rename = "Size")]
+    pub size: u64,
+    #[serde(rename = "CumulativeSize")]
+    pub cumulative_size: u64,
+    #[serde(rename = "Type")]
+    pub file_type: String,
+}
+
+/// IPFS client for a Kubo daemon's HTTP RPC API
 #[derive(Clone)]
 pub struct IpfsClient {
     pub host: String,
     pub port: u16,
+    http: reqwest::Client,
 }
 
 impl IpfsClient {
     /// Create new IPFS client
     pub fn new(host: String, port: u16) -> Self {
-        Self { host, port }
+        Self {
+            host,
+            port,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Base URL of the RPC API
+    pub fn api_url(&self) -> String {
+        format!("http://{}:{}/api/v0", self.host, self.port)
     }
 
     /// Add JSON string to IPFS (returns CID)
-    pub async fn add_json(&self, json: &str) -> Result<String, Box<dyn Error>> {
-        // In real implementation, this would use ipfs_api or HTTP API
-        // For now, return a mock CID
-        let cid = format!("Qm{:x}", json.len());
-        Ok(cid)
+    pub async fn add_json(&self, json: &str) -> Result<String, IpfsError> {
+        self.add_bytes(json.as_bytes()).await
     }
 
-    /// Add binary data to IPFS
-    pub async fn add_bytes(&self, data: &[u8]) -> Result<String, Box<dyn Error>> {
-        let cid = format!("Qm{:x}", data.len());
-        Ok(cid)
+    /// Add binary data to IPFS (returns CID); content is pinned by the daemon
+    pub async fn add_bytes(&self, data: &[u8]) -> Result<String, IpfsError> {
+        let form = Form::new().part("file", Part::bytes(data.to_vec()).file_name("data"));
+        let response = self
+            .http
+            .post(format!("{}/add", self.api_url()))
+            .multipart(form)
+            .send()
+            .await?;
+        let body = Self::check(response).await?.text().await?;
+
+        // Kubo streams one JSON object per added file
+        let line = body
+            .lines()
+            .rev()
+            .find(|line| !line.trim().is_empty())
+            .ok_or_else(|| IpfsError::InvalidResponse("empty add response".to_string()))?;
+        let added: AddResponse = serde_json::from_str(line)?;
+        Ok(added.hash)
     }
 
     /// Pin content by CID
-    pub async fn pin(&self, _cid: &str) -> Result<(), Box<dyn Error>> {
-        // Mock implementation
+    pub async fn pin(&self, cid: &str) -> Result<(), IpfsError> {
+        self.rpc("pin/add", &[("arg", cid)]).await?;
+        Ok(())
+    }
+
+    /// Remove the recursive pin for a CID
+    pub async fn unpin(&self, cid: &str) -> Result<(), IpfsError> {
+        self.rpc("pin/rm", &[("arg", cid)]).await?;
         Ok(())
     }
 
+    /// List recursive and direct pins, optionally restricted to one CID (CID -> pin type)
+    pub async fn pin_ls(&self, cid: Option<&str>) -> Result<HashMap<String, String>, IpfsError> {
+        let mut query = vec![("type", "all")];
+        if let Some(cid) = cid {
+            query.push(("arg", cid));
+        }
+        let response: PinLsResponse = self.rpc("pin/ls", &query).await?.json().await?;
+        Ok(response
+            .keys
+            .into_iter()
+            .map(|(cid, entry)| (cid, entry.pin_type))
+            .collect())
+    }
+
+    /// Check whether a CID is pinned
+    pub async fn is_pinned(&self, cid: &str) -> Result<bool, IpfsError> {
+        match self.pin_ls(Some(cid)).await {
+            Ok(pins) => Ok(!pins.is_empty()),
+            Err(IpfsError::Api { message, .. }) if message.contains("not pinned") => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Read content by CID
+    pub async fn cat(&self, cid: &str) -> Result<Vec<u8>, IpfsError> {
+        let bytes = self.rpc("cat", &[("arg", cid)]).await?.bytes().await?;
+        Ok(bytes.to_vec())
+    }
+
     /// Get content by CID
-    pub async fn get(&self, _cid: &str) -> Result<Vec<u8>, Box<dyn Error>> {
-        // Mock implementation
-        Ok(Vec::new())
+    pub async fn get(&self, cid: &str) -> Result<Vec<u8>, IpfsError> {
+        self.cat(cid).await
+    }
+
+    /// Size and type of the DAG behind a CID
+    pub async fn files_stat(&self, cid: &str) -> Result<FileStat, IpfsError> {
+        let path = format!("/ipfs/{}", cid);
+        let stat = self.rpc("files/stat", &[("arg", &path)]).await?.json().await?;
+        Ok(stat)
+    }
+
+    /// Call an RPC endpoint; Kubo expects POST for every command
+    async fn rpc(&self, command: &str, query: &[(&str, &str)]) -> Result<reqwest::Response, IpfsError> {
+        let response = self
+            .http
+            .post(format!("{}/{}", self.api_url(), command))
+            .query(query)
+            .send()
+            .await?;
+        Self::check(response).await
+    }
+
+    /// Turn non-2xx responses into `IpfsError::Api`
+    async fn check(response: reqwest::Response) -> Result<reqwest::Response, IpfsError> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let body = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<KuboError>(&body)
+            .map(|e| e.message)
+            .unwrap_or(body);
+        Err(IpfsError::Api {
+            status: status.as_u16(),
+            message,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_kubo::FakeKubo;
+
+    #[tokio::test]
+    async fn test_add_cat_roundtrip() {
+        let daemon = FakeKubo::start().await;
+        let client = daemon.client();
+
+        let cid = client.add_bytes(b"fractal frame").await.unwrap();
+        assert_eq!(client.cat(&cid).await.unwrap(), b"fractal frame");
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_pin_add_and_ls() {
+        let daemon = FakeKubo::start().await;
+        let client = daemon.client();
+
+        let cid = client.add_json(r#"{"session":"s1"}"#).await.unwrap();
+        daemon.unpin_all();
+        assert!(!client.is_pinned(&cid).await.unwrap());
+
+        client.pin(&cid).await.unwrap();
+        assert!(daemon.is_pinned(&cid));
+        assert!(client.is_pinned(&cid).await.unwrap());
+        assert_eq!(client.pin_ls(None).await.unwrap()[&cid], "recursive");
+
+        client.unpin(&cid).await.unwrap();
+        assert!(!daemon.is_pinned(&cid));
+    }
+
+    #[tokio::test]
+    async fn test_files_stat() {
+        let daemon = FakeKubo::start().await;
+        let client = daemon.client();
+
+        let cid = client.add_bytes(&[7u8; 1024]).await.unwrap();
+        let stat = client.files_stat(&cid).await.unwrap();
+        assert_eq!(stat.cid, cid);
+        assert_eq!(stat.size, 1024);
+    }
+
+    #[tokio::test]
+    async fn test_missing_content_is_api_error() {
+        let daemon = FakeKubo::start().await;
+        let client = daemon.client();
+
+        match client.cat("bafkreimissing").await {
+            Err(IpfsError::Api { status, .. }) => assert_eq!(status, 500),
+            other => panic!("expected API error, got {:?}", other.map(|b| b.len())),
+        }
+    }
+}
@@ -5,7 +5,6 @@
 //! Enhanced with Filecoin-specific features for decentralized storage with persistence guarantees.
 
 use cid::Cid;
-use multihash::{Code, MultihashDigest};
 use serde::{Deserialize, Serialize};
 use chrono::Utc;
 use std::collections::HashMap;
@@ -14,16 +13,34 @@ mod ipfs_client;
 mod nuwe_storage;
 mod modurust_storage;
 mod neuroemotive_storage;
+mod storage_backend;
+mod chunker;
+mod unixfs;
+mod car;
+mod pin_manager;
+mod near_reporter;
+mod patch_access;
+mod patch_versions;
+#[cfg(test)]
+mod fake_kubo;
 
 pub use ipfs_client::*;
 pub use nuwe_storage::*;
 pub use modurust_storage::*;
 pub use neuroemotive_storage::*;
-
-/// IPFS persistence layer for creative data
+pub use storage_backend::*;
+pub use chunker::*;
+pub use unixfs::*;
+pub use car::*;
+pub use pin_manager::*;
+pub use near_reporter::*;
+pub use patch_access::*;
+pub use patch_versions::*;
+
+/// IPFS persistence layer for creative data, backed by any `StorageBackend`
 #[derive(Clone)]
-pub struct IpfsPersistenceLayer {
-    client: IpfsClient,
+pub struct IpfsPersistenceLayer<B = IpfsClient> {
+    backend: B,
     gateway_url: String,
 }
 
@@ -48,57 +65,60 @@ pub struct CreativeAsset {
 }
 
 impl IpfsPersistenceLayer {
-    /// Create a new IPFS persistence layer
+    /// Create a new IPFS persistence layer talking to a Kubo daemon
     pub fn new(host: &str, port: u16) -> Self {
         Self {
-            client: IpfsClient::new(host.to_string(), port),
+            backend: IpfsClient::new(host.to_string(), port),
             gateway_url: format!("http://{}:{}", host, port),
         }
     }
+}
 
-    /// Generate CID from creative data
-    pub fn generate_cid(&self, data: &[u8]) -> Result<Cid, Box<dyn std::error::Error>> {
-        // Create SHA-256 hash
-        let hash = Code::Sha2_256.digest(data);
+impl<B: StorageBackend> IpfsPersistenceLayer<B> {
+    /// Create a persistence layer on top of any storage backend
+    pub fn with_backend(backend: B, gateway_url: &str) -> Self {
+        Self {
+            backend,
+            gateway_url: gateway_url.to_string(),
+        }
+    }
 
-        // Create CID v1 with raw codec
-        let cid = Cid::new_v1(0x55, hash);
+    /// Underlying storage backend
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
 
-        Ok(cid)
+    /// Generate the CID `ipfs add` would assign to creative data
+    pub fn generate_cid(&self, data: &[u8]) -> Result<Cid, Box<dyn std::error::Error>> {
+        Ok(file_cid(data, &ImportOptions::default()))
     }
 
     /// Add data to IPFS and return CID
-    pub async fn add_to_ipfs(&self, data: Vec<u8>) -> Result<String, Box<dyn std::error::Error>> {
-        let cid = self.client.add_bytes(&data).await?;
-        Ok(cid)
+    pub async fn add_to_ipfs(&self, data: Vec<u8>) -> Result<String, StorageError> {
+        self.backend.put(&data).await
     }
 
-    /// Pin content to IPFS with Filecoin storage information
-    pub async fn pin_content(&self, cid: &str) -> Result<PinResponse, Box<dyn std::error::Error>> {
-        self.client.pin(cid).await?;
-
-        // Add Filecoin storage provider information
-        let storage_providers = Some(vec![
-            "f0123456".to_string(), // Example Filecoin storage provider IDs
-            "f0789012".to_string(),
-        ]);
+    /// Pin content and report its stored size
+    pub async fn pin_content(&self, cid: &str) -> Result<PinResponse, StorageError> {
+        self.backend.pin(cid).await?;
+        let stat = self.backend.stat(cid).await?;
 
+        // Filecoin providers are only known once a deal is made; `PinManager` tracks replicas
         Ok(PinResponse {
             cid: cid.to_string(),
-            size: 0, // Would need to get actual size
+            size: stat.size,
             timestamp: Utc::now().to_rfc3339(),
-            storage_providers,
+            storage_providers: None,
         })
     }
 
     /// Retrieve data from IPFS by CID
-    pub async fn get_from_ipfs(&self, cid: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-        let data = self.client.get(cid).await?;
-        Ok(data)
+    pub async fn get_from_ipfs(&self, cid: &str) -> Result<Vec<u8>, StorageError> {
+        self.backend.get(cid).await
     }
 
     /// Create and upload creative asset
-    pub async fn upload_creative_asset(&self, asset: CreativeAsset) -> Result<(String, PinResponse), Box<dyn std::error::Error>> {
+    pub async fn upload_creative_asset(&self, asset: CreativeAsset) -> Result<(String, PinResponse), StorageError> {
         // Serialize asset data
         let asset_json = serde_json::to_vec(&asset)?;
 
@@ -144,8 +164,10 @@ impl IpfsPersistenceLayer {
     
     /// Verify data integrity by comparing CID
     pub fn verify_data_integrity(&self, data: &[u8], expected_cid: &str) -> Result<bool, Box<dyn std::error::Error>> {
-        let calculated_cid = self.generate_cid(data)?;
-        Ok(calculated_cid.to_string() == expected_cid)
+        let expected = Cid::try_from(expected_cid)?;
+        let mut verifier = CidVerifier::new(expected);
+        verifier.update(data);
+        Ok(verifier.finish())
     }
 }
 
@@ -187,10 +209,10 @@ pub fn create_creative_asset_with_traits(
 }
 
 /// Batch upload multiple assets
-pub async fn batch_upload_assets(
-    layer: &IpfsPersistenceLayer,
+pub async fn batch_upload_assets<B: StorageBackend>(
+    layer: &IpfsPersistenceLayer<B>,
     assets: Vec<CreativeAsset>
-) -> Result<Vec<(String, String, PinResponse)>, Box<dyn std::error::Error>> {
+) -> Result<Vec<(String, String, PinResponse)>, StorageError> {
     let mut results = Vec::new();
 
     for asset in assets {
@@ -246,6 +268,24 @@ mod tests {
         assert_eq!(layer.gateway_url, "http://localhost:5001");
     }
     
+    #[tokio::test]
+    async fn test_upload_creative_asset_to_memory_store() {
+        let layer = IpfsPersistenceLayer::with_backend(MemoryStore::new(), "https://ipfs.io");
+        let asset = create_creative_asset(
+            "Offline Art",
+            "Stored without a daemon",
+            vec![9, 9, 9],
+            "image/png",
+            serde_json::json!({})
+        );
+
+        let (cid, pin_response) = layer.upload_creative_asset(asset.clone()).await.unwrap();
+        assert_eq!(pin_response.size, serde_json::to_vec(&asset).unwrap().len() as u64);
+
+        let stored: CreativeAsset = serde_json::from_slice(&layer.get_from_ipfs(&cid).await.unwrap()).unwrap();
+        assert_eq!(stored.name, "Offline Art");
+    }
+
     #[test]
     fn test_data_integrity_verification() {
         let layer = IpfsPersistenceLayer::new("localhost", 5001);
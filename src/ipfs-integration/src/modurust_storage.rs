@@ -2,9 +2,8 @@
 //! 
 //! Handles storage of modular tools, patches, and configurations
 
-use crate::ipfs_client::IpfsClient;
+use crate::storage_backend::{StorageBackend, StorageError};
 use serde::{Deserialize, Serialize};
-use std::error::Error;
 
 /// MODURUST tool module
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -192,9 +191,9 @@ impl ModurustTool {
     }
 
     /// Store tool to IPFS
-    pub async fn store_to_ipfs(&self, client: &IpfsClient) -> Result<String, Box<dyn Error>> {
+    pub async fn store_to_ipfs<B: StorageBackend>(&self, backend: &B) -> Result<String, StorageError> {
         let json = serde_json::to_string_pretty(self)?;
-        client.add_json(&json).await
+        backend.put(json.as_bytes()).await
     }
 
     /// Get total asset size
@@ -244,9 +243,9 @@ impl ModurustPatch {
     }
 
     /// Store patch to IPFS
-    pub async fn store_to_ipfs(&self, client: &IpfsClient) -> Result<String, Box<dyn Error>> {
+    pub async fn store_to_ipfs<B: StorageBackend>(&self, backend: &B) -> Result<String, StorageError> {
         let json = serde_json::to_string_pretty(self)?;
-        client.add_json(&json).await
+        backend.put(json.as_bytes()).await
     }
 }
 
@@ -0,0 +1,370 @@
+//! On-chain pin status reporting
+//!
+//! `NearRpcReporter` signs `update_pin_status` function calls with the keeper's access
+//! key and submits them over NEAR JSON-RPC, waiting until they have executed. The
+//! transaction is borsh-encoded here with nearcore's `SignedTransaction` layout, so the
+//! pin manager does not pull in the nearcore crates.
+
+use crate::pin_manager::{PinError, PinStatusReporter, UpdatePinStatusArgs};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use borsh::{BorshDeserialize, BorshSerialize};
+use ed25519_dalek::{Signer, SigningKey};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::io::{self, Read, Write};
+use tokio::sync::Mutex;
+
+/// Gas attached to each `update_pin_status` call
+pub const UPDATE_PIN_STATUS_GAS: u64 = 30_000_000_000_000;
+
+/// `near_crypto::PublicKey`; only ed25519 keys are supported
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub(crate) enum PublicKey {
+    Ed25519([u8; 32]),
+}
+
+/// `near_crypto::Signature`
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub(crate) enum Signature {
+    Ed25519([u8; 64]),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub(crate) struct FunctionCallAction {
+    pub method_name: String,
+    pub args: Vec<u8>,
+    pub gas: u64,
+    pub deposit: u128,
+}
+
+/// `near_primitives::transaction::Action`, of which only function calls are sent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Action {
+    FunctionCall(FunctionCallAction),
+}
+
+/// Index of `FunctionCall` in nearcore's `Action` enum
+const FUNCTION_CALL_ACTION: u8 = 2;
+
+impl BorshSerialize for Action {
+    fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        match self {
+            Action::FunctionCall(call) => {
+                FUNCTION_CALL_ACTION.serialize(writer)?;
+                call.serialize(writer)
+            }
+        }
+    }
+}
+
+impl BorshDeserialize for Action {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> io::Result<Self> {
+        match u8::deserialize_reader(reader)? {
+            FUNCTION_CALL_ACTION => Ok(Action::FunctionCall(FunctionCallAction::deserialize_reader(reader)?)),
+            other => Err(io::Error::new(io::ErrorKind::InvalidData, format!("unsupported action {}", other))),
+        }
+    }
+}
+
+/// `near_primitives::transaction::Transaction` (V0)
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub(crate) struct Transaction {
+    pub signer_id: String,
+    pub public_key: PublicKey,
+    pub nonce: u64,
+    pub receiver_id: String,
+    pub block_hash: [u8; 32],
+    pub actions: Vec<Action>,
+}
+
+impl Transaction {
+    /// Hash the signature covers
+    pub fn hash(&self) -> [u8; 32] {
+        Sha256::digest(borsh::to_vec(self).expect("in-memory serialization")).into()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub(crate) struct SignedTransaction {
+    pub transaction: Transaction,
+    pub signature: Signature,
+}
+
+/// Reports pin status by calling `update_pin_status` on the storage contract
+///
+/// The signer must be the contract owner (or the content owner) and the key one of
+/// its full-access keys, or a function-call key for `update_pin_status`.
+pub struct NearRpcReporter {
+    url: String,
+    client: reqwest::Client,
+    contract_id: String,
+    signer_id: String,
+    key: SigningKey,
+    /// Last nonce used; also serializes submissions so nonces stay ordered
+    nonce: Mutex<u64>,
+}
+
+impl NearRpcReporter {
+    /// `secret_key` is in NEAR key-file form, `ed25519:<base58>`
+    pub fn new(
+        url: impl Into<String>,
+        contract_id: impl Into<String>,
+        signer_id: impl Into<String>,
+        secret_key: &str,
+    ) -> Result<Self, PinError> {
+        let bytes = secret_key
+            .strip_prefix("ed25519:")
+            .and_then(|key| bs58::decode(key).into_vec().ok())
+            .ok_or_else(|| PinError::Report("secret key must be ed25519:<base58>".to_string()))?;
+        // Key files hold the 32-byte seed followed by the public key
+        let seed: [u8; 32] = bytes
+            .get(..32)
+            .and_then(|seed| seed.try_into().ok())
+            .ok_or_else(|| PinError::Report("secret key is too short".to_string()))?;
+
+        Ok(Self {
+            url: url.into(),
+            client: reqwest::Client::new(),
+            contract_id: contract_id.into(),
+            signer_id: signer_id.into(),
+            key: SigningKey::from_bytes(&seed),
+            nonce: Mutex::new(0),
+        })
+    }
+
+    /// Access key the reporter signs with, as `ed25519:<base58>`
+    pub fn public_key(&self) -> String {
+        format!("ed25519:{}", bs58::encode(self.key.verifying_key().as_bytes()).into_string())
+    }
+
+    async fn rpc(&self, method: &str, params: Value) -> Result<Value, PinError> {
+        let request = json!({ "jsonrpc": "2.0", "id": "pin-manager", "method": method, "params": params });
+        let mut response: Value = self
+            .client
+            .post(&self.url)
+            .json(&request)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| PinError::Report(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| PinError::Report(e.to_string()))?;
+
+        if let Some(error) = response.get("error") {
+            return Err(PinError::Report(format!("{} failed: {}", method, error)));
+        }
+        Ok(response["result"].take())
+    }
+
+    /// Sign and submit one call, waiting for its execution outcome
+    async fn call(&self, method_name: &str, args: &impl serde::Serialize) -> Result<(), PinError> {
+        let mut last_nonce = self.nonce.lock().await;
+
+        let access_key = self
+            .rpc(
+                "query",
+                json!({
+                    "request_type": "view_access_key",
+                    "finality": "final",
+                    "account_id": self.signer_id,
+                    "public_key": self.public_key(),
+                }),
+            )
+            .await?;
+        let block_hash = access_key["block_hash"]
+            .as_str()
+            .and_then(|hash| bs58::decode(hash).into_vec().ok())
+            .and_then(|hash| <[u8; 32]>::try_from(hash).ok())
+            .ok_or_else(|| PinError::Report("access key query returned no block hash".to_string()))?;
+        // A transaction from the previous call may not be final yet, so never reuse its nonce
+        let nonce = access_key["nonce"].as_u64().unwrap_or_default().max(*last_nonce) + 1;
+
+        let transaction = Transaction {
+            signer_id: self.signer_id.clone(),
+            public_key: PublicKey::Ed25519(self.key.verifying_key().to_bytes()),
+            nonce,
+            receiver_id: self.contract_id.clone(),
+            block_hash,
+            actions: vec![Action::FunctionCall(FunctionCallAction {
+                method_name: method_name.to_string(),
+                args: serde_json::to_vec(args).map_err(|e| PinError::Report(e.to_string()))?,
+                gas: UPDATE_PIN_STATUS_GAS,
+                deposit: 0,
+            })],
+        };
+        let signature = Signature::Ed25519(self.key.sign(&transaction.hash()).to_bytes());
+        let signed = SignedTransaction { transaction, signature };
+        let encoded = BASE64.encode(borsh::to_vec(&signed).map_err(|e| PinError::Report(e.to_string()))?);
+
+        let outcome = self.rpc("broadcast_tx_commit", json!([encoded])).await?;
+        *last_nonce = nonce;
+        match outcome["status"].get("Failure") {
+            Some(failure) => Err(PinError::Report(format!("{} failed: {}", method_name, failure))),
+            None => Ok(()),
+        }
+    }
+}
+
+impl PinStatusReporter for NearRpcReporter {
+    async fn report(&self, update: UpdatePinStatusArgs) -> Result<(), PinError> {
+        self.call("update_pin_status", &update).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pin_manager::{PinManager, PinStatus, PinTarget, ReplicationPolicy, StorageProvider};
+    use crate::storage_backend::{MemoryStore, StorageBackend};
+    use ed25519_dalek::Verifier;
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Body, Request, Response, Server};
+    use std::convert::Infallible;
+    use std::net::SocketAddr;
+    use std::sync::{Arc, Mutex as StdMutex};
+
+    const CONTRACT: &str = "storage.testnet";
+    const KEEPER: &str = "keeper.testnet";
+
+    #[derive(Default)]
+    struct Chain {
+        nonce: u64,
+        /// Executed `update_pin_status` arguments
+        updates: Vec<UpdatePinStatusArgs>,
+        /// CIDs the contract does not know, so updating them panics
+        unknown: Vec<String>,
+    }
+
+    /// Fake RPC node holding one access key of the keeper and the storage contract
+    async fn start_node(key: PublicKey, chain: Arc<StdMutex<Chain>>) -> String {
+        let make_service = make_service_fn(move |_| {
+            let (key, chain) = (key.clone(), chain.clone());
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req: Request<Body>| {
+                    let (key, chain) = (key.clone(), chain.clone());
+                    async move {
+                        let body = hyper::body::to_bytes(req.into_body()).await.unwrap_or_default();
+                        let request: Value = serde_json::from_slice(&body).unwrap();
+                        let result = handle(&key, &mut chain.lock().unwrap(), &request);
+                        let response = match result {
+                            Ok(result) => json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }),
+                            Err(data) => json!({
+                                "jsonrpc": "2.0",
+                                "id": request["id"],
+                                "error": { "name": "HANDLER_ERROR", "code": -32000, "message": "Server error", "data": data }
+                            }),
+                        };
+                        Ok::<_, Infallible>(Response::new(Body::from(response.to_string())))
+                    }
+                }))
+            }
+        });
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service);
+        let url = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+        url
+    }
+
+    fn handle(key: &PublicKey, chain: &mut Chain, request: &Value) -> Result<Value, String> {
+        match request["method"].as_str() {
+            Some("query") => Ok(json!({
+                "nonce": chain.nonce,
+                "permission": "FullAccess",
+                "block_height": 1,
+                "block_hash": bs58::encode([7u8; 32]).into_string(),
+            })),
+            Some("broadcast_tx_commit") => {
+                let bytes = BASE64.decode(request["params"][0].as_str().unwrap()).unwrap();
+                let signed = SignedTransaction::try_from_slice(&bytes).map_err(|e| e.to_string())?;
+                let tx = &signed.transaction;
+                let (PublicKey::Ed25519(public), Signature::Ed25519(signature)) = (&tx.public_key, &signed.signature);
+                let verifying = ed25519_dalek::VerifyingKey::from_bytes(public).unwrap();
+                if &tx.public_key != key
+                    || verifying.verify(&tx.hash(), &ed25519_dalek::Signature::from_bytes(signature)).is_err()
+                {
+                    return Err("InvalidSignature".to_string());
+                }
+                if tx.signer_id != KEEPER || tx.receiver_id != CONTRACT || tx.block_hash != [7; 32] {
+                    return Err("InvalidTransaction".to_string());
+                }
+                if tx.nonce <= chain.nonce {
+                    return Err("InvalidNonce".to_string());
+                }
+                chain.nonce = tx.nonce;
+
+                let Action::FunctionCall(call) = &tx.actions[0];
+                let update: UpdatePinStatusArgs = serde_json::from_slice(&call.args).unwrap();
+                if call.method_name != "update_pin_status" || chain.unknown.contains(&update.cid) {
+                    return Ok(json!({ "status": { "Failure": { "ActionError": "Content not found" } } }));
+                }
+                chain.updates.push(update);
+                Ok(json!({ "status": { "SuccessValue": "" } }))
+            }
+            method => Err(format!("unknown method {:?}", method)),
+        }
+    }
+
+    fn reporter(url: &str) -> NearRpcReporter {
+        let mut key_file = [3u8; 32].to_vec();
+        key_file.extend_from_slice(SigningKey::from_bytes(&[3; 32]).verifying_key().as_bytes());
+        let secret = format!("ed25519:{}", bs58::encode(key_file).into_string());
+        NearRpcReporter::new(url, CONTRACT, KEEPER, &secret).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_pin_manager_reports_on_chain() {
+        let chain = Arc::new(StdMutex::new(Chain { nonce: 41, ..Chain::default() }));
+        let key = PublicKey::Ed25519(SigningKey::from_bytes(&[3; 32]).verifying_key().to_bytes());
+        let url = start_node(key, chain.clone()).await;
+
+        let store = MemoryStore::new();
+        let cid = store.put(b"frame").await.unwrap();
+        let targets = vec![PinTarget::new("local", StorageProvider::LocalIPFS, store)];
+        let policy = ReplicationPolicy { replicas: 1, ..ReplicationPolicy::default() };
+        let mut manager = PinManager::new(targets, policy, reporter(&url));
+        manager
+            .handle_registered(serde_json::from_value(json!({ "cid": cid, "owner": "artist.near", "size": 5 })).unwrap())
+            .await
+            .unwrap();
+
+        let chain = chain.lock().unwrap();
+        let statuses: Vec<_> = chain.updates.iter().map(|u| (u.cid.as_str(), u.status, u.provider)).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                (cid.as_str(), PinStatus::Pinning, None),
+                (cid.as_str(), PinStatus::Pinned, Some(StorageProvider::LocalIPFS)),
+            ]
+        );
+        assert_eq!(chain.nonce, 43);
+    }
+
+    #[tokio::test]
+    async fn test_failed_call_is_an_error() {
+        let chain = Arc::new(StdMutex::new(Chain { unknown: vec!["bafkmissing".to_string()], ..Chain::default() }));
+        let key = PublicKey::Ed25519(SigningKey::from_bytes(&[3; 32]).verifying_key().to_bytes());
+        let reporter = reporter(&start_node(key, chain.clone()).await);
+
+        let update = |cid: &str| UpdatePinStatusArgs {
+            cid: cid.to_string(),
+            status: PinStatus::Pinned,
+            provider: None,
+        };
+        assert!(matches!(reporter.report(update("bafkmissing")).await, Err(PinError::Report(_))));
+        // The failed transaction still consumed its nonce
+        reporter.report(update("bafkknown")).await.unwrap();
+        assert_eq!(chain.lock().unwrap().nonce, 2);
+
+        // A key the node does not know is refused
+        let stranger = NearRpcReporter::new(
+            reporter.url.clone(),
+            CONTRACT,
+            KEEPER,
+            &format!("ed25519:{}", bs58::encode([9u8; 64]).into_string()),
+        )
+        .unwrap();
+        assert!(stranger.report(update("bafkknown")).await.is_err());
+    }
+}
@@ -3,10 +3,11 @@
 //! Handles storage of emotional trajectories, diffusion generations, and creative sessions
 //! Enhanced with advanced emotional computing capabilities
 
-use crate::ipfs_client::IpfsClient;
+use crate::car::{read_car, CarArchive, CarError, CarPacker, CarVersion, MANIFEST_NAME};
+use crate::storage_backend::{StorageBackend, StorageError};
+use cid::Cid;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::error::Error;
 
 /// Emotional state vector (Valence-Arousal-Dominance model)
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
@@ -477,8 +478,77 @@ impl NeuroemotiveSession {
     }
     
     /// Store session to IPFS
-    pub async fn store_to_ipfs(&self, client: &IpfsClient) -> Result<String, Box<dyn Error>> {
+    pub async fn store_to_ipfs<B: StorageBackend>(&self, backend: &B) -> Result<String, StorageError> {
         let json = serde_json::to_string_pretty(self)?;
-        client.add_json(&json).await
+        backend.put(json.as_bytes()).await
     }
-}
\ No newline at end of file
+
+    /// Pack the session and its diffusion frames into a CAR archive
+    ///
+    /// Frame images are fetched from `backend` by their CIDs; returns the root CID.
+    pub async fn export_car<B: StorageBackend>(
+        &self,
+        backend: &B,
+        version: CarVersion,
+    ) -> Result<(Cid, Vec<u8>), CarError> {
+        let mut packer = CarPacker::default();
+        packer.add_file(MANIFEST_NAME, &serde_json::to_vec_pretty(self)?);
+        for frame in &self.frames {
+            packer
+                .add_referenced(&format!("frame-{}", frame.frame_id), &frame.image_cid, backend)
+                .await?;
+        }
+        Ok(packer.finish(version))
+    }
+
+    /// Read a session back from a CARv1/CARv2 archive
+    pub fn import_car(car: &[u8]) -> Result<(Self, CarArchive), CarError> {
+        let archive = read_car(car)?;
+        let session = archive.read_manifest()?;
+        Ok((session, archive))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage_backend::MemoryStore;
+
+    fn frame(frame_id: &str, image_cid: String) -> DiffusionFrame {
+        DiffusionFrame {
+            frame_id: frame_id.to_string(),
+            timestamp: 1,
+            emotional_state: EmotionalVector::new(0.5, 0.5, 0.5),
+            prompt_conditioning: "calm ocean".to_string(),
+            image_cid,
+            generation_parameters: HashMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_session_car_roundtrip() {
+        let backend = MemoryStore::new();
+        let mut session = NeuroemotiveSession::new("neuro".to_string(), "artist.near".to_string());
+        session.add_emotional_state(EmotionalVector::new(0.2, 0.7, 0.4));
+        session.add_frame(frame("f1", backend.put(b"diffusion frame").await.unwrap()));
+
+        let (_, car) = session.export_car(&backend, CarVersion::V1).await.unwrap();
+        let (imported, archive) = NeuroemotiveSession::import_car(&car).unwrap();
+
+        assert_eq!(imported.session_id, "neuro");
+        assert_eq!(imported.emotional_states.len(), 1);
+        let image = Cid::try_from(imported.frames[0].image_cid.as_str()).unwrap();
+        assert_eq!(archive.read_file(&image).unwrap(), b"diffusion frame");
+    }
+
+    #[tokio::test]
+    async fn test_export_requires_referenced_frames() {
+        let backend = MemoryStore::new();
+        let mut session = NeuroemotiveSession::new("neuro".to_string(), "artist.near".to_string());
+        let missing = MemoryStore::new().put(b"elsewhere").await.unwrap();
+        session.add_frame(frame("f1", missing));
+
+        let result = session.export_car(&backend, CarVersion::V1).await;
+        assert!(matches!(result, Err(CarError::Storage(StorageError::NotFound(_)))));
+    }
+}
@@ -2,9 +2,10 @@
 //! 
 //! Handles storage of VJ performances, fractal sessions, and shader outputs
 
-use crate::ipfs_client::IpfsClient;
+use crate::car::{read_car, CarArchive, CarError, CarPacker, CarVersion, MANIFEST_NAME};
+use crate::storage_backend::{StorageBackend, StorageError};
+use cid::Cid;
 use serde::{Deserialize, Serialize};
-use std::error::Error;
 
 /// NUWE creative session for IPFS storage
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -149,9 +150,9 @@ impl NuweSession {
     }
 
     /// Store session to IPFS
-    pub async fn store_to_ipfs(&self, client: &IpfsClient) -> Result<String, Box<dyn Error>> {
+    pub async fn store_to_ipfs<B: StorageBackend>(&self, backend: &B) -> Result<String, StorageError> {
         let json = serde_json::to_string_pretty(self)?;
-        client.add_json(&json).await
+        backend.put(json.as_bytes()).await
     }
 }
 
@@ -182,9 +183,42 @@ impl NuweAssetBundle {
     }
 
     /// Store complete bundle to IPFS
-    pub async fn store_to_ipfs(&self, client: &IpfsClient) -> Result<String, Box<dyn Error>> {
+    pub async fn store_to_ipfs<B: StorageBackend>(&self, backend: &B) -> Result<String, StorageError> {
         let json = serde_json::to_string_pretty(self)?;
-        client.add_json(&json).await
+        backend.put(json.as_bytes()).await
+    }
+
+    /// Pack the bundle with its frames, shader outputs and audio into a CAR archive
+    ///
+    /// Frames and audio are fetched from `backend` by their CIDs; returns the root CID.
+    pub async fn export_car<B: StorageBackend>(
+        &self,
+        backend: &B,
+        version: CarVersion,
+    ) -> Result<(Cid, Vec<u8>), CarError> {
+        let mut packer = CarPacker::default();
+        packer.add_file(MANIFEST_NAME, &serde_json::to_vec_pretty(self)?);
+        for frame in &self.rendered_frames {
+            let name = format!("frame-{:08}.{}", frame.frame_number, frame.format);
+            packer.add_referenced(&name, &frame.cid, backend).await?;
+        }
+        for shader in &self.shader_outputs {
+            let name = format!("shader-{}.{}", shader.shader_name, shader.shader_type);
+            packer.add_file(&name, shader.compiled_code.as_bytes());
+        }
+        if let Some(ref audio) = self.audio_track {
+            packer.add_referenced(&format!("audio.{}", audio.format), &audio.cid, backend).await?;
+        }
+        Ok(packer.finish(version))
+    }
+
+    /// Read a bundle back from a CARv1/CARv2 archive
+    ///
+    /// Frame and audio content stays readable through the returned archive by CID.
+    pub fn import_car(car: &[u8]) -> Result<(Self, CarArchive), CarError> {
+        let archive = read_car(car)?;
+        let bundle = archive.read_manifest()?;
+        Ok((bundle, archive))
     }
 
     /// Get total storage size estimate in bytes
@@ -271,4 +305,59 @@ mod tests {
         let size = bundle.estimated_size_bytes();
         assert!(size > 0);
     }
+
+    #[tokio::test]
+    async fn test_store_session_to_memory_backend() {
+        let backend = crate::storage_backend::MemoryStore::new();
+        let session = NuweSession::new(
+            "offline".to_string(),
+            SessionType::LiveCoding,
+            "creator".to_string(),
+        );
+
+        let cid = session.store_to_ipfs(&backend).await.unwrap();
+        let stored: NuweSession = serde_json::from_slice(&backend.get(&cid).await.unwrap()).unwrap();
+        assert_eq!(stored.session_id, "offline");
+    }
+
+    #[tokio::test]
+    async fn test_bundle_car_roundtrip() {
+        let backend = crate::storage_backend::MemoryStore::new();
+        let frame_cid = backend.put(&[0xAB; 300_000]).await.unwrap();
+        let audio_cid = backend.put(b"RIFF....WAVE").await.unwrap();
+
+        let session = NuweSession::new("perf".to_string(), SessionType::VJPerformance, "vj.near".to_string());
+        let mut bundle = NuweAssetBundle::new(session);
+        bundle.add_frame(FrameReference {
+            frame_number: 1,
+            timestamp: 100,
+            cid: frame_cid.clone(),
+            format: "png".to_string(),
+            resolution: (1920, 1080),
+        });
+        bundle.add_shader_output(ShaderOutput {
+            shader_name: "tunnel".to_string(),
+            shader_type: "wgsl".to_string(),
+            compiled_code: "@fragment fn main() {}".to_string(),
+            compilation_time_ms: 12,
+        });
+        bundle.set_audio(AudioReference {
+            cid: audio_cid.clone(),
+            duration_seconds: 1.0,
+            format: "wav".to_string(),
+            sample_rate: 44_100,
+        });
+
+        let (root, car) = bundle.export_car(&backend, CarVersion::V2).await.unwrap();
+        let (imported, archive) = NuweAssetBundle::import_car(&car).unwrap();
+
+        assert_eq!(archive.roots, vec![root]);
+        assert_eq!(imported.session.session_id, "perf");
+        assert_eq!(imported.shader_outputs[0].shader_name, "tunnel");
+        let frame = archive.read_file(&Cid::try_from(frame_cid.as_str()).unwrap()).unwrap();
+        assert_eq!(frame, vec![0xAB; 300_000]);
+        let entries = archive.read_directory(&root).unwrap();
+        assert!(entries.contains_key("audio.wav"));
+        assert!(entries.contains_key("shader-tunnel.wgsl"));
+    }
 }
@@ -0,0 +1,326 @@
+//! Patch access - encrypted paid patches and the keys that unlock them
+//!
+//! Paid patches are stored on IPFS as XChaCha20-Poly1305 ciphertext under a random
+//! content key. The marketplace contract only keeps a SHA-256 commitment to that key;
+//! the key service hands it to each buyer wrapped to the x25519 public key they
+//! registered with `request_access`. Wrapping uses an ephemeral x25519 key and
+//! HKDF-SHA256, so a wrapped key is only useful to the buyer that asked for it.
+
+use crate::storage_backend::{StorageBackend, StorageError};
+use crate::unixfs::{CidVerifier, ImportOptions};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use cid::Cid;
+use hkdf::Hkdf;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+/// Algorithm name recorded on-chain for patches encrypted here
+pub const PATCH_CIPHER: &str = "xchacha20poly1305";
+
+/// Prefix of an encrypted patch payload
+const PAYLOAD_MAGIC: &[u8; 4] = b"MPE1";
+const NONCE_LEN: usize = 24;
+const KEY_LEN: usize = 32;
+/// Ephemeral public key, nonce and sealed content key
+const WRAPPED_KEY_LEN: usize = KEY_LEN + NONCE_LEN + KEY_LEN + 16;
+
+/// Errors unlocking a patch
+#[derive(Debug, Error)]
+pub enum PatchAccessError {
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+
+    #[error("unsupported patch cipher: {0}")]
+    UnsupportedCipher(String),
+
+    #[error("malformed {0}")]
+    Malformed(&'static str),
+
+    #[error("content key does not match the on-chain commitment")]
+    KeyCommitmentMismatch,
+
+    #[error("decryption failed")]
+    Decryption,
+
+    #[error("decrypted patch does not match the published hash")]
+    PlaintextMismatch,
+
+    #[error("no wrapped key has been granted yet")]
+    NotGranted,
+}
+
+/// Mirror of the contract's `PatchEncryption`, without the key service account
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PatchEncryptionInfo {
+    pub algorithm: String,
+    pub key_commitment: String,
+    pub plaintext_sha256: String,
+}
+
+/// Symmetric key a patch payload is encrypted under
+#[derive(Clone)]
+pub struct ContentKey([u8; KEY_LEN]);
+
+impl ContentKey {
+    pub fn generate() -> Self {
+        Self(XChaCha20Poly1305::generate_key(&mut OsRng).into())
+    }
+
+    /// Hex SHA-256 of the key, as stored on-chain
+    pub fn commitment(&self) -> String {
+        hex::encode(Sha256::digest(self.0))
+    }
+}
+
+/// Output of `encrypt_patch`: the payload to upload and the info to publish
+pub struct EncryptedPatch {
+    pub payload: Vec<u8>,
+    pub encryption: PatchEncryptionInfo,
+}
+
+/// Encrypt a patch for upload, binding the ciphertext to its patch ID
+pub fn encrypt_patch(patch_id: &str, plaintext: &[u8], key: &ContentKey) -> EncryptedPatch {
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = XChaCha20Poly1305::new(&key.0.into())
+        .encrypt(&nonce, Payload { msg: plaintext, aad: patch_id.as_bytes() })
+        .expect("encrypting in memory cannot fail");
+
+    let mut payload = Vec::with_capacity(PAYLOAD_MAGIC.len() + NONCE_LEN + ciphertext.len());
+    payload.extend_from_slice(PAYLOAD_MAGIC);
+    payload.extend_from_slice(&nonce);
+    payload.extend_from_slice(&ciphertext);
+
+    EncryptedPatch {
+        payload,
+        encryption: PatchEncryptionInfo {
+            algorithm: PATCH_CIPHER.to_string(),
+            key_commitment: key.commitment(),
+            plaintext_sha256: hex::encode(Sha256::digest(plaintext)),
+        },
+    }
+}
+
+/// Decrypt a downloaded payload and check it against the published hash
+pub fn decrypt_patch(
+    patch_id: &str,
+    payload: &[u8],
+    key: &ContentKey,
+    encryption: &PatchEncryptionInfo,
+) -> Result<Vec<u8>, PatchAccessError> {
+    if encryption.algorithm != PATCH_CIPHER {
+        return Err(PatchAccessError::UnsupportedCipher(encryption.algorithm.clone()));
+    }
+    if key.commitment() != encryption.key_commitment {
+        return Err(PatchAccessError::KeyCommitmentMismatch);
+    }
+
+    let body = payload
+        .strip_prefix(PAYLOAD_MAGIC.as_slice())
+        .filter(|body| body.len() >= NONCE_LEN)
+        .ok_or(PatchAccessError::Malformed("patch payload"))?;
+    let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+
+    let plaintext = XChaCha20Poly1305::new(&key.0.into())
+        .decrypt(XNonce::from_slice(nonce), Payload { msg: ciphertext, aad: patch_id.as_bytes() })
+        .map_err(|_| PatchAccessError::Decryption)?;
+
+    if hex::encode(Sha256::digest(&plaintext)) != encryption.plaintext_sha256 {
+        return Err(PatchAccessError::PlaintextMismatch);
+    }
+    Ok(plaintext)
+}
+
+/// Buyer's x25519 key pair for receiving content keys
+pub struct BuyerKeypair {
+    secret: StaticSecret,
+}
+
+impl BuyerKeypair {
+    pub fn generate() -> Self {
+        Self { secret: StaticSecret::random_from_rng(OsRng) }
+    }
+
+    /// Restore a key pair from its 32-byte secret
+    pub fn from_secret(secret: [u8; 32]) -> Self {
+        Self { secret: StaticSecret::from(secret) }
+    }
+
+    pub fn secret_bytes(&self) -> [u8; 32] {
+        self.secret.to_bytes()
+    }
+
+    /// Hex public key to pass to `request_access`
+    pub fn public_key_hex(&self) -> String {
+        hex::encode(PublicKey::from(&self.secret).as_bytes())
+    }
+
+    /// Unwrap a granted content key and check it against the on-chain commitment
+    pub fn unwrap_key(
+        &self,
+        patch_id: &str,
+        wrapped_key: &str,
+        key_commitment: &str,
+    ) -> Result<ContentKey, PatchAccessError> {
+        let wrapped = hex::decode(wrapped_key).map_err(|_| PatchAccessError::Malformed("wrapped key"))?;
+        if wrapped.len() != WRAPPED_KEY_LEN {
+            return Err(PatchAccessError::Malformed("wrapped key"));
+        }
+        let (ephemeral, rest) = wrapped.split_at(KEY_LEN);
+        let (nonce, sealed) = rest.split_at(NONCE_LEN);
+
+        let ephemeral = PublicKey::from(<[u8; KEY_LEN]>::try_from(ephemeral).expect("length checked"));
+        let recipient = PublicKey::from(&self.secret);
+        let cipher = wrapping_cipher(&self.secret.diffie_hellman(&ephemeral).to_bytes(), &ephemeral, &recipient, patch_id);
+
+        let key = cipher
+            .decrypt(XNonce::from_slice(nonce), sealed)
+            .map_err(|_| PatchAccessError::Decryption)?;
+        let key = ContentKey(key.try_into().map_err(|_| PatchAccessError::Malformed("content key"))?);
+
+        if key.commitment() != key_commitment {
+            return Err(PatchAccessError::KeyCommitmentMismatch);
+        }
+        Ok(key)
+    }
+}
+
+/// Wrap a content key to a buyer's hex public key (key service side)
+pub fn wrap_key(patch_id: &str, key: &ContentKey, buyer_public_key: &str) -> Result<String, PatchAccessError> {
+    let recipient: [u8; KEY_LEN] = hex::decode(buyer_public_key)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(PatchAccessError::Malformed("buyer public key"))?;
+    let recipient = PublicKey::from(recipient);
+
+    let ephemeral_secret = StaticSecret::random_from_rng(OsRng);
+    let ephemeral = PublicKey::from(&ephemeral_secret);
+    let cipher = wrapping_cipher(&ephemeral_secret.diffie_hellman(&recipient).to_bytes(), &ephemeral, &recipient, patch_id);
+
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let sealed = cipher.encrypt(&nonce, key.0.as_slice()).expect("encrypting in memory cannot fail");
+
+    let mut wrapped = Vec::with_capacity(WRAPPED_KEY_LEN);
+    wrapped.extend_from_slice(ephemeral.as_bytes());
+    wrapped.extend_from_slice(&nonce);
+    wrapped.extend_from_slice(&sealed);
+    Ok(hex::encode(wrapped))
+}
+
+/// Derive the key-wrapping cipher from an x25519 shared secret
+fn wrapping_cipher(shared: &[u8; 32], ephemeral: &PublicKey, recipient: &PublicKey, patch_id: &str) -> XChaCha20Poly1305 {
+    let mut salt = [0u8; 64];
+    salt[..32].copy_from_slice(ephemeral.as_bytes());
+    salt[32..].copy_from_slice(recipient.as_bytes());
+
+    let mut okm = [0u8; KEY_LEN];
+    Hkdf::<Sha256>::new(Some(&salt), shared)
+        .expand(format!("patch-key:{}", patch_id).as_bytes(), &mut okm)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    XChaCha20Poly1305::new(&okm.into())
+}
+
+/// What a buyer reads from the contract to download a paid patch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatchDownload {
+    pub patch_id: String,
+    pub ipfs_cid: String,
+    pub encryption: PatchEncryptionInfo,
+    /// `wrapped_key` of the buyer's `AccessGrant`
+    pub wrapped_key: Option<String>,
+}
+
+impl PatchDownload {
+    /// Fetch the ciphertext, check its CID, unwrap the key and decrypt
+    pub async fn fetch<B: StorageBackend>(
+        &self,
+        backend: &B,
+        keypair: &BuyerKeypair,
+    ) -> Result<Vec<u8>, PatchAccessError> {
+        let wrapped_key = self.wrapped_key.as_deref().ok_or(PatchAccessError::NotGranted)?;
+        let key = keypair.unwrap_key(&self.patch_id, wrapped_key, &self.encryption.key_commitment)?;
+
+        let expected: Cid = self
+            .ipfs_cid
+            .parse()
+            .map_err(|_| StorageError::InvalidCid(self.ipfs_cid.clone()))?;
+        let payload = backend.get(&self.ipfs_cid).await?;
+
+        let mut verifier = CidVerifier::with_options(expected, ImportOptions::for_cid(&expected));
+        verifier.update(&payload);
+        if !verifier.finish() {
+            return Err(PatchAccessError::Malformed("patch payload: content does not match its CID"));
+        }
+
+        decrypt_patch(&self.patch_id, &payload, &key, &self.encryption)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage_backend::MemoryStore;
+
+    #[test]
+    fn test_encrypt_decrypt_round_trip() {
+        let key = ContentKey::generate();
+        let encrypted = encrypt_patch("fractal-1", b"shader patch source", &key);
+        assert_eq!(encrypted.encryption.key_commitment, key.commitment());
+
+        let plaintext = decrypt_patch("fractal-1", &encrypted.payload, &key, &encrypted.encryption).unwrap();
+        assert_eq!(plaintext, b"shader patch source");
+
+        // The ciphertext is bound to its patch ID
+        assert!(matches!(
+            decrypt_patch("fractal-2", &encrypted.payload, &key, &encrypted.encryption),
+            Err(PatchAccessError::Decryption)
+        ));
+    }
+
+    #[test]
+    fn test_wrapped_key_only_opens_for_buyer() {
+        let key = ContentKey::generate();
+        let buyer = BuyerKeypair::generate();
+        let wrapped = wrap_key("fractal-1", &key, &buyer.public_key_hex()).unwrap();
+        assert_eq!(wrapped.len(), WRAPPED_KEY_LEN * 2);
+
+        let unwrapped = buyer.unwrap_key("fractal-1", &wrapped, &key.commitment()).unwrap();
+        assert_eq!(unwrapped.0, key.0);
+
+        let restored = BuyerKeypair::from_secret(buyer.secret_bytes());
+        assert!(restored.unwrap_key("fractal-1", &wrapped, &key.commitment()).is_ok());
+        assert!(matches!(
+            BuyerKeypair::generate().unwrap_key("fractal-1", &wrapped, &key.commitment()),
+            Err(PatchAccessError::Decryption)
+        ));
+        assert!(matches!(
+            buyer.unwrap_key("fractal-1", &wrapped, &ContentKey::generate().commitment()),
+            Err(PatchAccessError::KeyCommitmentMismatch)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_verifies_and_decrypts() {
+        let store = MemoryStore::new();
+        let key = ContentKey::generate();
+        let encrypted = encrypt_patch("fractal-1", b"paid patch", &key);
+        let cid = store.put(&encrypted.payload).await.unwrap();
+
+        let buyer = BuyerKeypair::generate();
+        let mut download = PatchDownload {
+            patch_id: "fractal-1".to_string(),
+            ipfs_cid: cid,
+            encryption: encrypted.encryption.clone(),
+            wrapped_key: None,
+        };
+        assert!(matches!(download.fetch(&store, &buyer).await, Err(PatchAccessError::NotGranted)));
+
+        download.wrapped_key = Some(wrap_key("fractal-1", &key, &buyer.public_key_hex()).unwrap());
+        assert_eq!(download.fetch(&store, &buyer).await.unwrap(), b"paid patch");
+
+        download.encryption.plaintext_sha256 = hex::encode(Sha256::digest(b"something else"));
+        assert!(matches!(download.fetch(&store, &buyer).await, Err(PatchAccessError::PlaintextMismatch)));
+    }
+}
@@ -0,0 +1,265 @@
+//! Patch versions - semver releases and dependency resolution
+//!
+//! Mirrors the release records `PatchMarketplaceContract` returns from `get_releases`,
+//! and resolves a patch for a tool version the same way the contract's `resolve_patch`
+//! view does, so studios can resolve offline from a cached catalog.
+
+use crate::patch_access::PatchEncryptionInfo;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use thiserror::Error;
+
+/// Errors resolving patch versions
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ResolveError {
+    #[error("invalid semver version: {0}")]
+    InvalidVersion(String),
+
+    #[error("conflicting requirements on {patch_id}: {chosen} does not match {requirement}")]
+    Conflict {
+        patch_id: String,
+        chosen: String,
+        requirement: String,
+    },
+
+    #[error("no compatible release of dependency {0}")]
+    Unsatisfied(String),
+}
+
+/// Immutable published version of a patch, as returned by `get_releases`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatchRelease {
+    pub version: String,
+    pub ipfs_cid: String,
+    pub changelog: String,
+    pub compatibility: Vec<String>,
+    pub dependencies: Vec<String>,
+    pub encryption: Option<PatchEncryptionInfo>,
+    pub published_at: u64,
+    pub yanked: bool,
+}
+
+impl PatchRelease {
+    /// An empty compatibility list supports every tool version
+    pub fn supports_tool(&self, tool_version: &Version) -> bool {
+        self.compatibility.is_empty()
+            || self
+                .compatibility
+                .iter()
+                .filter_map(|r| parse_requirement(r))
+                .any(|r| r.matches(tool_version))
+    }
+}
+
+/// A release picked by the resolver
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResolvedPatch {
+    pub patch_id: String,
+    pub version: String,
+    pub ipfs_cid: String,
+}
+
+/// A patch and the releases of its transitive dependencies for one tool version
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Resolution {
+    pub patch: ResolvedPatch,
+    pub dependencies: Vec<ResolvedPatch>,
+}
+
+/// Parse a semver requirement, also accepting the legacy "v1.0+" form
+pub fn parse_requirement(requirement: &str) -> Option<VersionReq> {
+    let requirement = requirement.trim();
+    match requirement.strip_prefix('v').and_then(|r| r.strip_suffix('+')) {
+        Some(minimum) => VersionReq::parse(&format!(">={}", minimum)).ok(),
+        None => VersionReq::parse(requirement).ok(),
+    }
+}
+
+/// Split "patch_id@requirement" into its parts
+pub fn split_dependency(dependency: &str) -> (&str, Option<&str>) {
+    match dependency.split_once('@') {
+        Some((id, requirement)) => (id, Some(requirement)),
+        None => (dependency, None),
+    }
+}
+
+fn parse_version(version: &str) -> Result<Version, ResolveError> {
+    Version::parse(version).map_err(|_| ResolveError::InvalidVersion(version.to_string()))
+}
+
+/// Releases of many patches, keyed by patch ID
+#[derive(Debug, Clone, Default)]
+pub struct ReleaseCatalog {
+    releases: HashMap<String, Vec<PatchRelease>>,
+}
+
+impl ReleaseCatalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace a patch's releases
+    pub fn insert(&mut self, patch_id: impl Into<String>, releases: Vec<PatchRelease>) {
+        self.releases.insert(patch_id.into(), releases);
+    }
+
+    /// Add a patch's releases from a `get_releases` JSON response
+    pub fn insert_json(&mut self, patch_id: impl Into<String>, json: &str) -> Result<(), serde_json::Error> {
+        self.insert(patch_id, serde_json::from_str(json)?);
+        Ok(())
+    }
+
+    pub fn releases(&self, patch_id: &str) -> &[PatchRelease] {
+        self.releases.get(patch_id).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Newest unyanked release matching the tool version and an optional requirement
+    pub fn newest(
+        &self,
+        patch_id: &str,
+        tool_version: &Version,
+        requirement: Option<&VersionReq>,
+    ) -> Option<&PatchRelease> {
+        self.releases(patch_id)
+            .iter()
+            .filter(|r| !r.yanked && r.supports_tool(tool_version))
+            .filter_map(|r| Version::parse(&r.version).ok().map(|v| (v, r)))
+            .filter(|(v, _)| requirement.is_none_or(|req| req.matches(v)))
+            .max_by(|a, b| a.0.cmp(&b.0))
+            .map(|(_, r)| r)
+    }
+
+    /// Newest compatible release of a patch and its transitive dependencies
+    ///
+    /// Resolution is greedy and breadth-first: each patch gets the newest release that
+    /// fits the first requirement on it, and later requirements must accept that choice.
+    /// Returns `Ok(None)` when no unyanked release supports `tool_version`.
+    pub fn resolve(&self, patch_id: &str, tool_version: &str) -> Result<Option<Resolution>, ResolveError> {
+        let tool_version = parse_version(tool_version)?;
+        let root = match self.newest(patch_id, &tool_version, None) {
+            Some(release) => release,
+            None => return Ok(None),
+        };
+
+        let mut chosen: HashMap<&str, Version> = HashMap::new();
+        chosen.insert(patch_id, parse_version(&root.version)?);
+        let mut dependencies = Vec::new();
+        let mut queue = VecDeque::from([root]);
+
+        while let Some(release) = queue.pop_front() {
+            for dependency in &release.dependencies {
+                let (id, requirement) = split_dependency(dependency);
+                let requirement = requirement.and_then(parse_requirement);
+
+                if let Some(version) = chosen.get(id) {
+                    if requirement.as_ref().is_none_or(|req| req.matches(version)) {
+                        continue;
+                    }
+                    return Err(ResolveError::Conflict {
+                        patch_id: id.to_string(),
+                        chosen: version.to_string(),
+                        requirement: dependency.clone(),
+                    });
+                }
+
+                let picked = self
+                    .newest(id, &tool_version, requirement.as_ref())
+                    .ok_or_else(|| ResolveError::Unsatisfied(dependency.clone()))?;
+                chosen.insert(id, parse_version(&picked.version)?);
+                dependencies.push(ResolvedPatch {
+                    patch_id: id.to_string(),
+                    version: picked.version.clone(),
+                    ipfs_cid: picked.ipfs_cid.clone(),
+                });
+                queue.push_back(picked);
+            }
+        }
+
+        Ok(Some(Resolution {
+            patch: ResolvedPatch {
+                patch_id: patch_id.to_string(),
+                version: root.version.clone(),
+                ipfs_cid: root.ipfs_cid.clone(),
+            },
+            dependencies,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(version: &str, compatibility: &[&str], dependencies: &[&str]) -> PatchRelease {
+        PatchRelease {
+            version: version.to_string(),
+            ipfs_cid: format!("bafy-{}", version),
+            changelog: format!("Release {}", version),
+            compatibility: compatibility.iter().map(|s| s.to_string()).collect(),
+            dependencies: dependencies.iter().map(|s| s.to_string()).collect(),
+            encryption: None,
+            published_at: 0,
+            yanked: false,
+        }
+    }
+
+    fn catalog() -> ReleaseCatalog {
+        let mut catalog = ReleaseCatalog::new();
+        catalog.insert("lib", vec![
+            release("1.0.0", &[], &[]),
+            release("1.4.0", &["^2.0"], &[]),
+            release("2.0.0", &["^2.0"], &[]),
+        ]);
+        catalog.insert("app", vec![
+            release("1.0.0", &[], &[]),
+            release("1.1.0", &["v2.0+"], &["lib@^1.0"]),
+        ]);
+        catalog
+    }
+
+    #[test]
+    fn test_resolve_newest_compatible() {
+        let resolution = catalog().resolve("app", "2.3.0").unwrap().unwrap();
+        assert_eq!(resolution.patch.version, "1.1.0");
+        assert_eq!(resolution.dependencies, vec![ResolvedPatch {
+            patch_id: "lib".to_string(),
+            version: "1.4.0".to_string(),
+            ipfs_cid: "bafy-1.4.0".to_string(),
+        }]);
+
+        let resolution = catalog().resolve("app", "1.0.0").unwrap().unwrap();
+        assert_eq!(resolution.patch.version, "1.0.0");
+        assert!(resolution.dependencies.is_empty());
+
+        assert_eq!(catalog().resolve("missing", "1.0.0"), Ok(None));
+        assert!(matches!(catalog().resolve("app", "two"), Err(ResolveError::InvalidVersion(_))));
+    }
+
+    #[test]
+    fn test_yanked_and_conflicting_releases() {
+        let mut catalog = catalog();
+        let mut lib = catalog.releases("lib").to_vec();
+        lib[1].yanked = true;
+        catalog.insert("lib", lib);
+        let resolution = catalog.resolve("app", "2.3.0").unwrap().unwrap();
+        assert_eq!(resolution.dependencies[0].version, "1.0.0");
+
+        // fx wants lib 2.x while app already picked lib 1.x
+        catalog.insert("fx", vec![release("1.0.0", &[], &["lib@^2.0"])]);
+        catalog.insert("app", vec![release("3.0.0", &[], &["lib@^1.0", "fx"])]);
+        assert!(matches!(catalog.resolve("app", "2.3.0"), Err(ResolveError::Conflict { .. })));
+    }
+
+    #[test]
+    fn test_releases_from_contract_json() {
+        let json = r#"[{"version":"0.1.0","ipfs_cid":"bafyA","changelog":"Initial release",
+            "compatibility":["v1.0+"],"dependencies":[],"published_at":1,"yanked":false,
+            "encryption":{"algorithm":"xchacha20poly1305","key_commitment":"ab","plaintext_sha256":"cd","key_service":"keys.testnet"}}]"#;
+        let mut catalog = ReleaseCatalog::new();
+        catalog.insert_json("shader", json).unwrap();
+        assert!(catalog.releases("shader")[0].encryption.is_some());
+        assert_eq!(catalog.resolve("shader", "1.2.0").unwrap().unwrap().patch.ipfs_cid, "bafyA");
+        assert_eq!(catalog.resolve("shader", "0.9.0").unwrap(), None);
+    }
+}
@@ -0,0 +1,603 @@
+//! Pin Manager - keeps registered content pinned on the configured backends
+//!
+//! Watches `content_registered` events from `IPFSStorageContract` (the
+//! `nft-ipfs-storage-contract` crate), replicates each CID
+//! to the pinning targets with retries and exponential backoff, re-verifies pins
+//! periodically and reports progress as `update_pin_status` calls, which
+//! `NearRpcReporter` signs and submits on-chain. The account that signs those calls
+//! must be the storage contract's owner. Reports are retried with the same backoff;
+//! one that still fails is recorded on the CID and resent on the next verification,
+//! so a failing report never stops the keeper.
+
+use crate::storage_backend::{DynStorageBackend, StorageBackend, StorageError};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::mpsc;
+
+/// Pin status, mirroring `IPFSStorageContract::PinStatus`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PinStatus {
+    Queued,
+    Pinning,
+    Pinned,
+    Failed,
+}
+
+/// Storage provider, mirroring `IPFSStorageContract::StorageProvider`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StorageProvider {
+    LocalIPFS,
+    Web3Storage,
+    Pinata,
+    NFTStorage,
+    FilecoinDeal,
+}
+
+/// Arguments of `IPFSStorageContract::update_pin_status`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpdatePinStatusArgs {
+    pub cid: String,
+    pub status: PinStatus,
+    pub provider: Option<StorageProvider>,
+}
+
+/// `content_registered` event logged by `IPFSStorageContract::register_content`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ContentRegistered {
+    pub cid: String,
+    pub owner: String,
+    pub size: u64,
+}
+
+impl ContentRegistered {
+    /// Parse a contract log line; other events and non-JSON logs yield `None`
+    ///
+    /// Accepts NEP-297 `EVENT_JSON:` logs, whose payload sits under `data` (an object
+    /// or a one-element array), as well as the flat JSON logged by older deployments.
+    pub fn from_log(log: &str) -> Option<Self> {
+        let log = log.strip_prefix("EVENT_JSON:").unwrap_or(log);
+        let mut event: serde_json::Value = serde_json::from_str(log).ok()?;
+        if event.get("event")?.as_str()? != "content_registered" {
+            return None;
+        }
+        let payload = match event.get_mut("data").map(serde_json::Value::take) {
+            Some(serde_json::Value::Array(mut data)) if data.len() == 1 => data.pop()?,
+            Some(data @ serde_json::Value::Object(_)) => data,
+            Some(_) => return None,
+            None => event,
+        };
+        serde_json::from_value(payload).ok()
+    }
+}
+
+/// Errors that stop the pin manager
+#[derive(Debug, Error)]
+pub enum PinError {
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+
+    #[error("pin status reporter failed: {0}")]
+    Report(String),
+}
+
+/// Delivers `update_pin_status` calls to the storage contract
+pub trait PinStatusReporter: Send + Sync {
+    fn report(&self, update: UpdatePinStatusArgs) -> impl Future<Output = Result<(), PinError>> + Send;
+}
+
+/// Queue updates for another task to submit; also lets tests observe the reports
+impl PinStatusReporter for mpsc::UnboundedSender<UpdatePinStatusArgs> {
+    async fn report(&self, update: UpdatePinStatusArgs) -> Result<(), PinError> {
+        self.send(update).map_err(|e| PinError::Report(e.to_string()))
+    }
+}
+
+/// Backend content gets replicated to
+#[derive(Clone)]
+pub struct PinTarget {
+    pub name: String,
+    pub provider: StorageProvider,
+    pub backend: Arc<dyn DynStorageBackend>,
+}
+
+impl PinTarget {
+    pub fn new<B: StorageBackend + 'static>(name: &str, provider: StorageProvider, backend: B) -> Self {
+        Self {
+            name: name.to_string(),
+            provider,
+            backend: Arc::new(backend),
+        }
+    }
+}
+
+/// How many replicas to keep and how hard to try
+#[derive(Debug, Clone)]
+pub struct ReplicationPolicy {
+    /// Targets that must hold a pin for content to count as pinned
+    pub replicas: usize,
+    /// Rounds over the targets before giving up and reporting `Failed`
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub verify_interval: Duration,
+}
+
+impl Default for ReplicationPolicy {
+    fn default() -> Self {
+        Self {
+            replicas: 2,
+            max_attempts: 5,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            verify_interval: Duration::from_secs(3600),
+        }
+    }
+}
+
+impl ReplicationPolicy {
+    /// Delay before retry round `attempt` (1-based), doubling up to `max_backoff`
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+}
+
+/// Tracked state of one registered CID
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PinRecord {
+    pub cid: String,
+    pub owner: String,
+    pub size: u64,
+    pub status: PinStatus,
+    /// Names of the targets currently holding a pin
+    pub replicas: Vec<String>,
+    /// Why the current status could not be reported, if it could not; cleared once a
+    /// report succeeds
+    pub report_error: Option<String>,
+}
+
+/// Off-chain keeper for `IPFSStorageContract` pins
+pub struct PinManager<R> {
+    targets: Vec<PinTarget>,
+    source: Option<Arc<dyn DynStorageBackend>>,
+    policy: ReplicationPolicy,
+    reporter: R,
+    records: HashMap<String, PinRecord>,
+}
+
+impl<R: PinStatusReporter> PinManager<R> {
+    pub fn new(targets: Vec<PinTarget>, policy: ReplicationPolicy, reporter: R) -> Self {
+        Self {
+            targets,
+            source: None,
+            policy,
+            reporter,
+            records: HashMap::new(),
+        }
+    }
+
+    /// Backend to copy content from when a target cannot fetch a CID by itself
+    pub fn with_source<B: StorageBackend + 'static>(mut self, source: B) -> Self {
+        self.source = Some(Arc::new(source));
+        self
+    }
+
+    /// Tracked state of a CID
+    pub fn record(&self, cid: &str) -> Option<&PinRecord> {
+        self.records.get(cid)
+    }
+
+    /// Process events and verify pins until the log stream closes
+    ///
+    /// Retries back off inside this loop, so events queue up while content is retried.
+    pub async fn run(mut self, mut logs: mpsc::Receiver<String>) -> Result<(), PinError> {
+        let mut verify = tokio::time::interval(self.policy.verify_interval);
+        verify.tick().await; // the first tick completes immediately
+        loop {
+            tokio::select! {
+                log = logs.recv() => match log {
+                    Some(log) => self.handle_log(&log).await?,
+                    None => return Ok(()),
+                },
+                _ = verify.tick() => self.verify_all().await?,
+            }
+        }
+    }
+
+    /// Handle one contract log line, ignoring anything but `content_registered`
+    pub async fn handle_log(&mut self, log: &str) -> Result<(), PinError> {
+        match ContentRegistered::from_log(log) {
+            Some(event) => self.handle_registered(event).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Start tracking and replicating newly registered content
+    pub async fn handle_registered(&mut self, event: ContentRegistered) -> Result<(), PinError> {
+        if self.records.contains_key(&event.cid) {
+            return Ok(());
+        }
+        let cid = event.cid.clone();
+        self.records.insert(
+            cid.clone(),
+            PinRecord {
+                cid: event.cid,
+                owner: event.owner,
+                size: event.size,
+                status: PinStatus::Queued,
+                replicas: Vec::new(),
+                report_error: None,
+            },
+        );
+        self.replicate(&cid).await;
+        Ok(())
+    }
+
+    /// Check every tracked CID on its replicas, repair lost or failed ones and resend
+    /// statuses that could not be reported
+    pub async fn verify_all(&mut self) -> Result<(), PinError> {
+        let cids: Vec<String> = self.records.keys().cloned().collect();
+        for cid in cids {
+            let record = &self.records[&cid];
+            let mut healthy = Vec::new();
+            for name in &record.replicas {
+                let Some(target) = self.targets.iter().find(|t| &t.name == name) else {
+                    continue;
+                };
+                if matches!(target.backend.stat_boxed(&cid).await, Ok(stat) if stat.pinned) {
+                    healthy.push(name.clone());
+                }
+            }
+
+            let degraded = healthy.len() < record.replicas.len() || record.status != PinStatus::Pinned;
+            let unreported = record.report_error.is_some();
+            self.records.get_mut(&cid).unwrap().replicas = healthy;
+            if degraded {
+                self.replicate(&cid).await;
+            } else if unreported {
+                self.set_status(&cid, PinStatus::Pinned, None).await;
+            }
+        }
+        Ok(())
+    }
+
+    /// Pin on targets until the policy's replica count is met or attempts run out
+    async fn replicate(&mut self, cid: &str) {
+        let needed = self.policy.replicas.min(self.targets.len()).max(1);
+        self.set_status(cid, PinStatus::Pinning, None).await;
+
+        let mut attempt = 0;
+        loop {
+            for target in self.targets.clone() {
+                if self.records[cid].replicas.len() >= needed {
+                    break;
+                }
+                if self.records[cid].replicas.contains(&target.name) {
+                    continue;
+                }
+                if self.pin_on(&target, cid).await.is_ok() {
+                    let record = self.records.get_mut(cid).unwrap();
+                    record.replicas.push(target.name.clone());
+                    let status = if record.replicas.len() >= needed {
+                        PinStatus::Pinned
+                    } else {
+                        PinStatus::Pinning
+                    };
+                    self.set_status(cid, status, Some(target.provider)).await;
+                }
+            }
+
+            if self.records[cid].replicas.len() >= needed {
+                return;
+            }
+            attempt += 1;
+            if attempt >= self.policy.max_attempts {
+                return self.set_status(cid, PinStatus::Failed, None).await;
+            }
+            tokio::time::sleep(self.policy.backoff(attempt)).await;
+        }
+    }
+
+    /// Pin on one target, copying the content over from the source if the target lacks it
+    async fn pin_on(&self, target: &PinTarget, cid: &str) -> Result<(), StorageError> {
+        match target.backend.pin_boxed(cid).await {
+            Err(StorageError::NotFound(_)) if self.source.is_some() => {
+                let data = self.source.as_ref().unwrap().get_boxed(cid).await?;
+                let stored = target.backend.put_boxed(&data).await?;
+                if stored != cid {
+                    return Err(StorageError::CidMismatch {
+                        expected: cid.to_string(),
+                        actual: stored,
+                    });
+                }
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Update the tracked status and report it, retrying like pinning does
+    async fn set_status(&mut self, cid: &str, status: PinStatus, provider: Option<StorageProvider>) {
+        self.records.get_mut(cid).unwrap().status = status;
+        let update = UpdatePinStatusArgs {
+            cid: cid.to_string(),
+            status,
+            provider,
+        };
+
+        let mut attempt = 0;
+        let report_error = loop {
+            match self.reporter.report(update.clone()).await {
+                Ok(()) => break None,
+                Err(e) => {
+                    attempt += 1;
+                    if attempt >= self.policy.max_attempts {
+                        break Some(e.to_string());
+                    }
+                    tokio::time::sleep(self.policy.backoff(attempt)).await;
+                }
+            }
+        };
+        self.records.get_mut(cid).unwrap().report_error = report_error;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_kubo::FakeKubo;
+    use crate::storage_backend::{ContentStat, MemoryStore};
+    use crate::unixfs::ImportOptions;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Store that fails the first `failures` pin calls
+    struct FlakyStore {
+        inner: MemoryStore,
+        failures: AtomicU32,
+    }
+
+    impl StorageBackend for FlakyStore {
+        async fn put(&self, data: &[u8]) -> Result<String, StorageError> {
+            self.inner.put(data).await
+        }
+
+        async fn get(&self, cid: &str) -> Result<Vec<u8>, StorageError> {
+            self.inner.get(cid).await
+        }
+
+        async fn pin(&self, cid: &str) -> Result<(), StorageError> {
+            if self.failures.load(Ordering::SeqCst) > 0 {
+                self.failures.fetch_sub(1, Ordering::SeqCst);
+                return Err(StorageError::Io(std::io::ErrorKind::ConnectionRefused.into()));
+            }
+            self.inner.pin(cid).await
+        }
+
+        async fn unpin(&self, cid: &str) -> Result<(), StorageError> {
+            self.inner.unpin(cid).await
+        }
+
+        async fn stat(&self, cid: &str) -> Result<ContentStat, StorageError> {
+            self.inner.stat(cid).await
+        }
+    }
+
+    fn fast_policy(replicas: usize, max_attempts: u32) -> ReplicationPolicy {
+        ReplicationPolicy {
+            replicas,
+            max_attempts,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(4),
+            verify_interval: Duration::from_secs(3600),
+        }
+    }
+
+    fn registered_log(cid: &str) -> String {
+        format!(
+            r#"EVENT_JSON:{{"standard":"ipfs_storage","version":"1.0.0","event":"content_registered","data":{{"cid":"{}","owner":"artist.near","size":5}}}}"#,
+            cid
+        )
+    }
+
+    fn drain(rx: &mut mpsc::UnboundedReceiver<UpdatePinStatusArgs>) -> Vec<(PinStatus, Option<StorageProvider>)> {
+        std::iter::from_fn(|| rx.try_recv().ok())
+            .map(|u| (u.status, u.provider))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_content_registered() {
+        let event = ContentRegistered::from_log(&registered_log("bafkcid")).unwrap();
+        assert_eq!(event.cid, "bafkcid");
+        assert_eq!(event.size, 5);
+        assert!(ContentRegistered::from_log(r#"{"event":"pin_status_updated","cid":"x"}"#).is_none());
+        assert!(ContentRegistered::from_log("plain log").is_none());
+
+        // Pre-NEP-297 flat logs and array payloads still parse
+        let legacy = r#"{"event":"content_registered","cid":"bafkold","owner":"artist.near","size":9}"#;
+        assert_eq!(ContentRegistered::from_log(legacy).unwrap().size, 9);
+        let array = r#"EVENT_JSON:{"standard":"ipfs_storage","version":"1.0.0","event":"content_registered","data":[{"cid":"bafkarr","owner":"a.near","size":1}]}"#;
+        assert_eq!(ContentRegistered::from_log(array).unwrap().cid, "bafkarr");
+    }
+
+    #[test]
+    fn test_reads_contract_events_and_writes_its_args() {
+        use near_sdk::test_utils::{accounts, get_logs, VMContextBuilder};
+        use near_sdk::{testing_env, NearToken};
+        use nft_ipfs_storage_contract::{IPFSStorageContract, CID};
+
+        let mut context = VMContextBuilder::new();
+        context.predecessor_account_id(accounts(1));
+        context.attached_deposit(NearToken::from_near(1));
+        testing_env!(context.build());
+
+        let mut contract = IPFSStorageContract::new(accounts(0));
+        let cid = "bafkreigh2akiscaildcqabsyg3dfr6chu3fgpregiymsck7e7aqa4s52zy".to_string();
+        contract.register_content(CID(cid.clone()), 5, "text/plain".to_string(), None, vec![]);
+
+        let registered = ContentRegistered::from_log(&get_logs()[0]).unwrap();
+        assert_eq!(registered, ContentRegistered { cid: cid.clone(), owner: accounts(1).to_string(), size: 5 });
+
+        // The keeper signs as the contract owner with this module's argument encoding
+        let args = serde_json::to_value(UpdatePinStatusArgs {
+            cid: cid.clone(),
+            status: PinStatus::Pinned,
+            provider: Some(StorageProvider::Pinata),
+        })
+        .unwrap();
+        context.predecessor_account_id(accounts(0));
+        testing_env!(context.build());
+        contract.update_pin_status(
+            serde_json::from_value(args["cid"].clone()).unwrap(),
+            serde_json::from_value(args["status"].clone()).unwrap(),
+            serde_json::from_value(args["provider"].clone()).unwrap(),
+        );
+        let content = contract.get_content(CID(cid)).unwrap();
+        assert_eq!(serde_json::to_value(&content.pin_status).unwrap(), args["status"]);
+        assert_eq!(serde_json::to_value(&content.providers).unwrap(), serde_json::json!([args["provider"]]));
+    }
+
+    #[tokio::test]
+    async fn test_replicates_to_policy_count_and_repairs() {
+        let source = MemoryStore::new().with_options(ImportOptions::cid_v1());
+        let cid = source.put(b"frame").await.unwrap();
+        let local = MemoryStore::new().with_options(ImportOptions::cid_v1());
+        let pinata = MemoryStore::new().with_options(ImportOptions::cid_v1());
+        let spare = MemoryStore::new().with_options(ImportOptions::cid_v1());
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let targets = vec![
+            PinTarget::new("local", StorageProvider::LocalIPFS, local.clone()),
+            PinTarget::new("pinata", StorageProvider::Pinata, pinata.clone()),
+            PinTarget::new("spare", StorageProvider::Web3Storage, spare.clone()),
+        ];
+        let mut manager = PinManager::new(targets, fast_policy(2, 3), tx).with_source(source);
+
+        manager.handle_log(&registered_log(&cid)).await.unwrap();
+        assert_eq!(manager.record(&cid).unwrap().status, PinStatus::Pinned);
+        assert_eq!(manager.record(&cid).unwrap().replicas, vec!["local", "pinata"]);
+        assert!(spare.stat(&cid).await.is_err());
+        assert_eq!(
+            drain(&mut rx),
+            vec![
+                (PinStatus::Pinning, None),
+                (PinStatus::Pinning, Some(StorageProvider::LocalIPFS)),
+                (PinStatus::Pinned, Some(StorageProvider::Pinata)),
+            ]
+        );
+
+        // A lost pin is noticed on verification and re-established elsewhere
+        pinata.unpin(&cid).await.unwrap();
+        manager.verify_all().await.unwrap();
+        assert_eq!(manager.record(&cid).unwrap().replicas, vec!["local", "pinata"]);
+        assert_eq!(drain(&mut rx).last(), Some(&(PinStatus::Pinned, Some(StorageProvider::Pinata))));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retries_with_backoff_then_fails() {
+        let source = MemoryStore::new();
+        let cid = source.put(b"audio").await.unwrap();
+        let flaky = |failures| FlakyStore {
+            inner: source.clone(),
+            failures: AtomicU32::new(failures),
+        };
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let targets = vec![PinTarget::new("kubo", StorageProvider::LocalIPFS, flaky(2))];
+        let mut manager = PinManager::new(targets, fast_policy(1, 3), tx);
+        manager.handle_log(&registered_log(&cid)).await.unwrap();
+        assert_eq!(manager.record(&cid).unwrap().status, PinStatus::Pinned);
+        assert_eq!(drain(&mut rx).last().unwrap().0, PinStatus::Pinned);
+
+        // Backoff waits on the runtime's timer, which the paused clock skips ahead
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let targets = vec![PinTarget::new("kubo", StorageProvider::LocalIPFS, flaky(5))];
+        let policy = ReplicationPolicy { replicas: 1, max_attempts: 3, ..ReplicationPolicy::default() };
+        let mut manager = PinManager::new(targets, policy, tx);
+        let start = tokio::time::Instant::now();
+        manager.handle_log(&registered_log(&cid)).await.unwrap();
+        assert_eq!(manager.record(&cid).unwrap().status, PinStatus::Failed);
+        assert_eq!(drain(&mut rx).last(), Some(&(PinStatus::Failed, None)));
+        assert!(start.elapsed() >= Duration::from_secs(3) && start.elapsed() < Duration::from_secs(4));
+        assert_eq!(ReplicationPolicy::default().backoff(3), Duration::from_secs(4));
+    }
+
+    #[tokio::test]
+    async fn test_run_against_fake_pinning_services() {
+        let origin = FakeKubo::start().await;
+        let cid = origin.client().add_bytes(b"shader").await.unwrap();
+        let first = FakeKubo::start().await;
+        let second = FakeKubo::start().await;
+
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let targets = vec![
+            PinTarget::new("first", StorageProvider::LocalIPFS, first.client()),
+            PinTarget::new("second", StorageProvider::Pinata, second.client()),
+        ];
+        let manager = PinManager::new(targets, fast_policy(2, 2), tx).with_source(origin.client());
+
+        let (logs, events) = mpsc::channel(4);
+        logs.send(registered_log(&cid)).await.unwrap();
+        drop(logs);
+        manager.run(events).await.unwrap();
+
+        assert!(first.is_pinned(&cid));
+        assert!(second.is_pinned(&cid));
+    }
+
+    /// Reporter that fails every report for one CID, like a contract panicking on it
+    struct RejectingReporter {
+        failing_cid: String,
+        calls: Arc<AtomicU32>,
+        sent: mpsc::UnboundedSender<UpdatePinStatusArgs>,
+    }
+
+    impl PinStatusReporter for RejectingReporter {
+        async fn report(&self, update: UpdatePinStatusArgs) -> Result<(), PinError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if update.cid == self.failing_cid {
+                return Err(PinError::Report("update_pin_status failed".to_string()));
+            }
+            self.sent.report(update).await
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_failed_report_does_not_stop_the_manager() {
+        let store = MemoryStore::new();
+        let rejected = store.put(b"rejected").await.unwrap();
+        let accepted = store.put(b"accepted").await.unwrap();
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let calls = Arc::new(AtomicU32::new(0));
+        let reporter = RejectingReporter {
+            failing_cid: rejected.clone(),
+            calls: calls.clone(),
+            sent: tx,
+        };
+        let targets = vec![PinTarget::new("kubo", StorageProvider::LocalIPFS, store)];
+        let mut manager = PinManager::new(targets, fast_policy(1, 3), reporter);
+
+        // Both reports for the rejected CID (Pinning, Pinned) are tried three times each
+        manager.handle_log(&registered_log(&rejected)).await.unwrap();
+        let record = manager.record(&rejected).unwrap();
+        assert_eq!(record.status, PinStatus::Pinned);
+        assert_eq!(record.report_error.as_deref(), Some("pin status reporter failed: update_pin_status failed"));
+        assert_eq!(calls.load(Ordering::SeqCst), 6);
+
+        // Later content is still pinned and reported
+        let (logs, events) = mpsc::channel(4);
+        logs.send(registered_log(&accepted)).await.unwrap();
+        drop(logs);
+        manager.run(events).await.unwrap();
+        let reported: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok()).map(|u| (u.cid, u.status)).collect();
+        assert_eq!(
+            reported,
+            vec![(accepted.clone(), PinStatus::Pinning), (accepted, PinStatus::Pinned)]
+        );
+    }
+}
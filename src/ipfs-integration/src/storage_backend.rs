@@ -0,0 +1,369 @@
+//! Storage backends - pluggable content-addressed stores
+//!
+//! `StorageBackend` is implemented for a Kubo daemon (`IpfsClient`), a local
+//! content-addressed filesystem store and an in-memory store, so CI and offline
+//! studio machines can run without a daemon. The local stores assign the same CIDs
+//! `ipfs add` would, so content moves between backends without changing address.
+
+use crate::ipfs_client::{IpfsClient, IpfsError};
+use crate::unixfs::{file_cid, ImportOptions};
+use cid::Cid;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use futures::future::BoxFuture;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+/// Errors returned by storage backends
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error(transparent)]
+    Ipfs(#[from] IpfsError),
+
+    #[error("local store I/O failed: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("content not found: {0}")]
+    NotFound(String),
+
+    #[error("invalid CID: {0}")]
+    InvalidCid(String),
+
+    #[error("content expected at {expected} was stored as {actual}")]
+    CidMismatch { expected: String, actual: String },
+
+    #[error("serialization failed: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+/// Size and pin state of stored content
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContentStat {
+    pub cid: String,
+    pub size: u64,
+    pub pinned: bool,
+}
+
+/// Content-addressed store that CIDs can be written to and read from
+///
+/// `put` behaves like `ipfs add`: the content is stored and pinned.
+pub trait StorageBackend: Send + Sync {
+    /// Store data and return its CID
+    fn put(&self, data: &[u8]) -> impl Future<Output = Result<String, StorageError>> + Send;
+
+    /// Read content by CID
+    fn get(&self, cid: &str) -> impl Future<Output = Result<Vec<u8>, StorageError>> + Send;
+
+    /// Pin content so it survives garbage collection
+    fn pin(&self, cid: &str) -> impl Future<Output = Result<(), StorageError>> + Send;
+
+    /// Remove the pin for content
+    fn unpin(&self, cid: &str) -> impl Future<Output = Result<(), StorageError>> + Send;
+
+    /// Size and pin state of content
+    fn stat(&self, cid: &str) -> impl Future<Output = Result<ContentStat, StorageError>> + Send;
+}
+
+/// Object-safe form of `StorageBackend`, for holding different backends side by side
+pub trait DynStorageBackend: Send + Sync {
+    fn put_boxed<'a>(&'a self, data: &'a [u8]) -> BoxFuture<'a, Result<String, StorageError>>;
+    fn get_boxed<'a>(&'a self, cid: &'a str) -> BoxFuture<'a, Result<Vec<u8>, StorageError>>;
+    fn pin_boxed<'a>(&'a self, cid: &'a str) -> BoxFuture<'a, Result<(), StorageError>>;
+    fn unpin_boxed<'a>(&'a self, cid: &'a str) -> BoxFuture<'a, Result<(), StorageError>>;
+    fn stat_boxed<'a>(&'a self, cid: &'a str) -> BoxFuture<'a, Result<ContentStat, StorageError>>;
+}
+
+impl<B: StorageBackend> DynStorageBackend for B {
+    fn put_boxed<'a>(&'a self, data: &'a [u8]) -> BoxFuture<'a, Result<String, StorageError>> {
+        Box::pin(self.put(data))
+    }
+
+    fn get_boxed<'a>(&'a self, cid: &'a str) -> BoxFuture<'a, Result<Vec<u8>, StorageError>> {
+        Box::pin(self.get(cid))
+    }
+
+    fn pin_boxed<'a>(&'a self, cid: &'a str) -> BoxFuture<'a, Result<(), StorageError>> {
+        Box::pin(self.pin(cid))
+    }
+
+    fn unpin_boxed<'a>(&'a self, cid: &'a str) -> BoxFuture<'a, Result<(), StorageError>> {
+        Box::pin(self.unpin(cid))
+    }
+
+    fn stat_boxed<'a>(&'a self, cid: &'a str) -> BoxFuture<'a, Result<ContentStat, StorageError>> {
+        Box::pin(self.stat(cid))
+    }
+}
+
+/// Kubo reports missing blocks as command errors; surface them as `NotFound`
+fn kubo_error(cid: &str, error: IpfsError) -> StorageError {
+    match error {
+        IpfsError::Api { ref message, .. } if message.contains("not found") => {
+            StorageError::NotFound(cid.to_string())
+        }
+        error => error.into(),
+    }
+}
+
+impl StorageBackend for IpfsClient {
+    async fn put(&self, data: &[u8]) -> Result<String, StorageError> {
+        Ok(self.add_bytes(data).await?)
+    }
+
+    async fn get(&self, cid: &str) -> Result<Vec<u8>, StorageError> {
+        self.cat(cid).await.map_err(|e| kubo_error(cid, e))
+    }
+
+    async fn pin(&self, cid: &str) -> Result<(), StorageError> {
+        IpfsClient::pin(self, cid).await.map_err(|e| kubo_error(cid, e))
+    }
+
+    async fn unpin(&self, cid: &str) -> Result<(), StorageError> {
+        Ok(IpfsClient::unpin(self, cid).await?)
+    }
+
+    async fn stat(&self, cid: &str) -> Result<ContentStat, StorageError> {
+        let stat = self.files_stat(cid).await.map_err(|e| kubo_error(cid, e))?;
+        Ok(ContentStat {
+            cid: stat.cid,
+            size: stat.size,
+            pinned: self.is_pinned(cid).await?,
+        })
+    }
+}
+
+/// In-memory store, shared between clones
+#[derive(Clone, Default)]
+pub struct MemoryStore {
+    inner: Arc<Mutex<MemoryState>>,
+    options: ImportOptions,
+}
+
+#[derive(Default)]
+struct MemoryState {
+    blocks: HashMap<String, Vec<u8>>,
+    pins: HashSet<String>,
+}
+
+impl MemoryStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Address new content with these import settings (e.g. `ImportOptions::cid_v1()`)
+    pub fn with_options(mut self, options: ImportOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+impl StorageBackend for MemoryStore {
+    async fn put(&self, data: &[u8]) -> Result<String, StorageError> {
+        let cid = file_cid(data, &self.options).to_string();
+        let mut state = self.inner.lock().unwrap();
+        state.blocks.insert(cid.clone(), data.to_vec());
+        state.pins.insert(cid.clone());
+        Ok(cid)
+    }
+
+    async fn get(&self, cid: &str) -> Result<Vec<u8>, StorageError> {
+        let state = self.inner.lock().unwrap();
+        state
+            .blocks
+            .get(cid)
+            .cloned()
+            .ok_or_else(|| StorageError::NotFound(cid.to_string()))
+    }
+
+    async fn pin(&self, cid: &str) -> Result<(), StorageError> {
+        let mut state = self.inner.lock().unwrap();
+        if !state.blocks.contains_key(cid) {
+            return Err(StorageError::NotFound(cid.to_string()));
+        }
+        state.pins.insert(cid.to_string());
+        Ok(())
+    }
+
+    async fn unpin(&self, cid: &str) -> Result<(), StorageError> {
+        self.inner.lock().unwrap().pins.remove(cid);
+        Ok(())
+    }
+
+    async fn stat(&self, cid: &str) -> Result<ContentStat, StorageError> {
+        let state = self.inner.lock().unwrap();
+        let data = state
+            .blocks
+            .get(cid)
+            .ok_or_else(|| StorageError::NotFound(cid.to_string()))?;
+        Ok(ContentStat {
+            cid: cid.to_string(),
+            size: data.len() as u64,
+            pinned: state.pins.contains(cid),
+        })
+    }
+}
+
+/// Content-addressed store on the local filesystem
+///
+/// Layout: `<root>/blocks/<cid>` holds the content, `<root>/pins/<cid>` marks a pin.
+#[derive(Clone, Debug)]
+pub struct LocalStore {
+    root: PathBuf,
+    options: ImportOptions,
+}
+
+impl LocalStore {
+    /// Open (and create if needed) a store rooted at `root`
+    pub fn open(root: impl AsRef<Path>) -> Result<Self, StorageError> {
+        let root = root.as_ref().to_path_buf();
+        std::fs::create_dir_all(root.join("blocks"))?;
+        std::fs::create_dir_all(root.join("pins"))?;
+        Ok(Self {
+            root,
+            options: ImportOptions::default(),
+        })
+    }
+
+    /// Address new content with these import settings (e.g. `ImportOptions::cid_v1()`)
+    pub fn with_options(mut self, options: ImportOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Root directory of the store
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn block_path(&self, cid: &str) -> Result<PathBuf, StorageError> {
+        Ok(self.root.join("blocks").join(Self::checked(cid)?))
+    }
+
+    fn pin_path(&self, cid: &str) -> Result<PathBuf, StorageError> {
+        Ok(self.root.join("pins").join(Self::checked(cid)?))
+    }
+
+    /// CIDs become file names, so only accept well-formed ones
+    fn checked(cid: &str) -> Result<&str, StorageError> {
+        Cid::try_from(cid).map_err(|_| StorageError::InvalidCid(cid.to_string()))?;
+        Ok(cid)
+    }
+
+    async fn block_len(&self, cid: &str) -> Result<u64, StorageError> {
+        match tokio::fs::metadata(self.block_path(cid)?).await {
+            Ok(meta) => Ok(meta.len()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Err(StorageError::NotFound(cid.to_string()))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+impl StorageBackend for LocalStore {
+    async fn put(&self, data: &[u8]) -> Result<String, StorageError> {
+        let cid = file_cid(data, &self.options).to_string();
+        let path = self.block_path(&cid)?;
+        if tokio::fs::metadata(&path).await.is_err() {
+            // Write then rename so readers never observe a partial block
+            let tmp = path.with_extension("tmp");
+            tokio::fs::write(&tmp, data).await?;
+            tokio::fs::rename(&tmp, &path).await?;
+        }
+        tokio::fs::write(self.pin_path(&cid)?, b"").await?;
+        Ok(cid)
+    }
+
+    async fn get(&self, cid: &str) -> Result<Vec<u8>, StorageError> {
+        match tokio::fs::read(self.block_path(cid)?).await {
+            Ok(data) => Ok(data),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Err(StorageError::NotFound(cid.to_string()))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn pin(&self, cid: &str) -> Result<(), StorageError> {
+        self.block_len(cid).await?;
+        tokio::fs::write(self.pin_path(cid)?, b"").await?;
+        Ok(())
+    }
+
+    async fn unpin(&self, cid: &str) -> Result<(), StorageError> {
+        match tokio::fs::remove_file(self.pin_path(cid)?).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    async fn stat(&self, cid: &str) -> Result<ContentStat, StorageError> {
+        let size = self.block_len(cid).await?;
+        let pinned = tokio::fs::metadata(self.pin_path(cid)?).await.is_ok();
+        Ok(ContentStat {
+            cid: cid.to_string(),
+            size,
+            pinned,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_kubo::FakeKubo;
+
+    fn cid_of(data: &[u8]) -> String {
+        file_cid(data, &ImportOptions::default()).to_string()
+    }
+
+    async fn exercise<B: StorageBackend>(backend: &B) {
+        let cid = backend.put(b"shader output").await.unwrap();
+        assert_eq!(backend.get(&cid).await.unwrap(), b"shader output");
+
+        let stat = backend.stat(&cid).await.unwrap();
+        assert_eq!(stat.size, 13);
+        assert!(stat.pinned);
+
+        backend.unpin(&cid).await.unwrap();
+        assert!(!backend.stat(&cid).await.unwrap().pinned);
+        backend.pin(&cid).await.unwrap();
+        assert!(backend.stat(&cid).await.unwrap().pinned);
+    }
+
+    #[tokio::test]
+    async fn test_memory_store() {
+        let store = MemoryStore::new();
+        exercise(&store).await;
+        assert!(matches!(
+            store.get(&cid_of(b"missing")).await,
+            Err(StorageError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_local_store_persists_across_instances() {
+        let root = std::env::temp_dir().join(format!("ipfs-local-store-{}", std::process::id()));
+        let store = LocalStore::open(&root).unwrap();
+        exercise(&store).await;
+
+        let cid = cid_of(b"shader output");
+        let reopened = LocalStore::open(&root).unwrap();
+        assert_eq!(reopened.get(&cid).await.unwrap(), b"shader output");
+        assert!(matches!(
+            reopened.get("../../etc/passwd").await,
+            Err(StorageError::InvalidCid(_))
+        ));
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_kubo_backend() {
+        let daemon = FakeKubo::start().await;
+        exercise(&daemon.client()).await;
+    }
+}
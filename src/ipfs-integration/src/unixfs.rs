@@ -0,0 +1,589 @@
+//! UnixFS importer - computes the same CIDs as `ipfs add`
+//!
+//! Content is chunked, every chunk becomes a leaf (a dag-pb UnixFS file node, or a raw
+//! block with raw leaves) and leaves are linked into a balanced DAG of dag-pb nodes with
+//! at most `max_links` children each.
+
+use crate::chunker::{Chunker, Splitter};
+use cid::{Cid, Version};
+use multihash::{Code, MultihashDigest};
+use std::collections::BTreeMap;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Multicodec of dag-pb blocks
+pub const DAG_PB: u64 = 0x70;
+/// Multicodec of raw blocks
+pub const RAW: u64 = 0x55;
+/// Links per node of the balanced layout used by `ipfs add`
+pub const DEFAULT_MAX_LINKS: usize = 174;
+
+/// UnixFS `Data.Type` values
+const UNIXFS_RAW: u64 = 0;
+const UNIXFS_DIRECTORY: u64 = 1;
+const UNIXFS_FILE: u64 = 2;
+
+/// Import settings, defaulting to those of `ipfs add`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportOptions {
+    pub chunker: Chunker,
+    pub cid_version: Version,
+    pub raw_leaves: bool,
+    pub max_links: usize,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            chunker: Chunker::default(),
+            cid_version: Version::V0,
+            raw_leaves: false,
+            max_links: DEFAULT_MAX_LINKS,
+        }
+    }
+}
+
+impl ImportOptions {
+    /// Settings of `ipfs add --cid-version=1` (which implies raw leaves)
+    pub fn cid_v1() -> Self {
+        Self {
+            cid_version: Version::V1,
+            raw_leaves: true,
+            ..Self::default()
+        }
+    }
+
+    /// Settings `ipfs add` would have used to produce a CID of this version
+    pub fn for_cid(cid: &Cid) -> Self {
+        match cid.version() {
+            Version::V0 => Self::default(),
+            Version::V1 => Self::cid_v1(),
+        }
+    }
+}
+
+/// Encoded IPLD block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub cid: Cid,
+    pub data: Vec<u8>,
+}
+
+/// Result of importing a file
+#[derive(Debug, Clone)]
+pub struct ImportedFile {
+    pub root: Cid,
+    /// File size in bytes (not counting DAG overhead)
+    pub size: u64,
+    /// Size of the whole DAG, as used for `Tsize` when linking to the file
+    pub cumulative_size: u64,
+    /// Blocks in creation order, root last; empty unless blocks were retained
+    pub blocks: Vec<Block>,
+}
+
+/// Link of a dag-pb node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PbLink {
+    pub cid: Cid,
+    pub name: String,
+    pub tsize: u64,
+}
+
+/// Decoded dag-pb node
+#[derive(Debug, Clone, Default)]
+pub struct PbNode {
+    pub links: Vec<PbLink>,
+    pub data: Vec<u8>,
+}
+
+/// Decoded UnixFS `Data` message
+#[derive(Debug, Clone, Default)]
+pub struct UnixFsData {
+    pub data_type: u64,
+    pub data: Vec<u8>,
+    pub filesize: Option<u64>,
+    pub blocksizes: Vec<u64>,
+}
+
+/// Error decoding dag-pb or UnixFS protobufs
+#[derive(Debug, thiserror::Error)]
+#[error("malformed {0}")]
+pub struct DecodeError(&'static str);
+
+#[derive(Debug, Clone)]
+struct Link {
+    cid: Cid,
+    /// Cumulative size of the linked DAG, as stored in `PBLink.Tsize`
+    tsize: u64,
+    /// File bytes below the link, as stored in UnixFS `blocksizes`
+    filesize: u64,
+}
+
+/// Streaming UnixFS importer
+pub struct UnixFsBuilder {
+    options: ImportOptions,
+    splitter: Splitter,
+    /// Pending links per tree level, leaves at level 0
+    levels: Vec<Vec<Link>>,
+    blocks: Option<Vec<Block>>,
+    size: u64,
+}
+
+impl UnixFsBuilder {
+    /// Importer that only tracks the root CID
+    pub fn new(options: ImportOptions) -> Self {
+        assert!(options.max_links >= 2, "max_links must be at least 2");
+        Self {
+            splitter: options.chunker.splitter(),
+            options,
+            levels: vec![Vec::new()],
+            blocks: None,
+            size: 0,
+        }
+    }
+
+    /// Keep every encoded block so the DAG can be exported
+    pub fn retain_blocks(mut self) -> Self {
+        self.blocks = Some(Vec::new());
+        self
+    }
+
+    /// Feed the next slice of file content
+    pub fn update(&mut self, data: &[u8]) {
+        let mut chunks = Vec::new();
+        self.splitter.push(data, &mut chunks);
+        for chunk in chunks {
+            self.add_leaf(&chunk);
+        }
+    }
+
+    /// Flush the last chunk and close the DAG
+    pub fn finish(mut self) -> ImportedFile {
+        if let Some(chunk) = self.splitter.finish() {
+            self.add_leaf(&chunk);
+        }
+        if self.size == 0 && self.levels[0].is_empty() {
+            self.add_leaf(&[]);
+        }
+
+        // Close partial nodes bottom-up until a single root remains
+        let mut level = 0;
+        while level < self.levels.len() {
+            let higher = self.levels[level + 1..].iter().any(|links| !links.is_empty());
+            if !self.levels[level].is_empty() && (higher || self.levels[level].len() > 1) {
+                self.flush(level);
+            }
+            level += 1;
+        }
+
+        let root = self
+            .levels
+            .iter_mut()
+            .rev()
+            .find_map(|links| links.pop())
+            .expect("importer always produces a root");
+        ImportedFile {
+            root: root.cid,
+            size: self.size,
+            cumulative_size: root.tsize,
+            blocks: self.blocks.unwrap_or_default(),
+        }
+    }
+
+    fn add_leaf(&mut self, chunk: &[u8]) {
+        self.size += chunk.len() as u64;
+        let link = if self.options.raw_leaves {
+            let cid = Cid::new_v1(RAW, Code::Sha2_256.digest(chunk));
+            self.emit(cid, chunk.to_vec());
+            Link {
+                cid,
+                tsize: chunk.len() as u64,
+                filesize: chunk.len() as u64,
+            }
+        } else {
+            let node = encode_pb_node(&[], &encode_unixfs_file(chunk, chunk.len() as u64, &[]));
+            let tsize = node.len() as u64;
+            let cid = self.dag_pb_cid(&node);
+            self.emit(cid, node);
+            Link {
+                cid,
+                tsize,
+                filesize: chunk.len() as u64,
+            }
+        };
+        self.push_link(0, link);
+    }
+
+    fn push_link(&mut self, level: usize, link: Link) {
+        if self.levels.len() == level {
+            self.levels.push(Vec::new());
+        }
+        self.levels[level].push(link);
+        if self.levels[level].len() == self.options.max_links {
+            self.flush(level);
+        }
+    }
+
+    /// Replace the pending links of a level with one parent node on the level above
+    fn flush(&mut self, level: usize) {
+        let links = std::mem::take(&mut self.levels[level]);
+        let filesize = links.iter().map(|l| l.filesize).sum();
+        let blocksizes: Vec<u64> = links.iter().map(|l| l.filesize).collect();
+        let pb_links: Vec<PbLink> = links
+            .iter()
+            .map(|l| PbLink {
+                cid: l.cid,
+                name: String::new(),
+                tsize: l.tsize,
+            })
+            .collect();
+        let node = encode_pb_node(&pb_links, &encode_unixfs_file(&[], filesize, &blocksizes));
+        let tsize = node.len() as u64 + links.iter().map(|l| l.tsize).sum::<u64>();
+        let cid = self.dag_pb_cid(&node);
+        self.emit(cid, node);
+        self.push_link(level + 1, Link { cid, tsize, filesize });
+    }
+
+    fn dag_pb_cid(&self, node: &[u8]) -> Cid {
+        dag_pb_cid(node, self.options.cid_version)
+    }
+
+    fn emit(&mut self, cid: Cid, data: Vec<u8>) {
+        if let Some(blocks) = self.blocks.as_mut() {
+            blocks.push(Block { cid, data });
+        }
+    }
+}
+
+/// Import a complete buffer, keeping its blocks
+pub fn import(data: &[u8], options: &ImportOptions) -> ImportedFile {
+    let mut builder = UnixFsBuilder::new(options.clone()).retain_blocks();
+    builder.update(data);
+    builder.finish()
+}
+
+/// CID `ipfs add` would return for this content
+pub fn file_cid(data: &[u8], options: &ImportOptions) -> Cid {
+    let mut builder = UnixFsBuilder::new(options.clone());
+    builder.update(data);
+    builder.finish().root
+}
+
+/// UnixFS directory over already-imported entries (name -> (CID, cumulative size))
+///
+/// Returns the directory block and its own cumulative size.
+pub fn directory_block(entries: &BTreeMap<String, (Cid, u64)>, cid_version: Version) -> (Block, u64) {
+    // BTreeMap iterates names in byte order, as dag-pb requires
+    let links: Vec<PbLink> = entries
+        .iter()
+        .map(|(name, (cid, tsize))| PbLink {
+            cid: *cid,
+            name: name.clone(),
+            tsize: *tsize,
+        })
+        .collect();
+    let mut unixfs = Vec::new();
+    encode_varint_field(&mut unixfs, 1, UNIXFS_DIRECTORY);
+    let node = encode_pb_node(&links, &unixfs);
+    let cumulative_size = node.len() as u64 + links.iter().map(|l| l.tsize).sum::<u64>();
+    let block = Block {
+        cid: dag_pb_cid(&node, cid_version),
+        data: node,
+    };
+    (block, cumulative_size)
+}
+
+/// Incrementally checks content against an expected CID without buffering it
+pub struct CidVerifier {
+    expected: Cid,
+    builder: UnixFsBuilder,
+}
+
+impl CidVerifier {
+    /// Verifier using the `ipfs add` settings implied by the CID version
+    pub fn new(expected: Cid) -> Self {
+        let options = ImportOptions::for_cid(&expected);
+        Self::with_options(expected, options)
+    }
+
+    /// Verifier for content imported with non-default settings
+    pub fn with_options(expected: Cid, options: ImportOptions) -> Self {
+        Self {
+            expected,
+            builder: UnixFsBuilder::new(options),
+        }
+    }
+
+    /// Feed the next slice of content
+    pub fn update(&mut self, data: &[u8]) {
+        self.builder.update(data);
+    }
+
+    /// Whether the content fed so far hashes to the expected CID
+    pub fn finish(self) -> bool {
+        self.builder.finish().root == self.expected
+    }
+}
+
+/// Verify a stream (e.g. a large session bundle on disk) against an expected CID
+pub async fn verify_reader<R: AsyncRead + Unpin>(
+    mut reader: R,
+    expected: Cid,
+    options: ImportOptions,
+) -> std::io::Result<bool> {
+    let mut verifier = CidVerifier::with_options(expected, options);
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf).await?;
+        if n == 0 {
+            return Ok(verifier.finish());
+        }
+        verifier.update(&buf[..n]);
+    }
+}
+
+fn dag_pb_cid(node: &[u8], version: Version) -> Cid {
+    let hash = Code::Sha2_256.digest(node);
+    match version {
+        Version::V0 => Cid::new_v0(hash).expect("sha2-256 is valid for CIDv0"),
+        Version::V1 => Cid::new_v1(DAG_PB, hash),
+    }
+}
+
+/// Whether a dag-pb node is a UnixFS file (or raw) node holding content
+pub fn is_file_node(data: &UnixFsData) -> bool {
+    data.data_type == UNIXFS_FILE || data.data_type == UNIXFS_RAW
+}
+
+/// Whether a dag-pb node is a UnixFS directory
+pub fn is_directory_node(data: &UnixFsData) -> bool {
+    data.data_type == UNIXFS_DIRECTORY
+}
+
+fn encode_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn encode_varint_field(buf: &mut Vec<u8>, field: u64, value: u64) {
+    encode_varint(buf, field << 3);
+    encode_varint(buf, value);
+}
+
+fn encode_bytes_field(buf: &mut Vec<u8>, field: u64, bytes: &[u8]) {
+    encode_varint(buf, (field << 3) | 2);
+    encode_varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+/// UnixFS `Data` message of a file node
+fn encode_unixfs_file(data: &[u8], filesize: u64, blocksizes: &[u64]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(data.len() + 16);
+    encode_varint_field(&mut buf, 1, UNIXFS_FILE);
+    if !data.is_empty() {
+        encode_bytes_field(&mut buf, 2, data);
+    }
+    encode_varint_field(&mut buf, 3, filesize);
+    for size in blocksizes {
+        encode_varint_field(&mut buf, 4, *size);
+    }
+    buf
+}
+
+/// dag-pb `PBNode` in canonical order: links first, then data
+fn encode_pb_node(links: &[PbLink], data: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(data.len() + links.len() * 48 + 8);
+    for link in links {
+        let mut pb_link = Vec::with_capacity(48 + link.name.len());
+        encode_bytes_field(&mut pb_link, 1, &link.cid.to_bytes());
+        encode_bytes_field(&mut pb_link, 2, link.name.as_bytes());
+        encode_varint_field(&mut pb_link, 3, link.tsize);
+        encode_bytes_field(&mut buf, 2, &pb_link);
+    }
+    encode_bytes_field(&mut buf, 1, data);
+    buf
+}
+
+/// Read a varint, advancing `pos`
+pub(crate) fn decode_varint(buf: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *buf.get(*pos)?;
+        *pos += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// Take the next `len` bytes, advancing `pos`; `None` if they run past the buffer
+///
+/// Lengths come from untrusted varints, so the end offset is computed with checked
+/// arithmetic rather than `pos + len`.
+pub(crate) fn take_bytes<'a>(buf: &'a [u8], pos: &mut usize, len: u64) -> Option<&'a [u8]> {
+    let end = pos.checked_add(usize::try_from(len).ok()?)?;
+    let bytes = buf.get(*pos..end)?;
+    *pos = end;
+    Some(bytes)
+}
+
+/// Iterate protobuf fields as (field number, varint value or byte slice)
+fn decode_fields<'a>(buf: &'a [u8], what: &'static str) -> Result<Vec<(u64, Field<'a>)>, DecodeError> {
+    let mut fields = Vec::new();
+    let mut pos = 0;
+    while pos < buf.len() {
+        let key = decode_varint(buf, &mut pos).ok_or(DecodeError(what))?;
+        let field = match key & 7 {
+            0 => Field::Varint(decode_varint(buf, &mut pos).ok_or(DecodeError(what))?),
+            2 => {
+                let len = decode_varint(buf, &mut pos).ok_or(DecodeError(what))?;
+                Field::Bytes(take_bytes(buf, &mut pos, len).ok_or(DecodeError(what))?)
+            }
+            _ => return Err(DecodeError(what)),
+        };
+        fields.push((key >> 3, field));
+    }
+    Ok(fields)
+}
+
+enum Field<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+}
+
+/// Decode a dag-pb block
+pub fn decode_pb_node(block: &[u8]) -> Result<PbNode, DecodeError> {
+    let mut node = PbNode::default();
+    for (number, field) in decode_fields(block, "dag-pb node")? {
+        match (number, field) {
+            (1, Field::Bytes(data)) => node.data = data.to_vec(),
+            (2, Field::Bytes(link)) => {
+                let (mut cid, mut name, mut tsize) = (None, String::new(), 0);
+                for (number, field) in decode_fields(link, "dag-pb link")? {
+                    match (number, field) {
+                        (1, Field::Bytes(hash)) => {
+                            cid = Some(Cid::try_from(hash).map_err(|_| DecodeError("link CID"))?)
+                        }
+                        (2, Field::Bytes(bytes)) => {
+                            name = String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError("link name"))?
+                        }
+                        (3, Field::Varint(size)) => tsize = size,
+                        _ => return Err(DecodeError("dag-pb link")),
+                    }
+                }
+                let cid = cid.ok_or(DecodeError("link CID"))?;
+                node.links.push(PbLink { cid, name, tsize });
+            }
+            _ => return Err(DecodeError("dag-pb node")),
+        }
+    }
+    Ok(node)
+}
+
+/// Decode the UnixFS `Data` message carried in a dag-pb node
+pub fn decode_unixfs(data: &[u8]) -> Result<UnixFsData, DecodeError> {
+    let mut unixfs = UnixFsData::default();
+    for (number, field) in decode_fields(data, "UnixFS data")? {
+        match (number, field) {
+            (1, Field::Varint(data_type)) => unixfs.data_type = data_type,
+            (2, Field::Bytes(bytes)) => unixfs.data = bytes.to_vec(),
+            (3, Field::Varint(filesize)) => unixfs.filesize = Some(filesize),
+            (4, Field::Varint(size)) => unixfs.blocksizes.push(size),
+            // Remaining fields (hashType, fanout, mode, mtime) do not affect content
+            _ => {}
+        }
+    }
+    Ok(unixfs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_ipfs_add_vectors() {
+        let options = ImportOptions::default();
+        assert_eq!(
+            file_cid(b"hello world\n", &options).to_string(),
+            "QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o"
+        );
+        assert_eq!(
+            file_cid(b"", &options).to_string(),
+            "QmbFMke1KXqnYyBBWxB74N4c5SBnJMVAiMNRcGu6x1AwQH"
+        );
+
+        // With CIDv1 a single-chunk file is just its raw leaf
+        let raw = Cid::new_v1(RAW, Code::Sha2_256.digest(b"hello world\n"));
+        assert_eq!(file_cid(b"hello world\n", &ImportOptions::cid_v1()), raw);
+    }
+
+    /// Multi-level DAGs produced by go-ipfs 0.5 `ipfs add --chunker=size-<n>`
+    #[test]
+    fn test_matches_ipfs_add_multi_level_vectors() {
+        let lorem = b"Lorem ipsum dolor sit amet, sit enim montes aliquam. Cras non lorem, \
+            rhoncus condimentum, irure et ante. Pulvinar suscipit odio ante, et tellus a enim, \
+            wisi ipsum, vel rhoncus eget faucibus varius, luctus turpis nibh vel odio nulla pede.";
+        let size = |n| ImportOptions { chunker: Chunker::FixedSize(n), ..ImportOptions::default() };
+
+        // 237 one-byte leaves: a full 174-link node and a 63-link node under the root
+        let file = import(lorem, &size(1));
+        assert_eq!(file.root.to_string(), "QmRQ6NZNUs4JrCT2y7tmCC1wUhjqYuTssB8VXbbN3rMffg");
+        assert_eq!(file.blocks.len(), 240);
+        let root = decode_pb_node(&file.blocks.last().unwrap().data).unwrap();
+        let children: Vec<String> = root.links.iter().map(|link| link.cid.to_string()).collect();
+        assert_eq!(
+            children,
+            ["QmXUcuLGKc8SCMEqG4wgct6NKsSRZQfvB2FCfjDow1PfpB", "QmeEn8dxWTzGAFKvyXoLj4oWbh9putL4vSw4uhLXJrSZhs"]
+        );
+
+        assert_eq!(file_cid(lorem, &size(32)).to_string(), "QmYSLcVQqxKygiq7x9w1XGYxU29EShB8ZemiaQ8GAAw17h");
+        assert_eq!(file_cid(b"foobar\n", &size(2)).to_string(), "QmRJHYTNvC3hmd9gJQARxLR1QMEincccBV53bBw524yyq6");
+
+        // Exactly one full link node stays the root; one more leaf adds a level
+        assert_eq!(file_cid(&[0; 174], &size(1)).to_string(), "QmdgQac8c6Bo3MP5bHAg2yQ25KebFUsmkZFvyByYzf8UCB");
+        assert_eq!(file_cid(&[0; 349], &size(2)).to_string(), "QmcHNWF1d56uCDSfJPA7t9fadZRV9we5HGSTGSmwuqmMP9");
+    }
+
+    #[test]
+    fn test_balanced_dag_shape() {
+        let options = ImportOptions {
+            chunker: Chunker::FixedSize(4),
+            max_links: 3,
+            ..ImportOptions::default()
+        };
+        let data: Vec<u8> = (0..40).collect();
+        let file = import(&data, &options);
+
+        // 10 leaves -> 4 parents -> 2 parents -> root
+        assert_eq!(file.blocks.len(), 10 + 4 + 2 + 1);
+        assert_eq!(file.blocks.last().unwrap().cid, file.root);
+        assert_eq!(file.size, 40);
+
+        // Streaming in uneven slices yields the same DAG
+        let mut builder = UnixFsBuilder::new(options);
+        for piece in data.chunks(7) {
+            builder.update(piece);
+        }
+        assert_eq!(builder.finish().root, file.root);
+    }
+
+    #[tokio::test]
+    async fn test_streaming_verifier() {
+        let data = vec![42u8; 700_000];
+        let cid = file_cid(&data, &ImportOptions::default());
+        // Multi-block content no longer matches a single raw hash
+        assert_ne!(cid, Cid::new_v1(RAW, Code::Sha2_256.digest(&data)));
+
+        assert!(verify_reader(&data[..], cid, ImportOptions::default()).await.unwrap());
+
+        let mut tampered = data.clone();
+        tampered[500_000] ^= 1;
+        let mut verifier = CidVerifier::new(cid);
+        verifier.update(&tampered);
+        assert!(!verifier.finish());
+    }
+}
@@ -1,14 +1,14 @@
-// Production IPFS Storage Contract for NEAR
-// Based on IPFS best practices: https://docs.ipfs.tech/how-to/best-practices-for-nft-data/
-// Implements CIDv1, proper metadata structure, and pinning management
+//! Production IPFS Storage Contract for NEAR
+//!
+//! Based on IPFS best practices: https://docs.ipfs.tech/how-to/best-practices-for-nft-data/
+//! Implements CIDv1, proper metadata structure, and pinning management. The off-chain
+//! `PinManager` in `nft-ipfs-integration` follows its `content_registered` events and
+//! reports back through `update_pin_status`.
 
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::collections::{LookupMap, UnorderedMap, Vector};
-use near_sdk::json_types::U128;
 use near_sdk::serde::{Deserialize, Serialize};
-use near_sdk::{
-    env, near_bindgen, AccountId, Balance, PanicOnDefault, Promise, Timestamp,
-};
+use near_sdk::{env, near, AccountId, NearToken, PanicOnDefault, Timestamp};
 use std::collections::HashMap;
 
 /// IPFS CID (Content Identifier) - always use CIDv1 in base32
@@ -35,7 +35,7 @@ impl CID {
 }
 
 /// Storage provider types
-#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(crate = "near_sdk::serde")]
 pub enum StorageProvider {
     LocalIPFS,         // Local IPFS node
@@ -46,7 +46,7 @@ pub enum StorageProvider {
 }
 
 /// Pin status for content
-#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq)]
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(crate = "near_sdk::serde")]
 pub enum PinStatus {
     Queued,
@@ -107,29 +107,48 @@ pub struct StorageStats {
     pub unique_owners: u64,
 }
 
+/// NEP-297 events logged by the storage contract
+/// Pinning keepers (`pin_manager`) follow `content_registered`
+#[near(event_json(standard = "ipfs_storage"))]
+pub enum StorageEvent {
+    #[event_version("1.0.0")]
+    ContentRegistered {
+        cid: String,
+        owner: AccountId,
+        size: u64,
+        content_type: String,
+    },
+    #[event_version("1.0.0")]
+    PinStatusUpdated {
+        cid: String,
+        status: PinStatus,
+        provider: Option<StorageProvider>,
+    },
+}
+
 /// Main storage contract
-#[near_bindgen]
-#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
+#[near(contract_state)]
+#[derive(PanicOnDefault)]
 pub struct IPFSStorageContract {
     pub owner: AccountId,
     pub content_by_cid: UnorderedMap<CID, StoredContent>,
     pub content_by_owner: LookupMap<AccountId, Vector<CID>>,
     pub total_storage_bytes: u64,
-    pub storage_fee_per_mb: Balance, // Fee per MB per year
+    pub storage_fee_per_mb: NearToken, // Fee per MB per year
     pub pinning_services: HashMap<String, String>, // Service name -> API endpoint
 }
 
-#[near_bindgen]
+#[near]
 impl IPFSStorageContract {
     /// Initialize contract
     #[init]
     pub fn new(owner: AccountId) -> Self {
         Self {
             owner,
-            content_by_cid: UnorderedMap::new(b"c"),
-            content_by_owner: LookupMap::new(b"o"),
+            content_by_cid: UnorderedMap::new(b"c".to_vec()),
+            content_by_owner: LookupMap::new(b"o".to_vec()),
             total_storage_bytes: 0,
-            storage_fee_per_mb: 100_000_000_000_000_000_000_000, // 0.1 NEAR per MB per year
+            storage_fee_per_mb: NearToken::from_millinear(100), // 0.1 NEAR per MB per year
             pinning_services: HashMap::new(),
         }
     }
@@ -149,8 +168,8 @@ impl IPFSStorageContract {
         assert!(cid.validate(), "Invalid CID format. Use CIDv1 base32");
 
         // Calculate required storage fee
-        let size_mb = (size_bytes as f64 / 1_000_000.0).ceil() as u128;
-        let required_fee = size_mb * self.storage_fee_per_mb;
+        let size_mb = size_bytes.div_ceil(1_000_000) as u128;
+        let required_fee = self.storage_fee_per_mb.saturating_mul(size_mb);
 
         assert!(
             env::attached_deposit() >= required_fee,
@@ -180,18 +199,20 @@ impl IPFSStorageContract {
         let mut owner_content = self
             .content_by_owner
             .get(&owner_id)
-            .unwrap_or_else(|| Vector::new(owner_id.as_bytes()));
+            .unwrap_or_else(|| Vector::new(owner_id.as_bytes().to_vec()));
         owner_content.push(&cid);
         self.content_by_owner.insert(&owner_id, &owner_content);
 
         // Update stats
         self.total_storage_bytes += size_bytes;
 
-        // Emit event
-        env::log_str(&format!(
-            "{{\"event\":\"content_registered\",\"cid\":\"{}\",\"owner\":\"{}\",\"size\":{}}}",
-            cid.0, owner_id, size_bytes
-        ));
+        StorageEvent::ContentRegistered {
+            cid: cid.0,
+            owner: owner_id,
+            size: size_bytes,
+            content_type: content.content_type,
+        }
+        .emit();
     }
 
     /// Update pin status (called by oracle or keeper)
@@ -213,7 +234,7 @@ impl IPFSStorageContract {
         content.pin_status = status;
         content.last_pinned = env::block_timestamp();
 
-        if let Some(prov) = provider {
+        if let Some(prov) = provider.clone() {
             if !content.providers.contains(&prov) {
                 content.providers.push(prov);
             }
@@ -221,10 +242,12 @@ impl IPFSStorageContract {
 
         self.content_by_cid.insert(&cid, &content);
 
-        env::log_str(&format!(
-            "{{\"event\":\"pin_status_updated\",\"cid\":\"{}\",\"status\":\"{:?}\"}}",
-            cid.0, content.pin_status
-        ));
+        StorageEvent::PinStatusUpdated {
+            cid: cid.0,
+            status: content.pin_status,
+            provider,
+        }
+        .emit();
     }
 
     /// Get content by CID
@@ -317,7 +340,7 @@ impl IPFSStorageContract {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::test_utils::{accounts, get_logs, VMContextBuilder};
     use near_sdk::testing_env;
 
     #[test]
@@ -333,7 +356,7 @@ mod tests {
     fn test_content_registration() {
         let mut context = VMContextBuilder::new();
         context.predecessor_account_id(accounts(0));
-        context.attached_deposit(1_000_000_000_000_000_000_000_000); // 1 NEAR
+        context.attached_deposit(NearToken::from_near(1));
         testing_env!(context.build());
 
         let mut contract = IPFSStorageContract::new(accounts(0));
@@ -351,5 +374,36 @@ mod tests {
         let content = contract.get_content(cid).unwrap();
         assert_eq!(content.size_bytes, 1_000_000);
         assert_eq!(content.pin_status, PinStatus::Queued);
+
+        let logs = get_logs();
+        assert_eq!(logs.len(), 1);
+        assert!(logs[0].starts_with("EVENT_JSON:{\"standard\":\"ipfs_storage\",\"version\":\"1.0.0\",\"event\":\"content_registered\""));
+    }
+
+    #[test]
+    fn test_owner_updates_pin_status() {
+        let mut context = VMContextBuilder::new();
+        context.predecessor_account_id(accounts(1));
+        context.attached_deposit(NearToken::from_near(1));
+        testing_env!(context.build());
+
+        let mut contract = IPFSStorageContract::new(accounts(0));
+        let cid = CID("bafkreigh2akiscaildcqabsyg3dfr6chu3fgpregiymsck7e7aqa4s52zy".to_string());
+        contract.register_content(cid.clone(), 5, "text/plain".to_string(), None, vec![]);
+
+        context.predecessor_account_id(accounts(0));
+        testing_env!(context.build());
+        contract.update_pin_status(cid.clone(), PinStatus::Pinned, Some(StorageProvider::Pinata));
+
+        let content = contract.get_content(cid).unwrap();
+        assert_eq!(content.pin_status, PinStatus::Pinned);
+        assert!(content.providers == vec![StorageProvider::Pinata]);
+        assert_eq!(
+            get_logs()[0],
+            format!(
+                r#"EVENT_JSON:{{"standard":"ipfs_storage","version":"1.0.0","event":"pin_status_updated","data":{{"cid":"{}","status":"Pinned","provider":"Pinata"}}}}"#,
+                content.cid.0
+            )
+        );
     }
 }
@@ -0,0 +1,647 @@
+//! Creative NFT Auctions
+//!
+//! Timed English auctions with reserve price, minimum bid increment and anti-sniping
+//! extension, plus descending-price Dutch auctions. Auctions are created through
+//! `nft_on_approve` like fixed-price listings and settle through the same
+//! payout/transfer flow.
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near, require, AccountId, NearToken, Promise, Timestamp};
+use near_contract_standards::non_fungible_token::TokenId;
+
+use crate::{
+    BridgeStatus, ChainInfo, CreativeMarketplace, CreativeMarketplaceExt, EmotionalMetadata, ListingMetadata,
+    MarketplaceEvent, PendingSale, SaleArgs, SaleSource,
+};
+
+/// Nanoseconds in a minute
+const NANOS_PER_MINUTE: u64 = 60_000_000_000;
+
+/// Unique identifier for auctions
+pub type AuctionId = u64;
+
+/// Auction terms passed in the `nft_approve` msg
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub enum AuctionTerms {
+    English {
+        min_bid_increment: U128,
+        duration_minutes: u64,
+        // Bids placed within this window before the end push the end time back
+        extension_minutes: u64,
+    },
+    Dutch {
+        end_price: U128,
+        duration_minutes: u64,
+    },
+}
+
+/// Pricing rules of an auction
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub enum AuctionKind {
+    English {
+        reserve_price: NearToken,
+        min_bid_increment: NearToken,
+        extension_window: u64,
+    },
+    Dutch {
+        start_price: NearToken,
+        end_price: NearToken,
+    },
+}
+
+/// Auction lifecycle
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum AuctionStatus {
+    Active,
+    // Token transfer in flight
+    Settling,
+    Settled,
+    // Closed without a sale
+    Ended,
+    Cancelled,
+}
+
+/// Bid on an English auction
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Bid {
+    pub bidder: AccountId,
+    pub amount: NearToken,
+    pub placed_at: Timestamp,
+}
+
+/// Timed auction for a creative NFT
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Auction {
+    pub auction_id: AuctionId,
+    pub token_id: TokenId,
+    pub nft_contract_id: AccountId,
+    pub approval_id: u64,
+    pub seller: AccountId,
+    pub kind: AuctionKind,
+    pub chain: ChainInfo,
+    pub metadata: ListingMetadata,
+    pub emotional_traits: Option<EmotionalMetadata>,
+    pub start_time: Timestamp,
+    pub end_time: Timestamp,
+    // Bid history, highest bid last
+    pub bids: Vec<Bid>,
+    pub status: AuctionStatus,
+}
+
+impl Auction {
+    /// Current highest bid, if any
+    pub fn highest_bid(&self) -> Option<&Bid> {
+        self.bids.last()
+    }
+
+    /// Minimum amount the next English bid must reach
+    pub fn min_next_bid(&self) -> Option<NearToken> {
+        match &self.kind {
+            AuctionKind::English { reserve_price, min_bid_increment, .. } => Some(match self.highest_bid() {
+                Some(bid) => bid.amount.saturating_add(*min_bid_increment),
+                None => *reserve_price,
+            }),
+            AuctionKind::Dutch { .. } => None,
+        }
+    }
+
+    /// Dutch price at the given time, decreasing linearly from start to end price
+    pub fn dutch_price(&self, now: Timestamp) -> Option<NearToken> {
+        match &self.kind {
+            AuctionKind::Dutch { start_price, end_price } => {
+                if now >= self.end_time {
+                    return Some(*end_price);
+                }
+                let duration = (self.end_time - self.start_time) as u128;
+                let elapsed = (now.saturating_sub(self.start_time) as u128).min(duration);
+                if elapsed == 0 {
+                    return Some(*start_price);
+                }
+                // `range * elapsed` overflows for realistic prices over days of nanoseconds, so
+                // split the range into whole and remainder parts; both products fit in u128
+                // because elapsed <= duration and the remainder is below duration
+                let range = start_price.as_yoctonear() - end_price.as_yoctonear();
+                let drop = range / duration * elapsed + range % duration * elapsed / duration;
+                Some(NearToken::from_yoctonear(start_price.as_yoctonear() - drop))
+            }
+            AuctionKind::English { .. } => None,
+        }
+    }
+}
+
+#[near]
+impl CreativeMarketplace {
+    /// Place a bid on an English auction; the attached deposit is the bid
+    ///
+    /// The previous highest bidder is refunded immediately.
+    #[payable]
+    pub fn place_bid(&mut self, auction_id: AuctionId) {
+        let mut auction = self.auctions.get(&auction_id).expect("Auction not found");
+        let bidder = env::predecessor_account_id();
+        let amount = env::attached_deposit();
+        let now = env::block_timestamp();
+
+        if auction.status != AuctionStatus::Active {
+            env::panic_str("Auction is not active");
+        }
+
+        if now >= auction.end_time {
+            env::panic_str("Auction has ended");
+        }
+
+        if bidder == auction.seller {
+            env::panic_str("Seller cannot bid on their own auction");
+        }
+
+        let min_bid = auction.min_next_bid()
+            .unwrap_or_else(|| env::panic_str("Bids are only accepted on English auctions"));
+
+        if amount < min_bid {
+            env::panic_str("Bid is below the minimum required amount");
+        }
+
+        // Refund the outbid bidder
+        if let Some(previous) = auction.highest_bid() {
+            Promise::new(previous.bidder.clone()).transfer(previous.amount).detach();
+        }
+
+        // Anti-sniping: late bids extend the auction
+        if let AuctionKind::English { extension_window, .. } = auction.kind {
+            if auction.end_time - now < extension_window {
+                auction.end_time = now + extension_window;
+            }
+        }
+
+        auction.bids.push(Bid {
+            bidder: bidder.clone(),
+            amount,
+            placed_at: now,
+        });
+
+        self.auctions.insert(&auction_id, &auction);
+
+        MarketplaceEvent::BidPlaced {
+            auction_id,
+            bidder,
+            amount,
+            end_time: U64(auction.end_time),
+        }
+        .emit();
+    }
+
+    /// Buy a Dutch auction at its current price; any overpayment is refunded
+    #[payable]
+    pub fn buy_dutch(&mut self, auction_id: AuctionId) -> Promise {
+        let mut auction = self.auctions.get(&auction_id).expect("Auction not found");
+        let buyer = env::predecessor_account_id();
+        let deposit = env::attached_deposit();
+        let now = env::block_timestamp();
+
+        if auction.status != AuctionStatus::Active {
+            env::panic_str("Auction is not active");
+        }
+
+        if now >= auction.end_time {
+            env::panic_str("Auction has ended");
+        }
+
+        if buyer == auction.seller {
+            env::panic_str("Seller cannot buy their own NFT");
+        }
+
+        let price = auction.dutch_price(now)
+            .unwrap_or_else(|| env::panic_str("Only Dutch auctions can be bought directly"));
+
+        if deposit < price {
+            env::panic_str("Insufficient funds to buy NFT");
+        }
+
+        auction.status = AuctionStatus::Settling;
+        self.auctions.insert(&auction_id, &auction);
+
+        self.internal_start_sale(PendingSale {
+            source: SaleSource::Auction(auction_id),
+            token_id: auction.token_id,
+            nft_contract_id: auction.nft_contract_id,
+            approval_id: auction.approval_id,
+            seller: auction.seller,
+            buyer_id: buyer,
+            price,
+            deposit,
+        })
+    }
+
+    /// Settle an auction after its end time
+    ///
+    /// English auctions with a winning bid move the token to the winner and pay out
+    /// through the regular sale flow; auctions without a sale are closed.
+    pub fn settle_auction(&mut self, auction_id: AuctionId) -> Option<Promise> {
+        let mut auction = self.auctions.get(&auction_id).expect("Auction not found");
+
+        if auction.status != AuctionStatus::Active {
+            env::panic_str("Auction is not active");
+        }
+
+        if env::block_timestamp() < auction.end_time {
+            env::panic_str("Auction has not ended yet");
+        }
+
+        let winner = match (&auction.kind, auction.highest_bid()) {
+            (AuctionKind::English { .. }, Some(bid)) => bid.clone(),
+            _ => {
+                auction.status = AuctionStatus::Ended;
+                self.auctions.insert(&auction_id, &auction);
+                MarketplaceEvent::AuctionEnded { auction_id }.emit();
+                return None;
+            }
+        };
+
+        auction.status = AuctionStatus::Settling;
+        self.auctions.insert(&auction_id, &auction);
+
+        Some(self.internal_start_sale(PendingSale {
+            source: SaleSource::Auction(auction_id),
+            token_id: auction.token_id,
+            nft_contract_id: auction.nft_contract_id,
+            approval_id: auction.approval_id,
+            seller: auction.seller,
+            buyer_id: winner.bidder,
+            price: winner.amount,
+            deposit: winner.amount,
+        }))
+    }
+
+    /// Cancel an auction that has not received any bids
+    pub fn cancel_auction(&mut self, auction_id: AuctionId) {
+        let mut auction = self.auctions.get(&auction_id).expect("Auction not found");
+
+        if auction.seller != env::predecessor_account_id() {
+            env::panic_str("Only seller can cancel auction");
+        }
+
+        if auction.status != AuctionStatus::Active {
+            env::panic_str("Auction is not active");
+        }
+
+        if !auction.bids.is_empty() {
+            env::panic_str("Cannot cancel an auction with bids");
+        }
+
+        auction.status = AuctionStatus::Cancelled;
+        self.auctions.insert(&auction_id, &auction);
+
+        MarketplaceEvent::AuctionCancelled { auction_id }.emit();
+    }
+
+    /// Get auction by ID
+    pub fn get_auction(&self, auction_id: AuctionId) -> Option<Auction> {
+        self.auctions.get(&auction_id)
+    }
+
+    /// Get all active auctions
+    pub fn get_active_auctions(&self) -> Vec<Auction> {
+        self.auctions.values()
+            .filter(|auction| auction.status == AuctionStatus::Active)
+            .collect()
+    }
+
+    /// Get the bid history of an auction, highest bid last
+    pub fn get_auction_bids(&self, auction_id: AuctionId) -> Vec<Bid> {
+        self.auctions.get(&auction_id)
+            .map(|auction| auction.bids)
+            .unwrap_or_default()
+    }
+
+    /// Get the minimum next bid of an English auction
+    pub fn get_min_next_bid(&self, auction_id: AuctionId) -> Option<NearToken> {
+        self.auctions.get(&auction_id).and_then(|auction| auction.min_next_bid())
+    }
+
+    /// Get the current price of a Dutch auction
+    pub fn get_dutch_price(&self, auction_id: AuctionId) -> Option<NearToken> {
+        self.auctions.get(&auction_id)
+            .and_then(|auction| auction.dutch_price(env::block_timestamp()))
+    }
+}
+
+impl CreativeMarketplace {
+    /// Create an auction backed by a NEP-178 approval
+    pub(crate) fn internal_create_auction(
+        &mut self,
+        token_id: TokenId,
+        nft_contract_id: AccountId,
+        approval_id: u64,
+        seller: AccountId,
+        args: SaleArgs,
+    ) -> AuctionId {
+        let terms = args.auction.expect("Auction terms are required");
+        let price = NearToken::from_yoctonear(args.price.0);
+        let start_time = env::block_timestamp();
+
+        let (kind, duration_minutes) = match terms {
+            AuctionTerms::English { min_bid_increment, duration_minutes, extension_minutes } => {
+                require!(min_bid_increment.0 > 0, "Minimum bid increment must be positive");
+                (
+                    AuctionKind::English {
+                        reserve_price: price,
+                        min_bid_increment: NearToken::from_yoctonear(min_bid_increment.0),
+                        extension_window: extension_minutes * NANOS_PER_MINUTE,
+                    },
+                    duration_minutes,
+                )
+            }
+            AuctionTerms::Dutch { end_price, duration_minutes } => {
+                require!(end_price.0 < price.as_yoctonear(), "Dutch end price must be below the start price");
+                (
+                    AuctionKind::Dutch {
+                        start_price: price,
+                        end_price: NearToken::from_yoctonear(end_price.0),
+                    },
+                    duration_minutes,
+                )
+            }
+        };
+
+        require!(duration_minutes > 0, "Auction duration must be positive");
+
+        let auction_id = self.next_auction_id;
+        self.next_auction_id += 1;
+
+        let chain = args.chain_info.unwrap_or_else(|| ChainInfo {
+            chain_name: "NEAR".to_string(),
+            contract_address: nft_contract_id.to_string(),
+            bridge_status: BridgeStatus::NotBridged,
+        });
+
+        let auction = Auction {
+            auction_id,
+            token_id,
+            nft_contract_id,
+            approval_id,
+            seller,
+            kind,
+            chain,
+            metadata: args.metadata,
+            emotional_traits: args.emotional_traits,
+            start_time,
+            end_time: start_time + duration_minutes * NANOS_PER_MINUTE,
+            bids: vec![],
+            status: AuctionStatus::Active,
+        };
+
+        self.auctions.insert(&auction_id, &auction);
+        self.auction_by_token
+            .insert(&(auction.nft_contract_id.clone(), auction.token_id.clone()), &auction_id);
+
+        MarketplaceEvent::AuctionCreated {
+            auction_id,
+            nft_contract_id: auction.nft_contract_id,
+            token_id: auction.token_id,
+            seller: auction.seller,
+            end_time: U64(auction.end_time),
+        }
+        .emit();
+        auction_id
+    }
+
+    /// A new approval of the token replaces the one behind its live auction
+    ///
+    /// An auction without bids is cancelled. An auction with bids is moved onto the new
+    /// approval so it can still settle, and its ID is returned: the token stays in it.
+    pub(crate) fn internal_close_token_auction(
+        &mut self,
+        nft_contract_id: &AccountId,
+        token_id: &TokenId,
+        approval_id: u64,
+    ) -> Option<AuctionId> {
+        let auction_id = self.auction_by_token.get(&(nft_contract_id.clone(), token_id.clone()))?;
+        let mut auction = self.auctions.get(&auction_id).expect("Auction not found");
+        if auction.status != AuctionStatus::Active {
+            return None;
+        }
+
+        if auction.bids.is_empty() {
+            auction.status = AuctionStatus::Cancelled;
+            self.auctions.insert(&auction_id, &auction);
+            MarketplaceEvent::AuctionCancelled { auction_id }.emit();
+            return None;
+        }
+
+        auction.approval_id = approval_id;
+        self.auctions.insert(&auction_id, &auction);
+        Some(auction_id)
+    }
+
+    /// Token transfer failed: Dutch auctions reopen, English auctions close
+    /// (the winning bid has already been refunded by the sale flow)
+    pub(crate) fn internal_reopen_auction(&mut self, auction_id: AuctionId) {
+        let mut auction = self.auctions.get(&auction_id).expect("Auction not found");
+        auction.status = match auction.kind {
+            AuctionKind::Dutch { .. } => AuctionStatus::Active,
+            AuctionKind::English { .. } => AuctionStatus::Ended,
+        };
+        self.auctions.insert(&auction_id, &auction);
+    }
+
+    /// Token transfer succeeded
+    pub(crate) fn internal_complete_auction(&mut self, auction_id: AuctionId) {
+        let mut auction = self.auctions.get(&auction_id).expect("Auction not found");
+        auction.status = AuctionStatus::Settled;
+        self.auctions.insert(&auction_id, &auction);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::testing_env;
+    use near_sdk::PromiseOrValue;
+    use near_contract_standards::non_fungible_token::approval::NonFungibleTokenApprovalReceiver;
+
+    const ONE_NEAR: u128 = 1_000_000_000_000_000_000_000_000;
+
+    fn context(predecessor: &str, timestamp: Timestamp, deposit: u128) {
+        let mut builder = VMContextBuilder::new();
+        builder.current_account_id("marketplace.testnet".parse().unwrap());
+        builder.signer_account_id("seller.testnet".parse().unwrap());
+        builder.predecessor_account_id(predecessor.parse().unwrap());
+        builder.block_timestamp(timestamp);
+        builder.attached_deposit(NearToken::from_yoctonear(deposit));
+        testing_env!(builder.build());
+    }
+
+    /// `nft_approve` msg selling token1 at `price`, by auction if `terms` are given
+    fn sale_msg(price: u128, terms: Option<AuctionTerms>) -> String {
+        near_sdk::serde_json::to_string(&SaleArgs {
+            price: U128(price),
+            chain_info: None,
+            metadata: ListingMetadata {
+                title: "Fractal #1".to_string(),
+                description: "Live fractal session".to_string(),
+                media_url: "ipfs://fractal".to_string(),
+                attributes: vec![],
+            },
+            emotional_traits: None,
+            auction: terms,
+        })
+        .unwrap()
+    }
+
+    /// Runs the soulbound check callback for `approval_id`, as if nft.testnet reported a
+    /// transferable token; returns the listing or auction ID
+    fn approve(marketplace: &mut CreativeMarketplace, approval_id: u64, msg: &str) -> u64 {
+        context("marketplace.testnet", 0, 0);
+        match marketplace.on_soulbound_check(
+            "token1".to_string(),
+            "nft.testnet".parse().unwrap(),
+            "seller.testnet".parse().unwrap(),
+            approval_id,
+            near_sdk::serde_json::from_str(msg).unwrap(),
+            Ok(false),
+        ) {
+            PromiseOrValue::Value(id) => id.parse().unwrap(),
+            PromiseOrValue::Promise(_) => panic!("Expected listing or auction id"),
+        }
+    }
+
+    fn create_auction(marketplace: &mut CreativeMarketplace, price: u128, terms: AuctionTerms) -> AuctionId {
+        approve(marketplace, 1, &sale_msg(price, Some(terms)))
+    }
+
+    fn english() -> AuctionTerms {
+        AuctionTerms::English {
+            min_bid_increment: U128(ONE_NEAR / 10),
+            duration_minutes: 60,
+            extension_minutes: 5,
+        }
+    }
+
+    #[test]
+    fn test_english_bids_and_anti_sniping() {
+        context("seller.testnet", 0, 0);
+        let mut marketplace = CreativeMarketplace::new("owner.testnet".parse().unwrap());
+        let auction_id = create_auction(&mut marketplace, ONE_NEAR, english());
+
+        context("alice.testnet", 0, ONE_NEAR);
+        marketplace.place_bid(auction_id);
+        assert_eq!(marketplace.get_min_next_bid(auction_id).unwrap().as_yoctonear(), ONE_NEAR + ONE_NEAR / 10);
+
+        // A bid two minutes before the end extends the auction by the extension window
+        let late = 58 * NANOS_PER_MINUTE;
+        context("bob.testnet", late, 2 * ONE_NEAR);
+        marketplace.place_bid(auction_id);
+
+        let auction = marketplace.get_auction(auction_id).unwrap();
+        assert_eq!(auction.end_time, late + 5 * NANOS_PER_MINUTE);
+        assert_eq!(auction.highest_bid().unwrap().bidder, "bob.testnet".parse::<AccountId>().unwrap());
+        assert_eq!(marketplace.get_auction_bids(auction_id).len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Bid is below the minimum required amount")]
+    fn test_bid_below_reserve_is_rejected() {
+        context("seller.testnet", 0, 0);
+        let mut marketplace = CreativeMarketplace::new("owner.testnet".parse().unwrap());
+        let auction_id = create_auction(&mut marketplace, ONE_NEAR, english());
+
+        context("alice.testnet", 0, ONE_NEAR / 2);
+        marketplace.place_bid(auction_id);
+    }
+
+    #[test]
+    fn test_dutch_price_descends_linearly() {
+        context("seller.testnet", 0, 0);
+        let mut marketplace = CreativeMarketplace::new("owner.testnet".parse().unwrap());
+        let auction_id = create_auction(
+            &mut marketplace,
+            2 * ONE_NEAR,
+            AuctionTerms::Dutch { end_price: U128(ONE_NEAR), duration_minutes: 10 },
+        );
+
+        context("alice.testnet", 5 * NANOS_PER_MINUTE, 0);
+        assert_eq!(marketplace.get_dutch_price(auction_id).unwrap().as_yoctonear(), ONE_NEAR + ONE_NEAR / 2);
+
+        context("alice.testnet", 20 * NANOS_PER_MINUTE, 0);
+        assert_eq!(marketplace.get_dutch_price(auction_id).unwrap().as_yoctonear(), ONE_NEAR);
+    }
+
+    #[test]
+    fn test_dutch_price_over_a_week_does_not_overflow() {
+        context("seller.testnet", 0, 0);
+        let mut marketplace = CreativeMarketplace::new("owner.testnet".parse().unwrap());
+        let week_minutes = 7 * 24 * 60;
+        let auction_id = create_auction(
+            &mut marketplace,
+            10_000 * ONE_NEAR,
+            AuctionTerms::Dutch { end_price: U128(ONE_NEAR), duration_minutes: week_minutes },
+        );
+
+        context("alice.testnet", week_minutes / 2 * NANOS_PER_MINUTE, 0);
+        assert_eq!(marketplace.get_dutch_price(auction_id).unwrap().as_yoctonear(), 5_000 * ONE_NEAR + ONE_NEAR / 2);
+
+        context("alice.testnet", (week_minutes - 1) * NANOS_PER_MINUTE, 0);
+        let last_minute = marketplace.get_dutch_price(auction_id).unwrap().as_yoctonear();
+        assert!(last_minute > ONE_NEAR && last_minute < 2 * ONE_NEAR);
+    }
+
+    #[test]
+    fn test_settle_without_bids_ends_auction() {
+        context("seller.testnet", 0, 0);
+        let mut marketplace = CreativeMarketplace::new("owner.testnet".parse().unwrap());
+        let auction_id = create_auction(&mut marketplace, ONE_NEAR, english());
+
+        context("anyone.testnet", 61 * NANOS_PER_MINUTE, 0);
+        assert!(marketplace.settle_auction(auction_id).is_none());
+        assert_eq!(marketplace.get_auction(auction_id).unwrap().status, AuctionStatus::Ended);
+    }
+
+    #[test]
+    fn test_reapproval_cancels_auction_without_bids() {
+        context("seller.testnet", 0, 0);
+        let mut marketplace = CreativeMarketplace::new("owner.testnet".parse().unwrap());
+        let auction_id = create_auction(&mut marketplace, ONE_NEAR, english());
+
+        let listing_id = approve(&mut marketplace, 2, &sale_msg(ONE_NEAR, None));
+        assert_eq!(marketplace.get_auction(auction_id).unwrap().status, AuctionStatus::Cancelled);
+        assert!(marketplace.get_active_auctions().is_empty());
+        assert_eq!(marketplace.get_listing(listing_id).unwrap().approval_id, 2);
+    }
+
+    #[test]
+    fn test_reapproval_during_auction_with_bids_keeps_the_auction() {
+        context("seller.testnet", 0, 0);
+        let mut marketplace = CreativeMarketplace::new("owner.testnet".parse().unwrap());
+        let auction_id = create_auction(&mut marketplace, ONE_NEAR, english());
+        context("alice.testnet", 0, ONE_NEAR);
+        marketplace.place_bid(auction_id);
+
+        // The token contract calls back with the new approval; no second sale is opened
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id("marketplace.testnet".parse().unwrap())
+            .signer_account_id("seller.testnet".parse().unwrap())
+            .predecessor_account_id("nft.testnet".parse().unwrap());
+        testing_env!(builder.build());
+        let resolved = marketplace.nft_on_approve(
+            "token1".to_string(),
+            "seller.testnet".parse().unwrap(),
+            2,
+            sale_msg(ONE_NEAR, Some(english())),
+        );
+        assert!(matches!(resolved, PromiseOrValue::Value(id) if id == auction_id.to_string()));
+        assert_eq!(approve(&mut marketplace, 3, &sale_msg(ONE_NEAR, None)), auction_id);
+
+        let auction = marketplace.get_auction(auction_id).unwrap();
+        assert_eq!((auction.status, auction.approval_id), (AuctionStatus::Active, 3));
+        assert_eq!(marketplace.get_active_auctions().len(), 1);
+        assert!(marketplace.get_active_listings().is_empty());
+    }
+}
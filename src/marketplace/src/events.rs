@@ -0,0 +1,86 @@
+//! Marketplace Events
+//!
+//! NEP-297 `EVENT_JSON:` logs for every listing, auction, offer, sale and governance
+//! change, so indexers can follow the marketplace without polling its views.
+//! Bump a variant's `event_version` whenever its payload changes shape.
+
+use near_sdk::json_types::U64;
+use near_sdk::{near, AccountId, NearToken};
+use near_contract_standards::non_fungible_token::TokenId;
+
+use crate::{AuctionId, ListingId, OfferId, OfferTarget, ProposalId, ProposalStatus, ProposalType, SaleSource};
+
+#[near(event_json(standard = "creative_marketplace"))]
+pub enum MarketplaceEvent {
+    #[event_version("1.0.0")]
+    ListingCreated {
+        listing_id: ListingId,
+        nft_contract_id: AccountId,
+        token_id: TokenId,
+        seller: AccountId,
+        price: NearToken,
+    },
+    #[event_version("1.0.0")]
+    ListingCancelled { listing_id: ListingId },
+    #[event_version("1.0.0")]
+    AuctionCreated {
+        auction_id: AuctionId,
+        nft_contract_id: AccountId,
+        token_id: TokenId,
+        seller: AccountId,
+        end_time: U64,
+    },
+    #[event_version("1.0.0")]
+    BidPlaced {
+        auction_id: AuctionId,
+        bidder: AccountId,
+        amount: NearToken,
+        end_time: U64,
+    },
+    #[event_version("1.0.0")]
+    AuctionCancelled { auction_id: AuctionId },
+    // Closed without a sale
+    #[event_version("1.0.0")]
+    AuctionEnded { auction_id: AuctionId },
+    #[event_version("1.0.0")]
+    OfferMade {
+        offer_id: OfferId,
+        buyer: AccountId,
+        amount: NearToken,
+        target: OfferTarget,
+        expires_at: U64,
+    },
+    #[event_version("1.0.0")]
+    OfferCancelled { offer_id: OfferId },
+    // Token transferred and funds released
+    #[event_version("1.0.0")]
+    Sale {
+        source: SaleSource,
+        nft_contract_id: AccountId,
+        token_id: TokenId,
+        seller: AccountId,
+        buyer_id: AccountId,
+        price: NearToken,
+        platform_fee: NearToken,
+        royalties: NearToken,
+    },
+    #[event_version("1.0.0")]
+    ProposalCreated {
+        proposal_id: ProposalId,
+        proposer: AccountId,
+        proposal_type: ProposalType,
+        end_time: U64,
+    },
+    #[event_version("1.0.0")]
+    ProposalVoted {
+        proposal_id: ProposalId,
+        voter: AccountId,
+        vote: bool,
+        votes_for: u64,
+        votes_against: u64,
+    },
+    #[event_version("1.0.0")]
+    ProposalFinalized { proposal_id: ProposalId, status: ProposalStatus },
+    #[event_version("1.0.0")]
+    ProposalExecuted { proposal_id: ProposalId },
+}
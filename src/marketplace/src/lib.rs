@@ -5,18 +5,86 @@
 //! Includes NUWE session marketplace and MODURUST tool marketplace.
 //! Enhanced with emotional computing integration and reputation-based pricing.
 
+use std::collections::HashMap;
+
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{LookupMap, UnorderedMap, UnorderedSet};
-use near_sdk::json_types::U128;
+use near_sdk::collections::{LookupMap, LookupSet, UnorderedMap, UnorderedSet};
+use near_sdk::json_types::{U128, U64};
 use near_sdk::serde::{Deserialize, Serialize};
-use near_sdk::{env, near, AccountId, Promise, Timestamp, NearToken};
-use near_contract_standards::non_fungible_token::TokenId;
+use near_sdk::{
+    env, ext_contract, near, require, AccountId, Gas, NearToken, Promise, PromiseError, PromiseOrValue, Timestamp,
+};
+use near_contract_standards::non_fungible_token::approval::NonFungibleTokenApprovalReceiver;
+use near_contract_standards::non_fungible_token::core::ext_nft_core;
+use near_contract_standards::non_fungible_token::{Token, TokenId};
 
 mod nuwe_marketplace;
 mod modurust_marketplace;
+mod auctions;
+mod offers;
+mod events;
+#[cfg(test)]
+mod mock_nft;
 
 pub use nuwe_marketplace::*;
 pub use modurust_marketplace::*;
+pub use auctions::*;
+pub use offers::*;
+pub use events::*;
+
+/// Gas reserved for the NEP-199 `nft_transfer_payout` on the token contract
+const GAS_FOR_NFT_TRANSFER_PAYOUT: Gas = Gas::from_tgas(25);
+
+/// Gas reserved for the purchase resolution callback
+const GAS_FOR_RESOLVE_PURCHASE: Gas = Gas::from_tgas(15);
+
+/// Gas reserved for the `nft_is_soulbound` query on the token contract
+const GAS_FOR_SOULBOUND_CHECK: Gas = Gas::from_tgas(5);
+
+/// Gas reserved for the soulbound check callback; it also receives any unused gas
+const GAS_FOR_ON_SOULBOUND_CHECK: Gas = Gas::from_tgas(10);
+
+/// Gas reserved for the `nft_token` query behind the deprecated `list_nft`
+const GAS_FOR_NFT_TOKEN: Gas = Gas::from_tgas(5);
+
+/// Gas reserved for the `list_nft` callback, which runs the soulbound check
+const GAS_FOR_ON_LEGACY_LISTING: Gas = Gas::from_tgas(25);
+
+/// NEP-171 transfers require exactly one yoctoNEAR
+const ONE_YOCTO: NearToken = NearToken::from_yoctonear(1);
+
+/// Maximum number of royalty recipients accepted from `nft_transfer_payout`
+const MAX_LEN_PAYOUT: u32 = 10;
+
+/// Upper bound for the platform fee, in basis points (10%)
+const MAX_PLATFORM_FEE_BPS: u32 = 1_000;
+
+/// NEP-199 payout returned by the token contract
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Payout {
+    pub payout: HashMap<AccountId, U128>,
+}
+
+/// NEP-199 royalty interface of the NFT contract
+#[ext_contract(ext_nft_payout)]
+pub trait NonFungibleTokenPayout {
+    fn nft_transfer_payout(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+        balance: U128,
+        max_len_payout: Option<u32>,
+    ) -> Payout;
+}
+
+/// Soulbound view of NFT contracts; tokens of contracts without it cannot be listed
+#[ext_contract(ext_soulbound)]
+pub trait SoulboundView {
+    fn nft_is_soulbound(&self, token_id: TokenId) -> bool;
+}
 
 /// Marketplace contract
 #[near(contract_state)]
@@ -33,15 +101,15 @@ pub struct CreativeMarketplace {
     // DAO governance
     pub dao: DAO,
     
-    // Soulbound token verification
-    pub soulbound_tokens: LookupMap<TokenId, bool>,
-    
     // Cross-chain bridge tracking
     pub cross_chain_tokens: LookupMap<TokenId, ChainInfo>,
     
     // Next listing ID
     pub next_listing_id: u64,
     
+    // Active listing of each (nft_contract_id, token_id)
+    pub listing_by_token: LookupMap<(AccountId, TokenId), ListingId>,
+    
     // Token reputation tracking
     pub token_reputations: LookupMap<TokenId, f32>,
     
@@ -50,6 +118,24 @@ pub struct CreativeMarketplace {
     
     // Marketplace statistics
     pub marketplace_stats: MarketplaceStats,
+    
+    // Platform fee in basis points, paid to the treasury on every sale
+    pub platform_fee_bps: u32,
+    pub treasury_id: AccountId,
+    
+    // Emotional pricing toggle, governed by the DAO
+    pub emotional_pricing_enabled: bool,
+    
+    // English and Dutch auctions
+    pub auctions: UnorderedMap<AuctionId, Auction>,
+    pub next_auction_id: u64,
+    
+    // Latest auction of each (nft_contract_id, token_id)
+    pub auction_by_token: LookupMap<(AccountId, TokenId), AuctionId>,
+    
+    // Escrowed offers on tokens, collections and traits
+    pub offers: UnorderedMap<OfferId, Offer>,
+    pub next_offer_id: u64,
 }
 
 // Marketplace statistics
@@ -60,6 +146,8 @@ pub struct MarketplaceStats {
     pub total_volume: NearToken,
     pub active_listings: u64,
     pub total_users: u64,
+    pub total_platform_fees: NearToken,
+    pub total_royalties_paid: NearToken,
 }
 
 /// Unique identifier for listings
@@ -71,6 +159,9 @@ pub type ListingId = u64;
 pub struct NFTListing {
     pub listing_id: ListingId,
     pub token_id: TokenId,
+    // NFT contract holding the token and the approval granted to the marketplace
+    pub nft_contract_id: AccountId,
+    pub approval_id: u64,
     pub seller: AccountId,
     pub price: NearToken,
     pub chain: ChainInfo,
@@ -82,6 +173,52 @@ pub struct NFTListing {
     pub reputation_score: Option<f32>,
 }
 
+/// Sale arguments passed as `msg` to `nft_approve`
+///
+/// Without `auction` the token is listed at a fixed `price`. For English auctions
+/// `price` is the reserve price, for Dutch auctions it is the starting price.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SaleArgs {
+    pub price: U128,
+    pub chain_info: Option<ChainInfo>,
+    pub metadata: ListingMetadata,
+    pub emotional_traits: Option<EmotionalMetadata>,
+    #[serde(default)]
+    pub auction: Option<AuctionTerms>,
+}
+
+/// Instructions carried in the `nft_approve` msg
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde", untagged)]
+pub enum ApprovalMsg {
+    AcceptOffer(AcceptOfferArgs),
+    Sale(Box<SaleArgs>),
+}
+
+/// Where a sale being settled originated
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub enum SaleSource {
+    Listing(ListingId),
+    Auction(AuctionId),
+    Offer(OfferId),
+}
+
+/// Sale carried through the payout query, token transfer and fund release
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PendingSale {
+    pub source: SaleSource,
+    pub token_id: TokenId,
+    pub nft_contract_id: AccountId,
+    pub approval_id: u64,
+    pub seller: AccountId,
+    pub buyer_id: AccountId,
+    pub price: NearToken,
+    pub deposit: NearToken,
+}
+
 /// Emotional metadata for NFTs
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
@@ -134,7 +271,9 @@ pub struct DAO {
     pub proposals: UnorderedMap<ProposalId, Proposal>,
     pub members: UnorderedSet<AccountId>,
     pub next_proposal_id: u64,
-    pub quorum_percentage: u32, // Percentage of votes needed to pass
+    pub quorum_percentage: u32, // Percentage of members that must vote for a valid outcome
+    // Members that already voted on each proposal
+    pub voters: LookupSet<(ProposalId, AccountId)>,
 }
 
 /// Unique identifier for proposals
@@ -160,14 +299,16 @@ pub struct Proposal {
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
 pub enum ProposalType {
-    AddMarketplaceFee,
+    AddMarketplaceFee { fee_bps: u32 },
     RemoveMarketplaceFee,
-    ChangeQuorum,
-    AddMember,
-    RemoveMember,
+    ChangeQuorum { quorum_percentage: u32 },
+    AddMember { account_id: AccountId },
+    RemoveMember { account_id: AccountId },
+    // Signaling only: contract upgrades are deployed out of band
     UpdateContract,
     // Add new proposal types
-    AddEmotionalPricing,
+    AddEmotionalPricing { enabled: bool },
+    // Signaling only: reputation scores are managed off-chain
     UpdateReputationSystem,
 }
 
@@ -220,10 +361,11 @@ impl CreativeMarketplace {
                 members: UnorderedSet::new(b"m".to_vec()),
                 next_proposal_id: 1,
                 quorum_percentage: 51, // 51% required for quorum
+                voters: LookupSet::new(b"v".to_vec()),
             },
-            soulbound_tokens: LookupMap::new(b"s".to_vec()),
             cross_chain_tokens: LookupMap::new(b"c".to_vec()),
             next_listing_id: 1,
+            listing_by_token: LookupMap::new(b"t".to_vec()),
             token_reputations: LookupMap::new(b"r".to_vec()),
             emotional_data: LookupMap::new(b"e".to_vec()),
             marketplace_stats: MarketplaceStats {
@@ -231,12 +373,26 @@ impl CreativeMarketplace {
                 total_volume: NearToken::from_yoctonear(0),
                 active_listings: 0,
                 total_users: 1, // Owner is first user
+                total_platform_fees: NearToken::from_yoctonear(0),
+                total_royalties_paid: NearToken::from_yoctonear(0),
             },
+            platform_fee_bps: 0,
+            treasury_id: owner_id,
+            emotional_pricing_enabled: false,
+            auctions: UnorderedMap::new(b"a".to_vec()),
+            next_auction_id: 1,
+            auction_by_token: LookupMap::new(b"u".to_vec()),
+            offers: UnorderedMap::new(b"o".to_vec()),
+            next_offer_id: 1,
         }
     }
 
-    /// List an NFT for sale with emotional and reputation data
-    #[payable]
+    /// List an NFT for sale with emotional traits
+    ///
+    /// Deprecated: call `nft_approve` on the token contract with a `SaleArgs` msg instead.
+    /// Kept for existing callers, who must already have approved the marketplace on the
+    /// token contract named by `chain_info.contract_address`; the listing uses that
+    /// approval. Resolves to the listing ID.
     pub fn list_nft_with_emotion(
         &mut self,
         token_id: TokenId,
@@ -244,42 +400,31 @@ impl CreativeMarketplace {
         chain_info: ChainInfo,
         metadata: ListingMetadata,
         emotional_traits: Option<EmotionalMetadata>,
-    ) -> ListingId {
-        // Verify the token is not soulbound
-        if let Some(is_soulbound) = self.soulbound_tokens.get(&token_id) {
-            if is_soulbound {
-                env::panic_str("Cannot list soulbound tokens for sale");
-            }
-        }
-        
-        let listing_id = self.next_listing_id;
-        self.next_listing_id += 1;
-        
-        // Get reputation score if available
-        let reputation_score = self.token_reputations.get(&token_id);
-        
-        let listing = NFTListing {
-            listing_id,
-            token_id,
-            seller: env::predecessor_account_id(),
-            price: NearToken::from_yoctonear(price.into()),
-            chain: chain_info,
+    ) -> Promise {
+        env::log_str("list_nft is deprecated: call nft_approve on the token contract with SaleArgs as msg");
+        let nft_contract_id: AccountId = chain_info
+            .contract_address
+            .parse()
+            .unwrap_or_else(|_| env::panic_str("chain_info.contract_address must be the NFT contract account"));
+        let args = SaleArgs {
+            price,
+            chain_info: Some(chain_info),
             metadata,
-            created_at: env::block_timestamp(),
-            is_active: true,
             emotional_traits,
-            reputation_score,
+            auction: None,
         };
-        
-        self.listings.insert(&listing_id, &listing);
-        
-        // Update marketplace stats
-        self.marketplace_stats.active_listings += 1;
-        
-        listing_id
+
+        ext_nft_core::ext(nft_contract_id.clone())
+            .with_static_gas(GAS_FOR_NFT_TOKEN)
+            .nft_token(token_id.clone())
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_ON_LEGACY_LISTING)
+                    .on_legacy_listing(token_id, nft_contract_id, env::predecessor_account_id(), args),
+            )
     }
 
-    /// List an NFT for sale (backward compatibility)
+    /// List an NFT for sale (backward compatibility, deprecated like `list_nft_with_emotion`)
     #[payable]
     pub fn list_nft(
         &mut self,
@@ -287,11 +432,36 @@ impl CreativeMarketplace {
         price: U128,
         chain_info: ChainInfo,
         metadata: ListingMetadata,
-    ) -> ListingId {
+    ) -> Promise {
         self.list_nft_with_emotion(token_id, price, chain_info, metadata, None)
     }
 
+    /// Callback after `nft_token` for the deprecated `list_nft`: list with the seller's
+    /// existing approval, after the same soulbound check as `nft_on_approve`
+    #[private]
+    pub fn on_legacy_listing(
+        &mut self,
+        token_id: TokenId,
+        nft_contract_id: AccountId,
+        seller: AccountId,
+        args: SaleArgs,
+        #[callback_result] token: Result<Option<Token>, PromiseError>,
+    ) -> Promise {
+        let token = token.ok().flatten().unwrap_or_else(|| env::panic_str("Token not found"));
+        require!(token.owner_id == seller, "Only the token owner can list it for sale");
+        let approval_id = token
+            .approved_account_ids
+            .and_then(|approvals| approvals.get(&env::current_account_id()).copied())
+            .unwrap_or_else(|| env::panic_str("Approve the marketplace on the token contract first"));
+
+        self.internal_check_soulbound(token_id, nft_contract_id, seller, approval_id, ApprovalMsg::Sale(Box::new(args)))
+    }
+
     /// Buy an NFT with emotional pricing consideration
+    ///
+    /// Royalties are queried from the token contract (NEP-199) first, then the token is
+    /// moved with the marketplace's NEP-178 approval. Funds are only released once
+    /// `nft_transfer` succeeds, otherwise the buyer is refunded.
     #[payable]
     pub fn buy_nft(&mut self, listing_id: ListingId) -> Promise {
         let mut listing = self.listings.get(&listing_id).expect("Listing not found");
@@ -300,23 +470,176 @@ impl CreativeMarketplace {
             env::panic_str("Listing is not active");
         }
         
-        if env::attached_deposit() < listing.price {
+        let buyer = env::predecessor_account_id();
+        let deposit = env::attached_deposit();
+        
+        if buyer == listing.seller {
+            env::panic_str("Seller cannot buy their own NFT");
+        }
+        
+        if deposit < listing.price {
             env::panic_str("Insufficient funds to buy NFT");
         }
         
+        // Lock the listing while the payout query and transfer are in flight
         listing.is_active = false;
         self.listings.insert(&listing_id, &listing);
         
+        self.internal_start_sale(PendingSale {
+            source: SaleSource::Listing(listing_id),
+            token_id: listing.token_id,
+            nft_contract_id: listing.nft_contract_id,
+            approval_id: listing.approval_id,
+            seller: listing.seller,
+            buyer_id: buyer,
+            price: listing.price,
+            deposit,
+        })
+    }
+
+    /// Callback after `nft_is_soulbound`: refuse soulbound tokens, otherwise create the
+    /// listing or auction, or accept the offer, carried in the approval msg
+    ///
+    /// Fails closed: if the query fails, e.g. because the token contract has no soulbound
+    /// view, the token cannot be shown to be transferable and is refused too.
+    ///
+    /// A token has at most one open sale: an open listing or bid-less auction is
+    /// cancelled, while an auction with bids keeps the token (see `nft_on_approve`).
+    #[private]
+    pub fn on_soulbound_check(
+        &mut self,
+        token_id: TokenId,
+        nft_contract_id: AccountId,
+        owner_id: AccountId,
+        approval_id: u64,
+        msg: ApprovalMsg,
+        #[callback_result] is_soulbound: Result<bool, PromiseError>,
+    ) -> PromiseOrValue<String> {
+        match is_soulbound {
+            Ok(false) => {}
+            Ok(true) => env::panic_str("Cannot list soulbound tokens for sale"),
+            Err(_) => env::panic_str("Token contract did not confirm the token is transferable"),
+        }
+
+        // The new approval invalidates the one behind any open listing or auction of the token
+        self.internal_close_token_listing(&nft_contract_id, &token_id);
+        if let Some(auction_id) = self.internal_close_token_auction(&nft_contract_id, &token_id, approval_id) {
+            return PromiseOrValue::Value(auction_id.to_string());
+        }
+
+        let args = match msg {
+            ApprovalMsg::AcceptOffer(AcceptOfferArgs { accept_offer }) => {
+                return PromiseOrValue::Promise(
+                    self.internal_accept_offer(accept_offer, token_id, nft_contract_id, approval_id, owner_id),
+                );
+            }
+            ApprovalMsg::Sale(args) => *args,
+        };
+        
+        let id = if args.auction.is_some() {
+            self.internal_create_auction(token_id, nft_contract_id, approval_id, owner_id, args)
+        } else {
+            self.internal_create_listing(token_id, nft_contract_id, approval_id, owner_id, args)
+        };
+        
+        PromiseOrValue::Value(id.to_string())
+    }
+
+    /// Callback after `nft_transfer_payout`: split funds on success, refund the buyer on failure
+    ///
+    /// The token moved together with the payout, so an invalid royalty split only changes
+    /// who is paid: the full proceeds then go to the seller.
+    #[private]
+    pub fn resolve_purchase(
+        &mut self,
+        sale: PendingSale,
+        #[callback_result] payout: Result<Payout, PromiseError>,
+    ) -> bool {
+        let Ok(payout) = payout else {
+            // Transfer failed (approval revoked, token moved, no NEP-199, ...): reopen the sale and refund
+            if self.internal_reopen_sale(&sale.source) {
+                Promise::new(sale.buyer_id).transfer(sale.deposit).detach();
+            }
+            return false;
+        };
+        
+        let fee = self.platform_fee(sale.price);
+        let proceeds = sale.price.saturating_sub(fee);
+        let payout = Self::validate_payout(payout.payout, &sale.seller, proceeds)
+            .unwrap_or_else(|| HashMap::from([(sale.seller.clone(), U128(proceeds.as_yoctonear()))]));
+        let mut royalties = NearToken::from_yoctonear(0);
+        
+        // Pay royalty recipients and the seller
+        for (receiver_id, amount) in payout {
+            let amount = NearToken::from_yoctonear(amount.0);
+            if amount.is_zero() {
+                continue;
+            }
+            if receiver_id != sale.seller {
+                royalties = royalties.saturating_add(amount);
+            }
+            Promise::new(receiver_id).transfer(amount).detach();
+        }
+        
+        // Platform fee to the treasury
+        if !fee.is_zero() {
+            Promise::new(self.treasury_id.clone()).transfer(fee).detach();
+        }
+        
+        // Refund any overpayment
+        let refund = sale.deposit.saturating_sub(sale.price);
+        if !refund.is_zero() {
+            Promise::new(sale.buyer_id.clone()).transfer(refund).detach();
+        }
+        
+        self.internal_complete_sale(&sale.source);
+        // The token moved, so any listing of it can no longer be filled
+        self.internal_close_token_listing(&sale.nft_contract_id, &sale.token_id);
+
+        MarketplaceEvent::Sale {
+            source: sale.source,
+            nft_contract_id: sale.nft_contract_id,
+            token_id: sale.token_id,
+            seller: sale.seller,
+            buyer_id: sale.buyer_id,
+            price: sale.price,
+            platform_fee: fee,
+            royalties,
+        }
+        .emit();
+        
         // Update marketplace stats
         self.marketplace_stats.total_sales += 1;
         self.marketplace_stats.total_volume = self.marketplace_stats.total_volume
-            .checked_add(listing.price)
+            .checked_add(sale.price)
             .expect("Overflow in total volume calculation");
-        self.marketplace_stats.active_listings -= 1;
+        self.marketplace_stats.total_platform_fees = self.marketplace_stats.total_platform_fees.saturating_add(fee);
+        self.marketplace_stats.total_royalties_paid = self.marketplace_stats.total_royalties_paid.saturating_add(royalties);
         
-        // Transfer funds to seller
-        Promise::new(listing.seller)
-            .transfer(listing.price)
+        true
+    }
+
+    /// Set the platform fee in basis points (owner only)
+    pub fn set_platform_fee(&mut self, fee_bps: u32) {
+        assert_eq!(env::predecessor_account_id(), self.owner_id, "Only owner can set the platform fee");
+        require!(fee_bps <= MAX_PLATFORM_FEE_BPS, "Platform fee exceeds maximum");
+        self.platform_fee_bps = fee_bps;
+    }
+
+    /// Set the account receiving platform fees (owner only)
+    pub fn set_treasury(&mut self, treasury_id: AccountId) {
+        assert_eq!(env::predecessor_account_id(), self.owner_id, "Only owner can set the treasury");
+        self.treasury_id = treasury_id;
+    }
+
+    /// Get the platform fee in basis points
+    pub fn get_platform_fee(&self) -> u32 {
+        self.platform_fee_bps
+    }
+
+    /// Get the treasury account
+    pub fn get_treasury(&self) -> AccountId {
+        self.treasury_id.clone()
     }
 
     /// Cancel a listing
@@ -328,17 +651,19 @@ impl CreativeMarketplace {
             env::panic_str("Only seller can cancel listing");
         }
         
+        if !listing.is_active {
+            env::panic_str("Listing is not active");
+        }
+        
         let mut updated_listing = listing.clone();
         updated_listing.is_active = false;
         self.listings.insert(&listing_id, &updated_listing);
-        
+        self.listing_by_token.remove(&(listing.nft_contract_id, listing.token_id));
+
         // Update marketplace stats
         self.marketplace_stats.active_listings -= 1;
-    }
-
-    /// Register a soulbound token
-    pub fn register_soulbound_token(&mut self, token_id: TokenId) {
-        self.soulbound_tokens.insert(&token_id, &true);
+        
+        MarketplaceEvent::ListingCancelled { listing_id }.emit();
     }
 
     /// Register a cross-chain token
@@ -412,6 +737,8 @@ impl CreativeMarketplace {
             env::panic_str("Only DAO members can create proposals");
         }
         
+        self.assert_valid_proposal(&proposal_type);
+        
         let proposal_id = self.dao.next_proposal_id;
         self.dao.next_proposal_id += 1;
         
@@ -424,11 +751,19 @@ impl CreativeMarketplace {
             votes_for: 0,
             votes_against: 0,
             created_at: env::block_timestamp(),
-            end_time: env::block_timestamp() + (duration_hours * 3600_000_000_000), // Convert hours to nanoseconds
+            end_time: env::block_timestamp() + (duration_hours * 3_600_000_000_000), // Convert hours to nanoseconds
             status: ProposalStatus::Active,
         };
         
         self.dao.proposals.insert(&proposal_id, &proposal);
+        
+        MarketplaceEvent::ProposalCreated {
+            proposal_id,
+            proposer: proposal.proposer,
+            proposal_type: proposal.proposal_type,
+            end_time: U64(proposal.end_time),
+        }
+        .emit();
         proposal_id
     }
 
@@ -450,6 +785,11 @@ impl CreativeMarketplace {
             env::panic_str("Voting period has ended");
         }
         
+        // One vote per member per proposal
+        if !self.dao.voters.insert(&(proposal_id, env::predecessor_account_id())) {
+            env::panic_str("Member has already voted on this proposal");
+        }
+        
         if vote {
             proposal.votes_for += 1;
         } else {
@@ -457,15 +797,111 @@ impl CreativeMarketplace {
         }
         
         self.dao.proposals.insert(&proposal_id, &proposal);
+        
+        MarketplaceEvent::ProposalVoted {
+            proposal_id,
+            voter: env::predecessor_account_id(),
+            vote,
+            votes_for: proposal.votes_for,
+            votes_against: proposal.votes_against,
+        }
+        .emit();
+    }
+
+    /// DAO: Close voting and record whether the proposal passed
+    ///
+    /// Can be called by anyone once the voting period is over. A proposal passes when
+    /// `quorum_percentage` of the members voted and votes for outnumber votes against.
+    pub fn finalize_proposal(&mut self, proposal_id: ProposalId) -> ProposalStatus {
+        let mut proposal = self.dao.proposals.get(&proposal_id)
+            .expect("Proposal not found");
+            
+        if proposal.status != ProposalStatus::Active {
+            env::panic_str("Proposal is not active");
+        }
+        
+        if env::block_timestamp() <= proposal.end_time {
+            env::panic_str("Voting period has not ended");
+        }
+        
+        let total_votes = proposal.votes_for + proposal.votes_against;
+        let quorum_reached =
+            total_votes * 100 >= self.dao.members.len() * self.dao.quorum_percentage as u64;
+        
+        proposal.status = if quorum_reached && proposal.votes_for > proposal.votes_against {
+            ProposalStatus::Passed
+        } else {
+            ProposalStatus::Rejected
+        };
+        
+        self.dao.proposals.insert(&proposal_id, &proposal);
+        
+        MarketplaceEvent::ProposalFinalized { proposal_id, status: proposal.status.clone() }.emit();
+        proposal.status
+    }
+
+    /// DAO: Apply the effects of a passed proposal to the marketplace
+    pub fn execute_proposal(&mut self, proposal_id: ProposalId) {
+        if !self.dao.members.contains(&env::predecessor_account_id()) {
+            env::panic_str("Only DAO members can execute proposals");
+        }
+        
+        let mut proposal = self.dao.proposals.get(&proposal_id)
+            .expect("Proposal not found");
+            
+        if proposal.status != ProposalStatus::Passed {
+            env::panic_str("Only passed proposals can be executed");
+        }
+        
+        self.assert_valid_proposal(&proposal.proposal_type);
+        
+        match &proposal.proposal_type {
+            ProposalType::AddMarketplaceFee { fee_bps } => {
+                self.platform_fee_bps = *fee_bps;
+            }
+            ProposalType::RemoveMarketplaceFee => {
+                self.platform_fee_bps = 0;
+            }
+            ProposalType::ChangeQuorum { quorum_percentage } => {
+                self.dao.quorum_percentage = *quorum_percentage;
+            }
+            ProposalType::AddMember { account_id } => {
+                self.internal_add_dao_member(account_id);
+            }
+            ProposalType::RemoveMember { account_id } => {
+                self.dao.members.remove(account_id);
+            }
+            ProposalType::AddEmotionalPricing { enabled } => {
+                self.emotional_pricing_enabled = *enabled;
+            }
+            ProposalType::UpdateContract | ProposalType::UpdateReputationSystem => {}
+        }
+        
+        proposal.status = ProposalStatus::Executed;
+        self.dao.proposals.insert(&proposal_id, &proposal);
+        
+        MarketplaceEvent::ProposalExecuted { proposal_id }.emit();
+    }
+
+    /// Check whether a member already voted on a proposal
+    pub fn has_voted(&self, proposal_id: ProposalId, account_id: AccountId) -> bool {
+        self.dao.voters.contains(&(proposal_id, account_id))
+    }
+
+    /// Get the current DAO quorum percentage
+    pub fn get_quorum_percentage(&self) -> u32 {
+        self.dao.quorum_percentage
+    }
+
+    /// Check whether emotional pricing is enabled
+    pub fn is_emotional_pricing_enabled(&self) -> bool {
+        self.emotional_pricing_enabled
     }
 
     /// DAO: Add a member
     pub fn add_dao_member(&mut self, account_id: AccountId) {
         assert_eq!(env::predecessor_account_id(), self.owner_id, "Only owner can add DAO members");
-        self.dao.members.insert(&account_id);
-        
-        // Update marketplace stats
-        self.marketplace_stats.total_users += 1;
+        self.internal_add_dao_member(&account_id);
     }
 
     /// DAO: Remove a member
@@ -487,6 +923,241 @@ impl CreativeMarketplace {
     }
 }
 
+impl CreativeMarketplace {
+    /// Add a DAO member, counting them as a new user only once
+    fn internal_add_dao_member(&mut self, account_id: &AccountId) {
+        if self.dao.members.insert(account_id) {
+            // Update marketplace stats
+            self.marketplace_stats.total_users += 1;
+        }
+    }
+
+    /// Reject proposals whose parameters could never be applied
+    fn assert_valid_proposal(&self, proposal_type: &ProposalType) {
+        match proposal_type {
+            ProposalType::AddMarketplaceFee { fee_bps } => {
+                require!(*fee_bps <= MAX_PLATFORM_FEE_BPS, "Platform fee exceeds maximum");
+            }
+            ProposalType::ChangeQuorum { quorum_percentage } => {
+                require!(
+                    (1..=100).contains(quorum_percentage),
+                    "Quorum must be between 1 and 100 percent"
+                );
+            }
+            ProposalType::RemoveMember { account_id } => {
+                require!(self.dao.members.contains(account_id), "Account is not a DAO member");
+                require!(self.dao.members.len() > 1, "Cannot remove the last DAO member");
+            }
+            _ => {}
+        }
+    }
+
+    /// Platform fee owed on a sale at the given price
+    fn platform_fee(&self, price: NearToken) -> NearToken {
+        NearToken::from_yoctonear(price.as_yoctonear() * self.platform_fee_bps as u128 / 10_000)
+    }
+
+    /// Transfer the token together with its royalty split, then release funds in the callback
+    pub(crate) fn internal_start_sale(&mut self, sale: PendingSale) -> Promise {
+        let proceeds = sale.price.saturating_sub(self.platform_fee(sale.price));
+        
+        ext_nft_payout::ext(sale.nft_contract_id.clone())
+            .with_attached_deposit(ONE_YOCTO)
+            .with_static_gas(GAS_FOR_NFT_TRANSFER_PAYOUT)
+            .nft_transfer_payout(
+                sale.buyer_id.clone(),
+                sale.token_id.clone(),
+                Some(sale.approval_id),
+                Some("Creative marketplace sale".to_string()),
+                U128(proceeds.as_yoctonear()),
+                Some(MAX_LEN_PAYOUT),
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_PURCHASE)
+                    .resolve_purchase(sale),
+            )
+    }
+
+    /// Make a sale available again after its token transfer failed.
+    /// Returns whether the buyer's deposit should be refunded.
+    fn internal_reopen_sale(&mut self, source: &SaleSource) -> bool {
+        match source {
+            SaleSource::Listing(listing_id) => {
+                let mut listing = self.listings.get(listing_id).expect("Listing not found");
+                let token_key = (listing.nft_contract_id.clone(), listing.token_id.clone());
+                // A newer approval may have replaced the listing while the transfer was in flight
+                if self.listing_by_token.get(&token_key) == Some(*listing_id) {
+                    listing.is_active = true;
+                    self.listings.insert(listing_id, &listing);
+                } else {
+                    self.marketplace_stats.active_listings -= 1;
+                }
+                true
+            }
+            SaleSource::Auction(auction_id) => {
+                self.internal_reopen_auction(*auction_id);
+                true
+            }
+            SaleSource::Offer(offer_id) => {
+                // The escrow stays with the reopened offer
+                self.internal_reopen_offer(*offer_id);
+                false
+            }
+        }
+    }
+
+    /// Record the end of a sale after its token transfer succeeded
+    fn internal_complete_sale(&mut self, source: &SaleSource) {
+        match source {
+            SaleSource::Listing(_) => {
+                self.marketplace_stats.active_listings -= 1;
+            }
+            SaleSource::Auction(auction_id) => self.internal_complete_auction(*auction_id),
+            SaleSource::Offer(offer_id) => self.internal_complete_offer(*offer_id),
+        }
+    }
+
+    /// Accept a NEP-199 payout only if it fits within the proceeds; any remainder goes to the seller
+    fn validate_payout(
+        mut payout: HashMap<AccountId, U128>,
+        seller: &AccountId,
+        proceeds: NearToken,
+    ) -> Option<HashMap<AccountId, U128>> {
+        if payout.len() > MAX_LEN_PAYOUT as usize {
+            return None;
+        }
+        let total = payout.values().try_fold(0u128, |acc, amount| acc.checked_add(amount.0))?;
+        let remainder = proceeds.as_yoctonear().checked_sub(total)?;
+        if remainder > 0 {
+            let seller_share = payout.entry(seller.clone()).or_insert(U128(0));
+            seller_share.0 += remainder;
+        }
+        Some(payout)
+    }
+
+    /// Create a listing backed by a NEP-178 approval
+    fn internal_create_listing(
+        &mut self,
+        token_id: TokenId,
+        nft_contract_id: AccountId,
+        approval_id: u64,
+        seller: AccountId,
+        args: SaleArgs,
+    ) -> ListingId {
+        let listing_id = self.next_listing_id;
+        self.next_listing_id += 1;
+        
+        // Get reputation score if available
+        let reputation_score = self.token_reputations.get(&token_id);
+        
+        let chain = args.chain_info.unwrap_or_else(|| ChainInfo {
+            chain_name: "NEAR".to_string(),
+            contract_address: nft_contract_id.to_string(),
+            bridge_status: BridgeStatus::NotBridged,
+        });
+        
+        let listing = NFTListing {
+            listing_id,
+            token_id,
+            nft_contract_id,
+            approval_id,
+            seller,
+            price: NearToken::from_yoctonear(args.price.into()),
+            chain,
+            metadata: args.metadata,
+            created_at: env::block_timestamp(),
+            is_active: true,
+            emotional_traits: args.emotional_traits,
+            reputation_score,
+        };
+        
+        self.listings.insert(&listing_id, &listing);
+        self.listing_by_token.insert(&(listing.nft_contract_id.clone(), listing.token_id.clone()), &listing_id);
+        
+        // Update marketplace stats
+        self.marketplace_stats.active_listings += 1;
+        
+        MarketplaceEvent::ListingCreated {
+            listing_id,
+            nft_contract_id: listing.nft_contract_id,
+            token_id: listing.token_id,
+            seller: listing.seller,
+            price: listing.price,
+        }
+        .emit();
+        
+        listing_id
+    }
+
+    /// Forget the token's listing, closing it if it is still open
+    fn internal_close_token_listing(&mut self, nft_contract_id: &AccountId, token_id: &TokenId) {
+        let Some(listing_id) = self.listing_by_token.remove(&(nft_contract_id.clone(), token_id.clone())) else {
+            return;
+        };
+        let mut listing = self.listings.get(&listing_id).expect("Listing not found");
+        if listing.is_active {
+            listing.is_active = false;
+            self.listings.insert(&listing_id, &listing);
+            self.marketplace_stats.active_listings -= 1;
+            MarketplaceEvent::ListingCancelled { listing_id }.emit();
+        }
+    }
+
+    /// Ask the token contract `nft_is_soulbound`, then act on the approval msg in
+    /// `on_soulbound_check`
+    fn internal_check_soulbound(
+        &self,
+        token_id: TokenId,
+        nft_contract_id: AccountId,
+        owner_id: AccountId,
+        approval_id: u64,
+        msg: ApprovalMsg,
+    ) -> Promise {
+        ext_soulbound::ext(nft_contract_id.clone())
+            .with_static_gas(GAS_FOR_SOULBOUND_CHECK)
+            .with_unused_gas_weight(0)
+            .nft_is_soulbound(token_id.clone())
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_ON_SOULBOUND_CHECK)
+                    .on_soulbound_check(token_id, nft_contract_id, owner_id, approval_id, msg),
+            )
+    }
+}
+
+/// Listing entry point: the NFT contract calls this after the owner runs
+/// `nft_approve(token_id, marketplace, msg)` with a JSON-encoded `SaleArgs` msg,
+/// or `{"accept_offer": <offer_id>}` to accept an offer on the token.
+/// The token contract is first asked `nft_is_soulbound`; see `on_soulbound_check`.
+///
+/// While the token is in an auction with bids, the new approval only replaces the one
+/// the auction settles with, and the msg is refused; this resolves to the auction ID.
+#[near]
+impl NonFungibleTokenApprovalReceiver for CreativeMarketplace {
+    fn nft_on_approve(
+        &mut self,
+        token_id: TokenId,
+        owner_id: AccountId,
+        approval_id: u64,
+        msg: String,
+    ) -> PromiseOrValue<String> {
+        require!(
+            env::signer_account_id() == owner_id,
+            "Only the token owner can list it for sale"
+        );
+        
+        let msg: ApprovalMsg = near_sdk::serde_json::from_str(&msg)
+            .unwrap_or_else(|_| env::panic_str("Invalid sale arguments in msg"));
+        
+        let nft_contract_id = env::predecessor_account_id();
+        if let Some(auction_id) = self.internal_close_token_auction(&nft_contract_id, &token_id, approval_id) {
+            return PromiseOrValue::Value(auction_id.to_string());
+        }
+        PromiseOrValue::Promise(self.internal_check_soulbound(token_id, nft_contract_id, owner_id, approval_id, msg))
+    }
+}
+
 impl Default for CreativeMarketplace {
     fn default() -> Self {
         Self::new(env::current_account_id())
@@ -516,37 +1187,295 @@ mod tests {
         assert_eq!(marketplace.next_listing_id, 1);
     }
 
+    fn sale_msg(price: u128) -> String {
+        let args = SaleArgs {
+            price: U128(price),
+            chain_info: Some(ChainInfo {
+                chain_name: "NEAR".to_string(),
+                contract_address: "nft.testnet".to_string(),
+                bridge_status: BridgeStatus::NotBridged,
+            }),
+            metadata: ListingMetadata {
+                title: "Test NFT".to_string(),
+                description: "A test NFT".to_string(),
+                media_url: "https://example.com/image.png".to_string(),
+                attributes: vec![],
+            },
+            emotional_traits: None,
+            auction: None,
+        };
+        near_sdk::serde_json::to_string(&args).unwrap()
+    }
+
+    /// Simulates the mock NFT contract forwarding `nft_approve` to the marketplace,
+    /// resolved through the soulbound check callback
+    fn approve_listing(marketplace: &mut CreativeMarketplace, token_id: &str, price: u128) -> ListingId {
+        check_and_list(marketplace, token_id, price, Ok(false))
+    }
+
+    fn check_and_list(
+        marketplace: &mut CreativeMarketplace,
+        token_id: &str,
+        price: u128,
+        is_soulbound: Result<bool, PromiseError>,
+    ) -> ListingId {
+        let mut context = get_context();
+        context.predecessor_account_id("marketplace.testnet".parse().unwrap());
+        testing_env!(context.build());
+        
+        match marketplace.on_soulbound_check(
+            token_id.to_string(),
+            "nft.testnet".parse().unwrap(),
+            "user.testnet".parse().unwrap(),
+            7,
+            near_sdk::serde_json::from_str(&sale_msg(price)).unwrap(),
+            is_soulbound,
+        ) {
+            PromiseOrValue::Value(id) => id.parse().unwrap(),
+            PromiseOrValue::Promise(_) => panic!("Expected listing id"),
+        }
+    }
+
+    /// Simulates the purchase callback with the outcome of `nft_transfer_payout`
+    fn resolve_with(
+        marketplace: &mut CreativeMarketplace,
+        listing_id: ListingId,
+        payout: Result<HashMap<AccountId, U128>, PromiseError>,
+    ) -> bool {
+        let mut context = get_context();
+        context.predecessor_account_id("marketplace.testnet".parse().unwrap());
+        testing_env!(context.build());
+        // Lock the listing as `buy_nft` does
+        let mut listing = marketplace.get_listing(listing_id).unwrap();
+        listing.is_active = false;
+        marketplace.listings.insert(&listing_id, &listing);
+        let sale = PendingSale {
+            source: SaleSource::Listing(listing_id),
+            token_id: listing.token_id,
+            nft_contract_id: listing.nft_contract_id,
+            approval_id: listing.approval_id,
+            seller: listing.seller,
+            buyer_id: "buyer.testnet".parse().unwrap(),
+            price: listing.price,
+            deposit: NearToken::from_yoctonear(2 * ONE_NEAR),
+        };
+        marketplace.resolve_purchase(sale, payout.map(|payout| Payout { payout }))
+    }
+
+    const ONE_NEAR: u128 = 1_000_000_000_000_000_000_000_000;
+
     #[test]
     fn test_list_nft() {
         let context = get_context().build();
         testing_env!(context);
         
         let mut marketplace = CreativeMarketplace::new("owner.testnet".parse().unwrap());
+        let listing_id = approve_listing(&mut marketplace, "token1", ONE_NEAR);
         
-        let chain_info = ChainInfo {
-            chain_name: "NEAR".to_string(),
-            contract_address: "contract.testnet".to_string(),
-            bridge_status: BridgeStatus::NotBridged,
-        };
+        assert_eq!(listing_id, 1);
+        assert_eq!(marketplace.next_listing_id, 2);
         
-        let metadata = ListingMetadata {
-            title: "Test NFT".to_string(),
-            description: "A test NFT".to_string(),
-            media_url: "https://example.com/image.png".to_string(),
-            attributes: vec![],
-        };
+        let listing = marketplace.get_listing(listing_id).unwrap();
+        assert_eq!(listing.nft_contract_id, "nft.testnet".parse::<AccountId>().unwrap());
+        assert_eq!(listing.approval_id, 7);
+        assert_eq!(listing.seller, "user.testnet".parse::<AccountId>().unwrap());
         
-        let listing_id = marketplace.list_nft(
-            "token1".to_string(),
-            U128(1000000000000000000000000), // 1 NEAR
-            chain_info,
-            metadata,
+        assert_eq!(
+            near_sdk::test_utils::get_logs(),
+            vec![r#"EVENT_JSON:{"standard":"creative_marketplace","version":"1.0.0","event":"listing_created","data":{"listing_id":1,"nft_contract_id":"nft.testnet","token_id":"token1","seller":"user.testnet","price":"1000000000000000000000000"}}"#]
         );
+    }
+
+    #[test]
+    fn test_buy_nft_releases_funds_after_transfer() {
+        testing_env!(get_context().build());
+        let mut marketplace = CreativeMarketplace::new("owner.testnet".parse().unwrap());
+        let listing_id = approve_listing(&mut marketplace, "token1", ONE_NEAR);
         
-        assert_eq!(listing_id, 1);
-        assert_eq!(marketplace.next_listing_id, 2);
+        let mut context = get_context();
+        context.predecessor_account_id("buyer.testnet".parse().unwrap());
+        context.attached_deposit(NearToken::from_yoctonear(ONE_NEAR));
+        testing_env!(context.build());
+        marketplace.buy_nft(listing_id).detach();
+        
+        // Listing is locked while the transfer is pending
+        assert!(!marketplace.get_listing(listing_id).unwrap().is_active);
+        assert_eq!(marketplace.get_marketplace_stats().total_sales, 0);
+        
+        let payout = HashMap::from([("user.testnet".parse().unwrap(), U128(ONE_NEAR))]);
+        assert!(resolve_with(&mut marketplace, listing_id, Ok(payout)));
+        let stats = marketplace.get_marketplace_stats();
+        assert_eq!(stats.total_sales, 1);
+        assert_eq!(stats.active_listings, 0);
+    }
+
+    #[test]
+    fn test_buy_nft_refunds_on_failed_transfer() {
+        testing_env!(get_context().build());
+        let mut marketplace = CreativeMarketplace::new("owner.testnet".parse().unwrap());
+        let listing_id = approve_listing(&mut marketplace, "token1", ONE_NEAR);
+        
+        let mut context = get_context();
+        context.predecessor_account_id("buyer.testnet".parse().unwrap());
+        context.attached_deposit(NearToken::from_yoctonear(ONE_NEAR));
+        testing_env!(context.build());
+        marketplace.buy_nft(listing_id).detach();
+        
+        assert!(!resolve_with(&mut marketplace, listing_id, Err(PromiseError::Failed)));
+        assert!(marketplace.get_listing(listing_id).unwrap().is_active);
+        assert_eq!(marketplace.get_marketplace_stats().total_sales, 0);
+    }
+
+    #[test]
+    fn test_sale_splits_royalties_and_platform_fee() {
+        let mut context = get_context();
+        context.predecessor_account_id("owner.testnet".parse().unwrap());
+        testing_env!(context.build());
+        let mut marketplace = CreativeMarketplace::new("owner.testnet".parse().unwrap());
+        marketplace.set_platform_fee(500); // 5%
+        marketplace.set_treasury("treasury.testnet".parse().unwrap());
+        
+        let listing_id = approve_listing(&mut marketplace, "token1", ONE_NEAR);
+        let fee = ONE_NEAR / 20;
+        let royalty = ONE_NEAR / 10;
+        
+        // Creator takes a royalty, seller keeps the rest of the proceeds
+        let payout = CreativeMarketplace::validate_payout(
+            HashMap::from([("creator.testnet".parse().unwrap(), U128(royalty))]),
+            &"user.testnet".parse().unwrap(),
+            NearToken::from_yoctonear(ONE_NEAR - fee),
+        )
+        .unwrap();
+        assert_eq!(payout[&"user.testnet".parse::<AccountId>().unwrap()].0, ONE_NEAR - fee - royalty);
+        
+        assert!(resolve_with(&mut marketplace, listing_id, Ok(payout)));
+        let stats = marketplace.get_marketplace_stats();
+        assert_eq!(stats.total_platform_fees.as_yoctonear(), fee);
+        assert_eq!(stats.total_royalties_paid.as_yoctonear(), royalty);
+        
+        let log = near_sdk::test_utils::get_logs().pop().unwrap();
+        let event: near_sdk::serde_json::Value =
+            near_sdk::serde_json::from_str(log.strip_prefix("EVENT_JSON:").unwrap()).unwrap();
+        assert_eq!(event["event"], "sale");
+        assert_eq!(event["data"]["buyer_id"], "buyer.testnet");
+        assert_eq!(event["data"]["platform_fee"], fee.to_string());
+        assert_eq!(event["data"]["royalties"], royalty.to_string());
+    }
+
+    #[test]
+    fn test_payout_exceeding_proceeds_is_rejected() {
+        let payout = HashMap::from([("creator.testnet".parse().unwrap(), U128(2 * ONE_NEAR))]);
+        assert!(CreativeMarketplace::validate_payout(
+            payout,
+            &"user.testnet".parse().unwrap(),
+            NearToken::from_yoctonear(ONE_NEAR),
+        )
+        .is_none());
+    }
+
+    fn dao_with_members(members: &[&str]) -> CreativeMarketplace {
+        let mut context = get_context();
+        context.predecessor_account_id("owner.testnet".parse().unwrap());
+        testing_env!(context.build());
+        let mut marketplace = CreativeMarketplace::new("owner.testnet".parse().unwrap());
+        for member in members {
+            marketplace.add_dao_member(member.parse().unwrap());
+        }
+        marketplace
+    }
+
+    fn as_member(member: &str, timestamp: Timestamp) {
+        let mut context = get_context();
+        context.predecessor_account_id(member.parse().unwrap());
+        context.block_timestamp(timestamp);
+        testing_env!(context.build());
+    }
+
+    #[test]
+    fn test_passed_proposal_is_executed() {
+        let mut marketplace = dao_with_members(&["alice.testnet", "bob.testnet", "carol.testnet"]);
+        
+        as_member("alice.testnet", 0);
+        let proposal_id = marketplace.create_proposal(
+            "Lower quorum".to_string(),
+            "Make governance more responsive".to_string(),
+            ProposalType::ChangeQuorum { quorum_percentage: 30 },
+            1,
+        );
+        marketplace.vote_on_proposal(proposal_id, true);
+        as_member("bob.testnet", 0);
+        marketplace.vote_on_proposal(proposal_id, true);
+        
+        as_member("carol.testnet", 2 * 3_600_000_000_000);
+        assert!(marketplace.finalize_proposal(proposal_id) == ProposalStatus::Passed);
+        marketplace.execute_proposal(proposal_id);
+        
+        assert_eq!(marketplace.get_quorum_percentage(), 30);
+        assert!(marketplace.get_proposal(proposal_id).unwrap().status == ProposalStatus::Executed);
+    }
+
+    #[test]
+    fn test_proposal_without_quorum_is_rejected() {
+        let mut marketplace = dao_with_members(&["alice.testnet", "bob.testnet", "carol.testnet"]);
+        
+        as_member("alice.testnet", 0);
+        let proposal_id = marketplace.create_proposal(
+            "Enable emotional pricing".to_string(),
+            "Price listings by emotional traits".to_string(),
+            ProposalType::AddEmotionalPricing { enabled: true },
+            1,
+        );
+        marketplace.vote_on_proposal(proposal_id, true);
+        
+        as_member("alice.testnet", 2 * 3_600_000_000_000);
+        assert!(marketplace.finalize_proposal(proposal_id) == ProposalStatus::Rejected);
+        assert!(!marketplace.is_emotional_pricing_enabled());
+    }
+
+    #[test]
+    #[should_panic(expected = "Member has already voted on this proposal")]
+    fn test_double_vote_is_rejected() {
+        let mut marketplace = dao_with_members(&["alice.testnet"]);
+        
+        as_member("alice.testnet", 0);
+        let proposal_id = marketplace.create_proposal(
+            "Remove fee".to_string(),
+            "No platform fee".to_string(),
+            ProposalType::RemoveMarketplaceFee,
+            1,
+        );
+        marketplace.vote_on_proposal(proposal_id, true);
+        marketplace.vote_on_proposal(proposal_id, true);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the token owner can list it for sale")]
+    fn test_nft_on_approve_requires_owner_signer() {
+        let mut context = get_context();
+        context.predecessor_account_id("nft.testnet".parse().unwrap());
+        context.signer_account_id("mallory.testnet".parse().unwrap());
+        testing_env!(context.build());
+        
+        let mut marketplace = CreativeMarketplace::new("owner.testnet".parse().unwrap());
+        let _ = marketplace.nft_on_approve("token1".to_string(), "user.testnet".parse().unwrap(), 1, sale_msg(ONE_NEAR));
     }
     
+    #[test]
+    #[should_panic(expected = "Cannot list soulbound tokens for sale")]
+    fn test_soulbound_token_is_not_listed() {
+        testing_env!(get_context().build());
+        let mut marketplace = CreativeMarketplace::new("owner.testnet".parse().unwrap());
+        check_and_list(&mut marketplace, "identity1", ONE_NEAR, Ok(true));
+    }
+
+    #[test]
+    #[should_panic(expected = "Token contract did not confirm the token is transferable")]
+    fn test_contract_without_soulbound_view_is_refused() {
+        testing_env!(get_context().build());
+        let mut marketplace = CreativeMarketplace::new("owner.testnet".parse().unwrap());
+        check_and_list(&mut marketplace, "token1", ONE_NEAR, Err(PromiseError::Failed));
+    }
+
     #[test]
     fn test_marketplace_stats() {
         let context = get_context().build();
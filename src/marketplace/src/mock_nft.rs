@@ -0,0 +1,477 @@
+//! Mock NFT contract and an in-process receipt router for end-to-end marketplace tests
+//!
+//! `Sandbox` keeps separate storage for the marketplace and the mock NFT account and
+//! executes every receipt a call creates: callbacks receive the results of the receipts
+//! they depend on, a returned promise resolves to the last receipt it created, and a
+//! panicking call leaves its account's state untouched, as on chain. `MockNft` is a
+//! NEP-171/178/181 contract built on the standard implementation, with a configurable
+//! NEP-199 royalty and an optional `nft_is_soulbound` view.
+
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::mock::MockAction;
+use near_sdk::serde::de::DeserializeOwned;
+use near_sdk::serde::Serialize;
+use near_sdk::serde_json::{self, json, Value};
+use near_sdk::test_utils::{get_created_receipts, get_logs, VMContextBuilder};
+use near_sdk::{env, AccountId, MockedBlockchain, NearToken, Promise, PromiseError, PromiseOrValue, PromiseResult};
+use near_contract_standards::non_fungible_token::approval::NonFungibleTokenApproval;
+use near_contract_standards::non_fungible_token::core::NonFungibleTokenCore;
+use near_contract_standards::non_fungible_token::{NonFungibleToken, Token, TokenId};
+
+use crate::{CreativeMarketplace, ListingId, NFTListing, Payout};
+use near_contract_standards::non_fungible_token::approval::NonFungibleTokenApprovalReceiver;
+
+pub const MARKETPLACE: &str = "marketplace.testnet";
+pub const NFT: &str = "nft.testnet";
+pub const OWNER: &str = "owner.testnet";
+pub const SELLER: &str = "user.testnet";
+pub const BUYER: &str = "buyer.testnet";
+pub const CREATOR: &str = "creator.testnet";
+pub const ONE_NEAR: u128 = 1_000_000_000_000_000_000_000_000;
+
+/// NFT contract the marketplace talks to in tests
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct MockNft {
+    tokens: NonFungibleToken,
+    royalty: Option<(AccountId, u32)>, // Receiver and basis points of every sale
+    soulbound: Option<bool>,           // `None` behaves like a contract without `nft_is_soulbound`
+    payout: bool,                      // Whether NEP-199 is implemented
+}
+
+impl MockNft {
+    fn nft_payout(&self, token_id: TokenId, balance: u128, max_len_payout: Option<u32>) -> Payout {
+        assert!(self.payout, "Method nft_payout not found");
+        let owner_id = self.tokens.owner_by_id.get(&token_id).expect("Token not found");
+        let mut payout = HashMap::new();
+        let mut remaining = balance;
+        if let Some((receiver_id, bps)) = &self.royalty {
+            let amount = balance * *bps as u128 / 10_000;
+            payout.insert(receiver_id.clone(), amount.into());
+            remaining -= amount;
+        }
+        payout.insert(owner_id, remaining.into());
+        assert!(payout.len() as u32 <= max_len_payout.unwrap_or(u32::MAX), "Too many payout receivers");
+        Payout { payout }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn nft_transfer_payout(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+        balance: u128,
+        max_len_payout: Option<u32>,
+    ) -> Payout {
+        let payout = self.nft_payout(token_id.clone(), balance, max_len_payout);
+        self.tokens.nft_transfer(receiver_id, token_id, approval_id, memo);
+        payout
+    }
+}
+
+/// What a contract method handed back to the runtime
+enum Returned {
+    Value(Vec<u8>),
+    Promise,
+}
+
+impl Returned {
+    fn value<T: Serialize>(value: T) -> Self {
+        Self::Value(serde_json::to_vec(&value).unwrap())
+    }
+
+    fn promise(promise: Promise) -> Self {
+        drop(promise);
+        Self::Promise
+    }
+}
+
+impl<T: Serialize> From<PromiseOrValue<T>> for Returned {
+    fn from(returned: PromiseOrValue<T>) -> Self {
+        match returned {
+            PromiseOrValue::Promise(promise) => Self::promise(promise),
+            PromiseOrValue::Value(value) => Self::value(value),
+        }
+    }
+}
+
+fn arg<T: DeserializeOwned>(args: &Value, name: &str) -> T {
+    serde_json::from_value(args[name].clone()).unwrap_or_else(|_| panic!("Missing or invalid argument {name}"))
+}
+
+/// Result of the receipt a callback depends on, decoded as `#[callback_result]` does
+fn callback_result<T: DeserializeOwned>() -> Result<T, PromiseError> {
+    env::promise_result_checked(0, usize::MAX)
+        .and_then(|data| serde_json::from_slice(&data).map_err(|_| PromiseError::Failed))
+}
+
+fn call_marketplace(method: &str, args: &Value) -> Returned {
+    let mut contract: CreativeMarketplace = env::state_read().expect("Marketplace is not initialized");
+    let returned = match method {
+        "nft_on_approve" => contract
+            .nft_on_approve(arg(args, "token_id"), arg(args, "owner_id"), arg(args, "approval_id"), arg(args, "msg"))
+            .into(),
+        "on_soulbound_check" => contract
+            .on_soulbound_check(
+                arg(args, "token_id"),
+                arg(args, "nft_contract_id"),
+                arg(args, "owner_id"),
+                arg(args, "approval_id"),
+                arg(args, "msg"),
+                callback_result(),
+            )
+            .into(),
+        "list_nft" => Returned::promise(contract.list_nft(
+            arg(args, "token_id"),
+            arg(args, "price"),
+            arg(args, "chain_info"),
+            arg(args, "metadata"),
+        )),
+        "on_legacy_listing" => Returned::promise(contract.on_legacy_listing(
+            arg(args, "token_id"),
+            arg(args, "nft_contract_id"),
+            arg(args, "seller"),
+            arg(args, "args"),
+            callback_result(),
+        )),
+        "buy_nft" => Returned::promise(contract.buy_nft(arg(args, "listing_id"))),
+        "resolve_purchase" => Returned::value(contract.resolve_purchase(arg(args, "sale"), callback_result())),
+        _ => env::panic_str(&format!("Method {method} not found")),
+    };
+    env::state_write(&contract);
+    returned
+}
+
+fn call_nft(method: &str, args: &Value) -> Returned {
+    let mut contract: MockNft = env::state_read().expect("Mock NFT is not initialized");
+    let returned = match method {
+        "nft_approve" => {
+            match contract.tokens.nft_approve(arg(args, "token_id"), arg(args, "account_id"), arg(args, "msg")) {
+                Some(promise) => Returned::promise(promise),
+                None => Returned::value(()),
+            }
+        }
+        "nft_transfer" => {
+            contract.tokens.nft_transfer(
+                arg(args, "receiver_id"),
+                arg(args, "token_id"),
+                arg(args, "approval_id"),
+                arg(args, "memo"),
+            );
+            Returned::value(())
+        }
+        "nft_token" => Returned::value(contract.tokens.nft_token(arg(args, "token_id"))),
+        "nft_payout" => Returned::value(contract.nft_payout(
+            arg(args, "token_id"),
+            arg::<near_sdk::json_types::U128>(args, "balance").0,
+            arg(args, "max_len_payout"),
+        )),
+        "nft_transfer_payout" => Returned::value(contract.nft_transfer_payout(
+            arg(args, "receiver_id"),
+            arg(args, "token_id"),
+            arg(args, "approval_id"),
+            arg(args, "memo"),
+            arg::<near_sdk::json_types::U128>(args, "balance").0,
+            arg(args, "max_len_payout"),
+        )),
+        "nft_is_soulbound" => match contract.soulbound {
+            Some(soulbound) => Returned::value(soulbound),
+            None => env::panic_str("Method nft_is_soulbound not found"),
+        },
+        _ => env::panic_str(&format!("Method {method} not found")),
+    };
+    env::state_write(&contract);
+    returned
+}
+
+/// Marketplace and mock NFT contract wired together through their receipts
+pub struct Sandbox {
+    storage: HashMap<AccountId, HashMap<Vec<u8>, Vec<u8>>>,
+    pub transfers: Vec<(AccountId, NearToken)>,
+    pub logs: Vec<String>,
+}
+
+impl Sandbox {
+    /// Deploy both contracts; `SELLER` owns "token1" on the NFT contract
+    pub fn new(royalty: Option<(&str, u32)>, soulbound: Option<bool>) -> Self {
+        let mut sandbox = Self {
+            storage: HashMap::new(),
+            transfers: Vec::new(),
+            logs: Vec::new(),
+        };
+        sandbox.with_state(MARKETPLACE, || env::state_write(&CreativeMarketplace::new(OWNER.parse().unwrap())));
+        sandbox.with_state(NFT, || {
+            let mut tokens = NonFungibleToken::new(
+                b"t".to_vec(),
+                NFT.parse().unwrap(),
+                None::<Vec<u8>>,
+                Some(b"o".to_vec()),
+                Some(b"a".to_vec()),
+            );
+            tokens.internal_mint_with_refund("token1".to_string(), SELLER.parse().unwrap(), None, None);
+            env::state_write(&MockNft {
+                tokens,
+                royalty: royalty.map(|(receiver_id, bps)| (receiver_id.parse().unwrap(), bps)),
+                soulbound,
+                payout: true,
+            });
+        });
+        sandbox
+    }
+
+    /// Run a transaction from `signer` and every receipt it spawns
+    pub fn call(&mut self, signer: &str, receiver: &str, method: &str, args: Value, deposit: NearToken) -> PromiseResult {
+        let signer: AccountId = signer.parse().unwrap();
+        let receiver: AccountId = receiver.parse().unwrap();
+        let args = serde_json::to_vec(&args).unwrap();
+        self.execute(&signer, &signer, &receiver, method, &args, deposit, Vec::new())
+    }
+
+    pub fn listing(&mut self, listing_id: ListingId) -> NFTListing {
+        self.view_marketplace(|marketplace| marketplace.get_listing(listing_id).expect("Listing not found"))
+    }
+
+    pub fn view_marketplace<T>(&mut self, view: impl FnOnce(&CreativeMarketplace) -> T) -> T {
+        self.with_state(MARKETPLACE, || view(&env::state_read().unwrap()))
+    }
+
+    pub fn token(&mut self, token_id: &str) -> Token {
+        self.with_state(NFT, || {
+            let contract: MockNft = env::state_read().unwrap();
+            contract.tokens.nft_token(token_id.to_string()).expect("Token not found")
+        })
+    }
+
+    /// Turn the NFT contract into one without NEP-199 support
+    pub fn remove_payout(&mut self) {
+        self.with_state(NFT, || {
+            let mut contract: MockNft = env::state_read().unwrap();
+            contract.payout = false;
+            env::state_write(&contract);
+        });
+    }
+
+    /// Approve the marketplace on "token1" with `msg`, returning the listing ID
+    pub fn approve(&mut self, msg: Option<Value>) -> PromiseResult {
+        self.call(
+            SELLER,
+            NFT,
+            "nft_approve",
+            json!({ "token_id": "token1", "account_id": MARKETPLACE, "msg": msg.map(|msg| msg.to_string()) }),
+            NearToken::from_near(1),
+        )
+    }
+
+    fn with_state<T>(&mut self, account: &str, f: impl FnOnce() -> T) -> T {
+        let account: AccountId = account.parse().unwrap();
+        self.set_env(&account, &account, &account, NearToken::from_yoctonear(0), Vec::new());
+        let result = f();
+        let storage = near_sdk::mock::with_mocked_blockchain(|b| b.take_storage());
+        self.storage.insert(account, storage);
+        result
+    }
+
+    fn set_env(
+        &self,
+        signer: &AccountId,
+        predecessor: &AccountId,
+        receiver: &AccountId,
+        deposit: NearToken,
+        promise_results: Vec<PromiseResult>,
+    ) {
+        let mut context = VMContextBuilder::new();
+        context
+            .current_account_id(receiver.clone())
+            .signer_account_id(signer.clone())
+            .predecessor_account_id(predecessor.clone())
+            .attached_deposit(deposit)
+            .account_balance(NearToken::from_near(1_000));
+        env::set_blockchain_interface(MockedBlockchain::new(
+            context.build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            promise_results,
+            self.storage.get(receiver).cloned().unwrap_or_default(),
+            Default::default(),
+            None,
+        ));
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn execute(
+        &mut self,
+        signer: &AccountId,
+        predecessor: &AccountId,
+        receiver: &AccountId,
+        method: &str,
+        args: &[u8],
+        deposit: NearToken,
+        promise_results: Vec<PromiseResult>,
+    ) -> PromiseResult {
+        self.set_env(signer, predecessor, receiver, deposit, promise_results);
+        let args: Value = if args.is_empty() { json!({}) } else { serde_json::from_slice(args).unwrap() };
+        let outcome = panic::catch_unwind(AssertUnwindSafe(|| match receiver.as_str() {
+            MARKETPLACE => call_marketplace(method, &args),
+            NFT => call_nft(method, &args),
+            _ => env::panic_str("No contract deployed"),
+        }));
+        let Ok(returned) = outcome else {
+            // State changes and receipts of a failed call are dropped
+            return PromiseResult::Failed;
+        };
+
+        let receipts = get_created_receipts();
+        self.logs.extend(get_logs());
+        let storage = near_sdk::mock::with_mocked_blockchain(|b| b.take_storage());
+        self.storage.insert(receiver.clone(), storage);
+
+        let mut results: Vec<PromiseResult> = Vec::new();
+        for receipt in receipts {
+            let dependencies = receipt.receipt_indices.iter().map(|&index| copy_result(&results[index as usize])).collect();
+            let mut result = PromiseResult::Successful(Vec::new());
+            for action in receipt.actions {
+                match action {
+                    MockAction::Transfer { deposit, .. } => self.transfers.push((receipt.receiver_id.clone(), deposit)),
+                    MockAction::FunctionCallWeight { method_name, args, attached_deposit, .. } => {
+                        result = self.execute(
+                            signer,
+                            receiver,
+                            &receipt.receiver_id,
+                            std::str::from_utf8(&method_name).unwrap(),
+                            &args,
+                            attached_deposit,
+                            dependencies,
+                        );
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            results.push(result);
+        }
+
+        match returned {
+            Returned::Value(value) => PromiseResult::Successful(value),
+            Returned::Promise => results.pop().expect("Returned promise created no receipt"),
+        }
+    }
+}
+
+fn copy_result(result: &PromiseResult) -> PromiseResult {
+    match result {
+        PromiseResult::Successful(data) => PromiseResult::Successful(data.clone()),
+        PromiseResult::Failed => PromiseResult::Failed,
+    }
+}
+
+/// Decode a successful result
+pub fn result_of<T: DeserializeOwned>(result: PromiseResult) -> T {
+    match result {
+        PromiseResult::Successful(data) => serde_json::from_slice(&data).unwrap(),
+        PromiseResult::Failed => panic!("Call failed"),
+    }
+}
+
+pub fn sale_msg(price: u128) -> Value {
+    json!({
+        "price": price.to_string(),
+        "chain_info": null,
+        "metadata": { "title": "Test NFT", "description": "A test NFT", "media_url": "", "attributes": [] },
+        "emotional_traits": null,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buy(sandbox: &mut Sandbox, listing_id: ListingId) -> PromiseResult {
+        sandbox.call(
+            BUYER,
+            MARKETPLACE,
+            "buy_nft",
+            json!({ "listing_id": listing_id }),
+            NearToken::from_yoctonear(ONE_NEAR),
+        )
+    }
+
+    #[test]
+    fn test_sale_through_mock_nft_contract() {
+        let mut sandbox = Sandbox::new(Some((CREATOR, 1_000)), Some(false));
+        let listing_id: String = result_of(sandbox.approve(Some(sale_msg(ONE_NEAR))));
+        let listing_id: ListingId = listing_id.parse().unwrap();
+        assert_eq!(sandbox.listing(listing_id).approval_id, 1);
+
+        assert!(result_of::<bool>(buy(&mut sandbox, listing_id)));
+        assert_eq!(sandbox.token("token1").owner_id.as_str(), BUYER);
+        assert!(sandbox.transfers.contains(&(CREATOR.parse().unwrap(), NearToken::from_yoctonear(ONE_NEAR / 10))));
+        assert!(sandbox.transfers.contains(&(SELLER.parse().unwrap(), NearToken::from_yoctonear(ONE_NEAR * 9 / 10))));
+        assert!(!sandbox.listing(listing_id).is_active);
+    }
+
+    #[test]
+    fn test_sale_without_nep199_keeps_token_and_refunds() {
+        let mut sandbox = Sandbox::new(None, Some(false));
+        let listing_id: String = result_of(sandbox.approve(Some(sale_msg(ONE_NEAR))));
+        let listing_id: ListingId = listing_id.parse().unwrap();
+        sandbox.remove_payout();
+        sandbox.transfers.clear();
+
+        // The transfer and the payout fail together, so the token never leaves the seller
+        assert!(!result_of::<bool>(buy(&mut sandbox, listing_id)));
+        assert_eq!(sandbox.token("token1").owner_id.as_str(), SELLER);
+        assert_eq!(sandbox.transfers, vec![(BUYER.parse().unwrap(), NearToken::from_yoctonear(ONE_NEAR))]);
+        assert!(sandbox.listing(listing_id).is_active);
+    }
+
+    #[test]
+    fn test_contract_without_soulbound_view_cannot_list() {
+        let mut sandbox = Sandbox::new(None, None);
+        assert!(matches!(sandbox.approve(Some(sale_msg(ONE_NEAR))), PromiseResult::Failed));
+        assert!(sandbox.view_marketplace(|marketplace| marketplace.get_active_listings().is_empty()));
+    }
+
+    #[test]
+    fn test_second_approval_replaces_listing() {
+        let mut sandbox = Sandbox::new(None, Some(false));
+        let first: String = result_of(sandbox.approve(Some(sale_msg(ONE_NEAR))));
+        let second: String = result_of(sandbox.approve(Some(sale_msg(2 * ONE_NEAR))));
+        let (first, second): (ListingId, ListingId) = (first.parse().unwrap(), second.parse().unwrap());
+
+        assert!(!sandbox.listing(first).is_active);
+        assert!(sandbox.listing(second).is_active);
+        assert_eq!(sandbox.view_marketplace(|marketplace| marketplace.get_active_listings().len()), 1);
+        assert_eq!(sandbox.view_marketplace(|marketplace| marketplace.get_marketplace_stats().active_listings), 1);
+
+        // The first listing's approval was replaced, so it cannot be bought any more
+        assert!(matches!(buy(&mut sandbox, first), PromiseResult::Failed));
+    }
+
+    #[test]
+    fn test_deprecated_list_nft_uses_existing_approval() {
+        let mut sandbox = Sandbox::new(None, Some(false));
+        sandbox.approve(None);
+
+        let listing_id: String = result_of(sandbox.call(
+            SELLER,
+            MARKETPLACE,
+            "list_nft",
+            json!({
+                "token_id": "token1",
+                "price": ONE_NEAR.to_string(),
+                "chain_info": { "chain_name": "NEAR", "contract_address": NFT, "bridge_status": "NotBridged" },
+                "metadata": { "title": "Test NFT", "description": "", "media_url": "", "attributes": [] },
+            }),
+            NearToken::from_yoctonear(0),
+        ));
+        let listing = sandbox.listing(listing_id.parse().unwrap());
+        assert!(listing.is_active);
+        assert_eq!(listing.approval_id, 1);
+        assert_eq!(listing.nft_contract_id.as_str(), NFT);
+        assert!(sandbox.logs.iter().any(|log| log.starts_with("list_nft is deprecated")));
+    }
+}
@@ -0,0 +1,385 @@
+//! Offers and Collection Bids
+//!
+//! Buyers escrow NEAR for a specific token, any token of a collection, or any token
+//! carrying a given `NFTAttribute`, whether or not it is listed. The token owner
+//! accepts by approving the marketplace with `{"accept_offer": <offer_id>}` as msg,
+//! which runs the regular payout/transfer flow.
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U64;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near, AccountId, Gas, NearToken, Promise, PromiseError, PromiseOrValue, Timestamp};
+use near_contract_standards::non_fungible_token::core::ext_nft_core;
+use near_contract_standards::non_fungible_token::{Token, TokenId};
+
+use crate::{CreativeMarketplace, CreativeMarketplaceExt, MarketplaceEvent, NFTAttribute, PendingSale, SaleSource};
+
+/// Nanoseconds in a minute
+const NANOS_PER_MINUTE: u64 = 60_000_000_000;
+
+/// Gas reserved for the `nft_token` lookup used by trait offers
+const GAS_FOR_NFT_TOKEN: Gas = Gas::from_tgas(10);
+
+/// Gas reserved for the trait check callback, which starts the sale flow
+const GAS_FOR_ON_OFFER_TOKEN: Gas = Gas::from_tgas(100);
+
+/// Unique identifier for offers
+pub type OfferId = u64;
+
+/// What an offer applies to
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub enum OfferTarget {
+    Token {
+        nft_contract_id: AccountId,
+        token_id: TokenId,
+    },
+    Collection {
+        nft_contract_id: AccountId,
+    },
+    // Any token of the collection whose metadata `extra` lists the attribute
+    Trait {
+        nft_contract_id: AccountId,
+        attribute: NFTAttribute,
+    },
+}
+
+impl OfferTarget {
+    pub fn nft_contract_id(&self) -> &AccountId {
+        match self {
+            OfferTarget::Token { nft_contract_id, .. }
+            | OfferTarget::Collection { nft_contract_id }
+            | OfferTarget::Trait { nft_contract_id, .. } => nft_contract_id,
+        }
+    }
+}
+
+/// Offer lifecycle
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum OfferStatus {
+    Active,
+    // Token transfer in flight
+    Accepting,
+    Accepted,
+    Cancelled,
+}
+
+/// Escrowed offer
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Offer {
+    pub offer_id: OfferId,
+    pub buyer: AccountId,
+    pub amount: NearToken,
+    pub target: OfferTarget,
+    pub created_at: Timestamp,
+    pub expires_at: Timestamp,
+    pub status: OfferStatus,
+}
+
+impl Offer {
+    /// Whether the offer can still be accepted at the given time
+    pub fn is_open(&self, now: Timestamp) -> bool {
+        self.status == OfferStatus::Active && now < self.expires_at
+    }
+}
+
+/// Offer acceptance passed as `msg` to `nft_approve`
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AcceptOfferArgs {
+    pub accept_offer: OfferId,
+}
+
+/// Attributes stored in `TokenMetadata.extra`
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+struct TokenExtra {
+    #[serde(default)]
+    attributes: Vec<NFTAttribute>,
+}
+
+#[near]
+impl CreativeMarketplace {
+    /// Make an offer; the attached deposit is escrowed until acceptance, cancellation or expiry
+    #[payable]
+    pub fn make_offer(&mut self, target: OfferTarget, expires_in_minutes: u64) -> OfferId {
+        let amount = env::attached_deposit();
+
+        if amount.is_zero() {
+            env::panic_str("Offer requires an attached deposit");
+        }
+
+        if expires_in_minutes == 0 {
+            env::panic_str("Offer expiry must be in the future");
+        }
+
+        let offer_id = self.next_offer_id;
+        self.next_offer_id += 1;
+
+        let now = env::block_timestamp();
+        let offer = Offer {
+            offer_id,
+            buyer: env::predecessor_account_id(),
+            amount,
+            target,
+            created_at: now,
+            expires_at: now + expires_in_minutes * NANOS_PER_MINUTE,
+            status: OfferStatus::Active,
+        };
+
+        self.offers.insert(&offer_id, &offer);
+
+        MarketplaceEvent::OfferMade {
+            offer_id,
+            buyer: offer.buyer,
+            amount: offer.amount,
+            target: offer.target,
+            expires_at: U64(offer.expires_at),
+        }
+        .emit();
+        offer_id
+    }
+
+    /// Cancel an offer and refund the escrow; also used to reclaim expired offers
+    pub fn cancel_offer(&mut self, offer_id: OfferId) -> Promise {
+        let mut offer = self.offers.get(&offer_id).expect("Offer not found");
+
+        if offer.buyer != env::predecessor_account_id() {
+            env::panic_str("Only the buyer can cancel an offer");
+        }
+
+        if offer.status != OfferStatus::Active {
+            env::panic_str("Offer is not active");
+        }
+
+        offer.status = OfferStatus::Cancelled;
+        self.offers.insert(&offer_id, &offer);
+
+        MarketplaceEvent::OfferCancelled { offer_id }.emit();
+
+        Promise::new(offer.buyer).transfer(offer.amount)
+    }
+
+    /// Callback after `nft_token` for trait offers: start the sale if the token carries the attribute
+    #[private]
+    pub fn on_offer_token(
+        &mut self,
+        sale: PendingSale,
+        #[callback_result] token: Result<Option<Token>, PromiseError>,
+    ) -> PromiseOrValue<bool> {
+        let offer_id = match sale.source {
+            SaleSource::Offer(offer_id) => offer_id,
+            _ => env::panic_str("Expected an offer sale"),
+        };
+        let offer = self.offers.get(&offer_id).expect("Offer not found");
+
+        let attributes = token
+            .ok()
+            .flatten()
+            .and_then(|token| token.metadata)
+            .and_then(|metadata| metadata.extra)
+            .and_then(|extra| near_sdk::serde_json::from_str::<TokenExtra>(&extra).ok())
+            .map(|extra| extra.attributes)
+            .unwrap_or_default();
+
+        let matches = match &offer.target {
+            OfferTarget::Trait { attribute, .. } => attributes
+                .iter()
+                .any(|a| a.trait_type == attribute.trait_type && a.value == attribute.value),
+            _ => true,
+        };
+
+        if !matches {
+            // Keep the offer open for other tokens
+            env::log_str("Token does not match the offer's trait");
+            self.internal_reopen_offer(offer_id);
+            return PromiseOrValue::Value(false);
+        }
+
+        PromiseOrValue::Promise(self.internal_start_sale(sale))
+    }
+
+    /// Get offer by ID
+    pub fn get_offer(&self, offer_id: OfferId) -> Option<Offer> {
+        self.offers.get(&offer_id)
+    }
+
+    /// Get open offers a token could accept: token offers, collection bids and trait bids
+    pub fn get_offers_for_token(&self, nft_contract_id: AccountId, token_id: TokenId) -> Vec<Offer> {
+        let now = env::block_timestamp();
+        self.offers.values()
+            .filter(|offer| offer.is_open(now) && *offer.target.nft_contract_id() == nft_contract_id)
+            .filter(|offer| match &offer.target {
+                OfferTarget::Token { token_id: target, .. } => *target == token_id,
+                _ => true,
+            })
+            .collect()
+    }
+
+    /// Get all offers made by a buyer
+    pub fn get_offers_by_buyer(&self, account_id: AccountId) -> Vec<Offer> {
+        self.offers.values()
+            .filter(|offer| offer.buyer == account_id)
+            .collect()
+    }
+}
+
+impl CreativeMarketplace {
+    /// Accept an offer on behalf of the token owner who just approved the marketplace
+    pub(crate) fn internal_accept_offer(
+        &mut self,
+        offer_id: OfferId,
+        token_id: TokenId,
+        nft_contract_id: AccountId,
+        approval_id: u64,
+        seller: AccountId,
+    ) -> Promise {
+        let mut offer = self.offers.get(&offer_id).expect("Offer not found");
+
+        if !offer.is_open(env::block_timestamp()) {
+            env::panic_str("Offer is not open");
+        }
+
+        if *offer.target.nft_contract_id() != nft_contract_id {
+            env::panic_str("Offer is for a different collection");
+        }
+
+        if let OfferTarget::Token { token_id: target, .. } = &offer.target {
+            if *target != token_id {
+                env::panic_str("Offer is for a different token");
+            }
+        }
+
+        if offer.buyer == seller {
+            env::panic_str("Cannot accept your own offer");
+        }
+
+        offer.status = OfferStatus::Accepting;
+        self.offers.insert(&offer_id, &offer);
+
+        let sale = PendingSale {
+            source: SaleSource::Offer(offer_id),
+            token_id,
+            nft_contract_id,
+            approval_id,
+            seller,
+            buyer_id: offer.buyer,
+            price: offer.amount,
+            deposit: offer.amount,
+        };
+
+        match offer.target {
+            OfferTarget::Trait { .. } => ext_nft_core::ext(sale.nft_contract_id.clone())
+                .with_static_gas(GAS_FOR_NFT_TOKEN)
+                .nft_token(sale.token_id.clone())
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(GAS_FOR_ON_OFFER_TOKEN)
+                        .on_offer_token(sale),
+                ),
+            _ => self.internal_start_sale(sale),
+        }
+    }
+
+    /// Token transfer failed: the offer stays open and keeps its escrow
+    pub(crate) fn internal_reopen_offer(&mut self, offer_id: OfferId) {
+        let mut offer = self.offers.get(&offer_id).expect("Offer not found");
+        offer.status = OfferStatus::Active;
+        self.offers.insert(&offer_id, &offer);
+    }
+
+    /// Token transfer succeeded
+    pub(crate) fn internal_complete_offer(&mut self, offer_id: OfferId) {
+        let mut offer = self.offers.get(&offer_id).expect("Offer not found");
+        offer.status = OfferStatus::Accepted;
+        self.offers.insert(&offer_id, &offer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::testing_env;
+
+    const ONE_NEAR: u128 = 1_000_000_000_000_000_000_000_000;
+
+    fn context(predecessor: &str, timestamp: Timestamp, deposit: u128) {
+        let mut builder = VMContextBuilder::new();
+        builder.current_account_id("marketplace.testnet".parse().unwrap());
+        builder.signer_account_id("seller.testnet".parse().unwrap());
+        builder.predecessor_account_id(predecessor.parse().unwrap());
+        builder.block_timestamp(timestamp);
+        builder.attached_deposit(NearToken::from_yoctonear(deposit));
+        testing_env!(builder.build());
+    }
+
+    fn collection_offer(marketplace: &mut CreativeMarketplace) -> OfferId {
+        context("buyer.testnet", 0, ONE_NEAR);
+        marketplace.make_offer(
+            OfferTarget::Collection { nft_contract_id: "nft.testnet".parse().unwrap() },
+            60,
+        )
+    }
+
+    #[test]
+    fn test_collection_offer_applies_to_any_token() {
+        context("owner.testnet", 0, 0);
+        let mut marketplace = CreativeMarketplace::new("owner.testnet".parse().unwrap());
+        let offer_id = collection_offer(&mut marketplace);
+
+        let offers = marketplace.get_offers_for_token("nft.testnet".parse().unwrap(), "token42".to_string());
+        assert_eq!(offers.len(), 1);
+        assert_eq!(offers[0].offer_id, offer_id);
+        assert_eq!(offers[0].amount.as_yoctonear(), ONE_NEAR);
+    }
+
+    #[test]
+    fn test_accepting_offer_locks_it() {
+        context("owner.testnet", 0, 0);
+        let mut marketplace = CreativeMarketplace::new("owner.testnet".parse().unwrap());
+        let offer_id = collection_offer(&mut marketplace);
+
+        context("nft.testnet", 0, 0);
+        marketplace.internal_accept_offer(
+            offer_id,
+            "token42".to_string(),
+            "nft.testnet".parse().unwrap(),
+            3,
+            "seller.testnet".parse().unwrap(),
+        )
+        .detach();
+        assert_eq!(marketplace.get_offer(offer_id).unwrap().status, OfferStatus::Accepting);
+    }
+
+    #[test]
+    #[should_panic(expected = "Offer is not open")]
+    fn test_expired_offer_cannot_be_accepted() {
+        context("owner.testnet", 0, 0);
+        let mut marketplace = CreativeMarketplace::new("owner.testnet".parse().unwrap());
+        let offer_id = collection_offer(&mut marketplace);
+
+        context("nft.testnet", 61 * NANOS_PER_MINUTE, 0);
+        marketplace.internal_accept_offer(
+            offer_id,
+            "token42".to_string(),
+            "nft.testnet".parse().unwrap(),
+            3,
+            "seller.testnet".parse().unwrap(),
+        )
+        .detach();
+    }
+
+    #[test]
+    fn test_cancel_offer() {
+        context("owner.testnet", 0, 0);
+        let mut marketplace = CreativeMarketplace::new("owner.testnet".parse().unwrap());
+        let offer_id = collection_offer(&mut marketplace);
+
+        context("buyer.testnet", 0, 0);
+        marketplace.cancel_offer(offer_id).detach();
+        assert_eq!(marketplace.get_offer(offer_id).unwrap().status, OfferStatus::Cancelled);
+    }
+}
@@ -0,0 +1,736 @@
+//! Mintbase-compatible NFT store
+//!
+//! `MintbaseIntegration` is a NEP-171/177/178/181/199 store in the shape of a Mintbase
+//! store contract. Only minters approved by the owner can call `nft_batch_mint`, which
+//! mints numbered copies of one metadata record and forwards a per-token fee to
+//! `treasury_id`. Royalties are fixed per metadata; split owners share the rest of a
+//! sale until the token changes hands. Burning refunds the storage the tokens used. The
+//! `nft_mint` event carries the memo Mintbase's indexer parses.
+
+use std::collections::HashMap;
+
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::collections::{LazyOption, LookupMap, UnorderedMap};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{
+    assert_one_yocto, env, near, AccountId, NearToken, PanicOnDefault, Promise, PromiseOrValue,
+};
+use near_contract_standards::non_fungible_token::approval::NonFungibleTokenApproval;
+use near_contract_standards::non_fungible_token::core::{NonFungibleTokenCore, NonFungibleTokenResolver};
+use near_contract_standards::non_fungible_token::enumeration::NonFungibleTokenEnumeration;
+use near_contract_standards::non_fungible_token::events::{NftBurn, NftMint};
+use near_contract_standards::non_fungible_token::metadata::{
+    NFTContractMetadata, NonFungibleTokenMetadataProvider, TokenMetadata,
+};
+use near_contract_standards::non_fungible_token::{NonFungibleToken, Token, TokenId};
+
+/// Shares are expressed in basis points of this total
+pub const ONE_HUNDRED_PERCENT: u32 = 10_000;
+
+/// Upper bound for the royalty cut of a sale (50%)
+pub const MAX_ROYALTY_BPS: u32 = 5_000;
+
+/// Maximum number of royalty receivers or split owners
+pub const MAX_SPLIT_RECIPIENTS: usize = 10;
+
+/// Maximum copies minted by one `nft_batch_mint` call
+pub const MAX_MINT_BATCH: u64 = 100;
+
+/// Maximum tokens burned by one `nft_batch_burn` call
+pub const MAX_BURN_BATCH: usize = 100;
+
+/// Share of a balance, in basis points of `ONE_HUNDRED_PERCENT`
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SafeFraction {
+    pub numerator: u32,
+}
+
+impl SafeFraction {
+    pub fn new(numerator: u32) -> Self {
+        assert!(numerator <= ONE_HUNDRED_PERCENT, "Fraction cannot exceed 10000 basis points");
+        Self { numerator }
+    }
+
+    pub fn multiply_balance(&self, balance: u128) -> u128 {
+        balance * self.numerator as u128 / ONE_HUNDRED_PERCENT as u128
+    }
+}
+
+/// Royalty of every copy of one metadata record, as stored and logged by Mintbase stores
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Royalty {
+    pub split_between: HashMap<AccountId, SafeFraction>, // Sums to 100%
+    pub percentage: SafeFraction,                        // Cut of each sale paid out as royalty
+}
+
+/// Accounts sharing the non-royalty part of a sale; cleared on transfer
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SplitOwners {
+    pub split_between: HashMap<AccountId, SafeFraction>, // Sums to 100%
+}
+
+/// Royalty as passed to `nft_batch_mint`; shares in basis points
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RoyaltyArgs {
+    pub split_between: HashMap<AccountId, u32>,
+    pub percentage: u32,
+}
+
+/// Position of a token among the copies of its metadata
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Edition {
+    pub metadata_id: u64,
+    pub edition: u64, // 1-based; `copies` in the metadata is the edition size
+}
+
+/// NEP-199 payout
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Payout {
+    pub payout: HashMap<AccountId, U128>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MintbaseConfig {
+    pub treasury_id: AccountId,
+    pub minting_fee: NearToken, // Charged per minted token
+}
+
+/// Memo of the `nft_mint` event, in the shape Mintbase's indexer parses
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct NftMintLogMemo<'a> {
+    royalty: Option<&'a Royalty>,
+    split_owners: Option<&'a SplitOwners>,
+    meta_id: Option<&'a str>,
+    meta_extra: Option<&'a str>,
+    minter: &'a AccountId,
+}
+
+/// Mintbase-compatible NFT store contract
+#[near(contract_state)]
+#[derive(PanicOnDefault)]
+pub struct MintbaseIntegration {
+    tokens: NonFungibleToken,
+    metadata: LazyOption<NFTContractMetadata>,
+    pub minters: UnorderedMap<AccountId, bool>,
+    pub owner_id: AccountId,
+    pub treasury_id: AccountId,
+    pub minting_fee: NearToken,
+    next_token_id: u64,
+    next_metadata_id: u64,
+    token_metadata: LookupMap<u64, TokenMetadata>, // Shared by every copy, keyed by metadata ID
+    royalties: LookupMap<u64, Royalty>,
+    editions: LookupMap<TokenId, Edition>,
+    split_owners: LookupMap<TokenId, SplitOwners>,
+}
+
+#[near]
+impl MintbaseIntegration {
+    /// Create a store; the owner is its first minter and receives fees until `update_config`
+    #[init]
+    pub fn new(owner_id: AccountId, metadata: NFTContractMetadata) -> Self {
+        metadata.assert_valid();
+        let mut minters = UnorderedMap::new(b"mi".to_vec());
+        minters.insert(&owner_id, &true);
+        Self {
+            tokens: NonFungibleToken::new(
+                b"t".to_vec(),
+                owner_id.clone(),
+                None::<Vec<u8>>,
+                Some(b"o".to_vec()),
+                Some(b"a".to_vec()),
+            ),
+            metadata: LazyOption::new(b"m".to_vec(), Some(&metadata)),
+            minters,
+            treasury_id: owner_id.clone(),
+            owner_id,
+            minting_fee: NearToken::from_yoctonear(0),
+            next_token_id: 0,
+            next_metadata_id: 0,
+            token_metadata: LookupMap::new(b"md".to_vec()),
+            royalties: LookupMap::new(b"r".to_vec()),
+            editions: LookupMap::new(b"e".to_vec()),
+            split_owners: LookupMap::new(b"s".to_vec()),
+        }
+    }
+
+    /// Update configuration (contract owner only)
+    pub fn update_config(&mut self, config: MintbaseConfig) {
+        self.assert_contract_owner();
+        self.treasury_id = config.treasury_id;
+        self.minting_fee = config.minting_fee;
+    }
+
+    pub fn get_config(&self) -> MintbaseConfig {
+        MintbaseConfig {
+            treasury_id: self.treasury_id.clone(),
+            minting_fee: self.minting_fee,
+        }
+    }
+
+    /// Add a minter (contract owner only)
+    pub fn add_minter(&mut self, account_id: AccountId) {
+        self.assert_contract_owner();
+        self.minters.insert(&account_id, &true);
+    }
+
+    /// Remove a minter (contract owner only)
+    pub fn remove_minter(&mut self, account_id: AccountId) {
+        self.assert_contract_owner();
+        self.minters.remove(&account_id);
+    }
+
+    /// Check if account is a minter
+    pub fn is_minter(&self, account_id: AccountId) -> bool {
+        self.minters.get(&account_id).unwrap_or(false)
+    }
+
+    pub fn get_minters(&self) -> Vec<AccountId> {
+        self.minters.keys().collect()
+    }
+
+    /// Mint `num_to_mint` copies of `metadata` to `owner_id` (minters only)
+    ///
+    /// The attached deposit must cover `minting_fee` per copy plus storage; the fee goes
+    /// to the treasury and the excess is refunded. Royalty shares and split owner shares
+    /// are basis points that must each sum to 10000.
+    #[payable]
+    pub fn nft_batch_mint(
+        &mut self,
+        owner_id: AccountId,
+        metadata: TokenMetadata,
+        num_to_mint: u64,
+        royalty_args: Option<RoyaltyArgs>,
+        split_owners: Option<HashMap<AccountId, u32>>,
+    ) -> Vec<TokenId> {
+        let minter_id = env::predecessor_account_id();
+        assert!(self.is_minter(minter_id.clone()), "Only minters can mint");
+        assert!(
+            num_to_mint > 0 && num_to_mint <= MAX_MINT_BATCH,
+            "Can mint 1 to {} copies at once",
+            MAX_MINT_BATCH
+        );
+        let royalty = royalty_args.map(|args| {
+            assert!(args.percentage <= MAX_ROYALTY_BPS, "Royalty cannot exceed {} basis points", MAX_ROYALTY_BPS);
+            Royalty {
+                split_between: parse_split(args.split_between),
+                percentage: SafeFraction::new(args.percentage),
+            }
+        });
+        let split_owners = split_owners.map(|split_between| SplitOwners {
+            split_between: parse_split(split_between),
+        });
+        let initial_storage = env::storage_usage();
+
+        let metadata_id = self.next_metadata_id;
+        self.next_metadata_id += 1;
+        let metadata = TokenMetadata {
+            copies: Some(num_to_mint),
+            ..metadata
+        };
+        self.token_metadata.insert(&metadata_id, &metadata);
+        if let Some(royalty) = &royalty {
+            self.royalties.insert(&metadata_id, royalty);
+        }
+
+        let token_ids: Vec<TokenId> = (1..=num_to_mint)
+            .map(|edition| {
+                let token_id = self.next_token_id.to_string();
+                self.next_token_id += 1;
+                self.tokens.internal_mint_with_refund(token_id.clone(), owner_id.clone(), None, None);
+                self.editions.insert(&token_id, &Edition { metadata_id, edition });
+                if let Some(split_owners) = &split_owners {
+                    self.split_owners.insert(&token_id, split_owners);
+                }
+                token_id
+            })
+            .collect();
+
+        let memo = near_sdk::serde_json::to_string(&NftMintLogMemo {
+            royalty: royalty.as_ref(),
+            split_owners: split_owners.as_ref(),
+            meta_id: metadata.reference.as_deref(),
+            meta_extra: metadata.extra.as_deref(),
+            minter: &minter_id,
+        })
+        .unwrap_or_else(|_| env::panic_str("Mint memo does not serialize"));
+        let ids: Vec<&str> = token_ids.iter().map(String::as_str).collect();
+        NftMint {
+            owner_id: &owner_id,
+            token_ids: &ids,
+            memo: Some(&memo),
+        }
+        .emit();
+
+        let fee = self.minting_fee.saturating_mul(num_to_mint as u128);
+        self.settle_deposit(fee, env::storage_usage() - initial_storage);
+        token_ids
+    }
+
+    /// Burn tokens the caller owns and refund the storage they freed to the caller
+    #[payable]
+    pub fn nft_batch_burn(&mut self, token_ids: Vec<TokenId>) {
+        assert_one_yocto();
+        assert!(
+            !token_ids.is_empty() && token_ids.len() <= MAX_BURN_BATCH,
+            "Can burn 1 to {} tokens at once",
+            MAX_BURN_BATCH
+        );
+        let owner_id = env::predecessor_account_id();
+        let initial_storage = env::storage_usage();
+        for token_id in &token_ids {
+            self.internal_burn(&owner_id, token_id);
+        }
+
+        let storage_freed = initial_storage.saturating_sub(env::storage_usage());
+        let refund = env::storage_byte_cost().saturating_mul(storage_freed as u128);
+        if !refund.is_zero() {
+            Promise::new(owner_id.clone()).transfer(refund).detach();
+        }
+
+        let ids: Vec<&str> = token_ids.iter().map(String::as_str).collect();
+        NftBurn {
+            owner_id: &owner_id,
+            token_ids: &ids,
+            authorized_id: None,
+            memo: None,
+        }
+        .emit();
+    }
+
+    /// Share the owner's part of future sales; the splits are dropped when a token moves
+    /// The attached deposit must cover storage; the excess is refunded
+    #[payable]
+    pub fn set_split_owners(&mut self, token_ids: Vec<TokenId>, split_between: HashMap<AccountId, u32>) {
+        let owner_id = env::predecessor_account_id();
+        let split_owners = SplitOwners {
+            split_between: parse_split(split_between),
+        };
+        let initial_storage = env::storage_usage();
+
+        for token_id in &token_ids {
+            let token_owner = self.tokens.owner_by_id.get(token_id).expect("Token not found");
+            assert_eq!(token_owner, owner_id, "Only the token owner can set split owners");
+            self.split_owners.insert(token_id, &split_owners);
+        }
+
+        let storage_used = env::storage_usage().saturating_sub(initial_storage);
+        self.settle_deposit(NearToken::from_yoctonear(0), storage_used);
+    }
+
+    pub fn get_split_owners(&self, token_id: TokenId) -> Option<SplitOwners> {
+        self.split_owners.get(&token_id)
+    }
+
+    pub fn nft_token_royalty(&self, token_id: TokenId) -> Option<Royalty> {
+        self.editions
+            .get(&token_id)
+            .and_then(|edition| self.royalties.get(&edition.metadata_id))
+    }
+
+    pub fn nft_token_edition(&self, token_id: TokenId) -> Option<Edition> {
+        self.editions.get(&token_id)
+    }
+
+    /// Marketplaces refuse tokens they cannot confirm are transferable; these always are
+    pub fn nft_is_soulbound(&self, token_id: TokenId) -> bool {
+        assert!(self.tokens.owner_by_id.get(&token_id).is_some(), "Token not found");
+        false
+    }
+
+    /// NEP-199: split `balance` between royalty receivers and the owner or split owners
+    pub fn nft_payout(&self, token_id: TokenId, balance: U128, max_len_payout: Option<u32>) -> Payout {
+        let owner_id = self.tokens.owner_by_id.get(&token_id).expect("Token not found");
+        self.internal_payout(&token_id, &owner_id, balance.0, max_len_payout)
+    }
+
+    /// NEP-199: transfer the token and return the payout computed before the transfer
+    #[payable]
+    pub fn nft_transfer_payout(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+        balance: U128,
+        max_len_payout: Option<u32>,
+    ) -> Payout {
+        assert_one_yocto();
+        let owner_id = self.tokens.owner_by_id.get(&token_id).expect("Token not found");
+        let payout = self.internal_payout(&token_id, &owner_id, balance.0, max_len_payout);
+        self.tokens
+            .internal_transfer(&env::predecessor_account_id(), &receiver_id, &token_id, approval_id, memo);
+        self.split_owners.remove(&token_id);
+        payout
+    }
+}
+
+impl MintbaseIntegration {
+    fn assert_contract_owner(&self) {
+        assert_eq!(env::predecessor_account_id(), self.owner_id, "Only contract owner can manage the store");
+    }
+
+    /// Attach the shared metadata of the token's edition
+    fn with_metadata(&self, mut token: Token) -> Token {
+        token.metadata = self
+            .editions
+            .get(&token.token_id)
+            .and_then(|edition| self.token_metadata.get(&edition.metadata_id));
+        token
+    }
+
+    fn internal_burn(&mut self, owner_id: &AccountId, token_id: &TokenId) {
+        let token_owner = self.tokens.owner_by_id.get(token_id).expect("Token not found");
+        assert_eq!(&token_owner, owner_id, "Only the token owner can burn it");
+
+        self.tokens.owner_by_id.remove(token_id);
+        if let Some(tokens_per_owner) = &mut self.tokens.tokens_per_owner {
+            let mut token_ids = tokens_per_owner.get(owner_id).expect("Token owner has no tokens");
+            token_ids.remove(token_id);
+            if token_ids.is_empty() {
+                tokens_per_owner.remove(owner_id);
+            } else {
+                tokens_per_owner.insert(owner_id, &token_ids);
+            }
+        }
+        if let Some(approvals_by_id) = &mut self.tokens.approvals_by_id {
+            approvals_by_id.remove(token_id);
+        }
+        if let Some(next_approval_id_by_id) = &mut self.tokens.next_approval_id_by_id {
+            next_approval_id_by_id.remove(token_id);
+        }
+        // Metadata and royalty stay with the edition; other copies still use them
+        self.editions.remove(token_id);
+        self.split_owners.remove(token_id);
+    }
+
+    /// Royalties come off the top; split owners (or the owner) share the rest
+    /// Rounding dust goes to the owner
+    fn internal_payout(
+        &self,
+        token_id: &TokenId,
+        owner_id: &AccountId,
+        balance: u128,
+        max_len_payout: Option<u32>,
+    ) -> Payout {
+        let mut payout: HashMap<AccountId, u128> = HashMap::new();
+        let mut remaining = balance;
+
+        if let Some(royalty) = self.nft_token_royalty(token_id.clone()) {
+            let royalty_total = royalty.percentage.multiply_balance(balance);
+            for (account_id, share) in &royalty.split_between {
+                let amount = share.multiply_balance(royalty_total);
+                *payout.entry(account_id.clone()).or_default() += amount;
+                remaining -= amount;
+            }
+        }
+
+        let owners = self.split_owners.get(token_id).map_or_else(
+            || HashMap::from([(owner_id.clone(), SafeFraction::new(ONE_HUNDRED_PERCENT))]),
+            |split_owners| split_owners.split_between,
+        );
+        let proceeds = remaining;
+        for (account_id, share) in &owners {
+            let amount = share.multiply_balance(proceeds);
+            *payout.entry(account_id.clone()).or_default() += amount;
+            remaining -= amount;
+        }
+        if remaining > 0 {
+            *payout.entry(owner_id.clone()).or_default() += remaining;
+        }
+
+        if let Some(max_len_payout) = max_len_payout {
+            assert!(payout.len() <= max_len_payout as usize, "Payout exceeds max_len_payout");
+        }
+        Payout {
+            payout: payout.into_iter().map(|(account_id, amount)| (account_id, U128(amount))).collect(),
+        }
+    }
+
+    /// Charge `fee` plus `storage_used` bytes to the attached deposit, forward the fee to
+    /// the treasury and refund the excess
+    fn settle_deposit(&self, fee: NearToken, storage_used: u64) {
+        let required_deposit = env::storage_byte_cost()
+            .saturating_mul(storage_used as u128)
+            .saturating_add(fee);
+        let attached = env::attached_deposit();
+        assert!(attached >= required_deposit, "Not enough deposit for minting fee and storage");
+
+        if fee > NearToken::from_yoctonear(0) {
+            Promise::new(self.treasury_id.clone()).transfer(fee).detach();
+        }
+        let refund = attached.saturating_sub(required_deposit);
+        if refund > NearToken::from_yoctonear(0) {
+            Promise::new(env::predecessor_account_id()).transfer(refund).detach();
+        }
+    }
+}
+
+/// Validate basis point shares that must sum to 100%
+fn parse_split(split_between: HashMap<AccountId, u32>) -> HashMap<AccountId, SafeFraction> {
+    assert!(
+        !split_between.is_empty() && split_between.len() <= MAX_SPLIT_RECIPIENTS,
+        "Splits need 1 to {} accounts",
+        MAX_SPLIT_RECIPIENTS
+    );
+    let total: u64 = split_between.values().map(|&share| share as u64).sum();
+    assert_eq!(total, ONE_HUNDRED_PERCENT as u64, "Split shares must sum to 10000 basis points");
+    split_between
+        .into_iter()
+        .map(|(account_id, share)| {
+            assert!(share > 0, "Split shares must be positive");
+            (account_id, SafeFraction::new(share))
+        })
+        .collect()
+}
+
+#[near]
+impl NonFungibleTokenCore for MintbaseIntegration {
+    #[payable]
+    fn nft_transfer(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+    ) {
+        self.split_owners.remove(&token_id);
+        self.tokens.nft_transfer(receiver_id, token_id, approval_id, memo)
+    }
+
+    /// Split owners are dropped even if the receiver returns the token
+    #[payable]
+    fn nft_transfer_call(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+        msg: String,
+    ) -> PromiseOrValue<bool> {
+        self.split_owners.remove(&token_id);
+        self.tokens.nft_transfer_call(receiver_id, token_id, approval_id, memo, msg)
+    }
+
+    fn nft_token(&self, token_id: TokenId) -> Option<Token> {
+        self.tokens.nft_token(token_id).map(|token| self.with_metadata(token))
+    }
+}
+
+#[near]
+impl NonFungibleTokenResolver for MintbaseIntegration {
+    #[private]
+    fn nft_resolve_transfer(
+        &mut self,
+        previous_owner_id: AccountId,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approved_account_ids: Option<HashMap<AccountId, u64>>,
+    ) -> bool {
+        self.tokens
+            .nft_resolve_transfer(previous_owner_id, receiver_id, token_id, approved_account_ids)
+    }
+}
+
+#[near]
+impl NonFungibleTokenApproval for MintbaseIntegration {
+    #[payable]
+    fn nft_approve(&mut self, token_id: TokenId, account_id: AccountId, msg: Option<String>) -> Option<Promise> {
+        self.tokens.nft_approve(token_id, account_id, msg)
+    }
+
+    #[payable]
+    fn nft_revoke(&mut self, token_id: TokenId, account_id: AccountId) {
+        self.tokens.nft_revoke(token_id, account_id)
+    }
+
+    #[payable]
+    fn nft_revoke_all(&mut self, token_id: TokenId) {
+        self.tokens.nft_revoke_all(token_id)
+    }
+
+    fn nft_is_approved(&self, token_id: TokenId, approved_account_id: AccountId, approval_id: Option<u64>) -> bool {
+        self.tokens.nft_is_approved(token_id, approved_account_id, approval_id)
+    }
+}
+
+#[near]
+impl NonFungibleTokenEnumeration for MintbaseIntegration {
+    fn nft_total_supply(&self) -> U128 {
+        self.tokens.nft_total_supply()
+    }
+
+    fn nft_tokens(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<Token> {
+        self.tokens
+            .nft_tokens(from_index, limit)
+            .into_iter()
+            .map(|token| self.with_metadata(token))
+            .collect()
+    }
+
+    fn nft_supply_for_owner(&self, account_id: AccountId) -> U128 {
+        self.tokens.nft_supply_for_owner(account_id)
+    }
+
+    fn nft_tokens_for_owner(&self, account_id: AccountId, from_index: Option<U128>, limit: Option<u64>) -> Vec<Token> {
+        self.tokens
+            .nft_tokens_for_owner(account_id, from_index, limit)
+            .into_iter()
+            .map(|token| self.with_metadata(token))
+            .collect()
+    }
+}
+
+#[near]
+impl NonFungibleTokenMetadataProvider for MintbaseIntegration {
+    fn nft_metadata(&self) -> NFTContractMetadata {
+        self.metadata.get().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_contract_standards::non_fungible_token::metadata::NFT_METADATA_SPEC;
+    use near_sdk::mock::MockAction;
+    use near_sdk::test_utils::{get_created_receipts, get_logs, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    fn get_context(predecessor: &str) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder.current_account_id("contract.testnet".parse().unwrap());
+        builder.signer_account_id(predecessor.parse().unwrap());
+        builder.predecessor_account_id(predecessor.parse().unwrap());
+        builder.attached_deposit(NearToken::from_near(1));
+        builder
+    }
+
+    fn new_store() -> MintbaseIntegration {
+        testing_env!(get_context("owner.testnet").build());
+        MintbaseIntegration::new(
+            "owner.testnet".parse().unwrap(),
+            NFTContractMetadata {
+                spec: NFT_METADATA_SPEC.to_string(),
+                name: "Creative Store".to_string(),
+                symbol: "CRS".to_string(),
+                icon: None,
+                base_uri: None,
+                reference: None,
+                reference_hash: None,
+            },
+        )
+    }
+
+    fn artwork() -> TokenMetadata {
+        TokenMetadata {
+            title: Some("Generative piece".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_mintbase_integration_creation() {
+        let store = new_store();
+        assert_eq!(store.minting_fee, NearToken::from_yoctonear(0));
+        assert!(store.is_minter("owner.testnet".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_mintbase_config_update() {
+        let mut store = new_store();
+        let config = MintbaseConfig {
+            treasury_id: "treasury.testnet".parse().unwrap(),
+            minting_fee: NearToken::from_yoctonear(1000),
+        };
+
+        store.update_config(config.clone());
+        assert_eq!(store.get_config(), config);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only contract owner can manage the store")]
+    fn test_minter_management() {
+        let mut store = new_store();
+        let account_id: AccountId = "minter.testnet".parse().unwrap();
+
+        store.add_minter(account_id.clone());
+        assert!(store.is_minter(account_id.clone()));
+
+        store.remove_minter(account_id.clone());
+        assert!(!store.is_minter(account_id.clone()));
+
+        testing_env!(get_context("minter.testnet").build());
+        store.add_minter(account_id);
+    }
+
+    #[test]
+    fn test_batch_mint_editions_and_payout() {
+        let mut store = new_store();
+        store.add_minter("minter.testnet".parse().unwrap());
+
+        testing_env!(get_context("minter.testnet").build());
+        let token_ids = store.nft_batch_mint(
+            "collector.testnet".parse().unwrap(),
+            artwork(),
+            3,
+            Some(RoyaltyArgs {
+                split_between: HashMap::from([("artist.testnet".parse().unwrap(), 10_000)]),
+                percentage: 1_000,
+            }),
+            Some(HashMap::from([
+                ("collector.testnet".parse().unwrap(), 7_500),
+                ("gallery.testnet".parse().unwrap(), 2_500),
+            ])),
+        );
+        assert_eq!(token_ids, vec!["0", "1", "2"]);
+        assert!(get_logs()[0].contains(r#"\"minter\":\"minter.testnet\""#));
+
+        let token = store.nft_token("2".to_string()).unwrap();
+        assert_eq!(token.metadata.unwrap().copies, Some(3));
+        assert_eq!(store.nft_token_edition("2".to_string()).unwrap().edition, 3);
+
+        let payout = store.nft_payout("2".to_string(), U128(1_000), Some(10)).payout;
+        assert_eq!(payout[&"artist.testnet".parse::<AccountId>().unwrap()], U128(100));
+        assert_eq!(payout[&"collector.testnet".parse::<AccountId>().unwrap()], U128(675));
+        assert_eq!(payout[&"gallery.testnet".parse::<AccountId>().unwrap()], U128(225));
+    }
+
+    #[test]
+    fn test_burn_removes_token() {
+        let mut store = new_store();
+        let owner_id: AccountId = "owner.testnet".parse().unwrap();
+        store.nft_batch_mint(owner_id.clone(), artwork(), 2, None, None);
+
+        testing_env!(get_context("owner.testnet")
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        let initial_storage = env::storage_usage();
+        store.nft_batch_burn(vec!["0".to_string()]);
+        assert!(store.nft_token("0".to_string()).is_none());
+        assert_eq!(store.nft_supply_for_owner(owner_id.clone()), U128(1));
+        assert!(store.nft_token("1".to_string()).unwrap().metadata.is_some());
+        assert!(!store.nft_is_soulbound("1".to_string()));
+
+        // The freed storage goes back to the burner
+        let storage_freed = initial_storage - env::storage_usage();
+        assert!(storage_freed > 0);
+        let refunds: Vec<_> = get_created_receipts()
+            .into_iter()
+            .filter(|receipt| receipt.receiver_id == owner_id)
+            .flat_map(|receipt| receipt.actions)
+            .filter_map(|action| match action {
+                MockAction::Transfer { deposit, .. } => Some(deposit),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(refunds, vec![env::storage_byte_cost().saturating_mul(storage_freed as u128)]);
+    }
+}
@@ -0,0 +1,238 @@
+//! Privacy-preserving biometric templates
+//!
+//! A fuzzy commitment over locality-sensitive hashes of a feature vector (EEG band
+//! powers, HRV statistics, ...):
+//!
+//! 1. The vector is centered and projected onto `CODE_BITS` pseudo-random hyperplanes
+//!    (SimHash). Nearby vectors give codes with a small Hamming distance.
+//! 2. Enrollment draws a random `KEY_BITS` key, spreads it with a repetition code and
+//!    stores only the code XOR the LSH bits (helper data) plus a salted hash of the key.
+//! 3. A fresh sample XORed with the helper data gives the code with a few flipped
+//!    bits; majority decoding recovers the key, and the salted hash confirms it.
+//!
+//! Neither the features nor the key can be read back from a commitment. The
+//! hyperplanes are seeded by the salt, so re-enrolling with new entropy yields an
+//! unlinkable template. `max_bit_errors` is the false-accept knob: a lower value
+//! rejects more impostors and more noisy genuine samples.
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::env;
+use near_sdk::serde::{Deserialize, Serialize};
+
+/// Key length recovered from a matching sample
+pub const KEY_BITS: usize = 64;
+
+/// Copies of each key bit in the code; up to `(REPETITION - 1) / 2` flips per bit are corrected
+pub const REPETITION: usize = 5;
+
+/// Number of LSH bits (hyperplanes) per template
+pub const CODE_BITS: usize = KEY_BITS * REPETITION;
+
+/// Default accepted Hamming distance between enrolled and fresh LSH bits (15%)
+pub const DEFAULT_MAX_BIT_ERRORS: u32 = 48;
+
+/// Longest feature vector accepted, to bound hyperplane generation
+pub const MAX_DIMENSIONS: usize = 256;
+
+/// Hyperplane components drawn from one sha256 block (16 x i16)
+const COMPONENTS_PER_BLOCK: usize = 16;
+
+/// On-chain biometric template: salt, helper data and key commitment
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BiometricCommitment {
+    pub salt: Vec<u8>,       // 32 bytes; also seeds the hyperplanes
+    pub helper: Vec<u8>,     // CODE_BITS / 8 bytes: repetition-coded key XOR LSH bits
+    pub key_hash: Vec<u8>,   // sha256(salt || key)
+    pub dimensions: u32,     // Feature vector length at enrollment
+    pub max_bit_errors: u32, // Accept threshold for `verify`
+}
+
+impl BiometricCommitment {
+    /// Enroll a feature vector; `entropy` must be secret and fresh for every enrollment
+    ///
+    /// Features should share a scale, e.g. log band powers and standardized HRV statistics.
+    pub fn enroll(features: &[f32], entropy: &[u8; 32], max_bit_errors: u32) -> Self {
+        assert!(
+            !features.is_empty() && features.len() <= MAX_DIMENSIONS,
+            "Feature vector must have 1 to {} dimensions",
+            MAX_DIMENSIONS
+        );
+        let salt = env::sha256([b"biometric-salt".as_slice(), entropy].concat());
+        let key = env::sha256([b"biometric-key".as_slice(), entropy].concat())[..KEY_BITS / 8].to_vec();
+
+        let bits = lsh_bits(features, &salt);
+        let helper = xor(&encode(&key), &bits);
+        let key_hash = env::sha256([salt.as_slice(), &key].concat());
+
+        Self {
+            salt,
+            helper,
+            key_hash,
+            dimensions: features.len() as u32,
+            max_bit_errors,
+        }
+    }
+
+    /// Hamming distance between the enrolled and fresh LSH bits, if the sample unlocks the key
+    pub fn distance(&self, features: &[f32]) -> Option<u32> {
+        if features.len() != self.dimensions as usize {
+            return None;
+        }
+        let noisy_code = xor(&lsh_bits(features, &self.salt), &self.helper);
+        let key = decode(&noisy_code);
+        if env::sha256([self.salt.as_slice(), &key].concat()) != self.key_hash {
+            return None;
+        }
+        Some(popcount(&xor(&noisy_code, &encode(&key))))
+    }
+
+    /// Check a sample against the enrolled threshold
+    pub fn verify(&self, features: &[f32]) -> bool {
+        self.verify_with_threshold(features, self.max_bit_errors)
+    }
+
+    /// Check a sample with a stricter or looser threshold than the enrolled one
+    pub fn verify_with_threshold(&self, features: &[f32], max_bit_errors: u32) -> bool {
+        self.distance(features).is_some_and(|distance| distance <= max_bit_errors)
+    }
+}
+
+/// SimHash of the centered feature vector against salt-seeded hyperplanes, packed LSB first
+fn lsh_bits(features: &[f32], salt: &[u8]) -> Vec<u8> {
+    let mean = features.iter().map(|&x| x as f64).sum::<f64>() / features.len() as f64;
+    let mut bits = vec![0u8; CODE_BITS / 8];
+
+    for plane in 0..CODE_BITS {
+        let mut dot = 0.0f64;
+        for (block, chunk) in features.chunks(COMPONENTS_PER_BLOCK).enumerate() {
+            let seed = env::sha256(
+                [
+                    b"biometric-lsh".as_slice(),
+                    salt,
+                    &(plane as u32).to_le_bytes(),
+                    &(block as u32).to_le_bytes(),
+                ]
+                .concat(),
+            );
+            for (x, pair) in chunk.iter().zip(seed.chunks_exact(2)) {
+                // Uniform in [-1, 1)
+                let component = i16::from_le_bytes([pair[0], pair[1]]) as f64 / 32768.0;
+                dot += (*x as f64 - mean) * component;
+            }
+        }
+        if dot >= 0.0 {
+            bits[plane / 8] |= 1 << (plane % 8);
+        }
+    }
+    bits
+}
+
+/// Repetition code; code bit `i` carries key bit `i % KEY_BITS` so bursts spread across key bits
+fn encode(key: &[u8]) -> Vec<u8> {
+    let mut code = vec![0u8; CODE_BITS / 8];
+    for i in 0..CODE_BITS {
+        if bit_at(key, i % KEY_BITS) {
+            code[i / 8] |= 1 << (i % 8);
+        }
+    }
+    code
+}
+
+/// Majority vote over the copies of each key bit
+fn decode(code: &[u8]) -> Vec<u8> {
+    let mut key = vec![0u8; KEY_BITS / 8];
+    for k in 0..KEY_BITS {
+        let ones = (k..CODE_BITS).step_by(KEY_BITS).filter(|&i| bit_at(code, i)).count();
+        if ones * 2 > REPETITION {
+            key[k / 8] |= 1 << (k % 8);
+        }
+    }
+    key
+}
+
+fn bit_at(bytes: &[u8], index: usize) -> bool {
+    bytes[index / 8] >> (index % 8) & 1 == 1
+}
+
+fn xor(a: &[u8], b: &[u8]) -> Vec<u8> {
+    a.iter().zip(b).map(|(x, y)| x ^ y).collect()
+}
+
+fn popcount(bytes: &[u8]) -> u32 {
+    bytes.iter().map(|byte| byte.count_ones()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Synthetic features: log relative power of delta..gamma on Fz, Cz, Pz and Oz,
+    // then standardized mean RR, SDNN, RMSSD, pNN50 and LF/HF
+    const SUBJECT_A_SESSION_1: [f32; 25] = [
+        1.20, 0.85, 0.95, 0.40, -0.35,
+        1.10, 0.90, 1.05, 0.45, -0.30,
+        1.00, 0.80, 1.35, 0.35, -0.40,
+        0.95, 0.70, 1.60, 0.30, -0.45,
+        0.62, 0.35, 0.48, 0.41, -0.22,
+    ];
+    const SUBJECT_A_SESSION_2: [f32; 25] = [
+        1.23, 0.82, 0.97, 0.43, -0.33,
+        1.08, 0.93, 1.01, 0.44, -0.27,
+        1.03, 0.78, 1.31, 0.38, -0.42,
+        0.91, 0.72, 1.64, 0.27, -0.44,
+        0.58, 0.39, 0.45, 0.44, -0.25,
+    ];
+    const SUBJECT_B: [f32; 25] = [
+        0.90, 1.10, 0.40, 0.95, 0.10,
+        0.85, 1.15, 0.45, 1.00, 0.15,
+        0.80, 1.05, 0.60, 0.90, 0.05,
+        0.75, 0.95, 0.70, 0.85, 0.00,
+        -0.70, -0.55, -0.80, -0.60, 0.90,
+    ];
+    const ENTROPY: [u8; 32] = [7; 32];
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    #[test]
+    fn test_enrollment_vector() {
+        let commitment = BiometricCommitment::enroll(&SUBJECT_A_SESSION_1, &ENTROPY, DEFAULT_MAX_BIT_ERRORS);
+        assert_eq!(hex(&commitment.salt), "88f0a05ffbd3ca5a3f17e8c5675c8f6b85264917828cdbd75f596c0a995b596f");
+        assert_eq!(hex(&commitment.key_hash), "85ad24aada2f6fde52fcb631589e6cd39b926248a2d09746f4a526eb97bef8e8");
+        assert_eq!(
+            hex(&commitment.helper),
+            "74b8d7d8d0252584ece4311e1132f898c640b507d0c1eab7e58976b814c3219aee7190cf16d88646"
+        );
+        assert_eq!(commitment.distance(&SUBJECT_A_SESSION_1), Some(0));
+    }
+
+    #[test]
+    fn test_same_subject_matches_other_subject_does_not() {
+        let commitment = BiometricCommitment::enroll(&SUBJECT_A_SESSION_1, &ENTROPY, DEFAULT_MAX_BIT_ERRORS);
+        assert_eq!(commitment.distance(&SUBJECT_A_SESSION_2), Some(10));
+        assert!(commitment.verify(&SUBJECT_A_SESSION_2));
+
+        assert_eq!(commitment.distance(&SUBJECT_B), None);
+        assert!(!commitment.verify(&SUBJECT_B));
+        assert!(!commitment.verify(&SUBJECT_A_SESSION_2[..24]));
+    }
+
+    #[test]
+    fn test_threshold_is_tunable() {
+        let commitment = BiometricCommitment::enroll(&SUBJECT_A_SESSION_1, &ENTROPY, 9);
+        assert!(!commitment.verify(&SUBJECT_A_SESSION_2));
+        assert!(commitment.verify_with_threshold(&SUBJECT_A_SESSION_2, 10));
+    }
+
+    #[test]
+    fn test_reenrollment_is_unlinkable() {
+        let first = BiometricCommitment::enroll(&SUBJECT_A_SESSION_1, &ENTROPY, DEFAULT_MAX_BIT_ERRORS);
+        let second = BiometricCommitment::enroll(&SUBJECT_A_SESSION_1, &[8; 32], DEFAULT_MAX_BIT_ERRORS);
+        assert_ne!(first.salt, second.salt);
+        assert_ne!(first.helper, second.helper);
+        assert!(second.verify(&SUBJECT_A_SESSION_2));
+        assert!(!second.verify(&SUBJECT_B));
+    }
+}
@@ -1,10 +1,29 @@
 //! On-chain collaboration features for creative sessions
-
-use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{LookupMap, UnorderedMap, UnorderedSet};
+//!
+//! Session state only changes through patches: editors submit `StateChange` lists,
+//! participants vote, and merging applies the changes atomically. Every change
+//! carries the `old_value` it was made against, so a patch built on a stale state
+//! is rejected instead of silently overwriting newer work.
+//!
+//! Proposed patches are decided by a per-session `VotingPolicy`: quorum, approval
+//! threshold, voting deadline and vote weighting.
+//!
+//! Members hold a `Role` whose capabilities gate every call through
+//! `CollaborationContract::authorize`. Archived sessions stay readable but reject
+//! all changes.
+//!
+//! Real-time co-editing happens off-chain (see the `nft-collab-crdt` crate); peers
+//! record the hash of their merged state here with `checkpoint_state`.
+
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::collections::{LookupMap, UnorderedMap};
+use near_sdk::json_types::Base64VecU8;
 use near_sdk::serde::{Deserialize, Serialize};
-use near_sdk::{env, near_bindgen, AccountId, Balance, Promise, Timestamp};
-use std::collections::HashMap;
+use near_sdk::serde_json::{self, Value};
+use near_sdk::{env, ext_contract, near, AccountId, Gas, PromiseOrValue, Timestamp};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
 
 /// Live collaboration session
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
@@ -12,13 +31,30 @@ use std::collections::HashMap;
 pub struct CollaborationSession {
     pub session_id: String,
     pub creator: AccountId,
-    pub participants: Vec<AccountId>,
+    pub members: BTreeMap<AccountId, Role>,
+    pub banned: Vec<AccountId>,
+    pub invites: Vec<Invite>,
+    pub voting_policy: VotingPolicy,
     pub current_state: ToolState,
     pub patches: Vec<Patch>,
-    pub permissions: PermissionMatrix,
     pub created_at: Timestamp,
     pub last_activity: Timestamp,
-    pub is_active: bool,
+    pub is_active: bool, // False once archived
+    pub checkpoints: Vec<StateCheckpoint>, // Most recent last, at most MAX_CHECKPOINTS
+}
+
+/// Checkpoints kept per session
+pub const MAX_CHECKPOINTS: usize = 32;
+
+/// Hash of the off-chain CRDT state, as merged by one peer
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StateCheckpoint {
+    pub state_hash: Base64VecU8, // sha256 of the canonical snapshot JSON
+    pub op_count: u64,           // CRDT operations merged into the state
+    pub author: AccountId,
+    pub version: u64, // On-chain state version at the time
+    pub timestamp: Timestamp,
 }
 
 /// Tool state for synchronization
@@ -26,7 +62,7 @@ pub struct CollaborationSession {
 #[serde(crate = "near_sdk::serde")]
 pub struct ToolState {
     pub tool_type: String,
-    pub parameters: near_sdk::serde_json::Value,
+    pub parameters: String, // JSON text, usually an object
     pub canvas_data: Vec<u8>,
     pub timeline_position: f32,
     pub version: u64,
@@ -41,17 +77,136 @@ pub struct Patch {
     pub parent_patch: Option<String>,
     pub changes: Vec<StateChange>,
     pub timestamp: Timestamp,
-    pub votes: i32,
+    pub votes_for: u64,     // Weighted
+    pub votes_against: u64, // Weighted
+    pub voters: u32,
+    pub voting_ends_at: Option<Timestamp>, // Set when proposed
+    pub policy: Option<VotingPolicy>,      // Session policy at proposal time
     pub status: PatchStatus,
 }
 
+/// One member's vote on a patch, with the weight it was cast with
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PatchVote {
+    pub approve: bool,
+    pub weight: u64,
+}
+
+impl Patch {
+    fn new(id: String, author: AccountId, parent_patch: Option<String>, changes: Vec<StateChange>, status: PatchStatus) -> Self {
+        Self {
+            id,
+            author,
+            parent_patch,
+            changes,
+            timestamp: env::block_timestamp(),
+            votes_for: 0,
+            votes_against: 0,
+            voters: 0,
+            voting_ends_at: None,
+            policy: None,
+            status,
+        }
+    }
+
+    fn add_vote(&mut self, vote: &PatchVote) {
+        if vote.approve {
+            self.votes_for += vote.weight;
+        } else {
+            self.votes_against += vote.weight;
+        }
+        self.voters += 1;
+    }
+
+    fn remove_vote(&mut self, vote: &PatchVote) {
+        if vote.approve {
+            self.votes_for -= vote.weight;
+        } else {
+            self.votes_against -= vote.weight;
+        }
+        self.voters -= 1;
+    }
+}
+
+/// Longest voting period a policy can set (30 days)
+pub const MAX_VOTING_PERIOD: u64 = 30 * 24 * 60 * 60 * 1_000_000_000;
+
+/// Gas for the soulbound contract's `sbt_reputation` view
+const GAS_FOR_REPUTATION: Gas = Gas::from_tgas(5);
+
+/// Gas for recording a vote once its reputation weight is known
+const GAS_FOR_ON_REPUTATION: Gas = Gas::from_tgas(15);
+
+/// Reputation view of the soulbound contract, used for weighted voting
+#[ext_contract(ext_soulbound_reputation)]
+pub trait SoulboundReputation {
+    fn sbt_reputation(&self, account_id: AccountId) -> f32;
+}
+
+/// How much each vote counts
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum VoteWeighting {
+    OnePerMember,
+    /// 1 plus the voter's merged patches in the session
+    Contributions,
+    /// 1 plus the voter's rounded `sbt_reputation` on a soulbound contract
+    Reputation { soulbound_contract_id: AccountId },
+}
+
+/// Per-session rules for deciding proposed patches
+///
+/// Voting closes at the deadline, or as soon as every member who can vote has
+/// voted. A patch passes if enough members voted and the approving share of the
+/// cast weight exceeds the threshold.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct VotingPolicy {
+    pub quorum_bps: u16,    // Share of voting members that must vote, in basis points
+    pub threshold_bps: u16, // Approving share of cast weight that must be exceeded
+    pub voting_period: u64, // Nanoseconds from proposal to deadline
+    pub weighting: VoteWeighting,
+}
+
+impl Default for VotingPolicy {
+    /// Simple majority of at least half the voters, within three days
+    fn default() -> Self {
+        Self {
+            quorum_bps: 5_000,
+            threshold_bps: 5_000,
+            voting_period: 3 * 24 * 60 * 60 * 1_000_000_000,
+            weighting: VoteWeighting::OnePerMember,
+        }
+    }
+}
+
+impl VotingPolicy {
+    /// Whether a closed vote passes, given how many members could vote
+    pub fn passes(&self, patch: &Patch, eligible: usize) -> bool {
+        let cast = patch.votes_for as u128 + patch.votes_against as u128;
+        patch.voters as u128 * 10_000 >= self.quorum_bps as u128 * eligible as u128
+            && patch.votes_for as u128 * 10_000 > self.threshold_bps as u128 * cast
+    }
+}
+
 /// Individual state change
+///
+/// Both values are JSON text, and `old_value` must match the current state for the
+/// change to apply. What the values hold depends on `change_type`:
+/// - `ParameterUpdate`: the value at the JSON pointer `parameter_path` in
+///   `ToolState.parameters`; `null` means absent, so `null -> v` adds and `v -> null`
+///   removes. Array elements can be replaced, appended at index `len`, or removed
+///   from the end.
+/// - `ToolSwitch`: the tool type string
+/// - `CanvasAction`: the whole canvas, base64-encoded
+/// - `TimelineSeek`: the timeline position, a non-negative number
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
 pub struct StateChange {
     pub parameter_path: String,
-    pub old_value: near_sdk::serde_json::Value,
-    pub new_value: near_sdk::serde_json::Value,
+    pub old_value: String,
+    pub new_value: String,
     pub change_type: ChangeType,
 }
 
@@ -65,6 +220,164 @@ pub enum ChangeType {
     TimelineSeek,
 }
 
+/// Why a list of changes could not be applied
+#[derive(Debug, PartialEq)]
+pub enum PatchConflict {
+    /// `old_value` no longer matches the state; values are JSON text
+    Stale { path: String, expected: String, found: String },
+    /// The path or new value is not valid for the change type
+    Invalid { path: String, reason: &'static str },
+}
+
+impl fmt::Display for PatchConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PatchConflict::Stale { path, expected, found } => {
+                write!(f, "Patch conflict at {}: expected {}, found {}", path, expected, found)
+            }
+            PatchConflict::Invalid { path, reason } => write!(f, "Invalid change at {}: {}", path, reason),
+        }
+    }
+}
+
+impl StateChange {
+    /// The change that undoes this one
+    pub fn inverse(&self) -> Self {
+        Self {
+            parameter_path: self.parameter_path.clone(),
+            old_value: self.new_value.clone(),
+            new_value: self.old_value.clone(),
+            change_type: self.change_type.clone(),
+        }
+    }
+}
+
+impl ToolState {
+    /// Parameters parsed from their stored JSON text
+    pub fn parameters_value(&self) -> Value {
+        serde_json::from_str(&self.parameters).unwrap_or(Value::Null)
+    }
+
+    /// Apply `changes` in order and bump the version; on conflict nothing is changed
+    pub fn apply_changes(&mut self, changes: &[StateChange]) -> Result<(), PatchConflict> {
+        let mut next = self.clone();
+        let mut parameters = self.parameters_value();
+        for change in changes {
+            next.apply_change(&mut parameters, change)?;
+        }
+        next.parameters = parameters.to_string();
+        next.version += 1;
+        *self = next;
+        Ok(())
+    }
+
+    fn apply_change(&mut self, parameters: &mut Value, change: &StateChange) -> Result<(), PatchConflict> {
+        let path = change.parameter_path.as_str();
+        let old_value = parse_value(path, &change.old_value)?;
+        let new_value = parse_value(path, &change.new_value)?;
+        let found = self.current_value(parameters, change)?;
+        let matches = match change.change_type {
+            // Positions are stored as f32, so compare at that precision
+            ChangeType::TimelineSeek => old_value.as_f64().map(|old| old as f32) == Some(self.timeline_position),
+            _ => found == old_value,
+        };
+        if !matches {
+            return Err(PatchConflict::Stale {
+                path: path.to_string(),
+                expected: old_value.to_string(),
+                found: found.to_string(),
+            });
+        }
+
+        match change.change_type {
+            ChangeType::ParameterUpdate => set_pointer(parameters, path, new_value)?,
+            ChangeType::ToolSwitch => {
+                self.tool_type = new_value
+                    .as_str()
+                    .filter(|tool_type| !tool_type.is_empty())
+                    .ok_or_else(|| invalid(path, "tool type must be a non-empty string"))?
+                    .to_string();
+            }
+            ChangeType::CanvasAction => {
+                let canvas: Base64VecU8 = serde_json::from_value(new_value)
+                    .map_err(|_| invalid(path, "canvas must be base64"))?;
+                self.canvas_data = canvas.0;
+            }
+            ChangeType::TimelineSeek => {
+                self.timeline_position = new_value
+                    .as_f64()
+                    .filter(|position| position.is_finite() && *position >= 0.0)
+                    .ok_or_else(|| invalid(path, "timeline position must be a non-negative number"))?
+                    as f32;
+            }
+        }
+        Ok(())
+    }
+
+    /// The value a change's `old_value` is checked against
+    fn current_value(&self, parameters: &Value, change: &StateChange) -> Result<Value, PatchConflict> {
+        let path = change.parameter_path.as_str();
+        Ok(match change.change_type {
+            ChangeType::ParameterUpdate => {
+                if !path.is_empty() && !path.starts_with('/') {
+                    return Err(invalid(path, "parameter path must be a JSON pointer"));
+                }
+                parameters.pointer(path).cloned().unwrap_or(Value::Null)
+            }
+            ChangeType::ToolSwitch => Value::String(self.tool_type.clone()),
+            ChangeType::CanvasAction => serde_json::to_value(Base64VecU8(self.canvas_data.clone()))
+                .unwrap_or(Value::Null),
+            ChangeType::TimelineSeek => Value::from(self.timeline_position as f64),
+        })
+    }
+}
+
+fn invalid(path: &str, reason: &'static str) -> PatchConflict {
+    PatchConflict::Invalid { path: path.to_string(), reason }
+}
+
+fn parse_value(path: &str, json: &str) -> Result<Value, PatchConflict> {
+    serde_json::from_str(json).map_err(|_| invalid(path, "value must be JSON"))
+}
+
+/// Write `value` at a JSON pointer, removing the entry when `value` is null
+fn set_pointer(root: &mut Value, path: &str, value: Value) -> Result<(), PatchConflict> {
+    let Some((parent_path, token)) = path.rsplit_once('/') else {
+        if value.is_null() {
+            return Err(invalid(path, "parameters cannot be removed"));
+        }
+        *root = value;
+        return Ok(());
+    };
+    let key = token.replace("~1", "/").replace("~0", "~");
+    let parent = root
+        .pointer_mut(parent_path)
+        .ok_or_else(|| invalid(path, "parent does not exist"))?;
+
+    match parent {
+        Value::Object(entries) => {
+            if value.is_null() {
+                entries.remove(&key);
+            } else {
+                entries.insert(key, value);
+            }
+        }
+        Value::Array(items) => {
+            let index: usize = key.parse().map_err(|_| invalid(path, "array index must be a number"))?;
+            match (index.cmp(&items.len()), value.is_null()) {
+                (Ordering::Less, false) => items[index] = value,
+                (Ordering::Less, true) if index + 1 == items.len() => {
+                    items.pop();
+                }
+                (Ordering::Equal, false) => items.push(value),
+                _ => return Err(invalid(path, "arrays only support replace, append and removing the last element")),
+            }
+        }
+        _ => return Err(invalid(path, "parent is not an object or array")),
+    }
+    Ok(())
+}
+
 /// Patch approval status
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
@@ -74,49 +387,301 @@ pub enum PatchStatus {
     Approved,
     Rejected,
     Merged,
+    Reverted, // Merged, then undone by `revert_patch`
+}
+
+/// Session role, declared from least to most privileged
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Role {
+    Viewer,
+    Editor,
+    Maintainer,
+    Owner, // Exactly one per session
+}
+
+/// Actions a role may be allowed to take
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Capability {
+    View,
+    Edit,
+    Vote,
+    Merge,
+    Invite,
+    ManageMembers,
+    Archive,
+    TransferOwnership,
+    ConfigureVoting,
 }
 
-/// Permission matrix for session access
+impl Role {
+    pub fn capabilities(self) -> &'static [Capability] {
+        use Capability::*;
+        match self {
+            Role::Viewer => &[View],
+            Role::Editor => &[View, Edit, Vote],
+            Role::Maintainer => &[View, Edit, Vote, Merge, Invite, ManageMembers],
+            Role::Owner => &[
+                View,
+                Edit,
+                Vote,
+                Merge,
+                Invite,
+                ManageMembers,
+                Archive,
+                TransferOwnership,
+                ConfigureVoting,
+            ],
+        }
+    }
+
+    pub fn can(self, capability: Capability) -> bool {
+        self.capabilities().contains(&capability)
+    }
+}
+
+impl Capability {
+    fn as_str(self) -> &'static str {
+        match self {
+            Capability::View => "view",
+            Capability::Edit => "edit",
+            Capability::Vote => "vote",
+            Capability::Merge => "merge",
+            Capability::Invite => "invite",
+            Capability::ManageMembers => "member management",
+            Capability::Archive => "archive",
+            Capability::TransferOwnership => "ownership transfer",
+            Capability::ConfigureVoting => "voting configuration",
+        }
+    }
+}
+
+/// Longest an invite code stays valid (30 days)
+pub const MAX_INVITE_DURATION: u64 = 30 * 24 * 60 * 60 * 1_000_000_000;
+
+/// Open invites kept per session
+pub const MAX_INVITES: usize = 20;
+
+/// Invite code redeemable by any account until it expires or runs out of uses
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
-pub struct PermissionMatrix {
-    pub can_edit: Vec<AccountId>,
-    pub can_view: Vec<AccountId>,
-    pub can_invite: Vec<AccountId>,
-    pub can_merge: Vec<AccountId>,
+pub struct Invite {
+    pub code_hash: Base64VecU8, // sha256 of the code, so open codes can't be read from state
+    pub role: Role,
+    pub created_by: AccountId,
+    pub expires_at: Timestamp,
+    pub uses_left: u32,
+}
+
+impl CollaborationSession {
+    pub fn role_of(&self, account_id: &AccountId) -> Option<Role> {
+        self.members.get(account_id).copied()
+    }
+
+    pub fn owner(&self) -> &AccountId {
+        self.members
+            .iter()
+            .find(|(_, role)| **role == Role::Owner)
+            .map(|(account_id, _)| account_id)
+            .expect("Session has an owner")
+    }
+
+    /// Members whose votes count towards approval
+    pub fn voter_count(&self) -> usize {
+        self.members.values().filter(|role| role.can(Capability::Vote)).count()
+    }
+
+    /// Vote weight of a member under one-per-member or contribution weighting
+    fn local_vote_weight(&self, weighting: &VoteWeighting, voter: &AccountId) -> u64 {
+        match weighting {
+            VoteWeighting::Contributions => {
+                1 + self
+                    .patches
+                    .iter()
+                    .filter(|p| &p.author == voter && matches!(p.status, PatchStatus::Merged))
+                    .count() as u64
+            }
+            _ => 1,
+        }
+    }
+
+    /// Decide a proposed patch under the policy it was proposed with
+    fn close_vote(&mut self, index: usize) -> PatchStatus {
+        let eligible = self.voter_count();
+        let patch = &mut self.patches[index];
+        let policy = patch.policy.clone().unwrap_or_default();
+        patch.status = if policy.passes(patch, eligible) {
+            PatchStatus::Approved
+        } else {
+            PatchStatus::Rejected
+        };
+
+        CollaborationEvent::ProposalDecided {
+            session_id: self.session_id.clone(),
+            patch_id: patch.id.clone(),
+            status: patch.status.clone(),
+            votes_for: patch.votes_for,
+            votes_against: patch.votes_against,
+            voters: patch.voters,
+        }
+        .emit();
+        patch.status.clone()
+    }
+
+    /// Panics unless `account_id` holds `capability`; returns its role
+    pub fn require(&self, account_id: &AccountId, capability: Capability) -> Role {
+        match self.role_of(account_id) {
+            Some(role) if role.can(capability) => role,
+            _ => env::panic_str(&format!("No {} permission", capability.as_str())),
+        }
+    }
+}
+
+/// NEP-297 events logged by the collaboration contract
+#[near(event_json(standard = "collaboration"))]
+pub enum CollaborationEvent {
+    #[event_version("1.0.0")]
+    SessionCreated { session_id: String, creator: AccountId, tool_type: String },
+    #[event_version("1.0.0")]
+    SessionJoined { session_id: String, account_id: AccountId },
+    #[event_version("1.0.0")]
+    SessionLeft { session_id: String, account_id: AccountId },
+    #[event_version("2.0.0")]
+    MemberInvited {
+        session_id: String,
+        inviter: AccountId,
+        invitee: AccountId,
+        role: Role,
+    },
+    #[event_version("1.0.0")]
+    InviteCreated {
+        session_id: String,
+        created_by: AccountId,
+        role: Role,
+        expires_at: Timestamp,
+        max_uses: u32,
+    },
+    #[event_version("1.0.0")]
+    InviteRedeemed { session_id: String, account_id: AccountId, role: Role },
+    #[event_version("1.0.0")]
+    MemberRoleChanged {
+        session_id: String,
+        account_id: AccountId,
+        role: Role,
+        changed_by: AccountId,
+    },
+    #[event_version("1.0.0")]
+    MemberRemoved {
+        session_id: String,
+        account_id: AccountId,
+        removed_by: AccountId,
+        banned: bool,
+    },
+    #[event_version("1.0.0")]
+    OwnershipTransferred {
+        session_id: String,
+        previous_owner: AccountId,
+        new_owner: AccountId,
+    },
+    #[event_version("1.0.0")]
+    SessionArchived { session_id: String, archived_by: AccountId },
+    #[event_version("1.0.0")]
+    SessionRestored { session_id: String, restored_by: AccountId },
+    #[event_version("1.0.0")]
+    PatchSubmitted {
+        session_id: String,
+        patch_id: String,
+        author: AccountId,
+        version: u64,
+    },
+    #[event_version("1.0.0")]
+    PatchProposed { session_id: String, patch_id: String, author: AccountId },
+    #[event_version("2.0.0")]
+    ProposalVoted {
+        session_id: String,
+        patch_id: String,
+        voter: AccountId,
+        approve: bool,
+        weight: u64,
+        votes_for: u64,
+        votes_against: u64,
+    },
+    #[event_version("1.0.0")]
+    VoteWithdrawn {
+        session_id: String,
+        patch_id: String,
+        voter: AccountId,
+        votes_for: u64,
+        votes_against: u64,
+    },
+    #[event_version("1.0.0")]
+    ProposalDecided {
+        session_id: String,
+        patch_id: String,
+        status: PatchStatus,
+        votes_for: u64,
+        votes_against: u64,
+        voters: u32,
+    },
+    #[event_version("1.0.0")]
+    VotingPolicyUpdated { session_id: String, policy: VotingPolicy },
+    #[event_version("1.0.0")]
+    PatchMerged {
+        session_id: String,
+        patch_id: String,
+        merged_by: AccountId,
+        version: u64,
+    },
+    #[event_version("1.0.0")]
+    PatchReverted {
+        session_id: String,
+        patch_id: String,
+        revert_patch_id: String,
+        reverted_by: AccountId,
+        version: u64,
+    },
+    #[event_version("1.0.0")]
+    PatchPublished { session_id: String, patch_id: String, author: AccountId },
+    #[event_version("1.0.0")]
+    StateCheckpointed {
+        session_id: String,
+        author: AccountId,
+        state_hash: Base64VecU8,
+        op_count: u64,
+    },
 }
 
 /// Collaboration contract
-#[derive(BorshDeserialize, BorshSerialize)]
+#[near(contract_state)]
 pub struct CollaborationContract {
     pub sessions: UnorderedMap<String, CollaborationSession>,
     pub user_sessions: LookupMap<AccountId, Vec<String>>,
     pub published_patches: UnorderedMap<String, Patch>,
-    pub patch_votes: LookupMap<String, UnorderedMap<AccountId, bool>>, // patch_id -> (voter -> vote)
+    pub patch_votes: LookupMap<(String, String), UnorderedMap<AccountId, PatchVote>>, // (session_id, patch_id) -> (voter -> vote)
     pub owner_id: AccountId,
 }
 
 impl Default for CollaborationContract {
     fn default() -> Self {
         Self {
-            sessions: UnorderedMap::new(b"s"),
-            user_sessions: LookupMap::new(b"u"),
-            published_patches: UnorderedMap::new(b"p"),
-            patch_votes: LookupMap::new(b"v"),
+            sessions: UnorderedMap::new(b"s".to_vec()),
+            user_sessions: LookupMap::new(b"u".to_vec()),
+            published_patches: UnorderedMap::new(b"p".to_vec()),
+            patch_votes: LookupMap::new(b"v".to_vec()),
             owner_id: env::predecessor_account_id(),
         }
     }
 }
 
-#[near_bindgen]
+#[near]
 impl CollaborationContract {
     #[init]
     pub fn new(owner_id: AccountId) -> Self {
         Self {
-            sessions: UnorderedMap::new(b"s"),
-            user_sessions: LookupMap::new(b"u"),
-            published_patches: UnorderedMap::new(b"p"),
-            patch_votes: LookupMap::new(b"v"),
+            sessions: UnorderedMap::new(b"s".to_vec()),
+            user_sessions: LookupMap::new(b"u".to_vec()),
+            published_patches: UnorderedMap::new(b"p".to_vec()),
+            patch_votes: LookupMap::new(b"v".to_vec()),
             owner_id,
         }
     }
@@ -127,7 +692,7 @@ impl CollaborationContract {
         &mut self,
         session_id: String,
         tool_type: String,
-        initial_params: near_sdk::serde_json::Value,
+        initial_params: Value,
     ) -> CollaborationSession {
         let creator = env::predecessor_account_id();
 
@@ -137,184 +702,311 @@ impl CollaborationContract {
         let session = CollaborationSession {
             session_id: session_id.clone(),
             creator: creator.clone(),
-            participants: vec![creator.clone()],
+            members: BTreeMap::from([(creator.clone(), Role::Owner)]),
+            banned: Vec::new(),
+            invites: Vec::new(),
+            voting_policy: VotingPolicy::default(),
             current_state: ToolState {
                 tool_type,
-                parameters: initial_params,
+                parameters: initial_params.to_string(),
                 canvas_data: Vec::new(),
                 timeline_position: 0.0,
                 version: 1,
             },
             patches: Vec::new(),
-            permissions: PermissionMatrix {
-                can_edit: vec![creator.clone()],
-                can_view: vec![creator.clone()],
-                can_invite: vec![creator.clone()],
-                can_merge: vec![creator.clone()],
-            },
             created_at: env::block_timestamp(),
             last_activity: env::block_timestamp(),
             is_active: true,
+            checkpoints: Vec::new(),
         };
 
         // Store session
         self.sessions.insert(&session_id, &session);
 
-        // Update user's sessions
-        let mut user_sessions = self.user_sessions.get(&creator).unwrap_or_default();
-        user_sessions.push(session_id.clone());
-        self.user_sessions.insert(&creator, &user_sessions);
+        self.internal_add_user_session(&creator, &session_id);
+
+        CollaborationEvent::SessionCreated {
+            session_id,
+            creator,
+            tool_type: session.current_state.tool_type.clone(),
+        }
+        .emit();
 
         session
     }
 
-    /// Join an existing collaboration session
+    /// Join an active session as a viewer; members keep their role
     pub fn join_session(&mut self, session_id: String) -> bool {
         let user = env::predecessor_account_id();
 
-        if let Some(mut session) = self.sessions.get(&session_id) {
-            // Check if user can view the session
-            if !session.permissions.can_view.contains(&user) {
-                // Auto-add to viewers if session allows
-                session.permissions.can_view.push(user.clone());
-            }
-
-            // Update last activity
-            session.last_activity = env::block_timestamp();
-            self.sessions.insert(&session_id, &session);
+        match self.sessions.get(&session_id) {
+            Some(mut session) if session.is_active => {
+                assert!(!session.banned.contains(&user), "Banned from session");
+                session.members.entry(user.clone()).or_insert(Role::Viewer);
+                session.last_activity = env::block_timestamp();
+                self.sessions.insert(&session_id, &session);
+                self.internal_add_user_session(&user, &session_id);
 
-            // Update user's sessions
-            let mut user_sessions = self.user_sessions.get(&user).unwrap_or_default();
-            if !user_sessions.contains(&session_id) {
-                user_sessions.push(session_id.clone());
-                self.user_sessions.insert(&user, &user_sessions);
+                CollaborationEvent::SessionJoined { session_id, account_id: user }.emit();
+                true
             }
-
-            true
-        } else {
-            false
+            _ => false,
         }
     }
 
-    /// Update session state (real-time sync)
-    pub fn update_session_state(
-        &mut self,
-        session_id: String,
-        new_state: ToolState,
-        changes: Vec<StateChange>,
-    ) {
-        let user = env::predecessor_account_id();
+    /// Submit changes as a draft patch
+    /// They must apply to the current state now, and are applied when the patch is merged
+    pub fn submit_patch(&mut self, session_id: String, changes: Vec<StateChange>) -> String {
+        let (mut session, user) = self.authorize(&session_id, Capability::Edit);
 
-        if let Some(mut session) = self.sessions.get(&session_id) {
-            // Check edit permissions
-            assert!(session.permissions.can_edit.contains(&user), "No edit permission");
-
-            // Create patch from changes
-            let patch = Patch {
-                id: format!("{}_{}", session_id, env::block_timestamp()),
-                author: user,
-                parent_patch: session.patches.last().map(|p| p.id.clone()),
-                changes,
-                timestamp: env::block_timestamp(),
-                votes: 0,
-                status: PatchStatus::Draft,
-            };
+        if let Err(conflict) = session.current_state.clone().apply_changes(&changes) {
+            env::panic_str(&conflict.to_string());
+        }
 
-            // Update session state
-            session.current_state = new_state;
-            session.patches.push(patch);
-            session.last_activity = env::block_timestamp();
+        // Create patch from changes
+        let patch = Patch::new(
+            format!("{}_{}", session_id, env::block_timestamp()),
+            user.clone(),
+            session.patches.last().map(|p| p.id.clone()),
+            changes,
+            PatchStatus::Draft,
+        );
 
-            self.sessions.insert(&session_id, &session);
-        } else {
-            env::panic_str("Session not found");
+        let patch_id = patch.id.clone();
+        session.patches.push(patch);
+        session.last_activity = env::block_timestamp();
+
+        self.sessions.insert(&session_id, &session);
+
+        CollaborationEvent::PatchSubmitted {
+            session_id,
+            patch_id: patch_id.clone(),
+            author: user,
+            version: session.current_state.version,
         }
+        .emit();
+        patch_id
     }
 
-    /// Propose a patch for community approval
+    /// Propose a draft patch for approval under the session's current voting policy
     pub fn propose_patch(&mut self, session_id: String, patch_id: String) {
-        let user = env::predecessor_account_id();
+        let (mut session, user) = self.authorize(&session_id, Capability::Edit);
+
+        // Find and update patch status
+        if let Some(patch) = session.patches.iter_mut().find(|p| p.id == patch_id) {
+            assert_eq!(patch.author, user, "Only patch author can propose");
+            assert!(matches!(patch.status, PatchStatus::Draft), "Only draft patches can be proposed");
+            patch.status = PatchStatus::Proposed;
+            patch.voting_ends_at = Some(env::block_timestamp() + session.voting_policy.voting_period);
+            patch.policy = Some(session.voting_policy.clone());
+            self.sessions.insert(&session_id, &session);
+            CollaborationEvent::PatchProposed { session_id, patch_id, author: user }.emit();
+        }
+    }
 
-        if let Some(mut session) = self.sessions.get(&session_id) {
-            // Find and update patch status
-            if let Some(patch) = session.patches.iter_mut().find(|p| p.id == patch_id) {
-                assert_eq!(patch.author, user, "Only patch author can propose");
-                patch.status = PatchStatus::Proposed;
-                self.sessions.insert(&session_id, &session);
-            }
+    /// Cast or change a vote before the deadline
+    ///
+    /// Under reputation weighting the weight is looked up on the soulbound contract
+    /// first, and the vote is recorded in the callback.
+    pub fn vote_on_patch(&mut self, session_id: String, patch_id: String, approve: bool) -> PromiseOrValue<()> {
+        let (session, voter) = self.authorize(&session_id, Capability::Vote);
+        let patch = session
+            .patches
+            .iter()
+            .find(|p| p.id == patch_id)
+            .unwrap_or_else(|| env::panic_str("Patch not found"));
+        assert!(matches!(patch.status, PatchStatus::Proposed), "Patch is not open for voting");
+        let weighting = patch.policy.clone().unwrap_or_default().weighting;
+
+        if let VoteWeighting::Reputation { soulbound_contract_id } = weighting {
+            return ext_soulbound_reputation::ext(soulbound_contract_id)
+                .with_static_gas(GAS_FOR_REPUTATION)
+                .sbt_reputation(voter.clone())
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(GAS_FOR_ON_REPUTATION)
+                        .on_reputation_weight(session_id, patch_id, voter, approve),
+                )
+                .into();
         }
+
+        let weight = session.local_vote_weight(&weighting, &voter);
+        self.internal_record_vote(session_id, patch_id, voter, Some(approve), weight);
+        PromiseOrValue::Value(())
     }
 
-    /// Vote on a proposed patch
-    pub fn vote_on_patch(&mut self, session_id: String, patch_id: String, approve: bool) {
-        let voter = env::predecessor_account_id();
+    /// Record a reputation-weighted vote; a failed lookup counts as no reputation
+    #[private]
+    pub fn on_reputation_weight(&mut self, session_id: String, patch_id: String, voter: AccountId, approve: bool) {
+        let reputation = env::promise_result_checked(0, 64)
+            .ok()
+            .and_then(|value| serde_json::from_slice::<f32>(&value).ok())
+            .unwrap_or(0.0);
+        let weight = 1 + reputation.max(0.0).round() as u64;
+        self.internal_record_vote(session_id, patch_id, voter, Some(approve), weight);
+    }
 
-        if let Some(mut session) = self.sessions.get(&session_id) {
-            if let Some(patch) = session.patches.iter_mut().find(|p| p.id == patch_id) {
-                // Check if user already voted
-                let mut votes = self.patch_votes.get(&patch_id).unwrap_or_else(|| UnorderedMap::new(b"pv"));
-                if votes.get(&voter).is_some() {
-                    env::panic_str("Already voted on this patch");
-                }
+    /// Take back a vote before the deadline
+    pub fn withdraw_vote(&mut self, session_id: String, patch_id: String) {
+        let (_, voter) = self.authorize(&session_id, Capability::Vote);
+        self.internal_record_vote(session_id, patch_id, voter, None, 0);
+    }
 
-                // Record vote
-                votes.insert(&voter, &approve);
-                self.patch_votes.insert(&patch_id, &votes);
+    /// Decide a proposed patch once its deadline has passed; anyone can call
+    pub fn finalize_vote(&mut self, session_id: String, patch_id: String) -> PatchStatus {
+        let mut session = self.sessions.get(&session_id).expect("Session not found");
+        let index = session
+            .patches
+            .iter()
+            .position(|p| p.id == patch_id)
+            .expect("Patch not found");
+        let patch = &session.patches[index];
+        assert!(matches!(patch.status, PatchStatus::Proposed), "Patch is not open for voting");
+        assert!(
+            patch.voting_ends_at.is_some_and(|ends_at| env::block_timestamp() >= ends_at),
+            "Voting is still open"
+        );
 
-                // Update vote count
-                patch.votes += if approve { 1 } else { -1 };
+        let status = session.close_vote(index);
+        self.sessions.insert(&session_id, &session);
+        status
+    }
 
-                // Auto-merge if enough positive votes (simple majority)
-                let total_participants = session.participants.len() as i32;
-                if patch.votes > total_participants / 2 {
-                    patch.status = PatchStatus::Approved;
-                } else if patch.votes < -total_participants / 2 {
-                    patch.status = PatchStatus::Rejected;
-                }
+    /// Set how proposed patches are decided; applies to patches proposed afterwards
+    pub fn set_voting_policy(&mut self, session_id: String, policy: VotingPolicy) {
+        let (mut session, _) = self.authorize(&session_id, Capability::ConfigureVoting);
+        assert!(
+            policy.quorum_bps <= 10_000 && policy.threshold_bps < 10_000,
+            "Quorum and threshold are basis points below 10000"
+        );
+        assert!(
+            policy.voting_period > 0 && policy.voting_period <= MAX_VOTING_PERIOD,
+            "Voting period must be positive and at most 30 days"
+        );
 
-                self.sessions.insert(&session_id, &session);
-            }
-        }
+        session.voting_policy = policy.clone();
+        self.sessions.insert(&session_id, &session);
+        CollaborationEvent::VotingPolicyUpdated { session_id, policy }.emit();
     }
 
-    /// Merge an approved patch
-    pub fn merge_patch(&mut self, session_id: String, patch_id: String) {
-        let user = env::predecessor_account_id();
+    /// Votes cast on a patch
+    pub fn get_patch_votes(&self, session_id: String, patch_id: String) -> Vec<(AccountId, PatchVote)> {
+        self.patch_votes
+            .get(&(session_id, patch_id))
+            .map(|votes| votes.to_vec())
+            .unwrap_or_default()
+    }
 
-        if let Some(mut session) = self.sessions.get(&session_id) {
-            // Check merge permissions
-            assert!(session.permissions.can_merge.contains(&user), "No merge permission");
+    /// Merge an approved patch, applying its changes to the session state
+    /// Panics with the conflicting path if the state moved on since the patch was made
+    pub fn merge_patch(&mut self, session_id: String, patch_id: String) {
+        let (mut session, user) = self.authorize(&session_id, Capability::Merge);
 
-            if let Some(patch) = session.patches.iter_mut().find(|p| p.id == patch_id) {
-                assert!(matches!(patch.status, PatchStatus::Approved), "Patch not approved");
+        if let Some(patch) = session.patches.iter_mut().find(|p| p.id == patch_id) {
+            assert!(matches!(patch.status, PatchStatus::Approved), "Patch not approved");
 
-                // Apply changes to current state
-                for change in &patch.changes {
-                    // In practice, this would apply the changes to the session state
-                    // For now, just mark as merged
-                }
+            if let Err(conflict) = session.current_state.apply_changes(&patch.changes) {
+                env::panic_str(&conflict.to_string());
+            }
 
-                patch.status = PatchStatus::Merged;
-                session.current_state.version += 1;
+            patch.status = PatchStatus::Merged;
+            session.last_activity = env::block_timestamp();
 
-                self.sessions.insert(&session_id, &session);
+            self.sessions.insert(&session_id, &session);
+            CollaborationEvent::PatchMerged {
+                session_id,
+                patch_id,
+                merged_by: user,
+                version: session.current_state.version,
             }
+            .emit();
         }
     }
 
+    /// Undo a merged patch by merging its inverse as a new patch; returns the new patch ID
+    pub fn revert_patch(&mut self, session_id: String, patch_id: String) -> String {
+        let (mut session, user) = self.authorize(&session_id, Capability::Merge);
+
+        let index = session
+            .patches
+            .iter()
+            .position(|p| p.id == patch_id)
+            .expect("Patch not found");
+        assert!(
+            matches!(session.patches[index].status, PatchStatus::Merged),
+            "Only merged patches can be reverted"
+        );
+
+        // Undo the changes last to first
+        let inverse: Vec<StateChange> = session.patches[index].changes.iter().rev().map(StateChange::inverse).collect();
+        if let Err(conflict) = session.current_state.apply_changes(&inverse) {
+            env::panic_str(&conflict.to_string());
+        }
+        session.patches[index].status = PatchStatus::Reverted;
+
+        let revert_patch_id = format!("{}_revert", patch_id);
+        session.patches.push(Patch::new(
+            revert_patch_id.clone(),
+            user.clone(),
+            Some(patch_id.clone()),
+            inverse,
+            PatchStatus::Merged,
+        ));
+        session.last_activity = env::block_timestamp();
+        self.sessions.insert(&session_id, &session);
+
+        CollaborationEvent::PatchReverted {
+            session_id,
+            patch_id,
+            revert_patch_id: revert_patch_id.clone(),
+            reverted_by: user,
+            version: session.current_state.version,
+        }
+        .emit();
+        revert_patch_id
+    }
+
+    /// Record the hash of the off-chain CRDT state after `op_count` operations
+    pub fn checkpoint_state(&mut self, session_id: String, state_hash: Base64VecU8, op_count: u64) {
+        let (mut session, user) = self.authorize(&session_id, Capability::Edit);
+        assert_eq!(state_hash.0.len(), 32, "State hash must be 32 bytes");
+        if let Some(last) = session.checkpoints.last() {
+            assert!(op_count > last.op_count, "Checkpoint is not newer than the last one");
+        }
+
+        session.checkpoints.push(StateCheckpoint {
+            state_hash: state_hash.clone(),
+            op_count,
+            author: user.clone(),
+            version: session.current_state.version,
+            timestamp: env::block_timestamp(),
+        });
+        if session.checkpoints.len() > MAX_CHECKPOINTS {
+            session.checkpoints.remove(0);
+        }
+        session.last_activity = env::block_timestamp();
+        self.sessions.insert(&session_id, &session);
+
+        CollaborationEvent::StateCheckpointed { session_id, author: user, state_hash, op_count }.emit();
+    }
+
+    /// Latest checkpoint of a session
+    pub fn get_latest_checkpoint(&self, session_id: String) -> Option<StateCheckpoint> {
+        self.sessions.get(&session_id).and_then(|session| session.checkpoints.last().cloned())
+    }
+
     /// Publish a patch to the global patch repository
     pub fn publish_patch(&mut self, session_id: String, patch_id: String) {
-        let user = env::predecessor_account_id();
+        let (session, user) = self.authorize(&session_id, Capability::Edit);
 
-        if let Some(session) = self.sessions.get(&session_id) {
-            if let Some(patch) = session.patches.iter().find(|p| p.id == patch_id) {
-                assert_eq!(patch.author, user, "Only patch author can publish");
-                assert!(matches!(patch.status, PatchStatus::Merged | PatchStatus::Approved),
-                       "Patch must be approved or merged to publish");
+        if let Some(patch) = session.patches.iter().find(|p| p.id == patch_id) {
+            assert_eq!(patch.author, user, "Only patch author can publish");
+            assert!(matches!(patch.status, PatchStatus::Merged | PatchStatus::Approved),
+                   "Patch must be approved or merged to publish");
 
-                // Publish to global repository
-                self.published_patches.insert(&patch_id, patch);
-            }
+            // Publish to global repository
+            self.published_patches.insert(&patch_id, patch);
+            CollaborationEvent::PatchPublished { session_id, patch_id, author: user }.emit();
         }
     }
 
@@ -334,56 +1026,341 @@ impl CollaborationContract {
         self.published_patches.values().take(limit).collect()
     }
 
-    /// Invite user to session
-    pub fn invite_to_session(&mut self, session_id: String, invitee: AccountId, can_edit: bool) {
-        let inviter = env::predecessor_account_id();
+    /// Add an account to the session with a role below the inviter's
+    pub fn invite_to_session(&mut self, session_id: String, invitee: AccountId, role: Role) {
+        let (mut session, inviter) = self.authorize(&session_id, Capability::Invite);
+        assert_grantable(&session, &inviter, role);
+        assert!(!session.banned.contains(&invitee), "Account is banned from session");
 
-        if let Some(mut session) = self.sessions.get(&session_id) {
-            // Check invite permissions
-            assert!(session.permissions.can_invite.contains(&inviter), "No invite permission");
+        // Never demote an existing member through an invite
+        let role = session.role_of(&invitee).map_or(role, |current| current.max(role));
+        session.members.insert(invitee.clone(), role);
+        self.sessions.insert(&session_id, &session);
+        self.internal_add_user_session(&invitee, &session_id);
 
-            // Add to appropriate permission lists
-            if can_edit && !session.permissions.can_edit.contains(&invitee) {
-                session.permissions.can_edit.push(invitee.clone());
-            }
-            if !session.permissions.can_view.contains(&invitee) {
-                session.permissions.can_view.push(invitee.clone());
-            }
+        CollaborationEvent::MemberInvited { session_id, inviter, invitee, role }.emit();
+    }
 
-            // Add to participants if not already
-            if !session.participants.contains(&invitee) {
-                session.participants.push(invitee.clone());
-            }
+    /// Create an invite code; pass the base64 sha256 of the code and share the code itself
+    pub fn create_invite(
+        &mut self,
+        session_id: String,
+        code_hash: Base64VecU8,
+        role: Role,
+        valid_for_secs: u64,
+        max_uses: u32,
+    ) {
+        let (mut session, created_by) = self.authorize(&session_id, Capability::Invite);
+        assert_grantable(&session, &created_by, role);
+        assert_eq!(code_hash.0.len(), 32, "Code hash must be 32 bytes");
+        assert!(max_uses > 0, "Invite needs at least one use");
+        let duration = valid_for_secs.saturating_mul(1_000_000_000);
+        assert!(duration > 0 && duration <= MAX_INVITE_DURATION, "Invite must expire within 30 days");
+
+        let now = env::block_timestamp();
+        session.invites.retain(|invite| invite.expires_at > now && invite.uses_left > 0);
+        assert!(session.invites.iter().all(|invite| invite.code_hash != code_hash), "Invite code already exists");
+        assert!(session.invites.len() < MAX_INVITES, "Too many open invites");
+
+        let expires_at = now + duration;
+        session.invites.push(Invite {
+            code_hash,
+            role,
+            created_by: created_by.clone(),
+            expires_at,
+            uses_left: max_uses,
+        });
+        self.sessions.insert(&session_id, &session);
 
-            self.sessions.insert(&session_id, &session);
+        CollaborationEvent::InviteCreated { session_id, created_by, role, expires_at, max_uses }.emit();
+    }
+
+    /// Join a session with an invite code
+    pub fn redeem_invite(&mut self, session_id: String, code: String) -> Role {
+        let user = env::predecessor_account_id();
+        let mut session = self.sessions.get(&session_id).expect("Session not found");
+        assert!(session.is_active, "Session is archived");
+        assert!(!session.banned.contains(&user), "Banned from session");
+
+        let code_hash = env::sha256(code.as_bytes());
+        let invite = session
+            .invites
+            .iter_mut()
+            .find(|invite| invite.code_hash.0 == code_hash)
+            .unwrap_or_else(|| env::panic_str("Invalid invite code"));
+        assert!(invite.expires_at > env::block_timestamp(), "Invite has expired");
+        assert!(invite.uses_left > 0, "Invite has no uses left");
+        invite.uses_left -= 1;
+        let granted = invite.role;
+
+        let role = session.role_of(&user).map_or(granted, |current| current.max(granted));
+        session.members.insert(user.clone(), role);
+        session.last_activity = env::block_timestamp();
+        self.sessions.insert(&session_id, &session);
+        self.internal_add_user_session(&user, &session_id);
+
+        CollaborationEvent::InviteRedeemed { session_id, account_id: user, role }.emit();
+        role
+    }
+
+    /// Cancel an open invite code
+    pub fn revoke_invite(&mut self, session_id: String, code_hash: Base64VecU8) {
+        let (mut session, _) = self.authorize(&session_id, Capability::Invite);
+        let before = session.invites.len();
+        session.invites.retain(|invite| invite.code_hash != code_hash);
+        assert!(session.invites.len() < before, "Invite not found");
+        self.sessions.insert(&session_id, &session);
+    }
+
+    /// Change a member's role; both the old and new role must be below the caller's
+    pub fn set_member_role(&mut self, session_id: String, account_id: AccountId, role: Role) {
+        let (mut session, changed_by) = self.authorize(&session_id, Capability::ManageMembers);
+        assert_manageable(&session, &changed_by, &account_id);
+        assert_grantable(&session, &changed_by, role);
+
+        session.members.insert(account_id.clone(), role);
+        if !role.can(Capability::Vote) {
+            self.internal_retract_votes(&mut session, &account_id);
         }
+        self.sessions.insert(&session_id, &session);
+
+        CollaborationEvent::MemberRoleChanged { session_id, account_id, role, changed_by }.emit();
+    }
+
+    /// Remove a member ranked below the caller
+    pub fn kick_member(&mut self, session_id: String, account_id: AccountId) {
+        self.internal_remove_member(session_id, account_id, false);
+    }
+
+    /// Remove a member ranked below the caller and keep them from rejoining
+    pub fn ban_member(&mut self, session_id: String, account_id: AccountId) {
+        self.internal_remove_member(session_id, account_id, true);
+    }
+
+    pub fn unban_member(&mut self, session_id: String, account_id: AccountId) {
+        let (mut session, _) = self.authorize(&session_id, Capability::ManageMembers);
+        session.banned.retain(|banned| banned != &account_id);
+        self.sessions.insert(&session_id, &session);
     }
 
-    /// Leave session
+    /// Hand the session to another member; the previous owner becomes a maintainer
+    pub fn transfer_ownership(&mut self, session_id: String, new_owner: AccountId) {
+        let (mut session, previous_owner) = self.authorize(&session_id, Capability::TransferOwnership);
+        assert!(session.members.contains_key(&new_owner), "New owner must be a member");
+        assert_ne!(new_owner, previous_owner, "Already the owner");
+
+        session.members.insert(previous_owner.clone(), Role::Maintainer);
+        session.members.insert(new_owner.clone(), Role::Owner);
+        self.sessions.insert(&session_id, &session);
+
+        CollaborationEvent::OwnershipTransferred { session_id, previous_owner, new_owner }.emit();
+    }
+
+    /// Freeze the session: it stays readable, but every change is rejected
+    pub fn archive_session(&mut self, session_id: String) {
+        let (mut session, archived_by) = self.authorize(&session_id, Capability::Archive);
+        session.is_active = false;
+        session.invites.clear();
+        self.sessions.insert(&session_id, &session);
+
+        CollaborationEvent::SessionArchived { session_id, archived_by }.emit();
+    }
+
+    pub fn restore_session(&mut self, session_id: String) {
+        let user = env::predecessor_account_id();
+        let mut session = self.sessions.get(&session_id).expect("Session not found");
+        assert!(!session.is_active, "Session is not archived");
+        session.require(&user, Capability::Archive);
+
+        session.is_active = true;
+        session.last_activity = env::block_timestamp();
+        self.sessions.insert(&session_id, &session);
+
+        CollaborationEvent::SessionRestored { session_id, restored_by: user }.emit();
+    }
+
+    /// Leave session; the owner must transfer ownership first unless they are the last member
     pub fn leave_session(&mut self, session_id: String) {
         let user = env::predecessor_account_id();
 
         if let Some(mut session) = self.sessions.get(&session_id) {
-            // Remove from all permission lists
-            session.permissions.can_edit.retain(|u| u != &user);
-            session.permissions.can_view.retain(|u| u != &user);
-            session.permissions.can_invite.retain(|u| u != &user);
-            session.permissions.can_merge.retain(|u| u != &user);
-            session.participants.retain(|u| u != &user);
-
-            // If no participants left, mark as inactive
-            if session.participants.is_empty() {
+            if session.role_of(&user) == Some(Role::Owner) {
+                assert_eq!(session.members.len(), 1, "Transfer ownership before leaving");
+                // Nobody left to own it
                 session.is_active = false;
+                session.invites.clear();
             }
+            session.members.remove(&user);
+            self.internal_retract_votes(&mut session, &user);
 
             self.sessions.insert(&session_id, &session);
+            self.internal_remove_user_session(&user, &session_id);
+
+            CollaborationEvent::SessionLeft { session_id, account_id: user }.emit();
+        }
+    }
+}
+
+impl CollaborationContract {
+    /// Load an active session and check the caller's capability
+    ///
+    /// Every state-changing method goes through here, so archived sessions and
+    /// missing permissions are rejected in one place.
+    fn authorize(&self, session_id: &str, capability: Capability) -> (CollaborationSession, AccountId) {
+        let user = env::predecessor_account_id();
+        let session = self
+            .sessions
+            .get(&session_id.to_string())
+            .unwrap_or_else(|| env::panic_str("Session not found"));
+        assert!(session.is_active, "Session is archived");
+        session.require(&user, capability);
+        (session, user)
+    }
+
+    /// Replace `voter`'s vote with `approve` (`None` withdraws it)
+    ///
+    /// Everything is re-checked here because reputation votes land in a later block.
+    fn internal_record_vote(
+        &mut self,
+        session_id: String,
+        patch_id: String,
+        voter: AccountId,
+        approve: Option<bool>,
+        weight: u64,
+    ) {
+        let mut session = self.sessions.get(&session_id).expect("Session not found");
+        assert!(session.is_active, "Session is archived");
+        session.require(&voter, Capability::Vote);
+        let eligible = session.voter_count();
+        let index = session
+            .patches
+            .iter()
+            .position(|p| p.id == patch_id)
+            .expect("Patch not found");
+        let patch = &mut session.patches[index];
+        assert!(matches!(patch.status, PatchStatus::Proposed), "Patch is not open for voting");
+        assert!(
+            patch.voting_ends_at.is_none_or(|ends_at| env::block_timestamp() < ends_at),
+            "Voting has ended"
+        );
+
+        let key = (session_id.clone(), patch_id.clone());
+        let mut votes = self
+            .patch_votes
+            .get(&key)
+            .unwrap_or_else(|| UnorderedMap::new(patch_votes_prefix(&session_id, &patch_id)));
+        match votes.remove(&voter) {
+            Some(previous) => patch.remove_vote(&previous),
+            None => assert!(approve.is_some(), "No vote to withdraw"),
+        }
+        match approve {
+            Some(approve) => {
+                let vote = PatchVote { approve, weight };
+                patch.add_vote(&vote);
+                votes.insert(&voter, &vote);
+                CollaborationEvent::ProposalVoted {
+                    session_id: session_id.clone(),
+                    patch_id,
+                    voter,
+                    approve,
+                    weight,
+                    votes_for: patch.votes_for,
+                    votes_against: patch.votes_against,
+                }
+                .emit();
+            }
+            None => CollaborationEvent::VoteWithdrawn {
+                session_id: session_id.clone(),
+                patch_id,
+                voter,
+                votes_for: patch.votes_for,
+                votes_against: patch.votes_against,
+            }
+            .emit(),
+        }
+        self.patch_votes.insert(&key, &votes);
+
+        // Nothing can change once every member who can vote has voted
+        if patch.voters as usize >= eligible {
+            session.close_vote(index);
+        }
+        self.sessions.insert(&session_id, &session);
+    }
+
+    fn internal_remove_member(&mut self, session_id: String, account_id: AccountId, ban: bool) {
+        let (mut session, removed_by) = self.authorize(&session_id, Capability::ManageMembers);
+        // Banning a non-member keeps them from joining later
+        if session.members.contains_key(&account_id) {
+            assert_manageable(&session, &removed_by, &account_id);
+        } else {
+            assert!(ban, "Not a member");
+        }
+        session.members.remove(&account_id);
+        self.internal_retract_votes(&mut session, &account_id);
+        if ban && !session.banned.contains(&account_id) {
+            session.banned.push(account_id.clone());
+        }
+        self.sessions.insert(&session_id, &session);
+        self.internal_remove_user_session(&account_id, &session_id);
+
+        CollaborationEvent::MemberRemoved { session_id, account_id, removed_by, banned: ban }.emit();
+    }
+
+    /// Take back `account_id`'s votes on open proposals once they can no longer vote
+    fn internal_retract_votes(&mut self, session: &mut CollaborationSession, account_id: &AccountId) {
+        for patch in session.patches.iter_mut().filter(|p| matches!(p.status, PatchStatus::Proposed)) {
+            let key = (session.session_id.clone(), patch.id.clone());
+            let Some(mut votes) = self.patch_votes.get(&key) else {
+                continue;
+            };
+            if let Some(vote) = votes.remove(account_id) {
+                patch.remove_vote(&vote);
+                self.patch_votes.insert(&key, &votes);
+                CollaborationEvent::VoteWithdrawn {
+                    session_id: session.session_id.clone(),
+                    patch_id: patch.id.clone(),
+                    voter: account_id.clone(),
+                    votes_for: patch.votes_for,
+                    votes_against: patch.votes_against,
+                }
+                .emit();
+            }
+        }
+    }
 
-            // Update user's sessions
-            let mut user_sessions = self.user_sessions.get(&user).unwrap_or_default();
-            user_sessions.retain(|s| s != &session_id);
-            self.user_sessions.insert(&user, &user_sessions);
+    fn internal_add_user_session(&mut self, account_id: &AccountId, session_id: &String) {
+        let mut user_sessions = self.user_sessions.get(account_id).unwrap_or_default();
+        if !user_sessions.contains(session_id) {
+            user_sessions.push(session_id.clone());
+            self.user_sessions.insert(account_id, &user_sessions);
         }
     }
+
+    fn internal_remove_user_session(&mut self, account_id: &AccountId, session_id: &String) {
+        let mut user_sessions = self.user_sessions.get(account_id).unwrap_or_default();
+        user_sessions.retain(|s| s != session_id);
+        self.user_sessions.insert(account_id, &user_sessions);
+    }
+}
+
+/// Storage prefix for one patch's votes; patch IDs are only unique within a session
+fn patch_votes_prefix(session_id: &str, patch_id: &str) -> Vec<u8> {
+    let mut prefix = b"pv".to_vec();
+    prefix.extend(env::sha256([session_id.as_bytes(), &[0], patch_id.as_bytes()].concat()));
+    prefix
+}
+
+/// Only roles below the actor's can be handed out, and never ownership
+fn assert_grantable(session: &CollaborationSession, actor: &AccountId, role: Role) {
+    let actor_role = session.role_of(actor).unwrap_or(Role::Viewer);
+    assert!(role < actor_role && role != Role::Owner, "Cannot grant a role at or above your own");
+}
+
+/// Members can only manage members ranked below them
+fn assert_manageable(session: &CollaborationSession, actor: &AccountId, target: &AccountId) {
+    let actor_role = session.role_of(actor).unwrap_or(Role::Viewer);
+    let target_role = session.role_of(target).unwrap_or_else(|| env::panic_str("Not a member"));
+    assert!(target_role < actor_role, "Cannot manage a member at or above your role");
 }
 
 #[cfg(test)]
@@ -415,7 +1392,7 @@ mod tests {
         );
 
         assert_eq!(session.session_id, "test_session");
-        assert_eq!(session.creator, "alice.testnet".parse().unwrap());
+        assert_eq!(session.creator, "alice.testnet".parse::<AccountId>().unwrap());
         assert!(session.is_active);
     }
 
@@ -445,5 +1422,337 @@ mod tests {
         // Join session
         let joined = contract.join_session("test_session".to_string());
         assert!(joined);
+        assert_eq!(
+            near_sdk::test_utils::get_logs(),
+            vec![r#"EVENT_JSON:{"standard":"collaboration","version":"1.0.0","event":"session_joined","data":{"session_id":"test_session","account_id":"bob.testnet"}}"#]
+        );
+    }
+
+    fn change(change_type: ChangeType, path: &str, old_value: Value, new_value: Value) -> StateChange {
+        StateChange {
+            parameter_path: path.to_string(),
+            old_value: old_value.to_string(),
+            new_value: new_value.to_string(),
+            change_type,
+        }
+    }
+
+    /// Alice's session with one merged patch: zoom 1.0 -> 2.5, adds a color, switches tool and seeks
+    fn merged_session() -> (CollaborationContract, String) {
+        testing_env!(get_context().build());
+        let mut contract = CollaborationContract::default();
+        let params = near_sdk::serde_json::json!({"iterations": 50, "zoom": 1.0});
+        contract.create_session("test_session".to_string(), "fractal_shader".to_string(), params);
+
+        let patch_id = contract.submit_patch(
+            "test_session".to_string(),
+            vec![
+                change(ChangeType::ParameterUpdate, "/zoom", 1.0.into(), 2.5.into()),
+                change(ChangeType::ParameterUpdate, "/color", Value::Null, "blue".into()),
+                change(ChangeType::ToolSwitch, "", "fractal_shader".into(), "wgsl_shader".into()),
+                change(ChangeType::TimelineSeek, "", 0.into(), 12.5.into()),
+            ],
+        );
+        contract.propose_patch("test_session".to_string(), patch_id.clone());
+        let _ = contract.vote_on_patch("test_session".to_string(), patch_id.clone(), true);
+        contract.merge_patch("test_session".to_string(), patch_id.clone());
+        (contract, patch_id)
+    }
+
+    #[test]
+    fn test_merge_applies_changes() {
+        let (contract, _) = merged_session();
+        let state = contract.get_session("test_session".to_string()).unwrap().current_state;
+
+        assert_eq!(state.parameters_value(), near_sdk::serde_json::json!({"iterations": 50, "zoom": 2.5, "color": "blue"}));
+        assert_eq!(state.tool_type, "wgsl_shader");
+        assert_eq!(state.timeline_position, 12.5);
+        assert_eq!(state.version, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Patch conflict at /zoom: expected 1.0, found 2.5")]
+    fn test_stale_change_is_rejected() {
+        let (mut contract, _) = merged_session();
+        contract.submit_patch(
+            "test_session".to_string(),
+            vec![change(ChangeType::ParameterUpdate, "/zoom", 1.0.into(), 4.0.into())],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid change at /zoom: value must be JSON")]
+    fn test_change_values_must_be_json() {
+        let (mut contract, _) = merged_session();
+        let mut bad = change(ChangeType::ParameterUpdate, "/zoom", 2.5.into(), Value::Null);
+        bad.new_value = "{zoom".to_string();
+        contract.submit_patch("test_session".to_string(), vec![bad]);
+    }
+
+    #[test]
+    fn test_revert_restores_previous_state() {
+        let (mut contract, patch_id) = merged_session();
+        let revert_id = contract.revert_patch("test_session".to_string(), patch_id.clone());
+
+        let session = contract.get_session("test_session".to_string()).unwrap();
+        assert_eq!(revert_id, format!("{}_revert", patch_id));
+        assert_eq!(session.current_state.parameters_value(), near_sdk::serde_json::json!({"iterations": 50, "zoom": 1.0}));
+        assert_eq!(session.current_state.tool_type, "fractal_shader");
+        assert_eq!(session.current_state.timeline_position, 0.0);
+        assert_eq!(session.current_state.version, 3);
+        assert!(matches!(session.patches[0].status, PatchStatus::Reverted));
+        assert!(matches!(session.patches[1].status, PatchStatus::Merged));
+    }
+
+    #[test]
+    #[should_panic(expected = "Checkpoint is not newer than the last one")]
+    fn test_checkpoints_must_advance() {
+        testing_env!(get_context().build());
+        let mut contract = CollaborationContract::default();
+        contract.create_session("test_session".to_string(), "fractal_shader".to_string(), near_sdk::serde_json::json!({}));
+
+        contract.checkpoint_state("test_session".to_string(), Base64VecU8(vec![1; 32]), 10);
+        let checkpoint = contract.get_latest_checkpoint("test_session".to_string()).unwrap();
+        assert_eq!(checkpoint.state_hash.0, vec![1; 32]);
+        assert_eq!(checkpoint.op_count, 10);
+        assert_eq!(checkpoint.version, 1);
+
+        contract.checkpoint_state("test_session".to_string(), Base64VecU8(vec![2; 32]), 10);
+    }
+
+    /// The contract state in the shape the CRDT replicas hash
+    fn snapshot(state: &ToolState) -> nft_collab_crdt::Snapshot {
+        nft_collab_crdt::Snapshot {
+            tool_type: state.tool_type.clone(),
+            parameters: state.parameters_value(),
+        }
+    }
+
+    #[test]
+    fn test_crdt_edits_and_checkpoint_round_trip() {
+        testing_env!(get_context().build());
+        let mut contract = CollaborationContract::default();
+        let params = near_sdk::serde_json::json!({"iterations": 50, "zoom": 1.0});
+        contract.create_session("test_session".to_string(), "fractal_shader".to_string(), params);
+        let base = snapshot(&contract.get_session("test_session".to_string()).unwrap().current_state);
+
+        // Concurrent edits from two peers, merged on alice's replica
+        let mut alice = nft_collab_crdt::SessionReplica::new("alice.testnet", &base).unwrap();
+        let mut bob = nft_collab_crdt::SessionReplica::new("bob.testnet", &base).unwrap();
+        let (zoom, mut edits) = alice.set_parameter("zoom", near_sdk::serde_json::json!(2.5)).unwrap();
+        let (tool, _) = bob.set_tool("wgsl_shader").unwrap();
+        let (cue, _) = bob.insert_event(0, near_sdk::serde_json::json!({"cue": "drop"})).unwrap();
+        edits.extend(alice.apply(tool));
+        edits.extend(alice.apply(cue));
+        bob.apply(zoom);
+
+        // The replica's changes are valid `submit_patch` arguments
+        let changes: Vec<StateChange> =
+            serde_json::from_value(serde_json::to_value(&edits).unwrap()).unwrap();
+        let patch_id = contract.submit_patch("test_session".to_string(), changes);
+        contract.propose_patch("test_session".to_string(), patch_id.clone());
+        let _ = contract.vote_on_patch("test_session".to_string(), patch_id.clone(), true);
+        contract.merge_patch("test_session".to_string(), patch_id);
+        let state = contract.get_session("test_session".to_string()).unwrap().current_state;
+        assert_eq!(snapshot(&state), alice.snapshot());
+
+        // So are its checkpoints, and the recorded hash is the merged state's on both peers
+        let checkpoint = alice.checkpoint("test_session");
+        let args = serde_json::to_value(&checkpoint).unwrap();
+        contract.checkpoint_state(
+            checkpoint.session_id,
+            serde_json::from_value(args["state_hash"].clone()).unwrap(),
+            checkpoint.op_count,
+        );
+        let recorded = contract.get_latest_checkpoint("test_session".to_string()).unwrap();
+        assert_eq!(recorded.state_hash.0, snapshot(&state).hash());
+        assert_eq!(recorded.state_hash.0, bob.snapshot().hash());
+        assert_eq!((recorded.op_count, recorded.version), (3, 2));
     }
-}
\ No newline at end of file
+
+    fn act_as(account_id: &str, block_timestamp: u64) {
+        let mut builder = get_context();
+        builder
+            .predecessor_account_id(account_id.parse().unwrap())
+            .block_timestamp(block_timestamp);
+        testing_env!(builder.build());
+    }
+
+    fn alice_session() -> CollaborationContract {
+        act_as("alice.testnet", 0);
+        let mut contract = CollaborationContract::default();
+        contract.create_session("test_session".to_string(), "fractal_shader".to_string(), near_sdk::serde_json::json!({}));
+        contract
+    }
+
+    #[test]
+    #[should_panic(expected = "Invite has expired")]
+    fn test_invite_code_grants_role_until_expiry() {
+        let mut contract = alice_session();
+        let code_hash = Base64VecU8(env::sha256(b"jam-code"));
+        contract.create_invite("test_session".to_string(), code_hash, Role::Editor, 60, 2);
+
+        act_as("bob.testnet", 1_000_000_000);
+        assert_eq!(contract.redeem_invite("test_session".to_string(), "jam-code".to_string()), Role::Editor);
+        let session = contract.get_session("test_session".to_string()).unwrap();
+        assert_eq!(session.role_of(&"bob.testnet".parse().unwrap()), Some(Role::Editor));
+        assert_eq!(session.invites[0].uses_left, 1);
+
+        act_as("charlie.testnet", 61_000_000_000);
+        contract.redeem_invite("test_session".to_string(), "jam-code".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Banned from session")]
+    fn test_banned_member_cannot_rejoin() {
+        let mut contract = alice_session();
+        contract.invite_to_session("test_session".to_string(), "bob.testnet".parse().unwrap(), Role::Editor);
+        contract.ban_member("test_session".to_string(), "bob.testnet".parse().unwrap());
+        assert!(contract.get_user_sessions("bob.testnet".parse().unwrap()).is_empty());
+
+        act_as("bob.testnet", 0);
+        contract.join_session("test_session".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Session is archived")]
+    fn test_new_owner_can_archive_session() {
+        let mut contract = alice_session();
+        contract.invite_to_session("test_session".to_string(), "bob.testnet".parse().unwrap(), Role::Maintainer);
+        contract.transfer_ownership("test_session".to_string(), "bob.testnet".parse().unwrap());
+
+        let session = contract.get_session("test_session".to_string()).unwrap();
+        assert_eq!(session.owner(), &"bob.testnet".parse::<AccountId>().unwrap());
+        assert_eq!(session.role_of(&"alice.testnet".parse().unwrap()), Some(Role::Maintainer));
+
+        act_as("bob.testnet", 0);
+        contract.archive_session("test_session".to_string());
+
+        act_as("alice.testnet", 0);
+        contract.submit_patch("test_session".to_string(), Vec::new());
+    }
+
+    const DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+    /// `merged_session` plus bob and charlie as editors and a second patch from alice, proposed at `policy`
+    fn proposed_patch(policy: VotingPolicy) -> (CollaborationContract, String) {
+        let (mut contract, _) = merged_session();
+        for editor in ["bob.testnet", "charlie.testnet"] {
+            contract.invite_to_session("test_session".to_string(), editor.parse().unwrap(), Role::Editor);
+        }
+        contract.set_voting_policy("test_session".to_string(), policy);
+
+        act_as("alice.testnet", 1);
+        let patch_id = contract.submit_patch(
+            "test_session".to_string(),
+            vec![change(ChangeType::ParameterUpdate, "/zoom", 2.5.into(), 5.0.into())],
+        );
+        contract.propose_patch("test_session".to_string(), patch_id.clone());
+        (contract, patch_id)
+    }
+
+    #[test]
+    fn test_contributions_outweigh_headcount() {
+        let policy = VotingPolicy {
+            weighting: VoteWeighting::Contributions,
+            ..VotingPolicy::default()
+        };
+        let (mut contract, patch_id) = proposed_patch(policy);
+
+        // Alice authored the merged patch, so her vote counts twice
+        let _ = contract.vote_on_patch("test_session".to_string(), patch_id.clone(), true);
+        act_as("bob.testnet", 2);
+        let _ = contract.vote_on_patch("test_session".to_string(), patch_id.clone(), false);
+
+        let patch = contract.get_session("test_session".to_string()).unwrap().patches[1].clone();
+        assert_eq!((patch.votes_for, patch.votes_against, patch.voters), (2, 1, 2));
+        assert!(matches!(patch.status, PatchStatus::Proposed));
+
+        act_as("charlie.testnet", 3 * DAY + 1);
+        let status = contract.finalize_vote("test_session".to_string(), patch_id);
+        assert!(matches!(status, PatchStatus::Approved));
+    }
+
+    #[test]
+    #[should_panic(expected = "Voting has ended")]
+    fn test_votes_can_change_until_the_deadline() {
+        let (mut contract, patch_id) = proposed_patch(VotingPolicy::default());
+
+        act_as("bob.testnet", 2);
+        let _ = contract.vote_on_patch("test_session".to_string(), patch_id.clone(), true);
+        let _ = contract.vote_on_patch("test_session".to_string(), patch_id.clone(), false);
+        let votes = contract.get_patch_votes("test_session".to_string(), patch_id.clone());
+        assert_eq!(votes.len(), 1);
+        assert!(!votes[0].1.approve);
+
+        contract.withdraw_vote("test_session".to_string(), patch_id.clone());
+        let patch = contract.get_session("test_session".to_string()).unwrap().patches[1].clone();
+        assert_eq!((patch.votes_for, patch.votes_against, patch.voters), (0, 0, 0));
+        // Votes on the first patch live under their own prefix
+        assert_eq!(contract.get_patch_votes("test_session".to_string(), "test_session_0".to_string()).len(), 1);
+
+        act_as("bob.testnet", 3 * DAY + 1);
+        let _ = contract.vote_on_patch("test_session".to_string(), patch_id, true);
+    }
+
+    #[test]
+    fn test_removed_member_votes_are_retracted() {
+        let (mut contract, patch_id) = proposed_patch(VotingPolicy::default());
+        act_as("bob.testnet", 2);
+        let _ = contract.vote_on_patch("test_session".to_string(), patch_id.clone(), true);
+
+        act_as("alice.testnet", 3);
+        contract.kick_member("test_session".to_string(), "bob.testnet".parse().unwrap());
+        let patch = contract.get_session("test_session".to_string()).unwrap().patches[1].clone();
+        assert_eq!((patch.votes_for, patch.votes_against, patch.voters), (0, 0, 0));
+        assert!(contract.get_patch_votes("test_session".to_string(), patch_id.clone()).is_empty());
+
+        // Bob's approval no longer counts, so the remaining split vote fails
+        let _ = contract.vote_on_patch("test_session".to_string(), patch_id.clone(), true);
+        act_as("charlie.testnet", 4);
+        let _ = contract.vote_on_patch("test_session".to_string(), patch_id, false);
+        let patch = contract.get_session("test_session".to_string()).unwrap().patches[1].clone();
+        assert_eq!((patch.votes_for, patch.votes_against, patch.voters), (1, 1, 2));
+        assert!(matches!(patch.status, PatchStatus::Rejected));
+    }
+
+    /// Run the reputation callback with the soulbound contract's `result`
+    fn reputation_result(block_timestamp: u64, result: near_sdk::PromiseResult) {
+        let mut builder = get_context();
+        builder
+            .predecessor_account_id("contract.testnet".parse().unwrap())
+            .block_timestamp(block_timestamp);
+        testing_env!(
+            builder.build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![result]
+        );
+    }
+
+    #[test]
+    fn test_reputation_weight_comes_from_the_soulbound_contract() {
+        let policy = VotingPolicy {
+            weighting: VoteWeighting::Reputation { soulbound_contract_id: "sbt.testnet".parse().unwrap() },
+            ..VotingPolicy::default()
+        };
+        let (mut contract, patch_id) = proposed_patch(policy);
+
+        act_as("bob.testnet", 2);
+        let vote = contract.vote_on_patch("test_session".to_string(), patch_id.clone(), true);
+        assert!(matches!(vote, PromiseOrValue::Promise(_)));
+        reputation_result(3, near_sdk::PromiseResult::Successful(b"3.6".to_vec()));
+        contract.on_reputation_weight("test_session".to_string(), patch_id.clone(), "bob.testnet".parse().unwrap(), true);
+
+        // A failed lookup counts as no reputation
+        reputation_result(4, near_sdk::PromiseResult::Failed);
+        contract.on_reputation_weight("test_session".to_string(), patch_id.clone(), "charlie.testnet".parse().unwrap(), false);
+
+        let votes = contract.get_patch_votes("test_session".to_string(), patch_id);
+        let weights: Vec<_> = votes.iter().map(|(voter, vote)| (voter.as_str(), vote.weight)).collect();
+        assert_eq!(weights, vec![("bob.testnet", 5), ("charlie.testnet", 1)]);
+        let patch = contract.get_session("test_session".to_string()).unwrap().patches[1].clone();
+        assert_eq!((patch.votes_for, patch.votes_against, patch.voters), (5, 1, 2));
+    }
+}
@@ -8,9 +8,39 @@ use near_sdk::collections::{LazyOption, LookupMap, UnorderedMap, UnorderedSet};
 use near_sdk::json_types::{Base64VecU8, U128};
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::{
-    env, near_bindgen, AccountId, Balance, CryptoHash, PanicOnDefault, Promise, PromiseOrValue,
+    assert_one_yocto, env, ext_contract, near, AccountId, Gas, PanicOnDefault, Promise,
+    PromiseOrValue, PublicKey,
 };
 use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::oracle::{OracleInfo, OracleRegistry, SignedReading};
+
+/// Edge length of the rendered artwork's square viewBox
+const CANVAS_SIZE: u32 = 512;
+
+/// Emotion dimensions are quantized to this many steps before rendering
+const RENDER_STEPS: u32 = 1000;
+
+/// Gas for the receiver's `nft_on_transfer` hook
+const GAS_FOR_NFT_ON_TRANSFER: Gas = Gas::from_tgas(25);
+
+/// Gas for `nft_resolve_transfer` after the receiver hook returns
+const GAS_FOR_RESOLVE_TRANSFER: Gas = Gas::from_tgas(15);
+
+/// Gas for the approved account's `nft_on_approve` hook
+const GAS_FOR_NFT_ON_APPROVE: Gas = Gas::from_tgas(25);
+
+/// Royalties are in basis points; together they may take at most half of a sale
+const ROYALTY_BASIS: u32 = 10_000;
+const MAX_TOTAL_ROYALTY: u32 = 5_000;
+
+/// Maximum number of royalty receivers per token, so payouts fit `max_len_payout`
+const MAX_ROYALTY_RECEIVERS: usize = 10;
+
+/// Default and maximum page sizes for the NEP-181 enumeration views
+const DEFAULT_PAGE_SIZE: u64 = 50;
+const MAX_PAGE_SIZE: u64 = 100;
 
 /// NEP-177 Token Metadata
 /// https://nomicon.io/Standards/Tokens/NonFungibleToken/Metadata
@@ -61,6 +91,43 @@ pub struct Token {
     pub next_approval_id: u64,
     pub metadata: TokenMetadata,
     pub dynamic_metadata: DynamicMetadata,
+    pub royalty: HashMap<AccountId, u32>, // Perpetual royalties in basis points
+}
+
+/// Token as returned by the NEP-171 and NEP-181 views
+/// `metadata.media` is the on-chain render; use `get_dynamic_metadata` for the emotion history
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JsonToken {
+    pub token_id: String,
+    pub owner_id: AccountId,
+    pub metadata: TokenMetadata,
+    pub approved_account_ids: HashMap<AccountId, u64>,
+}
+
+/// NEP-199 payout: how the proceeds of a sale are split
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Payout {
+    pub payout: HashMap<AccountId, U128>,
+}
+
+/// NEP-171 receiver of `nft_transfer_call`; returns `true` to send the token back
+#[ext_contract(ext_nft_receiver)]
+pub trait NonFungibleTokenReceiver {
+    fn nft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        previous_owner_id: AccountId,
+        token_id: String,
+        msg: String,
+    ) -> PromiseOrValue<bool>;
+}
+
+/// NEP-178 approval receiver, e.g. a marketplace creating a listing
+#[ext_contract(ext_nft_approval_receiver)]
+pub trait NonFungibleTokenApprovalReceiver {
+    fn nft_on_approve(&mut self, token_id: String, owner_id: AccountId, approval_id: u64, msg: String);
 }
 
 /// Contract metadata following NEP-177
@@ -76,32 +143,235 @@ pub struct NFTContractMetadata {
     pub reference_hash: Option<Base64VecU8>, // Base64-encoded sha256 hash of JSON
 }
 
-#[near_bindgen]
-#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
+/// NEP-171 event payloads
+/// https://nomicon.io/Standards/Tokens/NonFungibleToken/Event
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftMintLog {
+    pub owner_id: AccountId,
+    pub token_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftTransferLog {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authorized_id: Option<AccountId>,
+    pub old_owner_id: AccountId,
+    pub new_owner_id: AccountId,
+    pub token_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftBurnLog {
+    pub owner_id: AccountId,
+    pub token_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authorized_id: Option<AccountId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+/// NEP-171 events; `data` is always an array of logs
+#[near(event_json(standard = "nep171"))]
+pub enum Nep171Event {
+    #[event_version("1.0.0")]
+    NftMint(Vec<NftMintLog>),
+    #[event_version("1.0.0")]
+    NftTransfer(Vec<NftTransferLog>),
+    #[event_version("1.0.0")]
+    NftBurn(Vec<NftBurnLog>),
+}
+
+/// Events for the dynamic metadata this contract layers on top of NEP-171
+#[near(event_json(standard = "dynamic_nft"))]
+pub enum DynamicNftEvent {
+    #[event_version("1.0.0")]
+    EmotionalStateUpdated {
+        token_id: String,
+        valence: f32,
+        arousal: f32,
+        dominance: f32,
+        interaction_count: u64,
+        ipfs_cid: Option<String>,
+    },
+}
+
+/// Parameterized shader descriptor derived from a token's dynamic metadata
+///
+/// Emotion values are quantized to integers first so that every node, wallet and
+/// off-chain shader renders the exact same artwork for the same on-chain state.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RenderParams {
+    pub shader: String,     // Descriptor name and version, e.g. "emotion_bloom/1"
+    pub hue: u16,           // 0-359, cool (sad) to warm (happy) from valence
+    pub saturation: u8,     // Percent, from confidence
+    pub lightness: u8,      // Percent, from valence
+    pub petals: u8,         // 3-12, from arousal
+    pub period_ms: u32,     // Rotation period, faster when aroused
+    pub core_radius: u16,   // From dominance
+    pub petal_length: u16,  // From valence
+    pub rings: u8,          // Grows with interaction count
+    pub seed: u32,          // From the token ID, so equal emotions still differ per token
+}
+
+impl RenderParams {
+    pub fn new(token_id: &str, dynamic: &DynamicMetadata) -> Self {
+        let emotion = &dynamic.emotional_state;
+        let valence = quantize(emotion.valence, -1.0, 1.0);
+        let arousal = quantize(emotion.arousal, 0.0, 1.0);
+        let dominance = quantize(emotion.dominance, 0.0, 1.0);
+        let confidence = quantize(emotion.confidence, 0.0, 1.0);
+
+        let digest = env::sha256(token_id.as_bytes());
+        let seed = u32::from_le_bytes([digest[0], digest[1], digest[2], digest[3]]);
+        // +-15 degrees of per-token hue jitter
+        let hue_shift = (seed >> 9) % 31;
+
+        Self {
+            shader: "emotion_bloom/1".to_string(),
+            hue: ((240 + 360 + hue_shift - 15 - valence * 200 / RENDER_STEPS) % 360) as u16,
+            saturation: (30 + confidence * 60 / RENDER_STEPS) as u8,
+            lightness: (35 + valence * 30 / RENDER_STEPS) as u8,
+            petals: (3 + arousal * 9 / RENDER_STEPS) as u8,
+            period_ms: 12_000 - arousal * 10_000 / RENDER_STEPS,
+            core_radius: (24 + dominance * 56 / RENDER_STEPS) as u16,
+            petal_length: (90 + valence * 60 / RENDER_STEPS) as u16,
+            rings: (u64::BITS - (dynamic.interaction_count + 1).leading_zeros()).min(8) as u8,
+            seed,
+        }
+    }
+
+    /// Render the descriptor as a self-contained animated SVG
+    pub fn to_svg(&self) -> String {
+        let center = CANVAS_SIZE / 2;
+        let (hue, sat, light) = (self.hue, self.saturation, self.lightness);
+        let mut svg = String::new();
+
+        // `write!` into a String cannot fail
+        let _ = write!(
+            svg,
+            "<svg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 {size} {size}' width='{size}' height='{size}'>\
+             <defs><radialGradient id='g'>\
+             <stop offset='0' stop-color='hsl({hue},{sat}%,{inner}%)'/>\
+             <stop offset='1' stop-color='hsl({outer_hue},{sat}%,{light}%)'/>\
+             </radialGradient></defs>\
+             <rect width='{size}' height='{size}' fill='hsl({hue},{bg_sat}%,10%)'/>",
+            size = CANVAS_SIZE,
+            inner = (light + 20).min(90),
+            outer_hue = (hue + 40) % 360,
+            bg_sat = sat / 3,
+        );
+
+        for ring in 0..self.rings as u32 {
+            let _ = write!(
+                svg,
+                "<circle cx='{center}' cy='{center}' r='{r}' fill='none' stroke='hsl({hue},{sat}%,{light}%)' \
+                 stroke-opacity='0.{opacity}' stroke-width='2'/>",
+                r = self.core_radius as u32 / 2 + self.petal_length as u32 + 8 * (ring + 1),
+                opacity = 8 - ring.min(7),
+            );
+        }
+
+        let offset = self.seed % 360;
+        let _ = write!(svg, "<g transform='rotate({offset} {center} {center})'><g>");
+        let petal_cy = center - self.core_radius as u32 / 2 - self.petal_length as u32 / 2;
+        let petal_width = (self.petal_length as u32 * 3 / (self.petals as u32 + 3)).max(8);
+        for petal in 0..self.petals as u32 {
+            let _ = write!(
+                svg,
+                "<ellipse cx='{center}' cy='{petal_cy}' rx='{rx}' ry='{ry}' fill='url(#g)' fill-opacity='0.8' \
+                 transform='rotate({angle} {center} {center})'/>",
+                rx = petal_width / 2,
+                ry = self.petal_length / 2,
+                angle = 360 * petal / self.petals as u32,
+            );
+        }
+        let _ = write!(
+            svg,
+            "<animateTransform attributeName='transform' type='rotate' from='0 {center} {center}' \
+             to='360 {center} {center}' dur='{period}ms' repeatCount='indefinite'/></g></g>\
+             <circle cx='{center}' cy='{center}' r='{core}' fill='hsl({hue},{sat}%,{light}%)'/></svg>",
+            period = self.period_ms,
+            core = self.core_radius / 2,
+        );
+        svg
+    }
+}
+
+/// Map `value` in `[min, max]` onto `0..=RENDER_STEPS`; NaN renders as `min`
+fn quantize(value: f32, min: f32, max: f32) -> u32 {
+    let unit = ((value - min) / (max - min)).clamp(0.0, 1.0);
+    (unit * RENDER_STEPS as f32) as u32
+}
+
+/// Percent-encode an SVG into a `data:` URI wallets can show inline
+pub fn svg_data_uri(svg: &str) -> String {
+    let mut uri = String::from("data:image/svg+xml,");
+    for byte in svg.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'\'' | b'(' | b')' | b'*'
+            | b'!' | b'=' | b':' | b'/' | b';' | b',' | b'+' | b'?' | b'@' | b'$' | b'&' => uri.push(byte as char),
+            _ => {
+                let _ = write!(uri, "%{:02X}", byte);
+            }
+        }
+    }
+    uri
+}
+
+#[near(contract_state)]
+#[derive(PanicOnDefault)]
 pub struct DynamicNFT {
     pub owner_id: AccountId,
     pub tokens_per_owner: LookupMap<AccountId, UnorderedSet<String>>,
     pub tokens_by_id: UnorderedMap<String, Token>,
     pub token_metadata_by_id: UnorderedMap<String, TokenMetadata>,
     pub metadata: LazyOption<NFTContractMetadata>,
+    pub oracles: OracleRegistry, // Sensor keys trusted to sign emotional state updates
 }
 
-#[near_bindgen]
+#[near]
 impl DynamicNFT {
     /// Initialize contract
     #[init]
     pub fn new(owner_id: AccountId, metadata: NFTContractMetadata) -> Self {
         Self {
             owner_id,
-            tokens_per_owner: LookupMap::new(b"t"),
-            tokens_by_id: UnorderedMap::new(b"i"),
-            token_metadata_by_id: UnorderedMap::new(b"m"),
-            metadata: LazyOption::new(b"d", Some(&metadata)),
+            tokens_per_owner: LookupMap::new(b"t".to_vec()),
+            tokens_by_id: UnorderedMap::new(b"i".to_vec()),
+            token_metadata_by_id: UnorderedMap::new(b"m".to_vec()),
+            metadata: LazyOption::new(b"d".to_vec(), Some(&metadata)),
+            oracles: OracleRegistry::new(b"r".to_vec()),
         }
     }
 
+    /// Trust a sensor/oracle key to sign emotional state updates (contract owner only)
+    pub fn add_oracle(&mut self, public_key: PublicKey, label: String) {
+        self.assert_contract_owner();
+        self.oracles.add(public_key, label);
+    }
+
+    /// Stop trusting an oracle key (contract owner only)
+    pub fn remove_oracle(&mut self, public_key: PublicKey) -> bool {
+        self.assert_contract_owner();
+        self.oracles.remove(&public_key)
+    }
+
+    /// Registered oracle keys with their last used nonce
+    pub fn get_oracles(&self) -> Vec<(PublicKey, OracleInfo)> {
+        self.oracles.list()
+    }
+
     /// Mint new NFT with initial emotional state
     /// IPFS CID should be passed in metadata.reference
+    /// `perpetual_royalties` maps receivers to basis points of every NEP-199 sale
     #[payable]
     pub fn nft_mint(
         &mut self,
@@ -109,7 +379,13 @@ impl DynamicNFT {
         receiver_id: AccountId,
         token_metadata: TokenMetadata,
         initial_emotion: EmotionalState,
-    ) -> Token {
+        perpetual_royalties: Option<HashMap<AccountId, u32>>,
+    ) -> JsonToken {
+        let royalty = perpetual_royalties.unwrap_or_default();
+        assert!(royalty.len() <= MAX_ROYALTY_RECEIVERS, "Too many royalty receivers");
+        let total_royalty = royalty.values().try_fold(0u32, |total, bps| total.checked_add(*bps));
+        assert!(total_royalty.is_some_and(|total| total <= MAX_TOTAL_ROYALTY), "Royalties cannot exceed 50%");
+
         // Validate deposit for storage
         let initial_storage = env::storage_usage();
 
@@ -131,6 +407,7 @@ impl DynamicNFT {
             next_approval_id: 0,
             metadata: token_metadata.clone(),
             dynamic_metadata,
+            royalty,
         };
 
         // Insert token
@@ -140,37 +417,203 @@ impl DynamicNFT {
         );
 
         // Update owner's token set
-        let mut owner_tokens = self
-            .tokens_per_owner
-            .get(&receiver_id)
-            .unwrap_or_else(|| UnorderedSet::new(receiver_id.as_bytes()));
-        owner_tokens.insert(&token_id);
-        self.tokens_per_owner.insert(&receiver_id, &owner_tokens);
+        self.internal_add_token_to_owner(&receiver_id, &token_id);
+
+        Nep171Event::NftMint(vec![NftMintLog {
+            owner_id: receiver_id,
+            token_ids: vec![token_id.clone()],
+            memo: None,
+        }])
+        .emit();
 
         // Refund excess storage deposit
-        let storage_used = env::storage_usage() - initial_storage;
-        let required_deposit = storage_used as u128 * env::storage_byte_cost();
-        let attached = env::attached_deposit();
-        
+        Self::refund_deposit(env::storage_usage() - initial_storage);
+
+        Self::json_token(token_id, token)
+    }
+
+    /// NEP-171: Transfer a token (owner or approved account, exactly 1 yoctoNEAR)
+    /// Emotional state, interaction count and IPFS history travel with the token
+    #[payable]
+    pub fn nft_transfer(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: String,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+    ) {
+        assert_one_yocto();
+        let (previous_owner_id, approved_account_ids) =
+            self.internal_transfer(&env::predecessor_account_id(), &receiver_id, &token_id, approval_id, memo);
+        Self::refund_approved_account_ids(previous_owner_id, approved_account_ids.keys());
+    }
+
+    /// NEP-171: Transfer a token and notify the receiver, which may return it
+    #[payable]
+    pub fn nft_transfer_call(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: String,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+        msg: String,
+    ) -> PromiseOrValue<bool> {
+        assert_one_yocto();
         assert!(
-            attached >= required_deposit,
-            "Not enough deposit for storage"
+            env::prepaid_gas() > GAS_FOR_NFT_ON_TRANSFER.saturating_add(GAS_FOR_RESOLVE_TRANSFER),
+            "More gas is required"
         );
+        let sender_id = env::predecessor_account_id();
+        let (previous_owner_id, approved_account_ids) =
+            self.internal_transfer(&sender_id, &receiver_id, &token_id, approval_id, memo);
 
-        if attached > required_deposit {
-            Promise::new(env::predecessor_account_id())
-                .transfer(attached - required_deposit);
+        ext_nft_receiver::ext(receiver_id.clone())
+            .with_static_gas(GAS_FOR_NFT_ON_TRANSFER)
+            .nft_on_transfer(sender_id, previous_owner_id.clone(), token_id.clone(), msg)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_TRANSFER)
+                    .nft_resolve_transfer(previous_owner_id, receiver_id, token_id, Some(approved_account_ids)),
+            )
+            .into()
+    }
+
+    /// NEP-171: Return the token to its previous owner if the receiver asked for it
+    /// Returns `true` if the transfer stands
+    #[private]
+    pub fn nft_resolve_transfer(
+        &mut self,
+        previous_owner_id: AccountId,
+        receiver_id: AccountId,
+        token_id: String,
+        approved_account_ids: Option<HashMap<AccountId, u64>>,
+    ) -> bool {
+        let must_revert = env::promise_result_checked(0, 16)
+            .ok()
+            .and_then(|value| near_sdk::serde_json::from_slice::<bool>(&value).ok())
+            .unwrap_or(true);
+        let approved_account_ids = approved_account_ids.unwrap_or_default();
+
+        // Keep the transfer if the receiver accepted it, or already moved the token on
+        let mut token = match self.tokens_by_id.get(&token_id) {
+            Some(token) if must_revert && token.owner_id == receiver_id => token,
+            _ => {
+                Self::refund_approved_account_ids(previous_owner_id, approved_account_ids.keys());
+                return true;
+            }
+        };
+
+        self.internal_remove_token_from_owner(&receiver_id, &token_id);
+        self.internal_add_token_to_owner(&previous_owner_id, &token_id);
+        Self::refund_approved_account_ids(receiver_id.clone(), token.approved_account_ids.keys());
+        token.owner_id = previous_owner_id.clone();
+        token.approved_account_ids = approved_account_ids;
+        self.tokens_by_id.insert(&token_id, &token);
+
+        Nep171Event::NftTransfer(vec![NftTransferLog {
+            authorized_id: None,
+            old_owner_id: receiver_id,
+            new_owner_id: previous_owner_id,
+            token_ids: vec![token_id],
+            memo: None,
+        }])
+        .emit();
+        false
+    }
+
+    /// NEP-199: Split `balance` between the token's royalty receivers and its owner
+    pub fn nft_payout(&self, token_id: String, balance: U128, max_len_payout: Option<u32>) -> Payout {
+        let token = self.tokens_by_id.get(&token_id).expect("Token not found");
+        Self::payout(&token, balance.0, max_len_payout)
+    }
+
+    /// NEP-199: Transfer the token and return the payout owed to the previous owner's side
+    /// Marketplaces call this instead of `nft_transfer` so the transfer and the split are atomic
+    #[payable]
+    pub fn nft_transfer_payout(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: String,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+        balance: U128,
+        max_len_payout: Option<u32>,
+    ) -> Payout {
+        assert_one_yocto();
+        let token = self.tokens_by_id.get(&token_id).expect("Token not found");
+        let payout = Self::payout(&token, balance.0, max_len_payout);
+        let (previous_owner_id, approved_account_ids) =
+            self.internal_transfer(&env::predecessor_account_id(), &receiver_id, &token_id, approval_id, memo);
+        Self::refund_approved_account_ids(previous_owner_id, approved_account_ids.keys());
+        payout
+    }
+
+    /// NEP-178: Let `account_id` transfer the token, optionally notifying it with `msg`
+    /// The deposit must cover the approval's storage; the excess is refunded
+    #[payable]
+    pub fn nft_approve(&mut self, token_id: String, account_id: AccountId, msg: Option<String>) -> Option<Promise> {
+        assert!(!env::attached_deposit().is_zero(), "Requires attached deposit of at least 1 yoctoNEAR");
+        let mut token = self.tokens_by_id.get(&token_id).expect("Token not found");
+        assert_eq!(env::predecessor_account_id(), token.owner_id, "Only the token owner can approve");
+
+        let approval_id = token.next_approval_id;
+        let is_new_approval = token.approved_account_ids.insert(account_id.clone(), approval_id).is_none();
+        token.next_approval_id += 1;
+        self.tokens_by_id.insert(&token_id, &token);
+
+        let storage_used = if is_new_approval { bytes_for_approved_account_id(&account_id) } else { 0 };
+        Self::refund_deposit(storage_used);
+
+        msg.map(|msg| {
+            ext_nft_approval_receiver::ext(account_id)
+                .with_static_gas(GAS_FOR_NFT_ON_APPROVE)
+                .nft_on_approve(token_id, token.owner_id, approval_id, msg)
+        })
+    }
+
+    /// NEP-178: Revoke one approval and refund its storage to the owner
+    #[payable]
+    pub fn nft_revoke(&mut self, token_id: String, account_id: AccountId) {
+        assert_one_yocto();
+        let mut token = self.tokens_by_id.get(&token_id).expect("Token not found");
+        assert_eq!(env::predecessor_account_id(), token.owner_id, "Only the token owner can revoke");
+
+        if token.approved_account_ids.remove(&account_id).is_some() {
+            self.tokens_by_id.insert(&token_id, &token);
+            Self::refund_approved_account_ids(token.owner_id, [account_id].iter());
         }
+    }
 
-        token
+    /// NEP-178: Revoke every approval and refund their storage to the owner
+    #[payable]
+    pub fn nft_revoke_all(&mut self, token_id: String) {
+        assert_one_yocto();
+        let mut token = self.tokens_by_id.get(&token_id).expect("Token not found");
+        assert_eq!(env::predecessor_account_id(), token.owner_id, "Only the token owner can revoke");
+
+        if !token.approved_account_ids.is_empty() {
+            let approved_account_ids = std::mem::take(&mut token.approved_account_ids);
+            self.tokens_by_id.insert(&token_id, &token);
+            Self::refund_approved_account_ids(token.owner_id, approved_account_ids.keys());
+        }
+    }
+
+    /// NEP-178: Whether `approved_account_id` may transfer the token (with `approval_id`, if given)
+    pub fn nft_is_approved(&self, token_id: String, approved_account_id: AccountId, approval_id: Option<u64>) -> bool {
+        let token = self.tokens_by_id.get(&token_id).expect("Token not found");
+        match token.approved_account_ids.get(&approved_account_id) {
+            Some(actual) => approval_id.is_none_or(|expected| *actual == expected),
+            None => false,
+        }
     }
 
     /// Update emotional state and generate new IPFS metadata
     /// This is the "dynamic" part - NFT metadata changes based on interaction
+    /// The new state must be an `EmotionalState` signed for this token by a registered oracle
     pub fn update_emotional_state(
         &mut self,
         token_id: String,
-        new_emotion: EmotionalState,
+        reading: SignedReading,
         new_ipfs_cid: Option<String>,
     ) {
         let mut token = self.tokens_by_id.get(&token_id).expect("Token not found");
@@ -182,23 +625,37 @@ impl DynamicNFT {
             "Only owner can update emotional state"
         );
 
+        let new_emotion: EmotionalState = self.oracles.verify(&reading, &token_id);
+
         // Update emotional state
         token.dynamic_metadata.emotional_state = new_emotion;
         token.dynamic_metadata.interaction_count += 1;
         token.dynamic_metadata.last_interaction = env::block_timestamp();
 
         // Update IPFS reference if provided
-        if let Some(cid) = new_ipfs_cid {
+        if let Some(cid) = new_ipfs_cid.clone() {
             token.metadata.reference = Some(cid.clone());
             token.metadata.updated_at = Some(env::block_timestamp() / 1_000_000);
             token.dynamic_metadata.ipfs_history.push(cid);
         }
 
         self.tokens_by_id.insert(&token_id, &token);
+
+        let emotion = &token.dynamic_metadata.emotional_state;
+        DynamicNftEvent::EmotionalStateUpdated {
+            token_id,
+            valence: emotion.valence,
+            arousal: emotion.arousal,
+            dominance: emotion.dominance,
+            interaction_count: token.dynamic_metadata.interaction_count,
+            ipfs_cid: new_ipfs_cid,
+        }
+        .emit();
     }
 
     /// Calculate visual parameters from emotional state
     /// Used by frontend to render dynamic visuals
+    /// Prefer `get_render_params`, which the on-chain artwork is drawn from
     pub fn get_visual_params(&self, token_id: String) -> HashMap<String, f32> {
         let token = self.tokens_by_id.get(&token_id).expect("Token not found");
         let emotion = &token.dynamic_metadata.emotional_state;
@@ -220,6 +677,17 @@ impl DynamicNFT {
         params
     }
 
+    /// Get the shader descriptor the token's artwork is rendered from
+    pub fn get_render_params(&self, token_id: String) -> RenderParams {
+        let token = self.tokens_by_id.get(&token_id).expect("Token not found");
+        RenderParams::new(&token_id, &token.dynamic_metadata)
+    }
+
+    /// Render the token's current artwork as SVG
+    pub fn render_svg(&self, token_id: String) -> String {
+        self.get_render_params(token_id).to_svg()
+    }
+
     /// Get full dynamic metadata
     pub fn get_dynamic_metadata(&self, token_id: String) -> DynamicMetadata {
         let token = self.tokens_by_id.get(&token_id).expect("Token not found");
@@ -232,8 +700,27 @@ impl DynamicNFT {
     }
 
     /// NEP-171: Get token info
-    pub fn nft_token(&self, token_id: String) -> Option<TokenMetadata> {
-        self.tokens_by_id.get(&token_id).map(|t| t.metadata)
+    /// `metadata.media` is the artwork rendered from the current emotional state, as a data URI
+    pub fn nft_token(&self, token_id: String) -> Option<JsonToken> {
+        self.tokens_by_id.get(&token_id).map(|token| Self::json_token(token_id, token))
+    }
+
+    /// NEP-181: Page through all tokens
+    pub fn nft_tokens(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<JsonToken> {
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+        let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+
+        self.tokens_by_id
+            .iter()
+            .skip(start as usize)
+            .take(limit as usize)
+            .map(|(token_id, token)| Self::json_token(token_id, token))
+            .collect()
+    }
+
+    /// NEP-181: Number of tokens owned by `account_id`
+    pub fn nft_supply_for_owner(&self, account_id: AccountId) -> U128 {
+        U128(self.tokens_per_owner.get(&account_id).map_or(0, |tokens| tokens.len() as u128))
     }
 
     /// NEP-181: Get tokens for owner
@@ -242,7 +729,7 @@ impl DynamicNFT {
         account_id: AccountId,
         from_index: Option<U128>,
         limit: Option<u64>,
-    ) -> Vec<TokenMetadata> {
+    ) -> Vec<JsonToken> {
         let tokens_set = self.tokens_per_owner.get(&account_id);
         let tokens = if let Some(tokens_set) = tokens_set {
             tokens_set
@@ -251,13 +738,16 @@ impl DynamicNFT {
         };
 
         let start = u128::from(from_index.unwrap_or(U128(0)));
-        let limit = limit.unwrap_or(50).min(100);
+        let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
 
         tokens
             .iter()
             .skip(start as usize)
             .take(limit as usize)
-            .map(|token_id| self.tokens_by_id.get(&token_id).unwrap().metadata)
+            .map(|token_id| {
+                let token = self.tokens_by_id.get(&token_id).unwrap();
+                Self::json_token(token_id, token)
+            })
             .collect()
     }
 
@@ -265,6 +755,150 @@ impl DynamicNFT {
     pub fn nft_total_supply(&self) -> U128 {
         U128(self.tokens_by_id.len() as u128)
     }
+
+    /// Marketplaces refuse tokens they cannot confirm are transferable; these always are
+    pub fn nft_is_soulbound(&self, token_id: String) -> bool {
+        assert!(self.tokens_by_id.get(&token_id).is_some(), "Token not found");
+        false
+    }
+}
+
+impl DynamicNFT {
+    fn assert_contract_owner(&self) {
+        assert_eq!(env::predecessor_account_id(), self.owner_id, "Only contract owner can manage oracles");
+    }
+
+    /// Move a token to `receiver_id`, checking the sender's ownership or approval
+    ///
+    /// The token keeps its `DynamicMetadata`; only the owner and approvals change.
+    /// Returns the previous owner and the approvals that were cleared.
+    fn internal_transfer(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        token_id: &String,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+    ) -> (AccountId, HashMap<AccountId, u64>) {
+        let mut token = self.tokens_by_id.get(token_id).expect("Token not found");
+
+        if sender_id != &token.owner_id {
+            let actual_approval_id = token
+                .approved_account_ids
+                .get(sender_id)
+                .unwrap_or_else(|| env::panic_str("Sender is not approved for this token"));
+            if approval_id.is_some_and(|expected| expected != *actual_approval_id) {
+                env::panic_str("Approval ID does not match");
+            }
+        }
+        assert_ne!(&token.owner_id, receiver_id, "The token owner and the receiver should be different");
+
+        let previous_owner_id = std::mem::replace(&mut token.owner_id, receiver_id.clone());
+        let approved_account_ids = std::mem::take(&mut token.approved_account_ids);
+        self.tokens_by_id.insert(token_id, &token);
+        self.internal_remove_token_from_owner(&previous_owner_id, token_id);
+        self.internal_add_token_to_owner(receiver_id, token_id);
+
+        Nep171Event::NftTransfer(vec![NftTransferLog {
+            authorized_id: (sender_id != &previous_owner_id).then(|| sender_id.clone()),
+            old_owner_id: previous_owner_id.clone(),
+            new_owner_id: receiver_id.clone(),
+            token_ids: vec![token_id.clone()],
+            memo,
+        }])
+        .emit();
+
+        (previous_owner_id, approved_account_ids)
+    }
+
+    fn internal_add_token_to_owner(&mut self, account_id: &AccountId, token_id: &String) {
+        let mut tokens = self
+            .tokens_per_owner
+            .get(account_id)
+            .unwrap_or_else(|| UnorderedSet::new(account_id.as_bytes().to_vec()));
+        tokens.insert(token_id);
+        self.tokens_per_owner.insert(account_id, &tokens);
+    }
+
+    fn internal_remove_token_from_owner(&mut self, account_id: &AccountId, token_id: &String) {
+        let mut tokens = self.tokens_per_owner.get(account_id).expect("Token owner has no tokens");
+        tokens.remove(token_id);
+        if tokens.is_empty() {
+            self.tokens_per_owner.remove(account_id);
+        } else {
+            self.tokens_per_owner.insert(account_id, &tokens);
+        }
+    }
+
+    /// Royalty shares rounded down; the owner receives the remainder
+    fn payout(token: &Token, balance: u128, max_len_payout: Option<u32>) -> Payout {
+        let mut payout: HashMap<AccountId, U128> = HashMap::new();
+        let mut paid = 0;
+        for (account_id, bps) in &token.royalty {
+            // Split so `balance * bps` cannot overflow
+            let basis = ROYALTY_BASIS as u128;
+            let amount = balance / basis * *bps as u128 + balance % basis * *bps as u128 / basis;
+            payout.entry(account_id.clone()).or_insert(U128(0)).0 += amount;
+            paid += amount;
+        }
+        payout.entry(token.owner_id.clone()).or_insert(U128(0)).0 += balance - paid;
+
+        if let Some(max_len_payout) = max_len_payout {
+            assert!(payout.len() <= max_len_payout as usize, "Payout exceeds max_len_payout");
+        }
+        Payout { payout }
+    }
+
+    /// Charge `storage_used` bytes to the attached deposit and refund the excess
+    fn refund_deposit(storage_used: u64) {
+        let required_deposit = env::storage_byte_cost().saturating_mul(storage_used as u128);
+        let attached = env::attached_deposit();
+
+        assert!(
+            attached >= required_deposit,
+            "Not enough deposit for storage"
+        );
+
+        if attached > required_deposit {
+            Promise::new(env::predecessor_account_id())
+                .transfer(attached.saturating_sub(required_deposit))
+                .detach();
+        }
+    }
+
+    /// Return the storage deposit of removed approvals to `account_id`
+    fn refund_approved_account_ids<'a>(account_id: AccountId, approved_account_ids: impl Iterator<Item = &'a AccountId>) {
+        let storage_released: u64 = approved_account_ids.map(bytes_for_approved_account_id).sum();
+        if storage_released > 0 {
+            Promise::new(account_id)
+                .transfer(env::storage_byte_cost().saturating_mul(storage_released as u128))
+                .detach();
+        }
+    }
+
+    fn json_token(token_id: String, token: Token) -> JsonToken {
+        JsonToken {
+            metadata: Self::rendered_metadata(&token_id, &token),
+            token_id,
+            owner_id: token.owner_id,
+            approved_account_ids: token.approved_account_ids,
+        }
+    }
+
+    /// Token metadata with `media` replaced by the on-chain render
+    /// The minted media stays reachable through `reference` and `ipfs_history`
+    fn rendered_metadata(token_id: &str, token: &Token) -> TokenMetadata {
+        let svg = RenderParams::new(token_id, &token.dynamic_metadata).to_svg();
+        let mut metadata = token.metadata.clone();
+        metadata.media_hash = Some(Base64VecU8(env::sha256(svg.as_bytes())));
+        metadata.media = Some(svg_data_uri(&svg));
+        metadata
+    }
+}
+
+/// Storage an approval adds to a token: a length-prefixed account ID and a u64 approval ID
+fn bytes_for_approved_account_id(account_id: &AccountId) -> u64 {
+    account_id.as_str().len() as u64 + 4 + 8
 }
 
 // Unit tests
@@ -272,8 +906,22 @@ impl DynamicNFT {
 mod tests {
     use super::*;
     use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::NearToken;
     use near_sdk::testing_env;
 
+    // Signed for token1 on accounts(0) by `OracleSigner::from_secret([7; 32])` from the Rust client
+    const ORACLE_KEY: &str = "ed25519:GmaDrppBC7P5ARKV8g3djiwP89vz1jLK23V2GBjuAEGB";
+    const READING_AT: u64 = 1_700_000_000_000_000_000;
+
+    fn excited_reading() -> SignedReading {
+        near_sdk::serde_json::from_value(near_sdk::serde_json::json!({
+            "public_key": ORACLE_KEY,
+            "payload": r#"{"contract_id":"alice","token_id":"token1","nonce":"1700000000000000000","timestamp":"1700000000000000000","reading":{"arousal":1.0,"confidence":0.5,"dominance":0.2,"timestamp":0,"valence":0.9}}"#,
+            "signature": "2EE7RYmj7qTP3ZRA5S6wJRtRqn1KiX7TiFJGhn7A9/qNQDal+qRPA9nCfvNizBjCGkROIoHCOVwX3nI71JNvCw==",
+        }))
+        .unwrap()
+    }
+
     fn get_context(predecessor_account_id: AccountId) -> VMContextBuilder {
         let mut builder = VMContextBuilder::new();
         builder
@@ -323,12 +971,248 @@ mod tests {
             timestamp: 1234567890,
         };
 
-        context.attached_deposit(10_000_000_000_000_000_000_000); // 0.01 NEAR
+        context.attached_deposit(NearToken::from_millinear(10));
         testing_env!(context.build());
 
-        contract.nft_mint("token1".to_string(), accounts(1), token_metadata, emotion);
+        contract.nft_mint("token1".to_string(), accounts(1), token_metadata, emotion, None);
 
         let dynamic_meta = contract.get_dynamic_metadata("token1".to_string());
         assert_eq!(dynamic_meta.emotional_state.valence, 0.5);
+
+        // Wallets get the on-chain render, not the minted media
+        let rendered = contract.nft_token("token1".to_string()).unwrap();
+        assert_eq!(rendered.owner_id, accounts(1));
+        let media = rendered.metadata.media.unwrap();
+        assert!(media.starts_with("data:image/svg+xml,%3Csvg"));
+        assert!(!media.contains('<') && !media.contains('#'));
+        assert_eq!(contract.nft_token("token1".to_string()).unwrap().metadata.media.unwrap(), media);
+
+        assert_eq!(
+            near_sdk::test_utils::get_logs(),
+            vec![format!(
+                r#"EVENT_JSON:{{"standard":"nep171","version":"1.0.0","event":"nft_mint","data":[{{"owner_id":"{}","token_ids":["token1"]}}]}}"#,
+                accounts(1)
+            )]
+        );
+    }
+
+    #[test]
+    fn test_render_follows_emotional_state() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = DynamicNFT::new(
+            accounts(0),
+            NFTContractMetadata {
+                spec: "nft-1.0.0".to_string(),
+                name: "Dynamic Emotion NFT".to_string(),
+                symbol: "DYNFT".to_string(),
+                icon: None,
+                base_uri: None,
+                reference: None,
+                reference_hash: None,
+            },
+        );
+        let calm = EmotionalState {
+            valence: -0.5,
+            arousal: 0.1,
+            dominance: 0.2,
+            confidence: 0.5,
+            timestamp: 0,
+        };
+        let token_metadata = TokenMetadata {
+            title: Some("Render".to_string()),
+            description: None,
+            media: None,
+            media_hash: None,
+            copies: Some(1),
+            issued_at: None,
+            expires_at: None,
+            starts_at: None,
+            updated_at: None,
+            extra: None,
+            reference: None,
+            reference_hash: None,
+        };
+        context.attached_deposit(NearToken::from_millinear(10));
+        testing_env!(context.build());
+        contract.nft_mint("token1".to_string(), accounts(1), token_metadata, calm, None);
+
+        let before = contract.get_render_params("token1".to_string());
+        assert_eq!(before.petals, 3);
+        assert_eq!(before.rings, 1);
+
+        contract.add_oracle(ORACLE_KEY.parse().unwrap(), "eeg-gateway".to_string());
+
+        // {valence: 0.9, arousal: 1.0, dominance: 0.2, confidence: 0.5}
+        let mut context = get_context(accounts(1));
+        context.block_timestamp(READING_AT);
+        testing_env!(context.build());
+        contract.update_emotional_state("token1".to_string(), excited_reading(), None);
+
+        let after = contract.get_render_params("token1".to_string());
+        assert_eq!(after.petals, 12);
+        assert_eq!(after.rings, 2);
+        assert!(after.hue != before.hue && after.period_ms < before.period_ms);
+
+        // The advertised hash covers exactly the rendered SVG
+        let metadata = contract.nft_token("token1".to_string()).unwrap().metadata;
+        let svg = contract.render_svg("token1".to_string());
+        assert_eq!(metadata.media_hash.unwrap().0, env::sha256(svg.as_bytes()));
+        assert_eq!(metadata.media.unwrap(), svg_data_uri(&svg));
+    }
+
+    /// Contract on accounts(0) with "token1" minted to accounts(1)
+    fn setup_minted() -> DynamicNFT {
+        setup_minted_with_royalty(None)
+    }
+
+    fn setup_minted_with_royalty(royalty: Option<HashMap<AccountId, u32>>) -> DynamicNFT {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = DynamicNFT::new(
+            accounts(0),
+            NFTContractMetadata {
+                spec: "nft-1.0.0".to_string(),
+                name: "Dynamic Emotion NFT".to_string(),
+                symbol: "DYNFT".to_string(),
+                icon: None,
+                base_uri: None,
+                reference: None,
+                reference_hash: None,
+            },
+        );
+        let token_metadata = TokenMetadata {
+            title: Some("Transferable".to_string()),
+            description: None,
+            media: None,
+            media_hash: None,
+            copies: Some(1),
+            issued_at: None,
+            expires_at: None,
+            starts_at: None,
+            updated_at: None,
+            extra: None,
+            reference: Some("ipfs://QmTestMetadata".to_string()),
+            reference_hash: None,
+        };
+        let emotion = EmotionalState {
+            valence: 0.5,
+            arousal: 0.7,
+            dominance: 0.6,
+            confidence: 0.9,
+            timestamp: 0,
+        };
+        context.attached_deposit(NearToken::from_millinear(10));
+        testing_env!(context.build());
+        contract.nft_mint("token1".to_string(), accounts(1), token_metadata, emotion, royalty);
+        contract
+    }
+
+    #[test]
+    fn test_approved_transfer_keeps_dynamic_metadata() {
+        let mut contract = setup_minted();
+
+        // The owner evolves the token, then approves a marketplace
+        contract.add_oracle(ORACLE_KEY.parse().unwrap(), "eeg-gateway".to_string());
+        let mut context = get_context(accounts(1));
+        context.block_timestamp(READING_AT);
+        testing_env!(context.build());
+        contract.update_emotional_state("token1".to_string(), excited_reading(), Some("ipfs://QmEvolved".to_string()));
+
+        context.attached_deposit(NearToken::from_millinear(1));
+        testing_env!(context.build());
+        contract.nft_approve("token1".to_string(), accounts(3), None);
+        assert!(contract.nft_is_approved("token1".to_string(), accounts(3), Some(0)));
+        assert!(!contract.nft_is_approved("token1".to_string(), accounts(3), Some(1)));
+
+        // The marketplace sells it on to accounts(2)
+        let mut context = get_context(accounts(3));
+        context.attached_deposit(NearToken::from_yoctonear(1));
+        testing_env!(context.build());
+        contract.nft_transfer(accounts(2), "token1".to_string(), Some(0), None);
+
+        assert_eq!(
+            near_sdk::test_utils::get_logs(),
+            vec![format!(
+                r#"EVENT_JSON:{{"standard":"nep171","version":"1.0.0","event":"nft_transfer","data":[{{"authorized_id":"{}","old_owner_id":"{}","new_owner_id":"{}","token_ids":["token1"]}}]}}"#,
+                accounts(3),
+                accounts(1),
+                accounts(2)
+            )]
+        );
+
+        let token = contract.nft_token("token1".to_string()).unwrap();
+        assert_eq!(token.owner_id, accounts(2));
+        assert!(token.approved_account_ids.is_empty());
+        assert_eq!(contract.nft_supply_for_owner(accounts(1)).0, 0);
+        assert_eq!(contract.nft_tokens_for_owner(accounts(2), None, None).len(), 1);
+        assert_eq!(contract.nft_tokens(None, None)[0].token_id, "token1");
+
+        let dynamic = contract.get_dynamic_metadata("token1".to_string());
+        assert_eq!(dynamic.interaction_count, 1);
+        assert_eq!(dynamic.emotional_state.valence, 0.9);
+        assert_eq!(dynamic.ipfs_history, vec!["ipfs://QmTestMetadata", "ipfs://QmEvolved"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Sender is not approved for this token")]
+    fn test_revoked_account_cannot_transfer() {
+        let mut contract = setup_minted();
+
+        let mut context = get_context(accounts(1));
+        context.attached_deposit(NearToken::from_millinear(1));
+        testing_env!(context.build());
+        contract.nft_approve("token1".to_string(), accounts(3), None);
+
+        context.attached_deposit(NearToken::from_yoctonear(1));
+        testing_env!(context.build());
+        contract.nft_revoke("token1".to_string(), accounts(3));
+
+        let mut context = get_context(accounts(3));
+        context.attached_deposit(NearToken::from_yoctonear(1));
+        testing_env!(context.build());
+        contract.nft_transfer(accounts(2), "token1".to_string(), None, None);
+    }
+
+    #[test]
+    fn test_transfer_payout_splits_royalties() {
+        let royalty = HashMap::from([(accounts(0), 1_000), (accounts(4), 250)]);
+        let mut contract = setup_minted_with_royalty(Some(royalty));
+
+        let mut context = get_context(accounts(1));
+        context.attached_deposit(NearToken::from_millinear(1));
+        testing_env!(context.build());
+        let _ = contract.nft_approve("token1".to_string(), accounts(3), None);
+
+        let balance = U128(NearToken::from_near(2).as_yoctonear() + 3);
+        let expected = Payout {
+            payout: HashMap::from([
+                (accounts(0), U128(NearToken::from_millinear(200).as_yoctonear())),
+                (accounts(4), U128(NearToken::from_millinear(50).as_yoctonear())),
+                (accounts(1), U128(NearToken::from_millinear(1_750).as_yoctonear() + 3)),
+            ]),
+        };
+        assert_eq!(contract.nft_payout("token1".to_string(), balance, Some(3)), expected);
+        assert!(!contract.nft_is_soulbound("token1".to_string()));
+
+        let mut context = get_context(accounts(3));
+        context.attached_deposit(NearToken::from_yoctonear(1));
+        testing_env!(context.build());
+        let payout = contract.nft_transfer_payout(accounts(2), "token1".to_string(), Some(0), None, balance, Some(3));
+        assert_eq!(payout, expected);
+        assert_eq!(contract.nft_token("token1".to_string()).unwrap().owner_id, accounts(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "Payout exceeds max_len_payout")]
+    fn test_payout_respects_max_len() {
+        let contract = setup_minted_with_royalty(Some(HashMap::from([(accounts(0), 1_000)])));
+        contract.nft_payout("token1".to_string(), U128(1_000), Some(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Royalties cannot exceed 50%")]
+    fn test_royalties_are_capped() {
+        setup_minted_with_royalty(Some(HashMap::from([(accounts(0), 4_000), (accounts(4), 1_001)])));
     }
 }
@@ -2,11 +2,12 @@
 
 use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
 use near_sdk::serde::{Deserialize, Serialize};
-use near_sdk::{env, AccountId, Timestamp, Balance};
+use near_sdk::{env, require, AccountId, NearToken, Timestamp};
 use near_contract_standards::non_fungible_token::metadata::TokenMetadata;
 use near_contract_standards::non_fungible_token::TokenId;
 use near_sdk::collections::{LookupMap, Vector};
-use near_sdk::json_types::U128;
+
+use crate::biometric_template::BiometricCommitment;
 
 /// Enhanced soulbound token with AI/ML biometric integration
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
@@ -18,7 +19,7 @@ pub struct EnhancedSoulboundToken {
     pub identity_data: EnhancedIdentityData,
     pub minted_at: Timestamp,
     pub soulbound: bool,
-    pub biometric_hash: Option<Vec<u8>>,
+    pub biometric_commitment: Option<BiometricCommitment>, // Fuzzy commitment; raw features are never stored
     pub ai_model_version: String,
 }
 
@@ -76,7 +77,7 @@ pub struct CreativeProfile {
     pub preferred_medium: String,
     pub collaboration_interest: bool,
     pub skill_tags: Vec<String>,
-    pub hourly_rate: Option<Balance>,
+    pub hourly_rate: Option<NearToken>,
 }
 
 /// Enhanced soulbound token contract state
@@ -96,7 +97,7 @@ impl EnhancedSoulboundToken {
         owner_id: AccountId,
         metadata: TokenMetadata,
         identity_data: EnhancedIdentityData,
-        biometric_hash: Option<Vec<u8>>,
+        biometric_commitment: Option<BiometricCommitment>,
         ai_model_version: String,
     ) -> Self {
         Self {
@@ -106,7 +107,7 @@ impl EnhancedSoulboundToken {
             identity_data,
             minted_at: env::block_timestamp(),
             soulbound: true,
-            biometric_hash,
+            biometric_commitment,
             ai_model_version,
         }
     }
@@ -117,11 +118,17 @@ impl EnhancedSoulboundToken {
         require!(env::predecessor_account_id() == self.owner_id, "Only owner can update biometric data");
         
         self.identity_data.biometric_data = new_biometric_data;
-        
-        // Generate new biometric hash for verification
-        self.biometric_hash = Some(env::sha256(
-            format!("{}{}", self.token_id, env::block_timestamp()).as_bytes()
-        ));
+    }
+
+    /// Enroll a biometric feature vector for later verification
+    ///
+    /// Only the commitment is kept; `entropy` must be secret and fresh, and enrolling
+    /// again replaces the previous template.
+    pub fn enroll_biometric(&mut self, features: &[f32], entropy: &[u8; 32], max_bit_errors: u32) {
+        require!(self.soulbound, "Cannot enroll biometrics for non-soulbound token");
+        require!(env::predecessor_account_id() == self.owner_id, "Only owner can enroll biometrics");
+
+        self.biometric_commitment = Some(BiometricCommitment::enroll(features, entropy, max_bit_errors));
     }
 
     /// Add AI insights from external computation
@@ -145,15 +152,11 @@ impl EnhancedSoulboundToken {
     }
 
     /// Verify biometric match (privacy-preserving)
+    /// Samples must come from the same feature extractor used at enrollment
     pub fn verify_biometric(&self, biometric_sample: &[f32]) -> bool {
-        if let Some(ref stored_hash) = self.biometric_hash {
-            let sample_hash = env::sha256(
-                format!("{}{}", self.token_id, biometric_sample.len()).as_bytes()
-            );
-            stored_hash == &sample_hash
-        } else {
-            false
-        }
+        self.biometric_commitment
+            .as_ref()
+            .is_some_and(|commitment| commitment.verify(biometric_sample))
     }
 
     /// Get AI-powered skill recommendations
@@ -229,6 +232,7 @@ impl Default for CreativeProfile {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::biometric_template::DEFAULT_MAX_BIT_ERRORS;
     use near_contract_standards::non_fungible_token::TokenId;
 
     #[test]
@@ -252,7 +256,11 @@ mod tests {
         };
         
         let identity_data = EnhancedIdentityData::default();
-        let biometric_hash = Some(vec![1, 2, 3, 4, 5]);
+        let biometric_commitment = Some(BiometricCommitment::enroll(
+            &[1.2, 0.9, 1.4, 0.4, -0.3, 0.6, 0.5],
+            &[1; 32],
+            DEFAULT_MAX_BIT_ERRORS,
+        ));
         let ai_model_version = "v1.0".to_string();
         
         let enhanced_token = EnhancedSoulboundToken::new(
@@ -260,7 +268,7 @@ mod tests {
             owner_id.clone(),
             metadata.clone(),
             identity_data.clone(),
-            biometric_hash.clone(),
+            biometric_commitment.clone(),
             ai_model_version.clone(),
         );
         
@@ -269,7 +277,7 @@ mod tests {
         assert_eq!(enhanced_token.metadata.title, Some("Enhanced Creative Identity".to_string()));
         assert!(enhanced_token.soulbound);
         assert_eq!(enhanced_token.ai_model_version, ai_model_version);
-        assert_eq!(enhanced_token.biometric_hash, biometric_hash);
+        assert_eq!(enhanced_token.biometric_commitment, biometric_commitment);
     }
 
     #[test]
@@ -315,4 +323,27 @@ mod tests {
         // Should be (1/2) * 0.8 = 0.4
         assert_eq!(compatibility, 0.4);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_verify_biometric_rejects_other_samples_of_same_length() {
+        let owner_id: AccountId = "creator.testnet".parse().unwrap();
+        let mut context = near_sdk::test_utils::VMContextBuilder::new();
+        context.predecessor_account_id(owner_id.clone());
+        near_sdk::testing_env!(context.build());
+
+        let mut token = EnhancedSoulboundToken::new(
+            "biometric_token".to_string(),
+            owner_id,
+            TokenMetadata::default(),
+            EnhancedIdentityData::default(),
+            None,
+            "v1.0".to_string(),
+        );
+        assert!(!token.verify_biometric(&[0.5; 7]));
+
+        let enrolled = [1.2, 0.9, 1.4, 0.4, -0.3, 0.6, 0.5];
+        token.enroll_biometric(&enrolled, &[3; 32], DEFAULT_MAX_BIT_ERRORS);
+        assert!(token.verify_biometric(&enrolled));
+        assert!(!token.verify_biometric(&[0.4, 1.3, 0.2, 1.1, 0.6, -0.8, -0.2]));
+    }
+}
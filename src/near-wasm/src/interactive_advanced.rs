@@ -5,8 +5,10 @@
 
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::serde::{Deserialize, Serialize};
-use near_sdk::{env, near, AccountId, Timestamp};
-use near_sdk::collections::{LookupMap, UnorderedMap, Vector};
+use near_sdk::{env, AccountId, Timestamp};
+use near_sdk::collections::{LookupMap, Vector};
+
+use crate::oracle::{OracleRegistry, SignedReading};
 
 /// Interactive NFT with biometric integration
 #[derive(BorshDeserialize, BorshSerialize)]
@@ -91,6 +93,14 @@ pub struct EmotionalInteraction {
     pub state_after: VisualStateSnapshot,
 }
 
+/// Sensor reading an oracle signs for `interact_with_biometrics`
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BiometricReading {
+    pub emotional_state: DetailedEmotionalState,
+    pub biometric_data: BiometricSnapshot,
+}
+
 /// Detailed emotional state with multiple dimensions
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
@@ -320,13 +330,17 @@ impl BiometricNFT {
     }
 
     /// Interact with NFT using real-time biometric data
+    ///
+    /// `reading` must be a `BiometricReading` signed for this token by one of the
+    /// hosting contract's registered oracles.
     pub fn interact_with_biometrics(
         &mut self,
-        emotional_state: DetailedEmotionalState,
-        biometric_data: BiometricSnapshot,
+        oracles: &mut OracleRegistry,
+        reading: &SignedReading,
         interaction_type: InteractionType,
     ) {
         let user = env::predecessor_account_id();
+        let BiometricReading { emotional_state, biometric_data } = oracles.verify(reading, &self.token_id);
         
         // Capture state before interaction
         let state_before = self.capture_state_snapshot();
@@ -462,3 +476,93 @@ impl Default for EmotionalResonance {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::serde_json::json;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    // Signed by `OracleSigner::from_secret([7; 32])` from the Rust client
+    const ORACLE_KEY: &str = "ed25519:GmaDrppBC7P5ARKV8g3djiwP89vz1jLK23V2GBjuAEGB";
+    const READING_AT: u64 = 1_700_000_000_000_000_000;
+
+    fn signed_reading() -> SignedReading {
+        near_sdk::serde_json::from_value(json!({
+            "public_key": ORACLE_KEY,
+            "payload": r#"{"contract_id":"contract.testnet","token_id":"token1","nonce":"1700000000000000000","timestamp":"1700000000000000000","reading":{"biometric_data":{"data_cid":"QmReading","eeg_data":{"alpha":0.0,"attention":0.0,"beta":0.0,"delta":0.0,"frontal_asymmetry":0.0,"gamma":0.0,"meditation":0.5,"theta":0.0},"facial_data":null,"gsr":null,"heart_rate":null,"quality_score":1.0},"emotional_state":{"arousal":0.5,"confidence":0.75,"dominance":0.5,"engagement":0.0,"focus":0.0,"intensity":0.5,"primary_emotion":"joy","relaxation":0.0,"stress":0.0,"valence":0.5}}}"#,
+            "signature": "+CKpzbakIlDUFTgNJtQlN1rNAlBnI91ln0QYhd6P8vi5ZP6APnMFMx3iTcAZprEL5RXigvwnIHDaOmXz6+5fBw==",
+        }))
+        .unwrap()
+    }
+
+    fn setup(token_id: &str) -> (BiometricNFT, OracleRegistry) {
+        let mut context = VMContextBuilder::new();
+        context
+            .current_account_id("contract.testnet".parse().unwrap())
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(READING_AT + 1_000_000_000);
+        testing_env!(context.build());
+
+        let metadata = InteractiveMetadata {
+            title: "Resonance".to_string(),
+            description: "Responds to its viewers".to_string(),
+            artist: accounts(0),
+            created_at: 0,
+            base_ipfs_cid: "QmBase".to_string(),
+            interaction_rules: InteractionRules {
+                valence_affects_color: true,
+                arousal_affects_speed: true,
+                dominance_affects_detail: true,
+                meditation_affects_morphing: true,
+                stress_affects_complexity: false,
+                sensitivity: 1.0,
+            },
+        };
+        let mut oracles = OracleRegistry::new(b"o");
+        oracles.add(ORACLE_KEY.parse().unwrap(), "eeg-gateway".to_string());
+        (BiometricNFT::new(token_id.to_string(), accounts(0), metadata), oracles)
+    }
+
+    #[test]
+    fn test_signed_reading_modulates_visual_state() {
+        let (mut nft, mut oracles) = setup("token1");
+        nft.interact_with_biometrics(&mut oracles, &signed_reading(), InteractionType::Meditation);
+
+        assert_eq!(nft.visual_state.color_intensity, 0.75);
+        assert_eq!(nft.visual_state.animation_speed, 1.0);
+        assert_eq!(nft.visual_state.detail_level, 125);
+        assert_eq!(nft.visual_state.morphing_rate, 0.5);
+        assert_eq!(nft.emotional_resonance.dominant_emotion, "joy");
+
+        let interaction = nft.interaction_history.get(0).unwrap();
+        assert_eq!(interaction.user, accounts(1));
+        assert_eq!(interaction.state_before.detail_level, 100);
+        assert_eq!(interaction.state_after.detail_level, 125);
+    }
+
+    #[test]
+    #[should_panic(expected = "Reading nonce was already used")]
+    fn test_replayed_reading_is_rejected() {
+        let (mut nft, mut oracles) = setup("token1");
+        nft.interact_with_biometrics(&mut oracles, &signed_reading(), InteractionType::View);
+        nft.interact_with_biometrics(&mut oracles, &signed_reading(), InteractionType::View);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid oracle signature")]
+    fn test_tampered_reading_is_rejected() {
+        let (mut nft, mut oracles) = setup("token1");
+        let mut reading = signed_reading();
+        reading.payload = reading.payload.replace(r#""stress":0.0"#, r#""stress":1.0"#);
+        nft.interact_with_biometrics(&mut oracles, &reading, InteractionType::View);
+    }
+
+    #[test]
+    #[should_panic(expected = "Reading was signed for another token")]
+    fn test_reading_for_another_token_is_rejected() {
+        let (mut nft, mut oracles) = setup("token2");
+        nft.interact_with_biometrics(&mut oracles, &signed_reading(), InteractionType::View);
+    }
+}
@@ -0,0 +1,223 @@
+//! Oracle-signed emotion readings
+//!
+//! Sensor gateways sign each emotion reading with an ed25519 key the contract owner
+//! has registered. Contracts check the signature with `env::ed25519_verify` and
+//! reject readings that are stale, dated in the future, meant for another
+//! contract or token, or replayed, before applying them.
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::UnorderedMap;
+use near_sdk::json_types::{Base64VecU8, U64};
+use near_sdk::serde::de::DeserializeOwned;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, AccountId, CurveType, IntoStorageKey, PublicKey, Timestamp};
+
+/// Readings older than this are rejected (5 minutes, in nanoseconds)
+pub const MAX_READING_AGE: u64 = 300_000_000_000;
+
+/// Tolerated lead of a reading's timestamp over the block time (30 seconds)
+pub const MAX_CLOCK_SKEW: u64 = 30_000_000_000;
+
+/// Message an oracle signs; `SignedReading::payload` is its JSON encoding
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OracleMessage<T> {
+    pub contract_id: AccountId, // Contract the reading is meant for
+    pub token_id: String,
+    pub nonce: U64,             // Must increase with every reading from the same key
+    pub timestamp: U64,         // When the reading was taken, nanoseconds since epoch
+    pub reading: T,
+}
+
+/// Reading submitted to a contract
+///
+/// The signature covers the exact bytes of `payload`, so the contract never has to
+/// re-serialize anything to check it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SignedReading {
+    pub public_key: PublicKey, // "ed25519:<base58>"
+    pub payload: String,
+    pub signature: Base64VecU8,
+}
+
+/// Registered oracle key
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OracleInfo {
+    pub label: String,
+    pub added_at: Timestamp,
+    pub last_nonce: U64,
+}
+
+/// Trusted sensor/oracle keys with their replay counters
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OracleRegistry {
+    oracles: UnorderedMap<PublicKey, OracleInfo>,
+}
+
+impl OracleRegistry {
+    pub fn new<S: IntoStorageKey>(prefix: S) -> Self {
+        Self {
+            oracles: UnorderedMap::new(prefix),
+        }
+    }
+
+    /// Trust a key; re-adding a known key keeps its nonce so old readings stay dead
+    pub fn add(&mut self, public_key: PublicKey, label: String) {
+        if public_key.curve_type() != CurveType::ED25519 {
+            env::panic_str("Oracle keys must be ed25519");
+        }
+        let last_nonce = self.oracles.get(&public_key).map_or(U64(0), |info| info.last_nonce);
+        self.oracles.insert(
+            &public_key,
+            &OracleInfo {
+                label,
+                added_at: env::block_timestamp(),
+                last_nonce,
+            },
+        );
+    }
+
+    pub fn remove(&mut self, public_key: &PublicKey) -> bool {
+        self.oracles.remove(public_key).is_some()
+    }
+
+    pub fn get(&self, public_key: &PublicKey) -> Option<OracleInfo> {
+        self.oracles.get(public_key)
+    }
+
+    pub fn list(&self) -> Vec<(PublicKey, OracleInfo)> {
+        self.oracles.to_vec()
+    }
+
+    /// Check a signed reading for `token_id` and consume its nonce
+    ///
+    /// Panics unless the key is registered, the signature is valid and the message
+    /// targets this contract and token, is fresh, and has an unused nonce.
+    pub fn verify<T: DeserializeOwned>(&mut self, signed: &SignedReading, token_id: &str) -> T {
+        let mut info = self
+            .oracles
+            .get(&signed.public_key)
+            .unwrap_or_else(|| env::panic_str("Unknown oracle key"));
+
+        let signature: [u8; 64] = signed
+            .signature
+            .0
+            .as_slice()
+            .try_into()
+            .unwrap_or_else(|_| env::panic_str("Signature must be 64 bytes"));
+        // Registered keys are ed25519: a curve type byte followed by 32 key bytes
+        let key: [u8; 32] = signed.public_key.as_bytes()[1..]
+            .try_into()
+            .unwrap_or_else(|_| env::panic_str("Oracle keys must be ed25519"));
+        if !env::ed25519_verify(&signature, signed.payload.as_bytes(), &key) {
+            env::panic_str("Invalid oracle signature");
+        }
+
+        let message: OracleMessage<T> = near_sdk::serde_json::from_str(&signed.payload)
+            .unwrap_or_else(|_| env::panic_str("Malformed oracle payload"));
+        if message.contract_id != env::current_account_id() {
+            env::panic_str("Reading was signed for another contract");
+        }
+        if message.token_id != token_id {
+            env::panic_str("Reading was signed for another token");
+        }
+
+        let now = env::block_timestamp();
+        let taken_at = message.timestamp.0;
+        if taken_at > now.saturating_add(MAX_CLOCK_SKEW) {
+            env::panic_str("Reading is dated in the future");
+        }
+        if now.saturating_sub(taken_at) > MAX_READING_AGE {
+            env::panic_str("Reading is too old");
+        }
+        if message.nonce.0 <= info.last_nonce.0 {
+            env::panic_str("Reading nonce was already used");
+        }
+
+        info.last_nonce = message.nonce;
+        self.oracles.insert(&signed.public_key, &info);
+        message.reading
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::serde_json::json;
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::testing_env;
+
+    // Signed by `OracleSigner::from_secret([7; 32])` from the Rust client
+    const ORACLE_KEY: &str = "ed25519:GmaDrppBC7P5ARKV8g3djiwP89vz1jLK23V2GBjuAEGB";
+    const READING_AT: u64 = 1_700_000_000_000_000_000;
+
+    fn signed(payload: &str, signature: &str) -> SignedReading {
+        near_sdk::serde_json::from_value(json!({
+            "public_key": ORACLE_KEY,
+            "payload": payload,
+            "signature": signature,
+        }))
+        .unwrap()
+    }
+
+    fn first_reading() -> SignedReading {
+        signed(
+            r#"{"contract_id":"contract.testnet","token_id":"token1","nonce":"1700000000000000000","timestamp":"1700000000000000000","reading":7}"#,
+            "KigxzsnthvdXTuCrgoIPx67/+bnj61wgMZgL8DOePgeqevdUQhM1O/mJfyTG0nn6geiM0yztytp4bVGw9xV1DQ==",
+        )
+    }
+
+    fn second_reading() -> SignedReading {
+        signed(
+            r#"{"contract_id":"contract.testnet","token_id":"token1","nonce":"1700000000000000001","timestamp":"1700000000000000000","reading":8}"#,
+            "Lno9qBAbXJx35/BY9KZO3lXqTSQmd+9ZIb71FcqZ1aWdTnWuEX+3STWueFL5JFjFc1Mhm/CdnTFPB05Ls9f4Aw==",
+        )
+    }
+
+    fn registry_at(block_timestamp: u64) -> OracleRegistry {
+        let mut context = VMContextBuilder::new();
+        context.current_account_id("contract.testnet".parse().unwrap());
+        context.block_timestamp(block_timestamp);
+        testing_env!(context.build());
+
+        let mut registry = OracleRegistry::new(b"o");
+        registry.add(ORACLE_KEY.parse().unwrap(), "eeg-gateway".to_string());
+        registry
+    }
+
+    #[test]
+    fn test_verifies_readings_in_nonce_order() {
+        let mut registry = registry_at(READING_AT + 1_000_000_000);
+        assert_eq!(registry.verify::<u32>(&first_reading(), "token1"), 7);
+        assert_eq!(registry.verify::<u32>(&second_reading(), "token1"), 8);
+
+        let info = registry.get(&ORACLE_KEY.parse().unwrap()).unwrap();
+        assert_eq!(info.last_nonce, U64(READING_AT + 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Reading nonce was already used")]
+    fn test_replayed_reading_is_rejected() {
+        let mut registry = registry_at(READING_AT);
+        registry.verify::<u32>(&second_reading(), "token1");
+        registry.verify::<u32>(&first_reading(), "token1");
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid oracle signature")]
+    fn test_tampered_reading_is_rejected() {
+        let mut registry = registry_at(READING_AT);
+        let mut reading = first_reading();
+        reading.payload = reading.payload.replace(r#""reading":7"#, r#""reading":9"#);
+        registry.verify::<u32>(&reading, "token1");
+    }
+
+    #[test]
+    #[should_panic(expected = "Reading is too old")]
+    fn test_stale_reading_is_rejected() {
+        let mut registry = registry_at(READING_AT + MAX_READING_AGE + 1);
+        registry.verify::<u32>(&first_reading(), "token1");
+    }
+}
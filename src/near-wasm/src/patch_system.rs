@@ -2,9 +2,17 @@
 
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::collections::{LookupMap, UnorderedMap, UnorderedSet};
+use near_sdk::json_types::U128;
 use near_sdk::serde::{Deserialize, Serialize};
-use near_sdk::{env, near_bindgen, AccountId, Balance, Promise, Timestamp};
-use std::collections::HashMap;
+use near_sdk::{env, near, AccountId, Gas, NearToken, Promise, Timestamp};
+use semver::{Version, VersionReq};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Hard cap on how far up the fork/dependency graph royalties travel
+pub const MAX_ROYALTY_DEPTH: u8 = 8;
+
+/// Gas reserved for the callback that restores earnings after a failed withdrawal
+const GAS_FOR_ON_EARNINGS_WITHDRAWN: Gas = Gas::from_tgas(10);
 
 /// Published creative patch
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
@@ -15,19 +23,99 @@ pub struct PublishedPatch {
     pub description: String,
     pub author: AccountId,
     pub tool_type: String,
-    pub version: String,
+    pub version: String, // Semver of the newest unyanked release
     pub tags: Vec<String>,
     pub ipfs_cid: String,
     pub license: String,
-    pub price: Option<Balance>,
+    pub price: Option<NearToken>,
     pub downloads: u64,
     pub rating: f32,
     pub total_ratings: u32,
     pub published_at: Timestamp,
     pub last_updated: Timestamp,
     pub fork_count: u32,
-    pub dependencies: Vec<String>, // Other patch IDs
-    pub compatibility: Vec<String>, // Compatible tool versions
+    pub dependencies: Vec<String>, // "patch_id" or "patch_id@<semver requirement>"
+    pub compatibility: Vec<String>, // Semver requirements on the tool version, e.g. "^1.2"
+    #[serde(default)]
+    pub encryption: Option<PatchEncryption>, // Required for paid patches
+}
+
+/// Immutable published version of a patch
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PatchRelease {
+    pub version: String,
+    pub ipfs_cid: String,
+    pub changelog: String,
+    pub compatibility: Vec<String>,
+    pub dependencies: Vec<String>,
+    pub encryption: Option<PatchEncryption>,
+    pub published_at: Timestamp,
+    pub yanked: bool,
+}
+
+/// Fields of a new release supplied by the author
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NewRelease {
+    pub version: String,
+    pub ipfs_cid: String,
+    pub changelog: String,
+    pub compatibility: Vec<String>,
+    pub dependencies: Vec<String>,
+    pub encryption: Option<PatchEncryption>,
+}
+
+/// Listing fields an author can edit; content changes go through `publish_release`
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PatchUpdate {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub tags: Option<Vec<String>>,
+}
+
+/// A release picked by `resolve_patch`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ResolvedPatch {
+    pub patch_id: String,
+    pub version: String,
+    pub ipfs_cid: String,
+}
+
+/// A patch and the releases of its transitive dependencies for one tool version
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Resolution {
+    pub patch: ResolvedPatch,
+    pub dependencies: Vec<ResolvedPatch>,
+}
+
+/// How a paid patch's payload is encrypted
+///
+/// `ipfs_cid` points at the ciphertext. The content key never touches the chain:
+/// only its SHA-256 commitment is stored, and the key service releases it to buyers
+/// wrapped to a public key they registered with `request_access`.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PatchEncryption {
+    pub algorithm: String, // e.g. "xchacha20poly1305"
+    pub key_commitment: String, // hex SHA-256 of the content key
+    pub plaintext_sha256: String, // hex SHA-256 of the decrypted patch
+    pub key_service: AccountId, // Only account allowed to deliver wrapped keys
+}
+
+/// A buyer's request for a patch key and the key service's answer
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AccessGrant {
+    pub patch_id: String,
+    pub buyer: AccountId,
+    pub public_key: String, // hex x25519 public key the content key is wrapped to
+    pub requested_at: Timestamp,
+    pub wrapped_key: Option<String>, // hex, set by the key service
+    pub granted_at: Option<Timestamp>,
 }
 
 /// Patch rating/review
@@ -51,6 +139,26 @@ pub struct PatchFork {
     pub changes_summary: String,
 }
 
+/// How sale revenue flows to upstream authors
+///
+/// At each hop a patch passes `upstream_share_bps` of what it receives to the patches it
+/// was forked from or depends on, split evenly, and its author keeps the rest.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RoyaltyPolicy {
+    pub upstream_share_bps: u16, // Basis points (0-10000)
+    pub max_depth: u8, // Hops above the purchased patch that can earn
+}
+
+impl Default for RoyaltyPolicy {
+    fn default() -> Self {
+        Self {
+            upstream_share_bps: 1_000, // 10% per hop
+            max_depth: 3,
+        }
+    }
+}
+
 /// Patch collection/series
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
@@ -65,8 +173,48 @@ pub struct PatchCollection {
     pub featured: bool,
 }
 
+/// NEP-297 events logged by the patch marketplace
+#[near(event_json(standard = "patch_marketplace"))]
+pub enum PatchEvent {
+    #[event_version("1.0.0")]
+    PatchPublished {
+        patch_id: String,
+        author: AccountId,
+        version: String,
+        price: Option<U128>,
+    },
+    #[event_version("1.0.0")]
+    PatchUpdated { patch_id: String },
+    #[event_version("1.0.0")]
+    ReleasePublished { patch_id: String, version: String },
+    #[event_version("1.0.0")]
+    ReleaseYanked { patch_id: String, version: String, yanked: bool },
+    #[event_version("1.0.0")]
+    PatchPurchased {
+        patch_id: String,
+        buyer: AccountId,
+        price: U128,
+        platform_fee: U128,
+        shares: HashMap<AccountId, U128>,
+    },
+    #[event_version("1.0.0")]
+    PatchRated { patch_id: String, rater: AccountId, rating: u8 },
+    #[event_version("1.0.0")]
+    PatchForked {
+        original_patch_id: String,
+        fork_patch_id: String,
+        forked_by: AccountId,
+    },
+    #[event_version("1.0.0")]
+    AccessRequested { patch_id: String, buyer: AccountId, public_key: String },
+    #[event_version("1.0.0")]
+    AccessGranted { patch_id: String, buyer: AccountId },
+    #[event_version("1.0.0")]
+    EarningsWithdrawn { account_id: AccountId, amount: U128 },
+}
+
 /// Patch marketplace contract
-#[derive(BorshDeserialize, BorshSerialize)]
+#[near(contract_state)]
 pub struct PatchMarketplaceContract {
     pub published_patches: UnorderedMap<String, PublishedPatch>,
     pub patch_ratings: LookupMap<String, Vec<PatchRating>>, // patch_id -> ratings
@@ -75,6 +223,11 @@ pub struct PatchMarketplaceContract {
     pub collections: UnorderedMap<String, PatchCollection>,
     pub user_patches: LookupMap<AccountId, Vec<String>>, // author -> their patch IDs
     pub featured_patches: UnorderedSet<String>,
+    pub access_grants: LookupMap<(String, AccountId), AccessGrant>, // (patch_id, buyer) -> grant
+    pub fork_parents: LookupMap<String, String>, // fork_patch_id -> original_patch_id
+    pub earnings: LookupMap<AccountId, NearToken>, // Withdrawable sale revenue
+    pub royalty_policy: RoyaltyPolicy,
+    pub patch_releases: LookupMap<String, Vec<PatchRelease>>, // patch_id -> releases, oldest first
     pub treasury_id: AccountId,
     pub platform_fee: u8, // Percentage (0-100)
 }
@@ -82,33 +235,45 @@ pub struct PatchMarketplaceContract {
 impl Default for PatchMarketplaceContract {
     fn default() -> Self {
         Self {
-            published_patches: UnorderedMap::new(b"p"),
-            patch_ratings: LookupMap::new(b"r"),
-            user_purchases: LookupMap::new(b"up"),
-            patch_forks: LookupMap::new(b"f"),
-            collections: UnorderedMap::new(b"c"),
-            user_patches: LookupMap::new(b"u"),
-            featured_patches: UnorderedSet::new(b"fp"),
+            published_patches: UnorderedMap::new(b"p".to_vec()),
+            patch_ratings: LookupMap::new(b"r".to_vec()),
+            user_purchases: LookupMap::new(b"up".to_vec()),
+            patch_forks: LookupMap::new(b"f".to_vec()),
+            collections: UnorderedMap::new(b"c".to_vec()),
+            user_patches: LookupMap::new(b"u".to_vec()),
+            featured_patches: UnorderedSet::new(b"fp".to_vec()),
+            access_grants: LookupMap::new(b"ag".to_vec()),
+            fork_parents: LookupMap::new(b"fk".to_vec()),
+            earnings: LookupMap::new(b"e".to_vec()),
+            royalty_policy: RoyaltyPolicy::default(),
+            patch_releases: LookupMap::new(b"v".to_vec()),
             treasury_id: env::predecessor_account_id(),
             platform_fee: 5, // 5% platform fee
         }
     }
 }
 
-#[near_bindgen]
+#[near]
 impl PatchMarketplaceContract {
     #[init]
     pub fn new(treasury_id: AccountId, platform_fee: Option<u8>) -> Self {
+        let platform_fee = platform_fee.unwrap_or(5);
+        assert!(platform_fee <= 100, "Platform fee cannot exceed 100%");
         Self {
-            published_patches: UnorderedMap::new(b"p"),
-            patch_ratings: LookupMap::new(b"r"),
-            user_purchases: LookupMap::new(b"up"),
-            patch_forks: LookupMap::new(b"f"),
-            collections: UnorderedMap::new(b"c"),
-            user_patches: LookupMap::new(b"u"),
-            featured_patches: UnorderedSet::new(b"fp"),
+            published_patches: UnorderedMap::new(b"p".to_vec()),
+            patch_ratings: LookupMap::new(b"r".to_vec()),
+            user_purchases: LookupMap::new(b"up".to_vec()),
+            patch_forks: LookupMap::new(b"f".to_vec()),
+            collections: UnorderedMap::new(b"c".to_vec()),
+            user_patches: LookupMap::new(b"u".to_vec()),
+            featured_patches: UnorderedSet::new(b"fp".to_vec()),
+            access_grants: LookupMap::new(b"ag".to_vec()),
+            fork_parents: LookupMap::new(b"fk".to_vec()),
+            earnings: LookupMap::new(b"e".to_vec()),
+            royalty_policy: RoyaltyPolicy::default(),
+            patch_releases: LookupMap::new(b"v".to_vec()),
             treasury_id,
-            platform_fee: platform_fee.unwrap_or(5),
+            platform_fee,
         }
     }
 
@@ -123,12 +288,20 @@ impl PatchMarketplaceContract {
         assert!(patch.rating >= 0.0 && patch.rating <= 5.0, "Invalid rating");
         assert!(!patch.title.is_empty(), "Title cannot be empty");
         assert!(!patch.ipfs_cid.is_empty(), "IPFS CID cannot be empty");
+        if patch.price.is_some() {
+            assert!(patch.encryption.is_some(), "Paid patches must be published encrypted");
+        }
+        if let Some(encryption) = &patch.encryption {
+            Self::assert_valid_encryption(encryption);
+        }
+        parse_version(&patch.version);
+        Self::assert_valid_requirements(&patch.compatibility, &patch.dependencies);
 
         // Check if patch ID already exists
         assert!(self.published_patches.get(&patch.id).is_none(), "Patch ID already exists");
 
         // Minimum deposit for publishing (0.1 NEAR)
-        let min_deposit = 100_000_000_000_000_000_000_000; // 0.1 NEAR
+        let min_deposit = NearToken::from_millinear(100); // 0.1 NEAR
         assert!(deposit >= min_deposit, "Minimum deposit: 0.1 NEAR for publishing");
 
         // Set publication timestamp
@@ -144,6 +317,19 @@ impl PatchMarketplaceContract {
         user_patches.push(patch.id.clone());
         self.user_patches.insert(&author, &user_patches);
 
+        // The listing doubles as the first release
+        let release = PatchRelease {
+            version: patch.version.clone(),
+            ipfs_cid: patch.ipfs_cid.clone(),
+            changelog: "Initial release".to_string(),
+            compatibility: patch.compatibility.clone(),
+            dependencies: patch.dependencies.clone(),
+            encryption: patch.encryption.clone(),
+            published_at: env::block_timestamp(),
+            yanked: false,
+        };
+        self.patch_releases.insert(&patch.id, &vec![release]);
+
         // Initialize empty ratings
         self.patch_ratings.insert(&patch.id, &Vec::new());
 
@@ -151,41 +337,109 @@ impl PatchMarketplaceContract {
         self.patch_forks.insert(&patch.id, &Vec::new());
 
         // Transfer deposit to treasury
-        Promise::new(self.treasury_id.clone()).transfer(deposit);
+        Promise::new(self.treasury_id.clone()).transfer(deposit).detach();
+
+        PatchEvent::PatchPublished {
+            patch_id: patch.id.clone(),
+            author,
+            version: patch.version,
+            price: patch.price.map(|price| U128(price.as_yoctonear())),
+        }
+        .emit();
 
         patch.id
     }
 
-    /// Update an existing patch
-    pub fn update_patch(&mut self, patch_id: String, updates: near_sdk::serde_json::Value) {
+    /// Update a patch's listing
+    pub fn update_patch(&mut self, patch_id: String, updates: PatchUpdate) {
         let author = env::predecessor_account_id();
 
         if let Some(mut patch) = self.published_patches.get(&patch_id) {
             // Verify ownership
             assert_eq!(patch.author, author, "Only patch author can update");
 
-            // Apply updates (simplified - in practice would parse specific fields)
-            if let Some(title) = updates.get("title").and_then(|v| v.as_str()) {
-                patch.title = title.to_string();
-            }
-            if let Some(description) = updates.get("description").and_then(|v| v.as_str()) {
-                patch.description = description.to_string();
+            if let Some(title) = updates.title {
+                assert!(!title.is_empty(), "Title cannot be empty");
+                patch.title = title;
             }
-            if let Some(version) = updates.get("version").and_then(|v| v.as_str()) {
-                patch.version = version.to_string();
+            if let Some(description) = updates.description {
+                patch.description = description;
             }
-            if let Some(ipfs_cid) = updates.get("ipfs_cid").and_then(|v| v.as_str()) {
-                patch.ipfs_cid = ipfs_cid.to_string();
+            if let Some(tags) = updates.tags {
+                patch.tags = tags;
             }
 
             patch.last_updated = env::block_timestamp();
             self.published_patches.insert(&patch_id, &patch);
+            PatchEvent::PatchUpdated { patch_id }.emit();
         } else {
             env::panic_str("Patch not found");
         }
     }
 
+    /// Publish a new version of a patch
+    ///
+    /// Releases are immutable; the version must be valid semver and newer than every
+    /// earlier release, yanked or not.
+    pub fn publish_release(&mut self, patch_id: String, release: NewRelease) {
+        let mut patch = self.published_patches.get(&patch_id).unwrap_or_else(|| env::panic_str("Patch not found"));
+        assert_eq!(patch.author, env::predecessor_account_id(), "Only patch author can publish releases");
+
+        let version = parse_version(&release.version);
+        assert!(!release.ipfs_cid.is_empty(), "IPFS CID cannot be empty");
+        assert!(!release.changelog.is_empty(), "Changelog cannot be empty");
+        Self::assert_valid_requirements(&release.compatibility, &release.dependencies);
+        if patch.price.is_some() {
+            // New ciphertext means a new content key
+            assert!(release.encryption.is_some(), "Paid patches must be published encrypted");
+        }
+        if let Some(encryption) = &release.encryption {
+            Self::assert_valid_encryption(encryption);
+        }
+
+        let mut releases = self.patch_releases.get(&patch_id).unwrap_or_default();
+        if let Some(latest) = releases.last() {
+            assert!(version > parse_version(&latest.version), "Version must be greater than {}", latest.version);
+        }
+        releases.push(PatchRelease {
+            version: release.version.clone(),
+            ipfs_cid: release.ipfs_cid,
+            changelog: release.changelog,
+            compatibility: release.compatibility,
+            dependencies: release.dependencies,
+            encryption: release.encryption,
+            published_at: env::block_timestamp(),
+            yanked: false,
+        });
+
+        Self::sync_latest(&mut patch, &releases);
+        self.patch_releases.insert(&patch_id, &releases);
+        self.published_patches.insert(&patch_id, &patch);
+
+        PatchEvent::ReleasePublished { patch_id, version: release.version }.emit();
+    }
+
+    /// Yank or restore a release; yanked releases stay downloadable but are never resolved
+    pub fn yank_release(&mut self, patch_id: String, version: String, yanked: bool) {
+        let mut patch = self.published_patches.get(&patch_id).unwrap_or_else(|| env::panic_str("Patch not found"));
+        assert_eq!(patch.author, env::predecessor_account_id(), "Only patch author can yank releases");
+
+        let mut releases = self.patch_releases.get(&patch_id).unwrap_or_default();
+        let release = releases.iter_mut()
+            .find(|r| r.version == version)
+            .unwrap_or_else(|| env::panic_str("Release not found"));
+        release.yanked = yanked;
+
+        Self::sync_latest(&mut patch, &releases);
+        self.patch_releases.insert(&patch_id, &releases);
+        self.published_patches.insert(&patch_id, &patch);
+
+        PatchEvent::ReleaseYanked { patch_id, version, yanked }.emit();
+    }
+
     /// Purchase a patch
+    ///
+    /// Any deposit above the price is credited to the buyer's earnings for withdrawal.
     #[payable]
     pub fn purchase_patch(&mut self, patch_id: String) {
         let buyer = env::predecessor_account_id();
@@ -194,17 +448,37 @@ impl PatchMarketplaceContract {
         if let Some(patch) = self.published_patches.get(&patch_id) {
             if let Some(price) = patch.price {
                 assert!(deposit >= price, "Insufficient payment");
+                let price = price.as_yoctonear();
 
                 // Calculate platform fee
                 let platform_fee = (price * self.platform_fee as u128) / 100;
                 let author_payment = price - platform_fee;
 
-                // Transfer payments
-                Promise::new(self.treasury_id.clone()).transfer(platform_fee);
-                Promise::new(patch.author.clone()).transfer(author_payment);
+                // Credit the ledger; authors and treasury withdraw on their own
+                let treasury_id = self.treasury_id.clone();
+                self.credit(&treasury_id, platform_fee);
+                let shares = self.royalty_shares(&patch_id, author_payment);
+                for (account, amount) in &shares {
+                    self.credit(account, *amount);
+                }
+                self.credit(&buyer, deposit.as_yoctonear() - price);
+
+                PatchEvent::PatchPurchased {
+                    patch_id: patch_id.clone(),
+                    buyer: buyer.clone(),
+                    price: U128(price),
+                    platform_fee: U128(platform_fee),
+                    shares: shares.iter().map(|(account, amount)| (account.clone(), U128(*amount))).collect(),
+                }
+                .emit();
 
                 // Record purchase
-                let mut user_purchases = self.user_purchases.get(&buyer).unwrap_or_else(|| UnorderedSet::new(b"usp"));
+                let mut user_purchases = self.user_purchases.get(&buyer).unwrap_or_else(|| {
+                    // Each buyer needs their own prefix, or every set would read the same storage
+                    let mut prefix = b"usp".to_vec();
+                    prefix.extend(env::sha256(buyer.as_bytes()));
+                    UnorderedSet::new(prefix)
+                });
                 user_purchases.insert(&patch_id);
                 self.user_purchases.insert(&buyer, &user_purchases);
 
@@ -224,7 +498,7 @@ impl PatchMarketplaceContract {
     pub fn rate_patch(&mut self, patch_id: String, rating: u8, review: Option<String>) {
         let rater = env::predecessor_account_id();
 
-        assert!(rating >= 1 && rating <= 5, "Rating must be between 1 and 5");
+        assert!((1..=5).contains(&rating), "Rating must be between 1 and 5");
 
         if let Some(mut patch) = self.published_patches.get(&patch_id) {
             let mut ratings = self.patch_ratings.get(&patch_id).unwrap_or_default();
@@ -234,7 +508,7 @@ impl PatchMarketplaceContract {
 
             // Add new rating
             let new_rating = PatchRating {
-                user: rater,
+                user: rater.clone(),
                 rating,
                 review,
                 timestamp: env::block_timestamp(),
@@ -249,29 +523,38 @@ impl PatchMarketplaceContract {
             // Save updates
             self.patch_ratings.insert(&patch_id, &ratings);
             self.published_patches.insert(&patch_id, &patch);
+
+            PatchEvent::PatchRated { patch_id, rater, rating }.emit();
         } else {
             env::panic_str("Patch not found");
         }
     }
 
-    /// Fork a patch
+    /// Record that a patch the caller published was forked from another patch
+    ///
+    /// The fork must already be published by the caller, so nobody can claim another
+    /// author's patch, or an ID they don't own yet, as a fork and collect its royalties.
     pub fn fork_patch(&mut self, original_patch_id: String, fork_patch_id: String, changes_summary: String) {
         let forker = env::predecessor_account_id();
 
         // Verify original patch exists
         assert!(self.published_patches.get(&original_patch_id).is_some(), "Original patch not found");
+        assert_ne!(original_patch_id, fork_patch_id, "A patch cannot fork itself");
 
-        // Verify fork ID doesn't exist
-        assert!(self.published_patches.get(&fork_patch_id).is_none(), "Fork patch ID already exists");
+        let fork_patch = self.published_patches.get(&fork_patch_id).unwrap_or_else(|| env::panic_str("Fork patch not found"));
+        assert_eq!(fork_patch.author, forker, "Only the fork's author can record it as a fork");
+        assert!(self.fork_parents.get(&fork_patch_id).is_none(), "Patch is already recorded as a fork");
 
         let fork = PatchFork {
             original_patch_id: original_patch_id.clone(),
             fork_patch_id: fork_patch_id.clone(),
-            forked_by: forker,
+            forked_by: forker.clone(),
             forked_at: env::block_timestamp(),
             changes_summary,
         };
 
+        self.fork_parents.insert(&fork_patch_id, &original_patch_id);
+
         // Add to forks list
         let mut forks = self.patch_forks.get(&original_patch_id).unwrap_or_default();
         forks.push(fork);
@@ -282,6 +565,13 @@ impl PatchMarketplaceContract {
             original_patch.fork_count += 1;
             self.published_patches.insert(&original_patch_id, &original_patch);
         }
+
+        PatchEvent::PatchForked {
+            original_patch_id,
+            fork_patch_id,
+            forked_by: forker,
+        }
+        .emit();
     }
 
     /// Create a patch collection
@@ -336,6 +626,16 @@ impl PatchMarketplaceContract {
         self.published_patches.get(&patch_id)
     }
 
+    /// Page through every published patch, in publication order
+    pub fn get_patches(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<PublishedPatch> {
+        let from_index = from_index.unwrap_or(0) as usize;
+        let limit = limit.unwrap_or(50).min(100) as usize;
+        self.published_patches.values_as_vector().iter()
+            .skip(from_index)
+            .take(limit)
+            .collect()
+    }
+
     /// Get user's patches
     pub fn get_user_patches(&self, author: AccountId) -> Vec<PublishedPatch> {
         let patch_ids = self.user_patches.get(&author).unwrap_or_default();
@@ -387,6 +687,235 @@ impl PatchMarketplaceContract {
         }
     }
 
+    /// Get every release of a patch, oldest first
+    pub fn get_releases(&self, patch_id: String) -> Vec<PatchRelease> {
+        self.patch_releases.get(&patch_id).unwrap_or_default()
+    }
+
+    /// Get one release of a patch
+    pub fn get_release(&self, patch_id: String, version: String) -> Option<PatchRelease> {
+        self.get_releases(patch_id).into_iter().find(|r| r.version == version)
+    }
+
+    /// Newest compatible release of a patch and its transitive dependencies
+    ///
+    /// Returns `None` when no unyanked release supports `tool_version`, and panics when a
+    /// dependency cannot be satisfied.
+    pub fn resolve_patch(&self, patch_id: String, tool_version: String) -> Option<Resolution> {
+        let tool_version = parse_version(&tool_version);
+        resolve(&patch_id, &tool_version, |id| self.patch_releases.get(&id.to_string()))
+            .unwrap_or_else(|err| env::panic_str(&err))
+    }
+
+    /// Withdraw accumulated sale revenue and royalties
+    ///
+    /// The balance is cleared up front so it can't be withdrawn twice while the transfer
+    /// is in flight, and credited back if the transfer fails.
+    pub fn withdraw_earnings(&mut self) -> Promise {
+        let account = env::predecessor_account_id();
+        let amount = self.earnings.get(&account).unwrap_or(NearToken::from_yoctonear(0));
+        assert!(!amount.is_zero(), "Nothing to withdraw");
+
+        self.earnings.remove(&account);
+        Promise::new(account.clone()).transfer(amount).then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_ON_EARNINGS_WITHDRAWN)
+                .on_earnings_withdrawn(account, U128(amount.as_yoctonear())),
+        )
+    }
+
+    /// Callback after a withdrawal transfer; returns whether the funds left the contract
+    #[private]
+    pub fn on_earnings_withdrawn(&mut self, account_id: AccountId, amount: U128) -> bool {
+        let transferred = env::promise_result_checked(0, 0).is_ok();
+        if transferred {
+            PatchEvent::EarningsWithdrawn { account_id, amount }.emit();
+        } else {
+            self.credit(&account_id, amount.0);
+        }
+        transferred
+    }
+
+    /// Get an account's withdrawable balance
+    pub fn get_earnings(&self, account: AccountId) -> U128 {
+        U128(self.earnings.get(&account).map_or(0, |balance| balance.as_yoctonear()))
+    }
+
+    /// Update the royalty policy (admin only)
+    pub fn set_royalty_policy(&mut self, policy: RoyaltyPolicy) {
+        assert_eq!(env::predecessor_account_id(), self.treasury_id, "Only admin can set royalty policy");
+        assert!(policy.upstream_share_bps <= 10_000, "Upstream share cannot exceed 100%");
+        assert!(policy.max_depth <= MAX_ROYALTY_DEPTH, "Royalty depth too large");
+        self.royalty_policy = policy;
+    }
+
+    pub fn get_royalty_policy(&self) -> RoyaltyPolicy {
+        self.royalty_policy.clone()
+    }
+
+    /// Get the patch a fork was made from
+    pub fn get_fork_parent(&self, patch_id: String) -> Option<String> {
+        self.fork_parents.get(&patch_id)
+    }
+
+    /// Preview how an author payment for a patch would be split
+    pub fn preview_royalties(&self, patch_id: String, amount: U128) -> Vec<(AccountId, U128)> {
+        assert!(self.published_patches.get(&patch_id).is_some(), "Patch not found");
+        let mut shares: Vec<(AccountId, U128)> = self.royalty_shares(&patch_id, amount.0)
+            .into_iter()
+            .map(|(account, amount)| (account, U128(amount)))
+            .collect();
+        shares.sort_by(|a, b| a.0.cmp(&b.0));
+        shares
+    }
+
+    /// Check if an account may download a patch's content
+    pub fn can_access(&self, account: AccountId, patch_id: String) -> bool {
+        match self.published_patches.get(&patch_id) {
+            Some(patch) => patch.price.is_none() || patch.author == account || self.has_purchased(account, patch_id),
+            None => false,
+        }
+    }
+
+    /// Ask the key service for an encrypted patch's content key
+    ///
+    /// `public_key` is the hex x25519 key the content key will be wrapped to.
+    /// Calling again replaces the key and clears any earlier grant.
+    pub fn request_access(&mut self, patch_id: String, public_key: String) {
+        let buyer = env::predecessor_account_id();
+        let patch = self.published_patches.get(&patch_id).unwrap_or_else(|| env::panic_str("Patch not found"));
+
+        assert!(patch.encryption.is_some(), "Patch is not encrypted");
+        assert!(self.can_access(buyer.clone(), patch_id.clone()), "Patch has not been purchased");
+        assert!(Self::is_hex(&public_key, 32), "Public key must be 32 bytes of hex");
+
+        let grant = AccessGrant {
+            patch_id: patch_id.clone(),
+            buyer: buyer.clone(),
+            public_key: public_key.clone(),
+            requested_at: env::block_timestamp(),
+            wrapped_key: None,
+            granted_at: None,
+        };
+        self.access_grants.insert(&(patch_id.clone(), buyer.clone()), &grant);
+
+        PatchEvent::AccessRequested { patch_id, buyer, public_key }.emit();
+    }
+
+    /// Deliver a content key wrapped to the buyer's public key (key service only)
+    pub fn grant_access(&mut self, patch_id: String, buyer: AccountId, wrapped_key: String) {
+        let patch = self.published_patches.get(&patch_id).unwrap_or_else(|| env::panic_str("Patch not found"));
+        let encryption = patch.encryption.unwrap_or_else(|| env::panic_str("Patch is not encrypted"));
+        assert_eq!(env::predecessor_account_id(), encryption.key_service, "Only the key service can grant access");
+
+        let key = (patch_id.clone(), buyer.clone());
+        let mut grant = self.access_grants.get(&key).unwrap_or_else(|| env::panic_str("No access request"));
+        // Re-checked here so a refunded or revoked purchase can't be served from a stale request
+        assert!(self.can_access(buyer.clone(), patch_id.clone()), "Patch has not been purchased");
+        assert!(!wrapped_key.is_empty() && Self::is_hex(&wrapped_key, wrapped_key.len() / 2), "Wrapped key must be hex");
+
+        grant.wrapped_key = Some(wrapped_key);
+        grant.granted_at = Some(env::block_timestamp());
+        self.access_grants.insert(&key, &grant);
+
+        PatchEvent::AccessGranted { patch_id, buyer }.emit();
+    }
+
+    /// Get a buyer's access request and wrapped key, if any
+    pub fn get_access_grant(&self, patch_id: String, buyer: AccountId) -> Option<AccessGrant> {
+        self.access_grants.get(&(patch_id, buyer))
+    }
+
+    fn credit(&mut self, account: &AccountId, amount: u128) {
+        if amount > 0 {
+            let balance = self.earnings.get(account).map_or(0, |balance| balance.as_yoctonear());
+            self.earnings.insert(account, &NearToken::from_yoctonear(balance + amount));
+        }
+    }
+
+    /// Split `amount` between a patch's author and its upstream authors
+    fn royalty_shares(&self, patch_id: &str, amount: u128) -> HashMap<AccountId, u128> {
+        let mut shares = HashMap::new();
+        let mut visited = HashSet::new();
+        visited.insert(patch_id.to_string());
+        self.distribute(patch_id, amount, 0, &mut visited, &mut shares);
+        shares
+    }
+
+    /// Each patch is paid at most once per sale, so cycles and diamonds in the graph terminate
+    fn distribute(
+        &self,
+        patch_id: &str,
+        amount: u128,
+        depth: u8,
+        visited: &mut HashSet<String>,
+        shares: &mut HashMap<AccountId, u128>,
+    ) {
+        let patch = match self.published_patches.get(&patch_id.to_string()) {
+            Some(patch) => patch,
+            None => return,
+        };
+
+        let mut upstream = Vec::new();
+        if depth < self.royalty_policy.max_depth.min(MAX_ROYALTY_DEPTH) {
+            let dependencies = patch.dependencies.iter().map(|dep| split_dependency(dep).0.to_string());
+            let parents = self.fork_parents.get(&patch.id).into_iter().chain(dependencies);
+            for parent in parents {
+                if !visited.contains(&parent) && self.published_patches.get(&parent).is_some() {
+                    visited.insert(parent.clone());
+                    upstream.push(parent);
+                }
+            }
+        }
+
+        let mut kept = amount;
+        if !upstream.is_empty() {
+            let pool = amount * self.royalty_policy.upstream_share_bps as u128 / 10_000;
+            let per_parent = pool / upstream.len() as u128;
+            for parent in &upstream {
+                self.distribute(parent, per_parent, depth + 1, visited, shares);
+            }
+            kept -= per_parent * upstream.len() as u128;
+        }
+
+        *shares.entry(patch.author).or_insert(0) += kept;
+    }
+
+    /// Point the listing at the newest unyanked release
+    fn sync_latest(patch: &mut PublishedPatch, releases: &[PatchRelease]) {
+        if let Some(latest) = releases.iter().rev().find(|r| !r.yanked) {
+            patch.version = latest.version.clone();
+            patch.ipfs_cid = latest.ipfs_cid.clone();
+            patch.compatibility = latest.compatibility.clone();
+            patch.dependencies = latest.dependencies.clone();
+            patch.encryption = latest.encryption.clone();
+        }
+        patch.last_updated = env::block_timestamp();
+    }
+
+    fn assert_valid_requirements(compatibility: &[String], dependencies: &[String]) {
+        for requirement in compatibility {
+            assert!(parse_requirement(requirement).is_some(), "Invalid compatibility requirement: {}", requirement);
+        }
+        for dependency in dependencies {
+            let (id, requirement) = split_dependency(dependency);
+            assert!(!id.is_empty(), "Dependency needs a patch ID");
+            if let Some(requirement) = requirement {
+                assert!(parse_requirement(requirement).is_some(), "Invalid dependency requirement: {}", dependency);
+            }
+        }
+    }
+
+    fn assert_valid_encryption(encryption: &PatchEncryption) {
+        assert!(!encryption.algorithm.is_empty(), "Encryption algorithm cannot be empty");
+        assert!(Self::is_hex(&encryption.key_commitment, 32), "Key commitment must be a hex SHA-256");
+        assert!(Self::is_hex(&encryption.plaintext_sha256, 32), "Plaintext hash must be a hex SHA-256");
+    }
+
+    fn is_hex(value: &str, bytes: usize) -> bool {
+        value.len() == bytes * 2 && value.chars().all(|c| c.is_ascii_hexdigit())
+    }
+
     /// Get marketplace stats
     pub fn get_marketplace_stats(&self) -> near_sdk::serde_json::Value {
         let total_patches = self.published_patches.len();
@@ -402,6 +931,96 @@ impl PatchMarketplaceContract {
     }
 }
 
+fn parse_version(version: &str) -> Version {
+    Version::parse(version).unwrap_or_else(|_| env::panic_str(&format!("Invalid semver version: {}", version)))
+}
+
+/// Parse a semver requirement, also accepting the legacy "v1.0+" form
+pub fn parse_requirement(requirement: &str) -> Option<VersionReq> {
+    let requirement = requirement.trim();
+    match requirement.strip_prefix('v').and_then(|r| r.strip_suffix('+')) {
+        Some(minimum) => VersionReq::parse(&format!(">={}", minimum)).ok(),
+        None => VersionReq::parse(requirement).ok(),
+    }
+}
+
+/// Split "patch_id@requirement" into its parts
+pub fn split_dependency(dependency: &str) -> (&str, Option<&str>) {
+    match dependency.split_once('@') {
+        Some((id, requirement)) => (id, Some(requirement)),
+        None => (dependency, None),
+    }
+}
+
+/// An empty compatibility list supports every tool version
+fn supports_tool(release: &PatchRelease, tool_version: &Version) -> bool {
+    release.compatibility.is_empty()
+        || release.compatibility.iter()
+            .filter_map(|r| parse_requirement(r))
+            .any(|r| r.matches(tool_version))
+}
+
+/// Newest unyanked release matching the tool version and an optional requirement
+fn newest_release(releases: &[PatchRelease], tool_version: &Version, requirement: Option<&VersionReq>) -> Option<PatchRelease> {
+    releases.iter()
+        .filter(|r| !r.yanked && supports_tool(r, tool_version))
+        .filter_map(|r| Version::parse(&r.version).ok().map(|v| (v, r)))
+        .filter(|(v, _)| requirement.is_none_or(|req| req.matches(v)))
+        .max_by(|a, b| a.0.cmp(&b.0))
+        .map(|(_, r)| r.clone())
+}
+
+/// Greedy breadth-first resolution: each patch gets the newest release that fits the
+/// first requirement on it, and later requirements must accept that choice
+fn resolve<F>(patch_id: &str, tool_version: &Version, releases_of: F) -> Result<Option<Resolution>, String>
+where
+    F: Fn(&str) -> Option<Vec<PatchRelease>>,
+{
+    let root = match releases_of(patch_id).and_then(|releases| newest_release(&releases, tool_version, None)) {
+        Some(release) => release,
+        None => return Ok(None),
+    };
+
+    let mut chosen: HashMap<String, Version> = HashMap::new();
+    chosen.insert(patch_id.to_string(), parse_version(&root.version));
+    let mut dependencies = Vec::new();
+    let mut queue: VecDeque<PatchRelease> = VecDeque::from([root.clone()]);
+
+    while let Some(release) = queue.pop_front() {
+        for dependency in &release.dependencies {
+            let (id, requirement) = split_dependency(dependency);
+            let requirement = requirement.and_then(parse_requirement);
+
+            if let Some(version) = chosen.get(id) {
+                if requirement.as_ref().is_none_or(|req| req.matches(version)) {
+                    continue;
+                }
+                return Err(format!("Conflicting requirements on {}: {} does not match {}", id, version, dependency));
+            }
+
+            let picked = releases_of(id)
+                .and_then(|releases| newest_release(&releases, tool_version, requirement.as_ref()))
+                .ok_or_else(|| format!("No compatible release of dependency {}", dependency))?;
+            chosen.insert(id.to_string(), parse_version(&picked.version));
+            dependencies.push(ResolvedPatch {
+                patch_id: id.to_string(),
+                version: picked.version.clone(),
+                ipfs_cid: picked.ipfs_cid.clone(),
+            });
+            queue.push_back(picked);
+        }
+    }
+
+    Ok(Some(Resolution {
+        patch: ResolvedPatch {
+            patch_id: patch_id.to_string(),
+            version: root.version,
+            ipfs_cid: root.ipfs_cid,
+        },
+        dependencies,
+    }))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -413,10 +1032,58 @@ mod tests {
         builder.current_account_id("contract.testnet".parse().unwrap());
         builder.signer_account_id("alice.testnet".parse().unwrap());
         builder.predecessor_account_id("alice.testnet".parse().unwrap());
-        builder.attached_deposit(100_000_000_000_000_000_000_000); // 0.1 NEAR
+        builder.attached_deposit(NearToken::from_millinear(100));
         builder
     }
 
+    fn test_encryption() -> PatchEncryption {
+        PatchEncryption {
+            algorithm: "xchacha20poly1305".to_string(),
+            key_commitment: "ab".repeat(32),
+            plaintext_sha256: "cd".repeat(32),
+            key_service: "keys.testnet".parse().unwrap(),
+        }
+    }
+
+    fn paid_patch(id: &str, encryption: Option<PatchEncryption>) -> PublishedPatch {
+        PublishedPatch {
+            id: id.to_string(),
+            title: "Paid Patch".to_string(),
+            description: "Encrypted patch".to_string(),
+            author: "alice.testnet".parse().unwrap(),
+            tool_type: "fractal_shader".to_string(),
+            version: "1.0.0".to_string(),
+            tags: vec![],
+            ipfs_cid: "bafyCiphertext".to_string(),
+            license: "Commercial".to_string(),
+            price: Some(NearToken::from_near(1)),
+            downloads: 0,
+            rating: 0.0,
+            total_ratings: 0,
+            published_at: 0,
+            last_updated: 0,
+            fork_count: 0,
+            dependencies: vec![],
+            compatibility: vec![],
+            encryption,
+        }
+    }
+
+    fn free_patch(id: &str, author: &str, dependencies: Vec<&str>) -> PublishedPatch {
+        let mut patch = paid_patch(id, None);
+        patch.author = author.parse().unwrap();
+        patch.price = None;
+        patch.dependencies = dependencies.into_iter().map(String::from).collect();
+        patch
+    }
+
+    fn set_caller(account: &str, deposit: NearToken) {
+        let mut context = get_context();
+        context.predecessor_account_id(account.parse().unwrap());
+        context.attached_deposit(deposit);
+        testing_env!(context.build());
+    }
+
     #[test]
     fn test_publish_patch() {
         let context = get_context().build();
@@ -434,7 +1101,7 @@ mod tests {
             tags: vec!["fractal".to_string(), "shader".to_string()],
             ipfs_cid: "QmTest123".to_string(),
             license: "MIT".to_string(),
-            price: Some(1_000_000_000_000_000_000_000_000), // 1 NEAR
+            price: Some(NearToken::from_near(1)),
             downloads: 0,
             rating: 0.0,
             total_ratings: 0,
@@ -443,6 +1110,7 @@ mod tests {
             fork_count: 0,
             dependencies: vec![],
             compatibility: vec!["v1.0+".to_string()],
+            encryption: Some(test_encryption()),
         };
 
         let patch_id = contract.publish_patch(patch);
@@ -480,6 +1148,7 @@ mod tests {
             fork_count: 0,
             dependencies: vec![],
             compatibility: vec![],
+            encryption: None,
         };
 
         contract.publish_patch(patch);
@@ -494,5 +1163,221 @@ mod tests {
         let patch = contract.get_patch("rate_test".to_string()).unwrap();
         assert_eq!(patch.rating, 5.0);
         assert_eq!(patch.total_ratings, 1);
+
+        let logs = near_sdk::test_utils::get_logs();
+        assert!(logs[0].starts_with(r#"EVENT_JSON:{"standard":"patch_marketplace","version":"1.0.0","event":"patch_published""#));
+        assert_eq!(
+            logs[1],
+            r#"EVENT_JSON:{"standard":"patch_marketplace","version":"1.0.0","event":"patch_rated","data":{"patch_id":"rate_test","rater":"alice.testnet","rating":5}}"#
+        );
     }
-}
\ No newline at end of file
+
+    #[test]
+    #[should_panic(expected = "Paid patches must be published encrypted")]
+    fn test_paid_patch_requires_encryption() {
+        testing_env!(get_context().build());
+        let mut contract = PatchMarketplaceContract::default();
+        contract.publish_patch(paid_patch("plain", None));
+    }
+
+    #[test]
+    fn test_access_grant_flow() {
+        testing_env!(get_context().build());
+        let mut contract = PatchMarketplaceContract::default();
+        contract.publish_patch(paid_patch("paid", Some(test_encryption())));
+
+        set_caller("bob.testnet", NearToken::from_near(1));
+        contract.purchase_patch("paid".to_string());
+        contract.request_access("paid".to_string(), "11".repeat(32));
+
+        // Purchases are per buyer: carol has bought nothing
+        assert!(!contract.can_access("carol.testnet".parse().unwrap(), "paid".to_string()));
+
+        set_caller("keys.testnet", NearToken::from_yoctonear(0));
+        contract.grant_access("paid".to_string(), "bob.testnet".parse().unwrap(), "22".repeat(104));
+
+        let grant = contract.get_access_grant("paid".to_string(), "bob.testnet".parse().unwrap()).unwrap();
+        assert_eq!(grant.public_key, "11".repeat(32));
+        assert_eq!(grant.wrapped_key, Some("22".repeat(104)));
+        assert!(grant.granted_at.is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "Patch has not been purchased")]
+    fn test_request_access_requires_purchase() {
+        testing_env!(get_context().build());
+        let mut contract = PatchMarketplaceContract::default();
+        contract.publish_patch(paid_patch("paid", Some(test_encryption())));
+
+        set_caller("carol.testnet", NearToken::from_yoctonear(0));
+        contract.request_access("paid".to_string(), "11".repeat(32));
+    }
+
+    #[test]
+    fn test_royalties_follow_forks_and_dependencies() {
+        const NEAR: u128 = 1_000_000_000_000_000_000_000_000;
+        testing_env!(get_context().build());
+        let mut contract = PatchMarketplaceContract::default();
+
+        // carol's base is a dependency of dave's fork of alice's paid patch
+        set_caller("carol.testnet", NearToken::from_yoctonear(NEAR / 10));
+        contract.publish_patch(free_patch("base", "carol.testnet", vec![]));
+        set_caller("alice.testnet", NearToken::from_yoctonear(NEAR / 10));
+        contract.publish_patch(paid_patch("paid", Some(test_encryption())));
+
+        set_caller("dave.testnet", NearToken::from_yoctonear(NEAR / 10));
+        let mut fork = paid_patch("fork", Some(test_encryption()));
+        fork.author = "dave.testnet".parse().unwrap();
+        fork.dependencies = vec!["base".to_string()];
+        contract.publish_patch(fork);
+        contract.fork_patch("paid".to_string(), "fork".to_string(), "Faster".to_string());
+
+        set_caller("bob.testnet", NearToken::from_yoctonear(NEAR + NEAR / 4));
+        contract.purchase_patch("fork".to_string());
+
+        // 5% fee, then 10% of the rest split between the fork parent and the dependency
+        let author_payment = NEAR * 95 / 100;
+        let upstream = author_payment / 10 / 2;
+        // alice is also the treasury in the default contract
+        assert_eq!(contract.get_earnings("alice.testnet".parse().unwrap()).0, NEAR * 5 / 100 + upstream);
+        assert_eq!(contract.get_earnings("carol.testnet".parse().unwrap()).0, upstream);
+        assert_eq!(contract.get_earnings("dave.testnet".parse().unwrap()).0, author_payment - 2 * upstream);
+        // The overpayment is not stranded: bob can withdraw it
+        assert_eq!(contract.get_earnings("bob.testnet".parse().unwrap()).0, NEAR / 4);
+
+        set_caller("dave.testnet", NearToken::from_yoctonear(0));
+        contract.withdraw_earnings().detach();
+        assert_eq!(contract.get_earnings("dave.testnet".parse().unwrap()).0, 0);
+    }
+
+    #[test]
+    fn test_failed_withdrawal_restores_earnings() {
+        testing_env!(get_context().build());
+        let mut contract = PatchMarketplaceContract::default();
+        contract.publish_patch(paid_patch("paid", Some(test_encryption())));
+        set_caller("bob.testnet", NearToken::from_near(1));
+        contract.purchase_patch("paid".to_string());
+
+        let alice: AccountId = "alice.testnet".parse().unwrap();
+        let earned = contract.get_earnings(alice.clone());
+        set_caller("alice.testnet", NearToken::from_yoctonear(0));
+        contract.withdraw_earnings().detach();
+        assert_eq!(contract.get_earnings(alice.clone()).0, 0);
+
+        // The transfer bounced, e.g. because the account was deleted meanwhile
+        let mut context = get_context();
+        context.predecessor_account_id("contract.testnet".parse().unwrap());
+        testing_env!(
+            context.build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![near_sdk::PromiseResult::Failed]
+        );
+        assert!(!contract.on_earnings_withdrawn(alice.clone(), earned));
+        assert_eq!(contract.get_earnings(alice), earned);
+        assert!(near_sdk::test_utils::get_logs().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Fork patch not found")]
+    fn test_unpublished_fork_id_cannot_be_claimed() {
+        testing_env!(get_context().build());
+        let mut contract = PatchMarketplaceContract::default();
+        contract.publish_patch(free_patch("base", "alice.testnet", vec![]));
+
+        // mallory squats on an ID before its real author publishes it
+        set_caller("mallory.testnet", NearToken::from_yoctonear(0));
+        contract.fork_patch("base".to_string(), "upcoming".to_string(), "Mine".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the fork's author can record it as a fork")]
+    fn test_fork_requires_fork_author() {
+        testing_env!(get_context().build());
+        let mut contract = PatchMarketplaceContract::default();
+        contract.publish_patch(free_patch("base", "alice.testnet", vec![]));
+        set_caller("bob.testnet", NearToken::from_millinear(100));
+        contract.publish_patch(free_patch("other", "bob.testnet", vec![]));
+
+        set_caller("mallory.testnet", NearToken::from_yoctonear(0));
+        contract.fork_patch("base".to_string(), "other".to_string(), "Mine".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Platform fee cannot exceed 100%")]
+    fn test_platform_fee_is_capped() {
+        testing_env!(get_context().build());
+        PatchMarketplaceContract::new("treasury.testnet".parse().unwrap(), Some(101));
+    }
+
+    #[test]
+    fn test_royalties_terminate_on_cycles() {
+        testing_env!(get_context().build());
+        let mut contract = PatchMarketplaceContract::default();
+        contract.publish_patch(free_patch("a", "alice.testnet", vec!["b"]));
+        contract.publish_patch(free_patch("b", "alice.testnet", vec!["a"]));
+
+        let shares = contract.preview_royalties("a".to_string(), U128(1_000));
+        assert_eq!(shares, vec![("alice.testnet".parse().unwrap(), U128(1_000))]);
+    }
+
+    fn release(version: &str, compatibility: Vec<&str>, dependencies: Vec<&str>) -> NewRelease {
+        NewRelease {
+            version: version.to_string(),
+            ipfs_cid: format!("bafy-{}", version),
+            changelog: format!("Release {}", version),
+            compatibility: compatibility.into_iter().map(String::from).collect(),
+            dependencies: dependencies.into_iter().map(String::from).collect(),
+            encryption: None,
+        }
+    }
+
+    #[test]
+    fn test_releases_are_ordered_and_yankable() {
+        testing_env!(get_context().build());
+        let mut contract = PatchMarketplaceContract::default();
+        contract.publish_patch(free_patch("lib", "alice.testnet", vec![]));
+        contract.publish_release("lib".to_string(), release("1.1.0", vec![], vec![]));
+        assert_eq!(contract.get_patch("lib".to_string()).unwrap().version, "1.1.0");
+
+        contract.yank_release("lib".to_string(), "1.1.0".to_string(), true);
+        let patch = contract.get_patch("lib".to_string()).unwrap();
+        assert_eq!(patch.version, "1.0.0");
+        assert_eq!(contract.get_releases("lib".to_string()).len(), 2);
+        assert!(contract.get_release("lib".to_string(), "1.1.0".to_string()).unwrap().yanked);
+    }
+
+    #[test]
+    #[should_panic(expected = "Version must be greater than 1.0.0")]
+    fn test_release_versions_must_increase() {
+        testing_env!(get_context().build());
+        let mut contract = PatchMarketplaceContract::default();
+        contract.publish_patch(free_patch("lib", "alice.testnet", vec![]));
+        contract.publish_release("lib".to_string(), release("0.9.0", vec![], vec![]));
+    }
+
+    #[test]
+    fn test_resolve_picks_newest_compatible_releases() {
+        testing_env!(get_context().build());
+        let mut contract = PatchMarketplaceContract::default();
+        contract.publish_patch(free_patch("lib", "alice.testnet", vec![]));
+        contract.publish_release("lib".to_string(), release("1.4.0", vec!["^2.0"], vec![]));
+        contract.publish_release("lib".to_string(), release("2.0.0", vec!["^2.0"], vec![]));
+        contract.publish_patch(free_patch("app", "alice.testnet", vec![]));
+        contract.publish_release("app".to_string(), release("1.1.0", vec!["v2.0+"], vec!["lib@^1.0"]));
+
+        let resolution = contract.resolve_patch("app".to_string(), "2.3.0".to_string()).unwrap();
+        assert_eq!(resolution.patch.version, "1.1.0");
+        assert_eq!(resolution.dependencies, vec![ResolvedPatch {
+            patch_id: "lib".to_string(),
+            version: "1.4.0".to_string(),
+            ipfs_cid: "bafy-1.4.0".to_string(),
+        }]);
+
+        // app 1.0.0 has no requirements, so an older tool still resolves it
+        let resolution = contract.resolve_patch("app".to_string(), "1.0.0".to_string()).unwrap();
+        assert_eq!(resolution.patch.version, "1.0.0");
+        assert!(resolution.dependencies.is_empty());
+    }
+}
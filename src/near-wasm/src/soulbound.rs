@@ -1,10 +1,33 @@
 //! Soulbound token functionality for creative identity
+//!
+//! `SoulboundContract` exposes the NEP-171/177/178/181 interfaces so wallets and
+//! indexers can show identity tokens, but every transfer and approval panics.
+//! Only the issuer that minted a token can revoke it, move it to a recovery
+//! account or renew its expiry. Marketplaces call `nft_is_soulbound` to refuse
+//! listings.
+
+use std::collections::HashMap;
 
 use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::collections::{LazyOption, LookupMap, UnorderedMap, UnorderedSet};
+use near_sdk::json_types::U128;
 use near_sdk::serde::{Deserialize, Serialize};
-use near_sdk::{env, AccountId, Timestamp};
-use near_contract_standards::non_fungible_token::metadata::TokenMetadata;
-use near_contract_standards::non_fungible_token::TokenId;
+use near_sdk::{env, near, AccountId, NearToken, PanicOnDefault, Promise, PromiseOrValue, Timestamp};
+use near_contract_standards::non_fungible_token::approval::NonFungibleTokenApproval;
+use near_contract_standards::non_fungible_token::core::NonFungibleTokenCore;
+use near_contract_standards::non_fungible_token::enumeration::NonFungibleTokenEnumeration;
+use near_contract_standards::non_fungible_token::events::{NftBurn, NftMint, NftTransfer};
+use near_contract_standards::non_fungible_token::metadata::{
+    NFTContractMetadata, NonFungibleTokenMetadataProvider, TokenMetadata,
+};
+use near_contract_standards::non_fungible_token::{Token, TokenId};
+
+/// Panic message for every NEP-171/178 method that would move a token
+const NOT_TRANSFERABLE: &str = "Soulbound tokens cannot be transferred or approved";
+
+/// Default and maximum page sizes for the NEP-181 enumeration views
+const DEFAULT_PAGE_SIZE: u64 = 50;
+const MAX_PAGE_SIZE: u64 = 100;
 
 /// Soulbound token representing creative identity
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
@@ -16,6 +39,8 @@ pub struct SoulboundToken {
     pub identity_data: IdentityData,
     pub minted_at: Timestamp,
     pub soulbound: bool,
+    pub issuer_id: AccountId,            // Only the issuer can revoke, recover or renew
+    pub expires_at: Option<Timestamp>,   // Block timestamp after which the token is no longer valid
 }
 
 /// Identity data for soulbound tokens
@@ -53,10 +78,338 @@ impl SoulboundToken {
             identity_data,
             minted_at: env::block_timestamp(),
             soulbound: true,
+            issuer_id: env::predecessor_account_id(),
+            expires_at: None,
+        }
+    }
+
+    /// Whether the token has passed its expiry at block time `now`
+    pub fn is_expired(&self, now: Timestamp) -> bool {
+        self.expires_at.is_some_and(|expires_at| now >= expires_at)
+    }
+
+    /// NEP-171 view; `metadata.expires_at` mirrors the expiry in milliseconds
+    fn to_nft_token(&self) -> Token {
+        let mut metadata = self.metadata.clone();
+        metadata.expires_at = self.expires_at.map(|expires_at| (expires_at / 1_000_000).to_string());
+        Token {
+            token_id: self.token_id.clone(),
+            owner_id: self.owner_id.clone(),
+            metadata: Some(metadata),
+            approved_account_ids: Some(HashMap::new()),
+        }
+    }
+}
+
+/// Non-transferable identity tokens minted by trusted issuers
+#[near(contract_state)]
+#[derive(PanicOnDefault)]
+pub struct SoulboundContract {
+    owner_id: AccountId,
+    issuers: UnorderedSet<AccountId>,
+    tokens: UnorderedMap<TokenId, SoulboundToken>,
+    tokens_per_owner: LookupMap<AccountId, UnorderedSet<TokenId>>,
+    metadata: LazyOption<NFTContractMetadata>,
+}
+
+#[near]
+impl SoulboundContract {
+    #[init]
+    pub fn new(owner_id: AccountId, metadata: NFTContractMetadata) -> Self {
+        Self {
+            owner_id,
+            issuers: UnorderedSet::new(b"i".to_vec()),
+            tokens: UnorderedMap::new(b"t".to_vec()),
+            tokens_per_owner: LookupMap::new(b"o".to_vec()),
+            metadata: LazyOption::new(b"m".to_vec(), Some(&metadata)),
+        }
+    }
+
+    /// Allow an account to mint identity tokens (contract owner only)
+    pub fn add_issuer(&mut self, issuer_id: AccountId) {
+        self.assert_contract_owner();
+        self.issuers.insert(&issuer_id);
+    }
+
+    /// Stop an account from minting; tokens it already issued stay under its control
+    pub fn remove_issuer(&mut self, issuer_id: AccountId) -> bool {
+        self.assert_contract_owner();
+        self.issuers.remove(&issuer_id)
+    }
+
+    pub fn get_issuers(&self) -> Vec<AccountId> {
+        self.issuers.to_vec()
+    }
+
+    /// Mint an identity token to `owner_id` (issuers only)
+    /// The attached deposit must cover storage; the excess is refunded
+    #[payable]
+    pub fn sbt_mint(
+        &mut self,
+        token_id: TokenId,
+        owner_id: AccountId,
+        metadata: TokenMetadata,
+        identity_data: IdentityData,
+        expires_at: Option<Timestamp>,
+    ) -> Token {
+        let issuer_id = env::predecessor_account_id();
+        assert!(self.issuers.contains(&issuer_id), "Only registered issuers can mint");
+        let initial_storage = env::storage_usage();
+
+        let mut token = SoulboundToken::new(token_id.clone(), owner_id.clone(), metadata, identity_data);
+        token.expires_at = expires_at;
+        assert!(self.tokens.insert(&token_id, &token).is_none(), "Token already exists");
+        self.internal_add_token_to_owner(&owner_id, &token_id);
+
+        NftMint {
+            owner_id: &owner_id,
+            token_ids: &[&token_id],
+            memo: None,
+        }
+        .emit();
+
+        Self::refund_deposit(env::storage_usage() - initial_storage);
+        token.to_nft_token()
+    }
+
+    /// Burn a token the caller issued, e.g. when the credential is withdrawn
+    pub fn sbt_revoke(&mut self, token_id: TokenId, memo: Option<String>) {
+        let token = self.internal_issued_token(&token_id);
+        let initial_storage = env::storage_usage();
+
+        self.tokens.remove(&token_id);
+        self.internal_remove_token_from_owner(&token.owner_id, &token_id);
+
+        NftBurn {
+            owner_id: &token.owner_id,
+            token_ids: &[&token_id],
+            authorized_id: Some(&token.issuer_id),
+            memo: memo.as_deref(),
+        }
+        .emit();
+
+        // Storage goes back to the issuer, who paid for it at mint
+        let storage_released = initial_storage.saturating_sub(env::storage_usage());
+        if storage_released > 0 {
+            Promise::new(token.issuer_id)
+                .transfer(env::storage_byte_cost().saturating_mul(storage_released as u128))
+                .detach();
+        }
+    }
+
+    /// Move every token the caller issued to `old_owner_id` over to `new_owner_id`
+    /// For holders who lost access to their account; returns the moved token IDs
+    pub fn sbt_recover(&mut self, old_owner_id: AccountId, new_owner_id: AccountId) -> Vec<TokenId> {
+        let issuer_id = env::predecessor_account_id();
+        assert_ne!(old_owner_id, new_owner_id, "Recovery account must differ from the old account");
+
+        let token_ids: Vec<TokenId> = self
+            .tokens_per_owner
+            .get(&old_owner_id)
+            .map(|tokens| tokens.to_vec())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|token_id| self.tokens.get(token_id).is_some_and(|token| token.issuer_id == issuer_id))
+            .collect();
+        assert!(!token_ids.is_empty(), "No tokens issued by the caller to recover");
+
+        for token_id in &token_ids {
+            let mut token = self.tokens.get(token_id).unwrap();
+            token.owner_id = new_owner_id.clone();
+            self.tokens.insert(token_id, &token);
+            self.internal_remove_token_from_owner(&old_owner_id, token_id);
+            self.internal_add_token_to_owner(&new_owner_id, token_id);
+        }
+
+        let ids: Vec<&str> = token_ids.iter().map(String::as_str).collect();
+        NftTransfer {
+            old_owner_id: &old_owner_id,
+            new_owner_id: &new_owner_id,
+            token_ids: &ids,
+            authorized_id: Some(&issuer_id),
+            memo: Some("recovery"),
+        }
+        .emit();
+
+        token_ids
+    }
+
+    /// Change or clear the expiry of a token the caller issued
+    pub fn sbt_renew(&mut self, token_id: TokenId, expires_at: Option<Timestamp>) {
+        let mut token = self.internal_issued_token(&token_id);
+        token.expires_at = expires_at;
+        self.tokens.insert(&token_id, &token);
+    }
+
+    /// Marketplaces call this before listing; every token minted here is soulbound
+    pub fn nft_is_soulbound(&self, token_id: TokenId) -> bool {
+        self.tokens.get(&token_id).is_some_and(|token| token.soulbound)
+    }
+
+    /// Whether the token exists and has not expired
+    pub fn sbt_is_valid(&self, token_id: TokenId) -> bool {
+        self.tokens
+            .get(&token_id)
+            .is_some_and(|token| !token.is_expired(env::block_timestamp()))
+    }
+
+    /// Full identity record, including issuer and expiry
+    pub fn get_soulbound_token(&self, token_id: TokenId) -> Option<SoulboundToken> {
+        self.tokens.get(&token_id)
+    }
+
+    /// Highest reputation score among the account's valid tokens; 0 without one
+    pub fn sbt_reputation(&self, account_id: AccountId) -> f32 {
+        let now = env::block_timestamp();
+        self.tokens_per_owner.get(&account_id).map_or(0.0, |token_ids| {
+            token_ids
+                .iter()
+                .filter_map(|token_id| self.tokens.get(&token_id))
+                .filter(|token| !token.is_expired(now))
+                .map(|token| token.identity_data.reputation_score)
+                .fold(0.0, f32::max)
+        })
+    }
+}
+
+impl SoulboundContract {
+    fn assert_contract_owner(&self) {
+        assert_eq!(env::predecessor_account_id(), self.owner_id, "Only contract owner can manage issuers");
+    }
+
+    /// Load a token, checking that the caller issued it
+    fn internal_issued_token(&self, token_id: &TokenId) -> SoulboundToken {
+        let token = self.tokens.get(token_id).expect("Token not found");
+        assert_eq!(env::predecessor_account_id(), token.issuer_id, "Only the issuer can manage this token");
+        token
+    }
+
+    fn internal_add_token_to_owner(&mut self, account_id: &AccountId, token_id: &TokenId) {
+        let mut tokens = self.tokens_per_owner.get(account_id).unwrap_or_else(|| {
+            UnorderedSet::new([b"s".as_slice(), &env::sha256(account_id.as_bytes())].concat())
+        });
+        tokens.insert(token_id);
+        self.tokens_per_owner.insert(account_id, &tokens);
+    }
+
+    fn internal_remove_token_from_owner(&mut self, account_id: &AccountId, token_id: &TokenId) {
+        let mut tokens = self.tokens_per_owner.get(account_id).expect("Token owner has no tokens");
+        tokens.remove(token_id);
+        if tokens.is_empty() {
+            self.tokens_per_owner.remove(account_id);
+        } else {
+            self.tokens_per_owner.insert(account_id, &tokens);
+        }
+    }
+
+    /// Charge `storage_used` bytes to the attached deposit and refund the excess
+    fn refund_deposit(storage_used: u64) {
+        let required_deposit = env::storage_byte_cost().saturating_mul(storage_used as u128);
+        let attached = env::attached_deposit();
+        assert!(attached >= required_deposit, "Not enough deposit for storage");
+
+        let refund = attached.saturating_sub(required_deposit);
+        if refund > NearToken::from_yoctonear(0) {
+            Promise::new(env::predecessor_account_id()).transfer(refund).detach();
         }
     }
 }
 
+#[near]
+impl NonFungibleTokenCore for SoulboundContract {
+    #[payable]
+    fn nft_transfer(
+        &mut self,
+        _receiver_id: AccountId,
+        _token_id: TokenId,
+        _approval_id: Option<u64>,
+        _memo: Option<String>,
+    ) {
+        env::panic_str(NOT_TRANSFERABLE)
+    }
+
+    #[payable]
+    fn nft_transfer_call(
+        &mut self,
+        _receiver_id: AccountId,
+        _token_id: TokenId,
+        _approval_id: Option<u64>,
+        _memo: Option<String>,
+        _msg: String,
+    ) -> PromiseOrValue<bool> {
+        env::panic_str(NOT_TRANSFERABLE)
+    }
+
+    fn nft_token(&self, token_id: TokenId) -> Option<Token> {
+        self.tokens.get(&token_id).map(|token| token.to_nft_token())
+    }
+}
+
+#[near]
+impl NonFungibleTokenApproval for SoulboundContract {
+    #[payable]
+    fn nft_approve(&mut self, _token_id: TokenId, _account_id: AccountId, _msg: Option<String>) -> Option<Promise> {
+        env::panic_str(NOT_TRANSFERABLE)
+    }
+
+    #[payable]
+    fn nft_revoke(&mut self, _token_id: TokenId, _account_id: AccountId) {
+        env::panic_str(NOT_TRANSFERABLE)
+    }
+
+    #[payable]
+    fn nft_revoke_all(&mut self, _token_id: TokenId) {
+        env::panic_str(NOT_TRANSFERABLE)
+    }
+
+    fn nft_is_approved(&self, _token_id: TokenId, _approved_account_id: AccountId, _approval_id: Option<u64>) -> bool {
+        false
+    }
+}
+
+#[near]
+impl NonFungibleTokenEnumeration for SoulboundContract {
+    fn nft_total_supply(&self) -> U128 {
+        U128(self.tokens.len() as u128)
+    }
+
+    fn nft_tokens(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<Token> {
+        let start = from_index.map_or(0, |index| index.0 as usize);
+        let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE) as usize;
+        self.tokens
+            .values()
+            .skip(start)
+            .take(limit)
+            .map(|token| token.to_nft_token())
+            .collect()
+    }
+
+    fn nft_supply_for_owner(&self, account_id: AccountId) -> U128 {
+        U128(self.tokens_per_owner.get(&account_id).map_or(0, |tokens| tokens.len() as u128))
+    }
+
+    fn nft_tokens_for_owner(&self, account_id: AccountId, from_index: Option<U128>, limit: Option<u64>) -> Vec<Token> {
+        let Some(tokens) = self.tokens_per_owner.get(&account_id) else {
+            return vec![];
+        };
+        let start = from_index.map_or(0, |index| index.0 as usize);
+        let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE) as usize;
+        tokens
+            .iter()
+            .skip(start)
+            .take(limit)
+            .filter_map(|token_id| self.nft_token(token_id))
+            .collect()
+    }
+}
+
+#[near]
+impl NonFungibleTokenMetadataProvider for SoulboundContract {
+    fn nft_metadata(&self) -> NFTContractMetadata {
+        self.metadata.get().unwrap()
+    }
+}
+
 impl Default for IdentityData {
     fn default() -> Self {
         Self {
@@ -134,4 +487,98 @@ mod tests {
         assert_eq!(profile.experience_level, "beginner");
         assert_eq!(profile.preferred_medium, "digital");
     }
-}
\ No newline at end of file
+
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::testing_env;
+
+    const SECOND: Timestamp = 1_000_000_000;
+
+    fn context(predecessor: &str, deposit: u128, timestamp: Timestamp) {
+        let mut builder = VMContextBuilder::new();
+        builder.current_account_id("identity.testnet".parse().unwrap());
+        builder.predecessor_account_id(predecessor.parse().unwrap());
+        builder.attached_deposit(NearToken::from_yoctonear(deposit));
+        builder.block_timestamp(timestamp);
+        testing_env!(builder.build());
+    }
+
+    /// Contract with "sbt1" issued by issuer.testnet to creator.testnet
+    fn issued_contract(expires_at: Option<Timestamp>) -> SoulboundContract {
+        context("owner.testnet", 0, 0);
+        let mut contract = SoulboundContract::new(
+            "owner.testnet".parse().unwrap(),
+            NFTContractMetadata {
+                spec: "nft-1.0.0".to_string(),
+                name: "Creative Identity".to_string(),
+                symbol: "CID".to_string(),
+                icon: None,
+                base_uri: None,
+                reference: None,
+                reference_hash: None,
+            },
+        );
+        contract.add_issuer("issuer.testnet".parse().unwrap());
+
+        context("issuer.testnet", 10_000_000_000_000_000_000_000, 0);
+        contract.sbt_mint(
+            "sbt1".to_string(),
+            "creator.testnet".parse().unwrap(),
+            TokenMetadata {
+                title: Some("Creative Identity".to_string()),
+                ..Default::default()
+            },
+            IdentityData::default(),
+            expires_at,
+        );
+        contract
+    }
+
+    #[test]
+    #[should_panic(expected = "Soulbound tokens cannot be transferred")]
+    fn test_owner_cannot_transfer() {
+        let mut contract = issued_contract(None);
+        assert!(contract.nft_is_soulbound("sbt1".to_string()));
+
+        context("creator.testnet", 1, 0);
+        contract.nft_transfer("buyer.testnet".parse().unwrap(), "sbt1".to_string(), None, None);
+    }
+
+    #[test]
+    fn test_issuer_recovers_then_revokes() {
+        let mut contract = issued_contract(None);
+
+        context("issuer.testnet", 0, 0);
+        let recovered = contract.sbt_recover("creator.testnet".parse().unwrap(), "creator-new.testnet".parse().unwrap());
+        assert_eq!(recovered, vec!["sbt1".to_string()]);
+        let token = contract.nft_token("sbt1".to_string()).unwrap();
+        assert_eq!(token.owner_id, "creator-new.testnet".parse::<AccountId>().unwrap());
+        assert_eq!(contract.nft_supply_for_owner("creator.testnet".parse().unwrap()), U128(0));
+
+        contract.sbt_revoke("sbt1".to_string(), Some("credential withdrawn".to_string()));
+        assert!(contract.nft_token("sbt1".to_string()).is_none());
+        assert!(!contract.sbt_is_valid("sbt1".to_string()));
+        assert_eq!(contract.nft_total_supply(), U128(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the issuer can manage this token")]
+    fn test_holder_cannot_revoke() {
+        let mut contract = issued_contract(None);
+        context("creator.testnet", 0, 0);
+        contract.sbt_revoke("sbt1".to_string(), None);
+    }
+
+    #[test]
+    fn test_token_expires_until_renewed() {
+        let mut contract = issued_contract(Some(5 * SECOND));
+        assert!(contract.sbt_is_valid("sbt1".to_string()));
+        let metadata = contract.nft_token("sbt1".to_string()).unwrap().metadata.unwrap();
+        assert_eq!(metadata.expires_at, Some("5000".to_string()));
+
+        context("issuer.testnet", 0, 5 * SECOND);
+        assert!(!contract.sbt_is_valid("sbt1".to_string()));
+
+        contract.sbt_renew("sbt1".to_string(), Some(60 * SECOND));
+        assert!(contract.sbt_is_valid("sbt1".to_string()));
+    }
+}
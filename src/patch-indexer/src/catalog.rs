@@ -0,0 +1,60 @@
+//! Catalog records - the indexed view of `PublishedPatch`
+
+use serde::{Deserialize, Serialize};
+
+/// One yoctoNEAR-denominated NEAR
+pub const ONE_NEAR: u128 = 1_000_000_000_000_000_000_000_000;
+
+/// Patch listing as returned by the contract's `get_patch` and `get_patches` views
+///
+/// Only the fields search needs are kept; anything else in the contract's JSON is ignored.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PatchRecord {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub author: String,
+    pub tool_type: String,
+    pub version: String,
+    pub tags: Vec<String>,
+    pub ipfs_cid: String,
+    pub license: String,
+    pub price: Option<u128>,
+    pub downloads: u64,
+    pub rating: f32,
+    pub total_ratings: u32,
+    pub published_at: u64,
+    #[serde(default)]
+    pub last_updated: u64,
+    #[serde(default)]
+    pub fork_count: u32,
+}
+
+impl PatchRecord {
+    /// Price in yoctoNEAR, with free patches at zero
+    pub fn price_or_free(&self) -> u128 {
+        self.price.unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn record(id: &str, title: &str, tool_type: &str, tags: &[&str]) -> PatchRecord {
+    PatchRecord {
+        id: id.to_string(),
+        title: title.to_string(),
+        description: String::new(),
+        author: "alice.testnet".to_string(),
+        tool_type: tool_type.to_string(),
+        version: "1.0.0".to_string(),
+        tags: tags.iter().map(|t| t.to_string()).collect(),
+        ipfs_cid: format!("bafy-{}", id),
+        license: "MIT".to_string(),
+        price: None,
+        downloads: 0,
+        rating: 0.0,
+        total_ratings: 0,
+        published_at: 0,
+        last_updated: 0,
+        fork_count: 0,
+    }
+}
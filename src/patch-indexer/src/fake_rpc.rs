@@ -0,0 +1,122 @@
+//! In-process fake NEAR RPC node serving the patch contract's catalog views
+//!
+//! Answers `query`/`call_function` for `get_patches` and `get_patch` with the
+//! response and error shapes of nearcore.
+
+use crate::catalog::PatchRecord;
+use crate::rpc::NearRpc;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+struct State {
+    contract_id: String,
+    patches: Vec<PatchRecord>,
+}
+
+/// Fake RPC node listening on an ephemeral localhost port
+pub struct FakeRpc {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl FakeRpc {
+    /// Start a node with one patch contract deployed
+    pub async fn start(contract_id: &str, patches: Vec<PatchRecord>) -> Self {
+        let state = Arc::new(Mutex::new(State {
+            contract_id: contract_id.to_string(),
+            patches,
+        }));
+        let service_state = state.clone();
+        let make_service = make_service_fn(move |_| {
+            let state = service_state.clone();
+            async move { Ok::<_, Infallible>(service_fn(move |req| handle(state.clone(), req))) }
+        });
+
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service);
+        let addr = server.local_addr();
+        let (shutdown, stopped) = oneshot::channel::<()>();
+        tokio::spawn(server.with_graceful_shutdown(async {
+            stopped.await.ok();
+        }));
+
+        Self {
+            addr,
+            state,
+            shutdown: Some(shutdown),
+        }
+    }
+
+    pub fn client(&self) -> NearRpc {
+        NearRpc::new(format!("http://{}", self.addr))
+    }
+
+    /// Replace the contract's catalog
+    pub fn set_patches(&self, patches: Vec<PatchRecord>) {
+        self.state.lock().unwrap().patches = patches;
+    }
+}
+
+impl Drop for FakeRpc {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+        }
+    }
+}
+
+async fn handle(state: Arc<Mutex<State>>, req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let body = hyper::body::to_bytes(req.into_body()).await.unwrap_or_default();
+    let request: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+    let params = &request["params"];
+
+    let response = match call(&state, params) {
+        Ok(result) => json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "result": { "result": result, "logs": [], "block_height": 1, "block_hash": "11111111111111111111111111111111" }
+        }),
+        Err(message) => json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "error": { "name": "HANDLER_ERROR", "code": -32000, "message": "Server error", "data": message }
+        }),
+    };
+    Ok(Response::new(Body::from(response.to_string())))
+}
+
+fn call(state: &Mutex<State>, params: &Value) -> Result<Vec<u8>, String> {
+    let state = state.lock().unwrap();
+    let account_id = params["account_id"].as_str().unwrap_or_default();
+    if params["request_type"] != "call_function" || account_id != state.contract_id {
+        return Err(format!("account {} does not exist while viewing", account_id));
+    }
+
+    let args = params["args_base64"]
+        .as_str()
+        .and_then(|args| BASE64.decode(args).ok())
+        .and_then(|args| serde_json::from_slice::<Value>(&args).ok())
+        .unwrap_or(Value::Null);
+
+    let result = match params["method_name"].as_str() {
+        Some("get_patches") => {
+            let from = args["from_index"].as_u64().unwrap_or(0) as usize;
+            let limit = args["limit"].as_u64().unwrap_or(50).min(100) as usize;
+            let page: Vec<&PatchRecord> = state.patches.iter().skip(from).take(limit).collect();
+            serde_json::to_vec(&page)
+        }
+        Some("get_patch") => {
+            let id = args["patch_id"].as_str().unwrap_or_default();
+            serde_json::to_vec(&state.patches.iter().find(|p| p.id == id))
+        }
+        method => return Err(format!("MethodNotFound: {:?}", method)),
+    };
+    result.map_err(|e| e.to_string())
+}
@@ -0,0 +1,439 @@
+//! Inverted index with BM25 ranking, facet counts and pagination
+//!
+//! Every indexed field feeds one posting list per term, weighted by field so a title
+//! or tag hit outranks a passing mention in a description. The last query term also
+//! matches as a prefix, so partially typed queries still find results.
+
+use crate::catalog::{PatchRecord, ONE_NEAR};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const TITLE_WEIGHT: f32 = 3.0;
+const TAG_WEIGHT: f32 = 2.5;
+const TOOL_TYPE_WEIGHT: f32 = 2.0;
+const LICENSE_WEIGHT: f32 = 1.0;
+const DESCRIPTION_WEIGHT: f32 = 1.0;
+
+/// Prefix matches count for less than whole-term matches
+const PREFIX_WEIGHT: f32 = 0.5;
+const BM25_K1: f32 = 1.2;
+const BM25_B: f32 = 0.75;
+
+/// Result ordering
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    /// Best text match first; most downloaded first when there is no text
+    #[default]
+    Relevance,
+    Rating,
+    Downloads,
+    Newest,
+}
+
+/// Search request: free text, facet filters and a page window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchQuery {
+    pub text: String,
+    /// Match any of these tool types; empty matches all
+    pub tool_types: Vec<String>,
+    /// Match any of these licenses; empty matches all
+    pub licenses: Vec<String>,
+    /// Inclusive price bounds in yoctoNEAR; free patches count as zero
+    pub min_price: Option<u128>,
+    pub max_price: Option<u128>,
+    pub min_rating: Option<f32>,
+    pub sort: SortOrder,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl Default for SearchQuery {
+    fn default() -> Self {
+        Self {
+            text: String::new(),
+            tool_types: Vec::new(),
+            licenses: Vec::new(),
+            min_price: None,
+            max_price: None,
+            min_rating: None,
+            sort: SortOrder::Relevance,
+            offset: 0,
+            limit: 20,
+        }
+    }
+}
+
+impl SearchQuery {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..Self::default()
+        }
+    }
+
+    fn accepts(&self, patch: &PatchRecord) -> bool {
+        let price = patch.price_or_free();
+        (self.tool_types.is_empty() || self.tool_types.iter().any(|t| t.eq_ignore_ascii_case(&patch.tool_type)))
+            && (self.licenses.is_empty() || self.licenses.iter().any(|l| l.eq_ignore_ascii_case(&patch.license)))
+            && self.min_price.is_none_or(|min| price >= min)
+            && self.max_price.is_none_or(|max| price <= max)
+            && self.min_rating.is_none_or(|min| patch.rating >= min)
+    }
+}
+
+/// Number of matching patches with a facet value
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FacetCount {
+    pub value: String,
+    pub count: usize,
+}
+
+/// Facet counts over every match of a query, before pagination
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Facets {
+    pub tool_types: Vec<FacetCount>,
+    pub licenses: Vec<FacetCount>,
+    /// "free", "under_1", "1_to_10" and "over_10" NEAR
+    pub price: Vec<FacetCount>,
+    /// Cumulative "4+", "3+", "2+" and "1+" stars
+    pub rating: Vec<FacetCount>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub patch: PatchRecord,
+    pub score: f32,
+}
+
+/// One page of results
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResults {
+    pub total: usize,
+    pub offset: usize,
+    pub hits: Vec<SearchHit>,
+    pub facets: Facets,
+}
+
+/// In-memory search index over the patch catalog
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    patches: HashMap<String, PatchRecord>,
+    /// term -> patch ID -> field-weighted term frequency
+    postings: HashMap<String, HashMap<String, f32>>,
+    /// patch ID -> field-weighted token count
+    lengths: HashMap<String, f32>,
+}
+
+impl SearchIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.patches.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patches.is_empty()
+    }
+
+    pub fn get(&self, patch_id: &str) -> Option<&PatchRecord> {
+        self.patches.get(patch_id)
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = &String> {
+        self.patches.keys()
+    }
+
+    /// Index a patch, replacing any earlier version of it
+    pub fn upsert(&mut self, patch: PatchRecord) {
+        self.remove(&patch.id);
+
+        let mut terms: HashMap<String, f32> = HashMap::new();
+        let mut add = |text: &str, weight: f32| {
+            for token in tokenize(text) {
+                *terms.entry(token).or_default() += weight;
+            }
+        };
+        add(&patch.title, TITLE_WEIGHT);
+        add(&patch.description, DESCRIPTION_WEIGHT);
+        add(&patch.tool_type, TOOL_TYPE_WEIGHT);
+        add(&patch.license, LICENSE_WEIGHT);
+        for tag in &patch.tags {
+            add(tag, TAG_WEIGHT);
+        }
+
+        self.lengths.insert(patch.id.clone(), terms.values().sum());
+        for (term, frequency) in terms {
+            self.postings.entry(term).or_default().insert(patch.id.clone(), frequency);
+        }
+        self.patches.insert(patch.id.clone(), patch);
+    }
+
+    /// Drop a patch from the index
+    pub fn remove(&mut self, patch_id: &str) -> Option<PatchRecord> {
+        let patch = self.patches.remove(patch_id)?;
+        self.lengths.remove(patch_id);
+        self.postings.retain(|_, docs| {
+            docs.remove(patch_id);
+            !docs.is_empty()
+        });
+        Some(patch)
+    }
+
+    /// Apply a download to a cached record without refetching it
+    pub fn record_download(&mut self, patch_id: &str) {
+        if let Some(patch) = self.patches.get_mut(patch_id) {
+            patch.downloads += 1;
+        }
+    }
+
+    /// Rank, filter, facet and paginate
+    pub fn search(&self, query: &SearchQuery) -> SearchResults {
+        let terms = tokenize(&query.text);
+        let scores = if terms.is_empty() {
+            self.patches.keys().map(|id| (id.as_str(), 0.0)).collect()
+        } else {
+            self.score(&terms)
+        };
+
+        let mut matches: Vec<(&PatchRecord, f32)> = scores
+            .into_iter()
+            .filter_map(|(id, score)| self.patches.get(id).map(|patch| (patch, score)))
+            .filter(|(patch, _)| query.accepts(patch))
+            .collect();
+
+        let relevance = !terms.is_empty();
+        matches.sort_by(|(a, a_score), (b, b_score)| {
+            let order = match query.sort {
+                SortOrder::Relevance if relevance => b_score.total_cmp(a_score),
+                SortOrder::Relevance | SortOrder::Downloads => b.downloads.cmp(&a.downloads),
+                SortOrder::Rating => b.rating.total_cmp(&a.rating).then(b.total_ratings.cmp(&a.total_ratings)),
+                SortOrder::Newest => b.published_at.cmp(&a.published_at),
+            };
+            order.then_with(|| a.id.cmp(&b.id))
+        });
+
+        let facets = facets(matches.iter().map(|(patch, _)| *patch));
+        let hits = matches
+            .iter()
+            .skip(query.offset)
+            .take(query.limit)
+            .map(|(patch, score)| SearchHit {
+                patch: (*patch).clone(),
+                score: *score,
+            })
+            .collect();
+
+        SearchResults {
+            total: matches.len(),
+            offset: query.offset,
+            hits,
+            facets,
+        }
+    }
+
+    /// BM25 over field-weighted frequencies; any query term can match
+    fn score(&self, terms: &[String]) -> HashMap<&str, f32> {
+        let count = self.patches.len() as f32;
+        let average_length = self.lengths.values().sum::<f32>() / count.max(1.0);
+        let mut scores: HashMap<&str, f32> = HashMap::new();
+
+        for (position, term) in terms.iter().enumerate() {
+            let mut matched: Vec<(&String, f32)> = Vec::new();
+            if let Some((key, _)) = self.postings.get_key_value(term) {
+                matched.push((key, 1.0));
+            }
+            if position == terms.len() - 1 && term.len() >= 2 {
+                matched.extend(
+                    self.postings
+                        .keys()
+                        .filter(|key| key.len() > term.len() && key.starts_with(term.as_str()))
+                        .map(|key| (key, PREFIX_WEIGHT)),
+                );
+            }
+
+            for (key, weight) in matched {
+                let docs = &self.postings[key];
+                let df = docs.len() as f32;
+                let idf = (1.0 + (count - df + 0.5) / (df + 0.5)).ln();
+                for (id, frequency) in docs {
+                    let length = self.lengths.get(id).copied().unwrap_or(0.0);
+                    let norm = BM25_K1 * (1.0 - BM25_B + BM25_B * length / average_length.max(f32::EPSILON));
+                    *scores.entry(id.as_str()).or_default() +=
+                        weight * idf * frequency * (BM25_K1 + 1.0) / (frequency + norm);
+                }
+            }
+        }
+        scores
+    }
+}
+
+/// Lowercase alphanumeric tokens
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn facets<'a>(patches: impl Iterator<Item = &'a PatchRecord>) -> Facets {
+    let mut tool_types: HashMap<&str, usize> = HashMap::new();
+    let mut licenses: HashMap<&str, usize> = HashMap::new();
+    let mut price = [0usize; 4];
+    let mut rating = [0usize; 4];
+
+    for patch in patches {
+        *tool_types.entry(&patch.tool_type).or_default() += 1;
+        *licenses.entry(&patch.license).or_default() += 1;
+
+        let bucket = match patch.price {
+            None | Some(0) => 0,
+            Some(p) if p < ONE_NEAR => 1,
+            Some(p) if p <= 10 * ONE_NEAR => 2,
+            Some(_) => 3,
+        };
+        price[bucket] += 1;
+
+        for (stars, count) in (1..=4).rev().zip(rating.iter_mut()) {
+            if patch.total_ratings > 0 && patch.rating >= stars as f32 {
+                *count += 1;
+            }
+        }
+    }
+
+    let labelled = |labels: [&str; 4], counts: [usize; 4]| {
+        labels
+            .iter()
+            .zip(counts)
+            .filter(|(_, count)| *count > 0)
+            .map(|(label, count)| FacetCount { value: label.to_string(), count })
+            .collect()
+    };
+
+    Facets {
+        tool_types: ranked(tool_types),
+        licenses: ranked(licenses),
+        price: labelled(["free", "under_1", "1_to_10", "over_10"], price),
+        rating: labelled(["4+", "3+", "2+", "1+"], rating),
+    }
+}
+
+/// Most common values first
+fn ranked(counts: HashMap<&str, usize>) -> Vec<FacetCount> {
+    let mut counts: Vec<FacetCount> = counts
+        .into_iter()
+        .map(|(value, count)| FacetCount { value: value.to_string(), count })
+        .collect();
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::record;
+
+    fn index() -> SearchIndex {
+        let mut index = SearchIndex::new();
+
+        let mut mandel = record("mandel", "Mandelbrot Explorer", "fractal_shader", &["fractal", "zoom"]);
+        mandel.description = "Deep zoom into the Mandelbrot set".to_string();
+        mandel.price = Some(2 * ONE_NEAR);
+        mandel.rating = 4.5;
+        mandel.total_ratings = 10;
+        mandel.downloads = 40;
+        index.upsert(mandel);
+
+        let mut julia = record("julia", "Julia Morph", "fractal_shader", &["fractal"]);
+        julia.description = "Animated julia sets driven by audio".to_string();
+        julia.license = "CC-BY-4.0".to_string();
+        julia.downloads = 90;
+        index.upsert(julia);
+
+        let mut reverb = record("reverb", "Shimmer Reverb", "audio_processor", &["reverb", "ambient"]);
+        reverb.description = "Reverb with a fractal diffusion network".to_string();
+        reverb.price = Some(ONE_NEAR / 2);
+        reverb.rating = 3.0;
+        reverb.total_ratings = 2;
+        index.upsert(reverb);
+
+        index
+    }
+
+    #[test]
+    fn test_ranks_title_and_tag_hits_above_description() {
+        let results = index().search(&SearchQuery::text("fractal"));
+        assert_eq!(results.total, 3);
+        assert_eq!(results.hits.last().unwrap().patch.id, "reverb");
+
+        // Prefix matching on the last term
+        let results = index().search(&SearchQuery::text("mandel"));
+        assert_eq!(results.hits[0].patch.id, "mandel");
+
+        // Snake-case tool types split into searchable words
+        let results = index().search(&SearchQuery::text("audio_processor"));
+        assert_eq!(results.hits[0].patch.id, "reverb");
+    }
+
+    #[test]
+    fn test_filters_and_facets() {
+        let query = SearchQuery {
+            tool_types: vec!["fractal_shader".to_string()],
+            ..SearchQuery::default()
+        };
+        let results = index().search(&query);
+        assert_eq!(results.total, 2);
+        // No text: most downloaded first
+        assert_eq!(results.hits[0].patch.id, "julia");
+        assert_eq!(results.facets.tool_types, vec![FacetCount { value: "fractal_shader".to_string(), count: 2 }]);
+        assert_eq!(results.facets.price, vec![
+            FacetCount { value: "free".to_string(), count: 1 },
+            FacetCount { value: "1_to_10".to_string(), count: 1 },
+        ]);
+
+        let query = SearchQuery {
+            max_price: Some(ONE_NEAR),
+            min_rating: Some(3.0),
+            ..SearchQuery::default()
+        };
+        let results = index().search(&query);
+        assert_eq!(results.hits.len(), 1);
+        assert_eq!(results.hits[0].patch.id, "reverb");
+        assert_eq!(results.facets.rating, vec![
+            FacetCount { value: "3+".to_string(), count: 1 },
+            FacetCount { value: "2+".to_string(), count: 1 },
+            FacetCount { value: "1+".to_string(), count: 1 },
+        ]);
+    }
+
+    #[test]
+    fn test_pagination_and_reindexing() {
+        let mut index = index();
+        let query = SearchQuery {
+            sort: SortOrder::Downloads,
+            offset: 1,
+            limit: 1,
+            ..SearchQuery::default()
+        };
+        let results = index.search(&query);
+        assert_eq!(results.total, 3);
+        assert_eq!(results.hits.len(), 1);
+        assert_eq!(results.hits[0].patch.id, "mandel");
+
+        let mut renamed = index.get("julia").unwrap().clone();
+        renamed.title = "Kaleidoscope".to_string();
+        renamed.tags.clear();
+        renamed.description.clear();
+        renamed.tool_type = "visual_effect".to_string();
+        index.upsert(renamed);
+        assert_eq!(index.search(&SearchQuery::text("julia")).total, 0);
+        assert_eq!(index.search(&SearchQuery::text("kaleidoscope")).total, 1);
+
+        index.remove("mandel");
+        assert_eq!(index.len(), 2);
+        assert_eq!(index.search(&SearchQuery::text("mandelbrot")).total, 0);
+    }
+}
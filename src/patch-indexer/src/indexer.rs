@@ -0,0 +1,174 @@
+//! Keeps a `SearchIndex` in step with a deployed `PatchMarketplaceContract`
+//!
+//! `sync` pages through the `get_patches` view for a full snapshot. Between syncs,
+//! `handle_log` takes contract log lines (from an archival node, a lake reader or a
+//! sandbox transaction outcome) and refetches every patch an event mentions.
+
+use crate::catalog::PatchRecord;
+use crate::index::{SearchIndex, SearchQuery, SearchResults};
+use crate::rpc::{NearRpc, RpcError};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+
+/// Patches requested per `get_patches` call; the contract caps pages at 100
+const PAGE_SIZE: u64 = 100;
+
+pub struct PatchIndexer {
+    rpc: NearRpc,
+    contract_id: String,
+    index: SearchIndex,
+}
+
+impl PatchIndexer {
+    pub fn new(rpc: NearRpc, contract_id: impl Into<String>) -> Self {
+        Self {
+            rpc,
+            contract_id: contract_id.into(),
+            index: SearchIndex::new(),
+        }
+    }
+
+    pub fn index(&self) -> &SearchIndex {
+        &self.index
+    }
+
+    pub fn search(&self, query: &SearchQuery) -> SearchResults {
+        self.index.search(query)
+    }
+
+    /// Load every published patch, dropping any the contract no longer lists
+    pub async fn sync(&mut self) -> Result<usize, RpcError> {
+        let mut seen = HashSet::new();
+        let mut from_index = 0;
+        loop {
+            let page: Vec<PatchRecord> = self
+                .rpc
+                .view(&self.contract_id, "get_patches", &json!({ "from_index": from_index, "limit": PAGE_SIZE }))
+                .await?;
+            if page.is_empty() {
+                break;
+            }
+            from_index += page.len() as u64;
+            for patch in page {
+                seen.insert(patch.id.clone());
+                self.index.upsert(patch);
+            }
+        }
+
+        let stale: Vec<String> = self.index.ids().filter(|id| !seen.contains(*id)).cloned().collect();
+        for id in stale {
+            self.index.remove(&id);
+        }
+        Ok(self.index.len())
+    }
+
+    /// Refetch one patch, removing it if the contract no longer has it
+    pub async fn refresh(&mut self, patch_id: &str) -> Result<(), RpcError> {
+        let patch: Option<PatchRecord> = self
+            .rpc
+            .view(&self.contract_id, "get_patch", &json!({ "patch_id": patch_id }))
+            .await?;
+        match patch {
+            Some(patch) => self.index.upsert(patch),
+            None => {
+                self.index.remove(patch_id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply a contract log line, returning the patch IDs that were refreshed
+    ///
+    /// Lines that are not JSON events, or mention no patch, are ignored.
+    pub async fn handle_log(&mut self, log: &str) -> Result<Vec<String>, RpcError> {
+        let ids = patch_ids(log);
+        for id in &ids {
+            self.refresh(id).await?;
+        }
+        Ok(ids)
+    }
+}
+
+/// Patch IDs mentioned by an event log, bare JSON or `EVENT_JSON:` prefixed
+fn patch_ids(log: &str) -> Vec<String> {
+    let log = log.strip_prefix("EVENT_JSON:").unwrap_or(log);
+    let event: Value = match serde_json::from_str(log) {
+        Ok(event @ Value::Object(_)) => event,
+        _ => return Vec::new(),
+    };
+
+    // NEP-297 payloads sit under `data`, as one object or an array of them
+    let mut payloads = vec![&event];
+    match event.get("data") {
+        Some(Value::Array(data)) => payloads.extend(data),
+        Some(data @ Value::Object(_)) => payloads.push(data),
+        _ => {}
+    }
+
+    let mut ids: Vec<String> = Vec::new();
+    for payload in payloads {
+        for key in ["patch_id", "original_patch_id", "fork_patch_id"] {
+            if let Some(id) = payload.get(key).and_then(Value::as_str) {
+                if !ids.iter().any(|seen| seen == id) {
+                    ids.push(id.to_string());
+                }
+            }
+        }
+    }
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::record;
+    use crate::fake_rpc::FakeRpc;
+
+    #[tokio::test]
+    async fn test_sync_pages_and_drops_removed_patches() {
+        let patches: Vec<PatchRecord> = (0..250)
+            .map(|i| record(&format!("patch-{}", i), &format!("Patch {}", i), "fractal_shader", &[]))
+            .collect();
+        let rpc = FakeRpc::start("patches.test.near", patches.clone()).await;
+        let mut indexer = PatchIndexer::new(rpc.client(), "patches.test.near");
+
+        assert_eq!(indexer.sync().await.unwrap(), 250);
+        assert_eq!(indexer.search(&SearchQuery::text("patch 42")).hits[0].patch.id, "patch-42");
+
+        rpc.set_patches(patches[..10].to_vec());
+        assert_eq!(indexer.sync().await.unwrap(), 10);
+        assert!(indexer.index().get("patch-42").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_logs_refresh_mentioned_patches() {
+        let mut patch = record("mandel", "Mandelbrot", "fractal_shader", &["fractal"]);
+        let rpc = FakeRpc::start("patches.test.near", vec![patch.clone()]).await;
+        let mut indexer = PatchIndexer::new(rpc.client(), "patches.test.near");
+        indexer.sync().await.unwrap();
+
+        patch.downloads = 7;
+        rpc.set_patches(vec![patch]);
+        let refreshed = indexer
+            .handle_log(r#"{"event":"patch_sale","patch_id":"mandel","buyer":"bob.testnet"}"#)
+            .await
+            .unwrap();
+        assert_eq!(refreshed, vec!["mandel".to_string()]);
+        assert_eq!(indexer.index().get("mandel").unwrap().downloads, 7);
+
+        assert!(indexer.handle_log("not an event").await.unwrap().is_empty());
+
+        // Wrong contract surfaces the node's error
+        let mut wrong = PatchIndexer::new(rpc.client(), "other.test.near");
+        assert!(matches!(wrong.sync().await, Err(RpcError::ViewFailed { .. })));
+    }
+
+    #[test]
+    fn test_patch_ids_from_nep297_events() {
+        let log = r#"EVENT_JSON:{"standard":"patches","version":"1.0.0","event":"patch_forked","data":[{"original_patch_id":"a","fork_patch_id":"b"}]}"#;
+        assert_eq!(patch_ids(log), vec!["a".to_string(), "b".to_string()]);
+
+        let log = r#"EVENT_JSON:{"standard":"patch_marketplace","version":"1.0.0","event":"patch_purchased","data":{"patch_id":"mandel","buyer":"bob.testnet"}}"#;
+        assert_eq!(patch_ids(log), vec!["mandel".to_string()]);
+    }
+}
@@ -0,0 +1,18 @@
+//! # NFT Patch Indexer
+//!
+//! Off-chain search over the `PatchMarketplaceContract` catalog. The contract's own
+//! `search_patches` view only matches exact tags with a linear scan; this crate
+//! mirrors the catalog into an inverted index and answers ranked full-text queries
+//! with facet counts (tool type, license, price, rating) and pagination.
+
+mod catalog;
+mod index;
+mod indexer;
+mod rpc;
+#[cfg(test)]
+mod fake_rpc;
+
+pub use catalog::*;
+pub use index::*;
+pub use indexer::*;
+pub use rpc::*;
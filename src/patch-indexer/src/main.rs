@@ -0,0 +1,71 @@
+//! Sync a patch marketplace contract into a local index and run one search
+//!
+//! ```text
+//! patch-indexer --contract patches.test.near [--rpc http://localhost:3030]
+//!     [--tool-type T]... [--license L]... [--min-price YOCTO] [--max-price YOCTO]
+//!     [--min-rating STARS] [--sort relevance|rating|downloads|newest]
+//!     [--offset N] [--limit N] [QUERY...]
+//! ```
+
+use nft_patch_indexer::{NearRpc, PatchIndexer, SearchQuery, SANDBOX_RPC_URL};
+use std::process::ExitCode;
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<(String, String, SearchQuery), String> {
+    let mut rpc_url = SANDBOX_RPC_URL.to_string();
+    let mut contract_id = None;
+    let mut query = SearchQuery::default();
+    let mut text = Vec::new();
+
+    let mut args = args.peekable();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            text.push(arg);
+            continue;
+        }
+        let value = args.next().ok_or_else(|| format!("{} needs a value", arg))?;
+        let number = |value: &str| value.parse::<u128>().map_err(|_| format!("{} expects a number", arg));
+        match arg.as_str() {
+            "--rpc" => rpc_url = value,
+            "--contract" => contract_id = Some(value),
+            "--tool-type" => query.tool_types.push(value),
+            "--license" => query.licenses.push(value),
+            "--min-price" => query.min_price = Some(number(&value)?),
+            "--max-price" => query.max_price = Some(number(&value)?),
+            "--min-rating" => {
+                query.min_rating = Some(value.parse().map_err(|_| "--min-rating expects a number".to_string())?)
+            }
+            "--sort" => {
+                query.sort = serde_json::from_value(serde_json::Value::String(value.clone()))
+                    .map_err(|_| format!("unknown sort order: {}", value))?
+            }
+            "--offset" => query.offset = number(&value)? as usize,
+            "--limit" => query.limit = number(&value)? as usize,
+            _ => return Err(format!("unknown option: {}", arg)),
+        }
+    }
+
+    query.text = text.join(" ");
+    let contract_id = contract_id.ok_or("--contract is required")?;
+    Ok((rpc_url, contract_id, query))
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let (rpc_url, contract_id, query) = match parse_args(std::env::args().skip(1)) {
+        Ok(parsed) => parsed,
+        Err(message) => {
+            eprintln!("patch-indexer: {}", message);
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut indexer = PatchIndexer::new(NearRpc::new(rpc_url), contract_id);
+    if let Err(e) = indexer.sync().await {
+        eprintln!("patch-indexer: sync failed: {}", e);
+        return ExitCode::FAILURE;
+    }
+
+    let results = indexer.search(&query);
+    println!("{}", serde_json::to_string_pretty(&results).expect("search results serialize"));
+    ExitCode::SUCCESS
+}
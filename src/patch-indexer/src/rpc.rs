@@ -0,0 +1,104 @@
+//! Minimal NEAR JSON-RPC client for contract view calls
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use thiserror::Error;
+
+/// RPC endpoint of a local `near-sandbox`
+pub const SANDBOX_RPC_URL: &str = "http://localhost:3030";
+
+/// Errors talking to a NEAR node
+#[derive(Debug, Error)]
+pub enum RpcError {
+    #[error("RPC request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("RPC error: {0}")]
+    Rpc(String),
+
+    #[error("view call {method} failed: {message}")]
+    ViewFailed { method: String, message: String },
+
+    #[error("invalid view result: {0}")]
+    InvalidResult(#[from] serde_json::Error),
+}
+
+#[derive(Deserialize)]
+struct RpcResponse {
+    result: Option<CallResult>,
+    error: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct CallResult {
+    #[serde(default)]
+    result: Vec<u8>,
+    /// Older nodes report contract panics here instead of in `error`
+    error: Option<String>,
+}
+
+/// Client for one NEAR RPC endpoint
+#[derive(Clone)]
+pub struct NearRpc {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl NearRpc {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Call a view method at final finality and decode its JSON result
+    pub async fn view<A: Serialize, T: DeserializeOwned>(
+        &self,
+        contract_id: &str,
+        method: &str,
+        args: &A,
+    ) -> Result<T, RpcError> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": "patch-indexer",
+            "method": "query",
+            "params": {
+                "request_type": "call_function",
+                "finality": "final",
+                "account_id": contract_id,
+                "method_name": method,
+                "args_base64": BASE64.encode(serde_json::to_vec(args)?),
+            }
+        });
+
+        let response: RpcResponse = self
+            .client
+            .post(&self.url)
+            .json(&request)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        if let Some(error) = response.error {
+            let message = error
+                .pointer("/cause/info/error_message")
+                .or_else(|| error.get("data"))
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| error.to_string());
+            return Err(RpcError::ViewFailed { method: method.to_string(), message });
+        }
+
+        let result = response.result.ok_or_else(|| RpcError::Rpc("response has no result".to_string()))?;
+        if let Some(message) = result.error {
+            return Err(RpcError::ViewFailed { method: method.to_string(), message });
+        }
+        Ok(serde_json::from_slice(&result.result)?)
+    }
+}
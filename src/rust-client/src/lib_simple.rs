@@ -14,10 +14,12 @@ use wasm_bindgen::prelude::*;
 // Simplified modules
 pub mod simple_webgpu;
 pub mod simple_blockchain;
+pub mod oracle_signer;
 
 // Re-export simplified functionality
 pub use simple_webgpu::*;
 pub use simple_blockchain::*;
+pub use oracle_signer::*;
 
 /// Core metadata structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -0,0 +1,188 @@
+//! Signing helper for oracle emotion readings
+//!
+//! Sensor gateways use `OracleSigner` to produce the `SignedReading` JSON that
+//! `DynamicNFT::update_emotional_state` and `BiometricNFT::interact_with_biometrics`
+//! verify on-chain. The signer's public key must first be registered on the contract
+//! with `add_oracle`.
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Errors producing or checking a signed reading
+#[derive(Debug, Error)]
+pub enum OracleSignerError {
+    #[error("reading does not serialize: {0}")]
+    Serialize(#[from] serde_json::Error),
+
+    #[error("invalid public key: {0}")]
+    InvalidKey(String),
+
+    #[error("invalid signature")]
+    InvalidSignature,
+}
+
+/// Signed message, mirroring the contract's `OracleMessage`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OracleMessage<T> {
+    pub contract_id: String,
+    pub token_id: String,
+    /// Decimal string, like the contract's `U64`
+    #[serde(with = "u64_string")]
+    pub nonce: u64,
+    /// Nanoseconds since the Unix epoch, as a decimal string
+    pub timestamp: String,
+    pub reading: T,
+}
+
+/// Serde for u64s carried as decimal strings; JSON numbers lose precision above 2^53
+mod u64_string {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(de::Error::custom)
+    }
+}
+
+/// Argument passed to the contract, mirroring its `SignedReading`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignedReading {
+    /// `ed25519:<base58>`
+    pub public_key: String,
+    /// JSON-encoded `OracleMessage`; the signature covers exactly these bytes
+    pub payload: String,
+    /// Base64 ed25519 signature
+    pub signature: String,
+}
+
+impl SignedReading {
+    /// Check the signature and decode the message, as the contract will
+    pub fn verify<T: for<'de> Deserialize<'de>>(&self) -> Result<OracleMessage<T>, OracleSignerError> {
+        let key = self
+            .public_key
+            .strip_prefix("ed25519:")
+            .and_then(|key| bs58::decode(key).into_vec().ok())
+            .and_then(|key| <[u8; 32]>::try_from(key).ok())
+            .and_then(|key| VerifyingKey::from_bytes(&key).ok())
+            .ok_or_else(|| OracleSignerError::InvalidKey(self.public_key.clone()))?;
+        let signature = BASE64
+            .decode(&self.signature)
+            .ok()
+            .and_then(|bytes| Signature::from_slice(&bytes).ok())
+            .ok_or(OracleSignerError::InvalidSignature)?;
+        key.verify(self.payload.as_bytes(), &signature)
+            .map_err(|_| OracleSignerError::InvalidSignature)?;
+        Ok(serde_json::from_str(&self.payload)?)
+    }
+}
+
+/// ed25519 key of one sensor gateway
+pub struct OracleSigner {
+    key: SigningKey,
+    last_nonce: u64,
+}
+
+impl OracleSigner {
+    pub fn generate() -> Self {
+        Self::from_secret(rand::random())
+    }
+
+    pub fn from_secret(secret: [u8; 32]) -> Self {
+        Self {
+            key: SigningKey::from_bytes(&secret),
+            last_nonce: 0,
+        }
+    }
+
+    pub fn secret_bytes(&self) -> [u8; 32] {
+        self.key.to_bytes()
+    }
+
+    /// Public key in NEAR's `ed25519:<base58>` format, as passed to `add_oracle`
+    pub fn public_key(&self) -> String {
+        format!("ed25519:{}", bs58::encode(self.key.verifying_key().as_bytes()).into_string())
+    }
+
+    /// Sign a reading for one token of one contract
+    ///
+    /// Nonces must increase per key on-chain. They never fall below the reading's
+    /// timestamp, so a restarted signer with a sane clock keeps producing valid ones.
+    pub fn sign<T: Serialize>(
+        &mut self,
+        contract_id: &str,
+        token_id: &str,
+        reading: &T,
+        timestamp_ns: u64,
+    ) -> Result<SignedReading, OracleSignerError> {
+        let nonce = (self.last_nonce + 1).max(timestamp_ns);
+        let payload = serde_json::to_string(&OracleMessage {
+            contract_id: contract_id.to_string(),
+            token_id: token_id.to_string(),
+            nonce,
+            timestamp: timestamp_ns.to_string(),
+            reading,
+        })?;
+        let signature = self.key.sign(payload.as_bytes());
+        self.last_nonce = nonce;
+
+        Ok(SignedReading {
+            public_key: self.public_key(),
+            payload,
+            signature: BASE64.encode(signature.to_bytes()),
+        })
+    }
+
+    /// Sign a reading taken now
+    pub fn sign_now<T: Serialize>(
+        &mut self,
+        contract_id: &str,
+        token_id: &str,
+        reading: &T,
+    ) -> Result<SignedReading, OracleSignerError> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos() as u64)
+            .unwrap_or_default();
+        self.sign(contract_id, token_id, reading, now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_signed_reading_round_trip() {
+        let mut signer = OracleSigner::from_secret([7; 32]);
+        let reading = json!({ "valence": 0.5, "arousal": 0.7, "dominance": 0.6, "confidence": 0.9, "timestamp": 0 });
+        let signed = signer.sign("dynamic.testnet", "token1", &reading, 42).unwrap();
+
+        assert!(signed.public_key.starts_with("ed25519:"));
+        let message = signed.verify::<serde_json::Value>().unwrap();
+        assert_eq!(message.contract_id, "dynamic.testnet");
+        assert_eq!(message.timestamp, "42");
+        assert_eq!(message.reading, reading);
+        assert!(signed.payload.contains(r#""nonce":"42""#));
+    }
+
+    #[test]
+    fn test_nonces_increase_and_tampering_is_detected() {
+        let mut signer = OracleSigner::from_secret([7; 32]);
+        let first = signer.sign("dynamic.testnet", "token1", &1, 1_000).unwrap();
+        let second = signer.sign("dynamic.testnet", "token1", &1, 500).unwrap();
+        let nonce = |signed: &SignedReading| signed.verify::<u8>().unwrap().nonce;
+        assert_eq!(nonce(&first), 1_000);
+        assert_eq!(nonce(&second), 1_001);
+
+        let mut tampered = second;
+        tampered.payload = tampered.payload.replace("token1", "token2");
+        assert!(matches!(tampered.verify::<u8>(), Err(OracleSignerError::InvalidSignature)));
+    }
+}